@@ -0,0 +1,90 @@
+//! Heap allocations and time per tick of the backtest engine's hot loop.
+//!
+//! Runs synthetic order books through the GPT market maker with a counting global
+//! allocator, so changes that add per-tick clones show up as a higher allocation count.
+//! Declared with `harness = false`; run with `cargo bench --bench hot_loop`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use happytest::{BacktestConfig, BacktestEngine, GptMarketMaker, GptMarketMakerConfig, OrderBook};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const TICKS: usize = 100_000;
+const LEVELS: usize = 50;
+
+/// Books 100ms apart whose mid oscillates and whose imbalance flips, so the maker keeps trading
+fn books() -> Vec<OrderBook> {
+    (0..TICKS)
+        .map(|i| {
+            let mid = 100.0 + (i as f64 / 50.0).sin();
+            let (bid_size, ask_size) = if (i / 20) % 2 == 0 { (3.0, 1.0) } else { (1.0, 3.0) };
+            let bids = (0..LEVELS).map(|level| (mid - 0.05 - level as f64 * 0.01, bid_size)).collect();
+            let asks = (0..LEVELS).map(|level| (mid + 0.05 + level as f64 * 0.01, ask_size)).collect();
+            OrderBook::new("BTCUSDT".to_string(), bids, asks, i as i64 * 100)
+        })
+        .collect()
+}
+
+fn run(label: &str, config: BacktestConfig) {
+    let books = books();
+    let engine = BacktestEngine::new(config);
+    let mut strategy = GptMarketMaker::new(
+        "BTCUSDT".to_string(),
+        GptMarketMakerConfig {
+            vwap_window: 20,
+            max_volatility_threshold: 1.0,
+            momentum_threshold: 1.0,
+            ..Default::default()
+        },
+    );
+
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let trade_state = engine.run_order_books(books, &mut strategy);
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+
+    println!(
+        "{:<16} {:>8} trades {:>8.2} allocations/tick {:>8.0} ns/tick",
+        label,
+        trade_state.get_all_trades().len(),
+        allocations as f64 / TICKS as f64,
+        elapsed.as_nanos() as f64 / TICKS as f64,
+    );
+}
+
+fn main() {
+    let config = BacktestConfig {
+        fill_rate: 1.0,
+        rejection_rate: 0.0,
+        seed: Some(7),
+        ..Default::default()
+    };
+    run("immediate", config.clone());
+    run("store_all_books", BacktestConfig { store_all_books: true, ..config.clone() });
+    run("requote_on_move", BacktestConfig { requote_on_move: true, ..config });
+}
@@ -0,0 +1,351 @@
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use comfy_table::Table;
+
+use crate::core::{PnLResult, TradeState};
+use crate::backtest::TradeDashboard;
+
+/// Milliseconds per UTC day, the width of a `SessionSplit::UtcDay` session
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Where a continuous multi-file run is cut into sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionSplit {
+    /// One session per UTC calendar day
+    UtcDay,
+    /// One session per input file, starting at its first book
+    File,
+}
+
+impl FromStr for SessionSplit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "day" | "utc_day" => Ok(SessionSplit::UtcDay),
+            "file" => Ok(SessionSplit::File),
+            other => Err(format!("unknown session split '{}', expected day or file", other)),
+        }
+    }
+}
+
+/// P&L of one session of a multi-file run
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    /// Session start in Unix milliseconds: midnight UTC or the file's first book
+    pub start: i64,
+    /// Change of the combined equity over the session
+    pub pnl: f64,
+    /// Filled trades in the session
+    pub trades: usize,
+    /// Largest peak-to-trough fall of the combined equity within the session
+    pub max_drawdown: f64,
+}
+
+/// Combined results of a multi-file or multi-symbol backtest
+#[derive(Debug, Clone)]
+pub struct AggregateResult {
+    /// Number of input files the results were built from
+    pub files: usize,
+    pub per_symbol: HashMap<String, PnLResult>,
+    /// Per-symbol Sharpe of equity increments on the combined time grid
+    pub symbol_sharpes: HashMap<String, f64>,
+    /// Sum of the per-symbol equity curves, forward-filled on the union of their timestamps
+    pub equity_curve: Vec<(i64, f64)>,
+    /// Realized plus unrealized P&L across all symbols
+    pub total_pnl: f64,
+    /// Sharpe of the combined equity increments (not annualized), so offsetting
+    /// symbols diversify rather than add up
+    pub portfolio_sharpe: f64,
+    /// Symbol whose mid-price returns `symbol_betas` are measured against, if any
+    pub beta_reference: Option<String>,
+    /// Per-symbol beta of mid-price returns to `beta_reference`
+    pub symbol_betas: HashMap<String, f64>,
+    /// Per-session breakdown of `equity_curve`, empty unless `with_sessions` was applied
+    pub sessions: Vec<SessionSummary>,
+}
+
+impl AggregateResult {
+    /// Combine the results of every symbol traded in the dashboard's trade state
+    pub fn from_dashboard(dashboard: &mut TradeDashboard, files: usize) -> Self {
+        let symbols: BTreeSet<String> = dashboard.trade_state.get_all_trades().iter()
+            .map(|t| t.symbol.clone())
+            .collect();
+
+        let mut per_symbol = HashMap::new();
+        let mut curves = Vec::new();
+        for symbol in &symbols {
+            per_symbol.extend(dashboard.pnl(symbol));
+            curves.push((symbol.clone(), dashboard.equity_curve(symbol)));
+        }
+
+        let grid: Vec<i64> = curves.iter()
+            .flat_map(|(_, curve)| curve.iter().map(|(t, _)| *t))
+            .collect::<BTreeSet<i64>>()
+            .into_iter()
+            .collect();
+
+        let mut combined = vec![0.0; grid.len()];
+        let mut symbol_sharpes = HashMap::new();
+        for (symbol, curve) in &curves {
+            let resampled = forward_fill(curve, &grid);
+            for (total, value) in combined.iter_mut().zip(&resampled) {
+                *total += value;
+            }
+            symbol_sharpes.insert(symbol.clone(), increment_sharpe(&resampled));
+        }
+
+        let total_pnl = per_symbol.values()
+            .map(|r| r.total_pnl + r.unrealized_pnl)
+            .sum();
+
+        Self {
+            files,
+            per_symbol,
+            symbol_sharpes,
+            portfolio_sharpe: increment_sharpe(&combined),
+            equity_curve: grid.into_iter().zip(combined).collect(),
+            total_pnl,
+            beta_reference: None,
+            symbol_betas: HashMap::new(),
+            sessions: Vec::new(),
+        }
+    }
+
+    /// Add each symbol's beta to `reference` from the books stored in `trade_state`
+    pub fn with_beta_reference(mut self, trade_state: &TradeState, reference: &str) -> Self {
+        self.symbol_betas = self.per_symbol.keys()
+            .filter_map(|symbol| trade_state.beta_to_reference(symbol, reference).map(|beta| (symbol.clone(), beta)))
+            .collect();
+        self.beta_reference = Some(reference.to_string());
+        self
+    }
+
+    /// Break the combined equity curve into sessions split by UTC day or by the input files
+    /// recorded in `trade_state`
+    pub fn with_sessions(mut self, trade_state: &TradeState, split: SessionSplit) -> Self {
+        let fill_times: Vec<i64> = trade_state.get_trades_history().iter().map(|t| t.time).collect();
+        let mut starts: Vec<i64> = match split {
+            SessionSplit::UtcDay => self.equity_curve.iter().map(|(time, _)| *time)
+                .chain(fill_times.iter().copied())
+                .map(|time| time.div_euclid(MS_PER_DAY) * MS_PER_DAY)
+                .collect(),
+            SessionSplit::File => trade_state.file_starts().to_vec(),
+        };
+        starts.sort_unstable();
+        starts.dedup();
+
+        // Anything before the first start belongs to the first session
+        let session_of = |time: i64| starts.partition_point(|&start| start <= time).saturating_sub(1);
+        let mut sessions: Vec<SessionSummary> = starts.iter()
+            .map(|&start| SessionSummary { start, pnl: 0.0, trades: 0, max_drawdown: 0.0 })
+            .collect();
+        if sessions.is_empty() {
+            self.sessions = sessions;
+            return self;
+        }
+        for time in fill_times {
+            sessions[session_of(time)].trades += 1;
+        }
+
+        let mut points = self.equity_curve.iter().peekable();
+        let mut opening = 0.0;
+        for (i, session) in sessions.iter_mut().enumerate() {
+            let (mut equity, mut peak) = (opening, opening);
+            while let Some((_, value)) = points.next_if(|(time, _)| session_of(*time) == i) {
+                equity = *value;
+                peak = peak.max(equity);
+                session.max_drawdown = session.max_drawdown.max(peak - equity);
+            }
+            session.pnl = equity - opening;
+            opening = equity;
+        }
+        self.sessions = sessions;
+        self
+    }
+
+    /// Table with one row per session and a total row
+    pub fn sessions_table(&self) -> String {
+        let mut table = Table::new();
+        table.set_header(vec!["Session start (UTC)", "P&L", "Trades", "Max drawdown"]);
+        let start_label = |start: i64| chrono::DateTime::from_timestamp_millis(start)
+            .map_or(start.to_string(), |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+        for session in &self.sessions {
+            table.add_row(vec![
+                start_label(session.start),
+                format!("${:.2}", session.pnl),
+                session.trades.to_string(),
+                format!("${:.2}", session.max_drawdown),
+            ]);
+        }
+        table.add_row(vec![
+            "TOTAL".to_string(),
+            format!("${:.2}", self.sessions.iter().map(|s| s.pnl).sum::<f64>()),
+            self.sessions.iter().map(|s| s.trades).sum::<usize>().to_string(),
+            format!("${:.2}", max_drawdown(&self.equity_curve)),
+        ]);
+
+        format!("\n=== Sessions ({}) ===\n{}", self.sessions.len(), table)
+    }
+
+    /// Table with one row per symbol and a portfolio row
+    pub fn to_table(&self) -> String {
+        let mut table = Table::new();
+        let mut header = vec!["Symbol".to_string(), "Closed".to_string(), "Realized P&L".to_string(),
+                              "Unrealized P&L".to_string(), "Sharpe".to_string()];
+        if let Some(reference) = &self.beta_reference {
+            header.push(format!("Beta to {}", reference));
+        }
+        table.set_header(header);
+
+        let mut symbols: Vec<&String> = self.per_symbol.keys().collect();
+        symbols.sort();
+        for symbol in symbols {
+            let result = &self.per_symbol[symbol];
+            let mut row = vec![
+                symbol.clone(),
+                result.closed_trades.len().to_string(),
+                format!("${:.2}", result.total_pnl),
+                format!("${:.2}", result.unrealized_pnl),
+                format!("{:.3}", self.symbol_sharpes.get(symbol).copied().unwrap_or(0.0)),
+            ];
+            if self.beta_reference.is_some() {
+                row.push(self.symbol_betas.get(symbol).map_or("n/a".to_string(), |beta| format!("{:.3}", beta)));
+            }
+            table.add_row(row);
+        }
+
+        let mut portfolio_row = vec![
+            "PORTFOLIO".to_string(),
+            self.per_symbol.values().map(|r| r.closed_trades.len()).sum::<usize>().to_string(),
+            format!("${:.2}", self.per_symbol.values().map(|r| r.total_pnl).sum::<f64>()),
+            format!("${:.2}", self.per_symbol.values().map(|r| r.unrealized_pnl).sum::<f64>()),
+            format!("{:.3}", self.portfolio_sharpe),
+        ];
+        if self.beta_reference.is_some() {
+            portfolio_row.push(String::new());
+        }
+        table.add_row(portfolio_row);
+
+        format!("\n=== Aggregate Result ({} files, total P&L ${:.2}) ===\n{}", self.files, self.total_pnl, table)
+    }
+}
+
+/// Value of `curve` at each grid time, holding the last point (0.0 before the first)
+fn forward_fill(curve: &[(i64, f64)], grid: &[i64]) -> Vec<f64> {
+    let mut values = Vec::with_capacity(grid.len());
+    let mut idx = 0;
+    let mut current = 0.0;
+    for &time in grid {
+        while idx < curve.len() && curve[idx].0 <= time {
+            current = curve[idx].1;
+            idx += 1;
+        }
+        values.push(current);
+    }
+    values
+}
+
+/// Largest peak-to-trough fall of an equity curve starting from 0.0
+fn max_drawdown(curve: &[(i64, f64)]) -> f64 {
+    let mut peak: f64 = 0.0;
+    curve.iter().fold(0.0, |drawdown, (_, equity)| {
+        peak = peak.max(*equity);
+        f64::max(drawdown, peak - equity)
+    })
+}
+
+/// Mean over standard deviation of successive equity increments
+fn increment_sharpe(equity: &[f64]) -> f64 {
+    if equity.len() < 2 {
+        return 0.0;
+    }
+
+    let increments: Vec<f64> = equity.windows(2).map(|w| w[1] - w[0]).collect();
+    let mean = increments.iter().sum::<f64>() / increments.len() as f64;
+    let variance = increments.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / increments.len() as f64;
+
+    if variance > 0.0 {
+        mean / variance.sqrt()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Trade;
+
+    fn filled(symbol: &str, side: &str, price: f64, time: i64) -> Trade {
+        let mut trade = Trade::new(time, symbol.to_string(), side.to_string(), price, 1.0);
+        trade.status = "filled".to_string();
+        trade
+    }
+
+    #[test]
+    fn test_portfolio_sharpe_differs_from_sum_of_symbols() {
+        let mut trade_state = TradeState::new();
+        for trade in [
+            filled("BTCUSDT", "Buy", 100.0, 1000),
+            filled("ETHUSDT", "Buy", 100.0, 1000),
+            filled("BTCUSDT", "Sell", 110.0, 2000),
+            filled("ETHUSDT", "Sell", 90.0, 2000),
+            filled("BTCUSDT", "Buy", 100.0, 3000),
+            filled("ETHUSDT", "Buy", 100.0, 3000),
+            filled("BTCUSDT", "Sell", 95.0, 4000),
+            filled("ETHUSDT", "Sell", 108.0, 4000),
+        ] {
+            trade_state.add(trade);
+        }
+        let mut dashboard = TradeDashboard::new(trade_state, 0.05);
+
+        let aggregate = AggregateResult::from_dashboard(&mut dashboard, 2);
+
+        assert_eq!(aggregate.per_symbol.len(), 2);
+        assert!((aggregate.total_pnl - 3.0).abs() < 1e-9);
+        assert_eq!(aggregate.equity_curve.last(), Some(&(4000, 3.0)));
+
+        // Offsetting moves diversify: equity goes 0, 0, 0, 3
+        let naive_sum: f64 = aggregate.symbol_sharpes.values().sum();
+        assert!((aggregate.portfolio_sharpe - 1.0 / 2.0_f64.sqrt()).abs() < 1e-9);
+        assert!((aggregate.portfolio_sharpe - naive_sum).abs() > 0.1);
+    }
+
+    #[test]
+    fn test_sessions_split_a_two_day_run() {
+        const DAY: i64 = 86_400_000;
+        let mut trade_state = TradeState::new();
+        for trade in [
+            // Day one: +10, then a round trip losing 4 after a 6 gain
+            filled("BTCUSDT", "Buy", 100.0, 1_000),
+            filled("BTCUSDT", "Sell", 110.0, 2_000),
+            filled("BTCUSDT", "Buy", 100.0, 3_000),
+            filled("BTCUSDT", "Sell", 96.0, 4_000),
+            // Day two: -5
+            filled("BTCUSDT", "Buy", 100.0, DAY + 1_000),
+            filled("BTCUSDT", "Sell", 95.0, DAY + 2_000),
+        ] {
+            trade_state.add(trade);
+        }
+        trade_state.note_file_start(0);
+        trade_state.note_file_start(DAY);
+        let mut dashboard = TradeDashboard::new(trade_state, 0.05);
+        let aggregate = AggregateResult::from_dashboard(&mut dashboard, 2);
+
+        for split in [SessionSplit::UtcDay, SessionSplit::File] {
+            let sessions = aggregate.clone().with_sessions(&dashboard.trade_state, split).sessions;
+            assert_eq!(sessions.len(), 2);
+            assert_eq!((sessions[0].start, sessions[0].trades), (0, 4));
+            assert_eq!((sessions[1].start, sessions[1].trades), (DAY, 2));
+            assert!((sessions[0].pnl - 6.0).abs() < 1e-9);
+            assert!((sessions[1].pnl + 5.0).abs() < 1e-9);
+            assert!((sessions[0].max_drawdown - 4.0).abs() < 1e-9);
+            assert!((sessions[1].max_drawdown - 5.0).abs() < 1e-9);
+        }
+
+        let table = aggregate.with_sessions(&dashboard.trade_state, SessionSplit::UtcDay).sessions_table();
+        assert!(table.contains("1970-01-01 00:00:00") && table.contains("1970-01-02 00:00:00"));
+        assert!(table.contains("TOTAL") && table.contains("$1.00") && table.contains("$9.00"));
+    }
+}
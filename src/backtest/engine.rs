@@ -1,21 +1,528 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Instant;
-use log::{info, warn};
+use log::{info, warn, Level};
 use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
 
-use crate::core::{TradeState, Result, TradeError};
-use crate::utils::{FileDataSource, ParquetDataSource, extract_symbol_from_filename, MultiFileDataSource};
+use crate::core::{Liquidity, OrderBook, Trade, TradeState, Result, TradeError};
+use crate::utils::{open_data_source_with, SymbolExtractor, MultiFileDataSource, FeatureSource, FilteredDataSource, ResamplingDataSource, DedupDataSource};
 use crate::strategy::{Strategy, GptMarketMaker, GptMarketMakerConfig};
-use crate::trading::{BacktestTradeEmitter, BacktestConfig, TradeEmitter};
+use crate::trading::{BacktestTradeEmitter, BacktestConfig, TradeEmitter, SizingMode, CrossFileState, QuoteSimulator};
 use crate::core::DataSource;
+use crate::backtest::TradeDashboard;
+use crate::backtest::sweep::{self, ParamRange, SweepResult};
+use crate::pnl::{Method, PnlReport};
+use crate::utils::logging::{log_event, log_risk};
 
+#[derive(Clone)]
 pub struct BacktestEngine {
     config: BacktestConfig,
+    features: Option<FeatureSource>,
+    symbols: SymbolExtractor,
 }
 
 impl BacktestEngine {
     pub fn new(config: BacktestConfig) -> Self {
-        Self { config }
+        Self { config, features: None, symbols: SymbolExtractor::default() }
+    }
+    
+    /// Feed as-of feature values to the strategy alongside each order book
+    pub fn with_feature_source(mut self, features: FeatureSource) -> Self {
+        self.features = Some(features);
+        self
+    }
+    
+    /// Derive run symbols from file names with `symbols` instead of the filename prefix
+    pub fn with_symbol_extractor(mut self, symbols: SymbolExtractor) -> Self {
+        self.symbols = symbols;
+        self
+    }
+    
+    /// Dashboard over a finished run, with capital metrics at the engine's `margin_rate`
+    pub fn dashboard(&self, trade_state: TradeState) -> TradeDashboard {
+        TradeDashboard::from_config(trade_state, &self.config)
+    }
+    
+    /// Run a single order book through the strategy, resting quotes when `requote_on_move` is on
+    fn step(
+        &self,
+        order_book: &OrderBook,
+        strategy: &mut dyn Strategy,
+        executor: &mut dyn TradeEmitter,
+        trade_state: &mut TradeState,
+        quotes: Option<&mut QuoteSimulator>,
+    ) {
+        trade_state.note_book_time(order_book.current_time);
+        trade_state.mark(&order_book.symbol, order_book.mid_price());
+        if self.config.store_all_books {
+            trade_state.add_orderbook(self.stored_book(order_book));
+        }
+        
+        if self.force_exit(order_book, strategy, executor, trade_state) {
+            for cancelled in quotes.map(|quotes| quotes.cancel()).unwrap_or_default() {
+                strategy.update_position(&cancelled, false);
+            }
+            return;
+        }
+        
+        match quotes {
+            Some(quotes) => self.process_orderbook_quoting(order_book, strategy, trade_state, quotes),
+            None => self.process_orderbook(order_book, strategy, executor, trade_state),
+        }
+    }
+    
+    /// Run a single order book through the strategy and executor
+    fn process_orderbook(
+        &self,
+        order_book: &OrderBook,
+        strategy: &mut dyn Strategy,
+        executor: &mut dyn TradeEmitter,
+        trade_state: &mut TradeState,
+    ) {
+        self.feed_features(order_book, strategy);
+        
+        // Propose trades; each order is moved through the executor into the history, never cloned
+        for pending_order in self.admit_orders(order_book, strategy, trade_state) {
+            self.store_trade_book(order_book, trade_state);
+            self.execute(pending_order, order_book, executor, strategy, trade_state);
+        }
+    }
+    
+    /// Execute an admitted order against `order_book`, report the fill to the strategy and
+    /// record the executed trade at the tick-rounded quote rather than the slipped fill price.
+    /// The slippage is kept in `TradeState::slippage_costs` and only shows up in reports built
+    /// with `PnlReport::with_slippage_costs`; dashboard P&L, the equity curve and capital metrics
+    /// are pre-slippage. A partial fill is reported and recorded as a fill of the executed
+    /// quantity; the remainder is dropped. Returns whether anything filled.
+    fn execute(
+        &self,
+        order: Trade,
+        order_book: &OrderBook,
+        executor: &mut dyn TradeEmitter,
+        strategy: &mut dyn Strategy,
+        trade_state: &mut TradeState,
+    ) -> bool {
+        let quoted = self.config.instruments.get(&order.symbol).round_price(order.price, &order.side);
+        if let Some(mut executed_trade) = executor.execute_trade_against_book(Some(order), order_book) {
+            let partial = executed_trade.status == "partially_filled";
+            let filled = partial || executed_trade.status == "filled";
+            strategy.update_position(&executed_trade, filled);
+            if filled {
+                let slipped = (executed_trade.price - quoted).abs();
+                let cost = self.config.instruments.notional(&executed_trade.symbol, slipped, executed_trade.quantity);
+                if cost > 0.0 {
+                    trade_state.record_slippage(&executed_trade.id, cost);
+                }
+                executed_trade.price = quoted;
+                executed_trade.status = "filled".to_string();
+            }
+            trade_state.add(executed_trade);
+            return filled;
+        }
+        false
+    }
+    
+    /// Run a single order book with the strategy's orders resting as maker quotes.
+    /// Touched quotes fill at their own price; each new proposal cancel-replaces the resting quote.
+    fn process_orderbook_quoting(
+        &self,
+        order_book: &OrderBook,
+        strategy: &mut dyn Strategy,
+        trade_state: &mut TradeState,
+        quotes: &mut QuoteSimulator,
+    ) {
+        self.feed_features(order_book, strategy);
+        
+        for mut fill in quotes.on_book(order_book) {
+            self.config.charge_fee(&mut fill, Liquidity::Maker);
+            self.store_trade_book(order_book, trade_state);
+            strategy.update_position(&fill, true);
+            trade_state.add(fill);
+        }
+        
+        // Each proposed side is cancel-replaced by its last proposal; sides the strategy
+        // no longer quotes are pulled
+        let mut latest: [Option<Trade>; 2] = [None, None];
+        for quote in self.admit_orders(order_book, strategy, trade_state) {
+            match quote.side.as_str() {
+                "Buy" => latest[0] = Some(quote),
+                "Sell" => latest[1] = Some(quote),
+                _ => {}
+            }
+        }
+        let mut cancelled = Vec::new();
+        for (side, quote) in ["Buy", "Sell"].into_iter().zip(latest) {
+            match quote {
+                // Quotes face the same tick, lot and minimum-notional checks as taker orders
+                Some(mut quote) if !self.config.snap_to_grid(&mut quote) => {
+                    cancelled.extend(quotes.cancel_side(side));
+                    quote.status = "rejected".to_string();
+                    strategy.update_position(&quote, false);
+                    trade_state.add(quote);
+                }
+                Some(quote) => cancelled.extend(quotes.requote(quote)),
+                None => cancelled.extend(quotes.cancel_side(side)),
+            }
+        }
+        for cancelled in cancelled {
+            strategy.update_position(&cancelled, false);
+        }
+    }
+    
+    /// Close the whole position at the touch once it has been open longer than `max_hold_ms`
+    /// of book time, instead of consulting the strategy. Returns whether the exit filled; when
+    /// it doesn't, e.g. for a residual below the instrument's minimum notional, the strategy
+    /// still gets the book.
+    fn force_exit(
+        &self,
+        order_book: &OrderBook,
+        strategy: &mut dyn Strategy,
+        executor: &mut dyn TradeEmitter,
+        trade_state: &mut TradeState,
+    ) -> bool {
+        if self.config.max_hold_ms <= 0 {
+            return false;
+        }
+        let Some(entry_time) = trade_state.position_entry_time(&order_book.symbol) else {
+            return false;
+        };
+        let held_ms = order_book.current_time - entry_time;
+        if held_ms < self.config.max_hold_ms {
+            return false;
+        }
+        
+        let position = trade_state.get_position(&order_book.symbol);
+        let (side, touch) = if position > 0.0 {
+            ("Sell", order_book.best_bid())
+        } else {
+            ("Buy", order_book.best_ask())
+        };
+        let Some((price, _)) = touch else {
+            return false;
+        };
+        
+        // A residual the instrument can't trade is left to the strategy instead of being
+        // rejected again on every tick
+        let mut exit = Trade::new(order_book.current_time, order_book.symbol.clone(), side.to_string(), price, position.abs())
+            .with_reduce_only(true);
+        if !self.config.snap_to_grid(&mut exit) {
+            return false;
+        }
+        
+        log_risk("forced_exit", &[
+            ("symbol", order_book.symbol.clone()),
+            ("position", position.to_string()),
+            ("held_ms", held_ms.to_string()),
+            ("max_hold_ms", self.config.max_hold_ms.to_string()),
+        ]);
+        self.store_trade_book(order_book, trade_state);
+        let filled = self.execute(exit, order_book, executor, strategy, trade_state);
+        if filled {
+            Self::record_decision(trade_state, order_book, true, &format!("MAX_HOLD_EXIT: held {}ms", held_ms));
+        }
+        filled
+    }
+    
+    fn feed_features(&self, order_book: &OrderBook, strategy: &mut dyn Strategy) {
+        if let Some(values) = self.features.as_ref().and_then(|f| f.values_at(order_book.current_time)) {
+            strategy.on_features(values);
+        }
+    }
+    
+    /// Ask the strategy for this tick's orders and keep those that pass `admit_order`.
+    /// Records one decision for the tick: traded when any order got through, otherwise the
+    /// reason the first order was discarded.
+    fn admit_orders(
+        &self,
+        order_book: &OrderBook,
+        strategy: &mut dyn Strategy,
+        trade_state: &mut TradeState,
+    ) -> Vec<Trade> {
+        let proposals = strategy.propose_trades(order_book);
+        if proposals.is_empty() {
+            Self::record_decision(trade_state, order_book, false, strategy.decision_reason().unwrap_or("NO_PROPOSAL"));
+            return Vec::new();
+        }
+        let proposed = proposals.len();
+        let kept = self.screen_self_crosses(proposals, strategy, trade_state);
+        let mut skipped = (kept.len() < proposed).then_some("SELF_CROSS");
+        let admitted: Vec<Trade> = kept.into_iter()
+            .filter_map(|order| match self.admit_order(order, order_book, strategy, trade_state) {
+                Ok(order) => Some(order),
+                Err(reason) => {
+                    skipped.get_or_insert(reason);
+                    None
+                }
+            })
+            .collect();
+        
+        match skipped {
+            Some(reason) if admitted.is_empty() => Self::record_decision(trade_state, order_book, false, reason),
+            _ => Self::record_decision(trade_state, order_book, true, strategy.decision_reason().unwrap_or("PROPOSED")),
+        }
+        admitted
+    }
+    
+    /// Report proposals that would trade against each other: a buy priced at or above a sell
+    /// of the same symbol. Unless `allow_self_cross` is set, both sides of the cross are
+    /// discarded and reported back to the strategy as unfilled.
+    fn screen_self_crosses(
+        &self,
+        proposals: Vec<Trade>,
+        strategy: &mut dyn Strategy,
+        trade_state: &mut TradeState,
+    ) -> Vec<Trade> {
+        // Highest bid and lowest ask proposed per symbol
+        let mut bounds: HashMap<String, (f64, f64)> = HashMap::new();
+        for order in &proposals {
+            let (bid, ask) = bounds.entry(order.symbol.clone()).or_insert((f64::NEG_INFINITY, f64::INFINITY));
+            if order.side.eq_ignore_ascii_case("buy") {
+                *bid = bid.max(order.price);
+            } else {
+                *ask = ask.min(order.price);
+            }
+        }
+        
+        let mut kept = Vec::with_capacity(proposals.len());
+        for order in proposals {
+            let (bid, ask) = bounds[&order.symbol];
+            let crosses = if order.side.eq_ignore_ascii_case("buy") { order.price >= ask } else { order.price <= bid };
+            if !crosses {
+                kept.push(order);
+                continue;
+            }
+            
+            trade_state.record_self_cross();
+            log_risk("self_cross", &[
+                ("strategy", strategy.name().to_string()),
+                ("symbol", order.symbol.clone()),
+                ("side", order.side.clone()),
+                ("price", order.price.to_string()),
+                ("bid", bid.to_string()),
+                ("ask", ask.to_string()),
+                ("suppressed", (!self.config.allow_self_cross).to_string()),
+            ]);
+            if self.config.allow_self_cross {
+                kept.push(order);
+            } else {
+                strategy.update_position(&order, false);
+            }
+        }
+        kept
+    }
+    
+    /// Apply the causality, spread and sizing rules to a proposed order.
+    /// Discarded orders are reported back to the strategy as unfilled, and the reason returned.
+    fn admit_order(
+        &self,
+        mut pending_order: Trade,
+        order_book: &OrderBook,
+        strategy: &mut dyn Strategy,
+        trade_state: &TradeState,
+    ) -> std::result::Result<Trade, &'static str> {
+        
+        // Strategies only borrow the current book, so a future timestamp is the one leak left to catch
+        if self.config.strict_causality && pending_order.time > order_book.current_time {
+            if cfg!(debug_assertions) {
+                panic!("{} proposed a trade at {} from a book at {}",
+                       strategy.name(), pending_order.time, order_book.current_time);
+            }
+            log_risk("causality_violation", &[
+                ("strategy", strategy.name().to_string()),
+                ("trade_time", pending_order.time.to_string()),
+                ("book_time", order_book.current_time.to_string()),
+            ]);
+            strategy.update_position(&pending_order, false);
+            return Err("CAUSALITY_VIOLATION");
+        }
+        
+        // Can't make a market tighter than the book, so discard opening orders on tight spreads;
+        // closes still go through so inventory isn't trapped when the market tightens
+        if order_book.spread_pct() < self.config.min_spread_pct && !Self::reduces_position(&pending_order, trade_state) {
+            log_event(Level::Debug, "spread_discard", &[
+                ("symbol", order_book.symbol.clone()),
+                ("spread_pct", format!("{:.6}", order_book.spread_pct())),
+                ("min_spread_pct", self.config.min_spread_pct.to_string()),
+            ]);
+            strategy.update_position(&pending_order, false);
+            return Err("SPREAD_TOO_TIGHT");
+        }
+        
+        pending_order.quantity = self.resolve_quantity(&pending_order, order_book, trade_state);
+        
+        if self.config.max_order_volume > 0.0 && pending_order.quantity > self.config.max_order_volume {
+            log_risk("order_size_capped", &[
+                ("symbol", pending_order.symbol.clone()),
+                ("qty", pending_order.quantity.to_string()),
+                ("max_order_volume", self.config.max_order_volume.to_string()),
+            ]);
+            pending_order.quantity = self.config.max_order_volume;
+        }
+        
+        Ok(pending_order)
+    }
+    
+    /// Whether `order` is reduce-only or trades against the current position
+    fn reduces_position(order: &Trade, trade_state: &TradeState) -> bool {
+        let position = trade_state.get_position(&order.symbol);
+        order.reduce_only
+            || if order.side.eq_ignore_ascii_case("buy") { position < 0.0 } else { position > 0.0 }
+    }
+    
+    fn record_decision(trade_state: &mut TradeState, order_book: &OrderBook, traded: bool, reason: &str) {
+        if let Some(log) = trade_state.decision_log_mut() {
+            log.record(order_book.current_time, &order_book.symbol, traded, reason);
+        }
+    }
+    
+    /// Fresh trade history, collecting decisions when `record_decisions` is on
+    fn new_trade_state(&self) -> TradeState {
+        let mut trade_state = TradeState::new();
+        if self.config.record_decisions {
+            trade_state.enable_decision_log();
+        }
+        trade_state
+    }
+    
+    /// Log quote fill ratio and uptime for a quoting run
+    fn report_quotes(&self, quotes: Option<&QuoteSimulator>) {
+        if let Some(quotes) = quotes {
+            let stats = quotes.stats();
+            println!("Quotes: {} placed, {} cancelled, {} filled (fill ratio {:.2}%, uptime {:.2}%)",
+                     stats.placed, stats.cancelled, stats.filled,
+                     stats.fill_ratio() * 100.0, stats.uptime_ratio() * 100.0);
+            info!("Quote fill ratio {:.4}, uptime {:.4}", stats.fill_ratio(), stats.uptime_ratio());
+        }
+    }
+    
+    /// Print how often each decision reason occurred and how many orders self-crossed
+    fn report_decisions(&self, trade_state: &TradeState) {
+        if let Some(decisions) = trade_state.decision_log() {
+            print!("{}", decisions.summary());
+        }
+        if trade_state.self_cross_count() > 0 {
+            let outcome = if self.config.allow_self_cross { "executed" } else { "suppressed" };
+            println!("Self-crossing orders: {} ({})", trade_state.self_cross_count(), outcome);
+        }
+    }
+    
+    /// Run every book of a multi-file source, applying `cross_file_state` at each file boundary.
+    /// Returns the number of books processed.
+    fn process_files(
+        &self,
+        data_source: &mut MultiFileDataSource,
+        strategy: &mut dyn Strategy,
+        executor: &mut dyn TradeEmitter,
+        trade_state: &mut TradeState,
+        mut quotes: Option<&mut QuoteSimulator>,
+        pb: &ProgressBar,
+    ) -> Result<usize> {
+        let mut processed = 0;
+        let mut current_file = data_source.current_file();
+        let mut file_started = false;
+        
+        while let Some(order_book) = data_source.next_orderbook()? {
+            if data_source.current_file() != current_file {
+                current_file = data_source.current_file();
+                file_started = false;
+                if self.config.cross_file_state == CrossFileState::Reset {
+                    info!("Resetting {} at file {} (inventory {} left in trade history)",
+                          strategy.name(), current_file, trade_state.get_position(&order_book.symbol));
+                    strategy.reset();
+                }
+            }
+            if !file_started {
+                trade_state.note_file_start(order_book.current_time);
+                file_started = true;
+            }
+            
+            self.step(&order_book, strategy, executor, trade_state, quotes.as_deref_mut());
+            
+            processed += 1;
+            pb.set_position(processed as u64);
+        }
+        
+        Ok(processed)
+    }
+    
+    /// Order quantity under the configured sizing mode, resolved against the current book and equity
+    fn resolve_quantity(&self, order: &Trade, order_book: &OrderBook, trade_state: &TradeState) -> f64 {
+        match self.config.sizing_mode {
+            SizingMode::AbsoluteQuantity => order.quantity,
+            SizingMode::PercentOfEquity(pct) => {
+                if order.price <= 0.0 {
+                    return 0.0;
+                }
+                // Every symbol's position at its latest mid, net of fees
+                let equity = self.config.initial_equity - trade_state.total_fees()
+                    + trade_state.marked_pnl()
+                        .map(|(symbol, pnl)| self.config.instruments.notional(symbol, pnl, 1.0))
+                        .sum::<f64>();
+                (equity * pct / 100.0).max(0.0) / order.price
+            }
+            SizingMode::PercentOfTopOfBook(pct) => {
+                let top = if order.side == "Buy" { order_book.best_ask() } else { order_book.best_bid() };
+                top.map_or(0.0, |(_, quantity)| quantity * pct / 100.0)
+            }
+        }
+    }
+    
+    /// Wrap a data source in the configured update_id dedup, mid-price spike filter and
+    /// time-grid resampler, if any
+    fn filtered(&self, source: Box<dyn DataSource>) -> Box<dyn DataSource> {
+        let source: Box<dyn DataSource> = if self.config.dedup_update_ids {
+            Box::new(DedupDataSource::new(source))
+        } else {
+            source
+        };
+        let source: Box<dyn DataSource> = match self.config.mid_price_filter {
+            Some(filter_config) => Box::new(FilteredDataSource::new(source, filter_config)),
+            None => source,
+        };
+        if self.config.resample_interval_ms > 0 {
+            Box::new(ResamplingDataSource::new(source, self.config.resample_interval_ms))
+        } else {
+            source
+        }
+    }
+    
+    /// Keep the book a trade was made on, once per tick, unless `store_all_books` already
+    /// kept every book
+    fn store_trade_book(&self, order_book: &OrderBook, trade_state: &mut TradeState) {
+        if self.config.store_all_books {
+            return;
+        }
+        let already_stored = trade_state.get_orderbooks().last()
+            .is_some_and(|last| last.current_time == order_book.current_time && last.symbol == order_book.symbol);
+        if !already_stored {
+            trade_state.add_orderbook(self.stored_book(order_book));
+        }
+    }
+    
+    /// Copy of the book kept in `TradeState`, limited to `stored_book_depth` levels per side
+    fn stored_book(&self, order_book: &OrderBook) -> OrderBook {
+        if self.config.stored_book_depth > 0 {
+            order_book.truncated(self.config.stored_book_depth)
+        } else {
+            order_book.clone()
+        }
+    }
+    
+    /// Run in-memory order books through `strategy`, without a data source, filters or progress
+    /// output. Meant for embedding and benchmarks; quoting follows `requote_on_move`.
+    pub fn run_order_books(
+        &self,
+        order_books: impl IntoIterator<Item = OrderBook>,
+        strategy: &mut dyn Strategy,
+    ) -> TradeState {
+        let mut trade_state = self.new_trade_state();
+        let mut executor = BacktestTradeEmitter::new(self.config.clone());
+        let mut quotes = self.config.requote_on_move.then(QuoteSimulator::new);
+        for order_book in order_books {
+            self.step(&order_book, strategy, &mut executor, &mut trade_state, quotes.as_mut());
+        }
+        trade_state
     }
     
     pub fn run_backtest(
@@ -29,14 +536,15 @@ impl BacktestEngine {
             .ok_or_else(|| TradeError::DataLoadingError("Invalid file path".to_string()))?
             .to_str()
             .ok_or_else(|| TradeError::DataLoadingError("Invalid filename encoding".to_string()))?;
-        let symbol = extract_symbol_from_filename(filename);
+        let symbol = self.symbols.extract(filename);
+        self.config.instruments.warn_if_missing(&symbol);
         
         println!("Processing file: {:?}", data_file);
         println!("Extracted symbol: {}", symbol);
         println!("Using strategy: {}", strategy_name);
         
         // Initialize components
-        let mut trade_state = TradeState::new();
+        let mut trade_state = self.new_trade_state();
         
         // Create strategy based on name
         let mut strategy: Box<dyn Strategy> = match strategy_name {
@@ -51,17 +559,10 @@ impl BacktestEngine {
         
         // Create executor
         let mut executor = BacktestTradeEmitter::new(self.config.clone());
+        let mut quotes = self.config.requote_on_move.then(QuoteSimulator::new);
         
-        // Create data source based on file extension
-        let mut data_source: Box<dyn DataSource> = if data_file.extension().and_then(|s| s.to_str()) == Some("parquet") {
-            let mut source = ParquetDataSource::new(data_file)?;
-            source.count_messages()?;
-            Box::new(source)
-        } else {
-            let mut source = FileDataSource::new(data_file)?.with_batch_size(10000);
-            source.count_messages()?;
-            Box::new(source)
-        };
+        let data_source = open_data_source_with(data_file, self.config.lenient_parquet)?;
+        let mut data_source = self.filtered(data_source);
         
         // Count messages for progress tracking
         let total_messages = data_source.total_count().unwrap_or(0);
@@ -78,22 +579,7 @@ impl BacktestEngine {
         
         // Process each orderbook
         while let Some(order_book) = data_source.next_orderbook()? {
-            // Propose trade
-            if let Some(pending_order) = strategy.propose_trade(&order_book) {
-                trade_state.add(pending_order.clone());
-                trade_state.add_orderbook(order_book.clone());
-                
-                // Execute trade
-                if let Some(executed_trade) = executor.execute_trade(Some(pending_order)) {
-                    trade_state.change_status(&executed_trade.id, executed_trade.status.clone());
-                    
-                    if executed_trade.status == "filled" {
-                        strategy.update_position(&executed_trade, true);
-                    } else {
-                        strategy.update_position(&executed_trade, false);
-                    }
-                }
-            }
+            self.step(&order_book, strategy.as_mut(), &mut executor, &mut trade_state, quotes.as_mut());
             
             // Progress tracking
             processed += 1;
@@ -104,6 +590,9 @@ impl BacktestEngine {
             }
         }
         
+        self.report_quotes(quotes.as_ref());
+        self.report_decisions(&trade_state);
+        
         let execution_time = start_time.elapsed();
         println!("Backtest completed in {:.2} seconds", execution_time.as_secs_f64());
         info!("Backtest completed in {:.2} seconds", execution_time.as_secs_f64());
@@ -122,28 +611,22 @@ impl BacktestEngine {
             .ok_or_else(|| TradeError::DataLoadingError("Invalid file path".to_string()))?
             .to_str()
             .ok_or_else(|| TradeError::DataLoadingError("Invalid filename encoding".to_string()))?;
-        let symbol = extract_symbol_from_filename(filename);
+        let symbol = self.symbols.extract(filename);
+        self.config.instruments.warn_if_missing(&symbol);
         
         println!("Processing file: {:?}", data_file);
         println!("Extracted symbol: {}", symbol);
         println!("Using custom strategy: {}", strategy.name());
         
         // Initialize components
-        let mut trade_state = TradeState::new();
+        let mut trade_state = self.new_trade_state();
         
         // Create executor
         let mut executor = BacktestTradeEmitter::new(self.config.clone());
+        let mut quotes = self.config.requote_on_move.then(QuoteSimulator::new);
         
-        // Create data source based on file extension
-        let mut data_source: Box<dyn DataSource> = if data_file.extension().and_then(|s| s.to_str()) == Some("parquet") {
-            let mut source = ParquetDataSource::new(data_file)?;
-            source.count_messages()?;
-            Box::new(source)
-        } else {
-            let mut source = FileDataSource::new(data_file)?.with_batch_size(10000);
-            source.count_messages()?;
-            Box::new(source)
-        };
+        let data_source = open_data_source_with(data_file, self.config.lenient_parquet)?;
+        let mut data_source = self.filtered(data_source);
         
         // Count messages for progress tracking
         let total_messages = data_source.total_count().unwrap_or(0);
@@ -169,22 +652,7 @@ impl BacktestEngine {
         
         // Process each orderbook
         while let Some(order_book) = data_source.next_orderbook()? {
-            // Propose trade
-            if let Some(pending_order) = strategy.propose_trade(&order_book) {
-                trade_state.add(pending_order.clone());
-                trade_state.add_orderbook(order_book.clone());
-                
-                // Execute trade
-                if let Some(executed_trade) = executor.execute_trade(Some(pending_order)) {
-                    trade_state.change_status(&executed_trade.id, executed_trade.status.clone());
-                    
-                    if executed_trade.status == "filled" {
-                        strategy.update_position(&executed_trade, true);
-                    } else {
-                        strategy.update_position(&executed_trade, false);
-                    }
-                }
-            }
+            self.step(&order_book, strategy.as_mut(), &mut executor, &mut trade_state, quotes.as_mut());
             
             processed += 1;
             pb.set_position(processed as u64);
@@ -192,6 +660,9 @@ impl BacktestEngine {
         
         pb.finish_with_message(format!("✅ Analyzed {} orderbook messages in {:.2}s", processed, start_time.elapsed().as_secs_f64()));
         
+        self.report_quotes(quotes.as_ref());
+        self.report_decisions(&trade_state);
+        
         let execution_time = start_time.elapsed();
         info!("Backtest completed in {:.2} seconds ({} messages processed)", 
              execution_time.as_secs_f64(), processed);
@@ -199,6 +670,80 @@ impl BacktestEngine {
         Ok(trade_state)
     }
     
+    /// Backtest `data_file` once per seed in `base_seed..base_seed + runs`, in parallel.
+    /// Returns each run's seed and trades in seed order.
+    pub fn run_monte_carlo<F>(
+        &self,
+        data_file: &Path,
+        runs: usize,
+        base_seed: u64,
+        make_strategy: F,
+    ) -> Result<Vec<(u64, TradeState)>>
+    where
+        F: Fn() -> Box<dyn Strategy> + Sync,
+    {
+        (0..runs as u64)
+            .into_par_iter()
+            .map(|run| {
+                let seed = base_seed + run;
+                let mut engine = self.clone();
+                engine.config.seed = Some(seed);
+                engine.run_backtest_with_custom_strategy(data_file, make_strategy())
+                    .map(|trade_state| (seed, trade_state))
+            })
+            .collect()
+    }
+    
+    /// Backtest `data_file` with `base_config` once per combination of the `sweep` ranges,
+    /// in parallel. Results are sorted by net P&L, best first; combinations the strategy
+    /// rejects as invalid are skipped with a warning.
+    pub fn run_parameter_sweep(
+        &self,
+        data_file: &Path,
+        base_config: &GptMarketMakerConfig,
+        sweep: &[ParamRange],
+    ) -> Result<Vec<SweepResult>> {
+        let filename = data_file.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| TradeError::DataLoadingError("Invalid file path".to_string()))?;
+        let symbol = self.symbols.extract(filename);
+        
+        let configs = sweep::combinations(sweep)?
+            .into_iter()
+            .map(|params| -> Result<_> { Ok((sweep::apply_params(base_config, &params)?, params)) })
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut results = configs.into_par_iter()
+            .filter_map(|(config, params)| match config.validate() {
+                Ok(()) => Some((config, params)),
+                Err(e) => {
+                    warn!("Skipping sweep combination {:?}: {}", params, e);
+                    None
+                }
+            })
+            .map(|(config, params)| -> Result<SweepResult> {
+                let strategy = Box::new(GptMarketMaker::new(symbol.clone(), config));
+                let trade_state = self.run_backtest_with_custom_strategy(data_file, strategy)?;
+                let trades = trade_state.get_all_trades();
+                let report = PnlReport::new()
+                    .with_instruments(self.config.instruments.clone())
+                    .with_mark_prices(trade_state.last_mids())
+                    .with_slippage_costs(trade_state.slippage_costs().clone());
+                let costs = report.cost_attribution(trades, Method::Fifo);
+                Ok(SweepResult {
+                    params,
+                    total_pnl: costs.frictionless_pnl,
+                    net_pnl: costs.net_pnl,
+                    max_drawdown: sweep::max_drawdown(&report.equity_curve(trades, Method::Fifo)),
+                    trade_count: trade_state.get_trades_history().len(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        results.sort_by(|a, b| b.net_pnl.total_cmp(&a.net_pnl));
+        Ok(results)
+    }
+    
     pub fn run_backtest_with_multiple_files(
         &self,
         file_paths: &[std::path::PathBuf],
@@ -216,20 +761,23 @@ impl BacktestEngine {
             .ok_or_else(|| TradeError::DataLoadingError("Invalid file path".to_string()))?
             .to_str()
             .ok_or_else(|| TradeError::DataLoadingError("Invalid filename encoding".to_string()))?;
-        let symbol = extract_symbol_from_filename(filename);
+        let symbol = self.symbols.extract(filename);
+        self.config.instruments.warn_if_missing(&symbol);
         
         println!("Processing {} files as continuous range", file_paths.len());
         println!("Extracted symbol: {}", symbol);
         println!("Using strategy: {}", strategy.name());
         
         // Initialize components
-        let mut trade_state = TradeState::new();
+        let mut trade_state = self.new_trade_state();
         
         // Create executor
         let mut executor = BacktestTradeEmitter::new(self.config.clone());
+        let mut quotes = self.config.requote_on_move.then(QuoteSimulator::new);
         
         // Create multi-file data source
-        let mut data_source = MultiFileDataSource::new(file_paths.to_vec())?;
+        let mut data_source = MultiFileDataSource::open(file_paths.to_vec(), self.config.lenient_parquet)?
+            .map_files(|source| self.filtered(source));
         
         // Count messages for progress tracking
         let total_messages = data_source.total_count().unwrap_or(0);
@@ -252,37 +800,917 @@ impl BacktestEngine {
         pb.set_message(format!("Analyzing {} files for {}", file_paths.len(), symbol));
         pb.enable_steady_tick(std::time::Duration::from_millis(100));
         
-        let mut processed = 0;
-        
-        // Process each orderbook
-        while let Some(order_book) = data_source.next_orderbook()? {
-            // Propose trade
-            if let Some(pending_order) = strategy.propose_trade(&order_book) {
-                trade_state.add(pending_order.clone());
-                trade_state.add_orderbook(order_book.clone());
-                
-                // Execute trade
-                if let Some(executed_trade) = executor.execute_trade(Some(pending_order)) {
-                    trade_state.change_status(&executed_trade.id, executed_trade.status.clone());
-                    
-                    if executed_trade.status == "filled" {
-                        strategy.update_position(&executed_trade, true);
-                    } else {
-                        strategy.update_position(&executed_trade, false);
-                    }
-                }
-            }
-            
-            processed += 1;
-            pb.set_position(processed as u64);
-        }
+        let processed = self.process_files(&mut data_source, strategy.as_mut(), &mut executor, &mut trade_state, quotes.as_mut(), &pb)?;
         
         pb.finish_with_message(format!("✅ Analyzed {} messages from {} files in {:.2}s", processed, file_paths.len(), start_time.elapsed().as_secs_f64()));
         
+        self.report_quotes(quotes.as_ref());
+        self.report_decisions(&trade_state);
+        
         let execution_time = start_time.elapsed();
         info!("Backtest completed in {:.2} seconds ({} messages processed from {} files)", 
              execution_time.as_secs_f64(), processed, file_paths.len());
         
         Ok(trade_state)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Trade;
+    use crate::backtest::MonteCarloSummary;
+    use crate::utils::test_dir::TestDir;
+
+    /// Strategy that proposes a small buy on every book
+    struct AlwaysBuy {
+        position: f64,
+    }
+
+    impl Strategy for AlwaysBuy {
+        fn name(&self) -> &str {
+            "always_buy"
+        }
+
+        fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade> {
+            let (best_ask, _) = order_book.best_ask()?;
+            Some(Trade::new(
+                order_book.current_time,
+                order_book.symbol.clone(),
+                "Buy".to_string(),
+                best_ask,
+                0.01,
+            ))
+        }
+
+        fn update_position(&mut self, trade: &Trade, filled: bool) {
+            if filled {
+                self.position += trade.quantity;
+            }
+        }
+
+        fn get_position(&self, _symbol: &str) -> f64 {
+            self.position
+        }
+
+        fn reset(&mut self) {
+            self.position = 0.0;
+        }
+    }
+
+    /// Strategy proposing whatever `propose` returns for each book, given the latest external
+    /// features. It tracks no position; `with_reasons` sets the decision reasons it reports.
+    struct FnStrategy<F> {
+        name: &'static str,
+        propose: F,
+        features: HashMap<String, f64>,
+        reasons: Option<(&'static str, &'static str)>,
+        proposed: bool,
+    }
+
+    impl<F> FnStrategy<F>
+    where
+        F: FnMut(&OrderBook, &HashMap<String, f64>) -> Vec<Trade> + Send + Sync,
+    {
+        fn new(name: &'static str, propose: F) -> Self {
+            Self { name, propose, features: HashMap::new(), reasons: None, proposed: false }
+        }
+
+        /// Report `traded` on ticks with proposals and `skipped` on ticks without
+        fn with_reasons(mut self, traded: &'static str, skipped: &'static str) -> Self {
+            self.reasons = Some((traded, skipped));
+            self
+        }
+    }
+
+    impl<F> Strategy for FnStrategy<F>
+    where
+        F: FnMut(&OrderBook, &HashMap<String, f64>) -> Vec<Trade> + Send + Sync,
+    {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn on_features(&mut self, features: &HashMap<String, f64>) {
+            self.features = features.clone();
+        }
+
+        fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade> {
+            self.propose_trades(order_book).into_iter().next()
+        }
+
+        fn propose_trades(&mut self, order_book: &OrderBook) -> Vec<Trade> {
+            let proposals = (self.propose)(order_book, &self.features);
+            self.proposed = !proposals.is_empty();
+            proposals
+        }
+
+        fn decision_reason(&self) -> Option<&str> {
+            self.reasons.map(|(traded, skipped)| if self.proposed { traded } else { skipped })
+        }
+
+        fn update_position(&mut self, _trade: &Trade, _filled: bool) {}
+
+        fn get_position(&self, _symbol: &str) -> f64 {
+            0.0
+        }
+
+        fn reset(&mut self) {
+            self.features.clear();
+        }
+    }
+
+    /// A 0.01 order on `order_book` at `price`
+    fn order(order_book: &OrderBook, side: &str, price: f64) -> Trade {
+        Trade::new(order_book.current_time, order_book.symbol.clone(), side.to_string(), price, 0.01)
+    }
+
+    /// Buy at the best ask, if there is one
+    fn buy_at_ask(order_book: &OrderBook) -> Vec<Trade> {
+        order_book.best_ask().map(|(ask, _)| order(order_book, "Buy", ask)).into_iter().collect()
+    }
+
+    fn deterministic_config() -> BacktestConfig {
+        BacktestConfig {
+            fill_rate: 1.0,
+            rejection_rate: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn deep_book(levels: usize, time: i64) -> OrderBook {
+        let bids = (0..levels).map(|i| (100.0 - i as f64 * 0.1, 1.0)).collect();
+        let asks = (0..levels).map(|i| (100.1 + i as f64 * 0.1, 1.0)).collect();
+        OrderBook::new("BTCUSDT".to_string(), bids, asks, time)
+    }
+
+    #[test]
+    fn test_stored_books_are_truncated() {
+        let engine = BacktestEngine::new(BacktestConfig {
+            stored_book_depth: 3,
+            ..deterministic_config()
+        });
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+
+        engine.process_orderbook(&deep_book(1500, 1_000), &mut strategy, &mut executor, &mut trade_state);
+
+        let stored = &trade_state.get_orderbooks()[0];
+        assert_eq!(stored.bids.len(), 3);
+        assert_eq!(stored.asks.len(), 3);
+        assert_eq!(stored.mid_price(), deep_book(1500, 1_000).mid_price());
+        assert_eq!(strategy.get_position("BTCUSDT"), 0.01);
+    }
+
+    #[test]
+    fn test_min_spread_gate() {
+        let engine = BacktestEngine::new(BacktestConfig {
+            min_spread_pct: 0.001,
+            ..deterministic_config()
+        });
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+
+        // 1 bp spread is below the 10 bp gate
+        let tight = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.01, 1.0)], 1_000);
+        engine.process_orderbook(&tight, &mut strategy, &mut executor, &mut trade_state);
+        assert!(trade_state.get_all_trades().is_empty());
+        assert!(trade_state.get_orderbooks().is_empty());
+        assert_eq!(strategy.get_position("BTCUSDT"), 0.0);
+
+        // ~100 bp spread passes
+        let wide = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(101.0, 1.0)], 2_000);
+        engine.process_orderbook(&wide, &mut strategy, &mut executor, &mut trade_state);
+        assert_eq!(trade_state.get_trades_history().len(), 1);
+        assert_eq!(strategy.get_position("BTCUSDT"), 0.01);
+    }
+
+    #[test]
+    fn test_min_spread_gate_lets_closes_through() {
+        let engine = BacktestEngine::new(BacktestConfig {
+            min_spread_pct: 0.001,
+            ..deterministic_config()
+        });
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+        let mut short = Trade::new(500, "BTCUSDT".to_string(), "Sell".to_string(), 100.0, 0.01);
+        short.status = "filled".to_string();
+        trade_state.add(short);
+
+        // The buy on the 1 bp book closes the short, so it isn't gated
+        let tight = |time: i64| OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.01, 1.0)], time);
+        engine.process_orderbook(&tight(1_000), &mut strategy, &mut executor, &mut trade_state);
+        assert_eq!(trade_state.get_trades_history().len(), 2);
+        assert_eq!(trade_state.get_position("BTCUSDT"), 0.0);
+
+        // Flat again, the next buy would open a position and is discarded
+        engine.process_orderbook(&tight(2_000), &mut strategy, &mut executor, &mut trade_state);
+        assert_eq!(trade_state.get_trades_history().len(), 2);
+    }
+
+    #[test]
+    fn test_min_spread_gate_disabled_by_default() {
+        let engine = BacktestEngine::new(deterministic_config());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+
+        let tight = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.01, 1.0)], 1_000);
+        engine.process_orderbook(&tight, &mut strategy, &mut executor, &mut trade_state);
+        assert_eq!(trade_state.get_trades_history().len(), 1);
+    }
+
+    #[test]
+    fn test_stored_books_keep_full_depth_by_default() {
+        let engine = BacktestEngine::new(deterministic_config());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+
+        engine.process_orderbook(&deep_book(20, 1_000), &mut strategy, &mut executor, &mut trade_state);
+
+        assert_eq!(trade_state.get_orderbooks()[0].bids.len(), 20);
+    }
+
+    #[test]
+    fn test_order_size_comes_from_strategy() {
+        let engine = BacktestEngine::new(deterministic_config());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+
+        engine.process_orderbook(&deep_book(1, 1000), &mut strategy, &mut executor, &mut trade_state);
+
+        assert_eq!(trade_state.get_all_trades()[0].quantity, 0.01);
+        assert_eq!(strategy.position, 0.01);
+    }
+
+    #[test]
+    fn test_max_order_volume_caps_strategy_size() {
+        let config = BacktestConfig {
+            max_order_volume: 0.004,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+
+        engine.process_orderbook(&deep_book(1, 1000), &mut strategy, &mut executor, &mut trade_state);
+
+        assert_eq!(trade_state.get_all_trades()[0].quantity, 0.004);
+        assert_eq!(strategy.position, 0.004);
+    }
+
+    #[test]
+    fn test_lot_rounded_fill_keeps_strategy_and_history_in_step() {
+        let instruments = crate::trading::InstrumentSpecRegistry::new()
+            .with_spec("BTCUSDT", crate::trading::InstrumentSpec { tick_size: 0.1, lot_size: 0.001, ..Default::default() });
+        let config = BacktestConfig { slippage_bps: 0.0, max_order_volume: 0.0015, instruments, ..deterministic_config() };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let book = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.17, 1.0)], 1_000);
+        engine.process_orderbook(&book, &mut strategy, &mut executor, &mut trade_state);
+
+        // 0.0015 rounds down to one lot and the 100.17 quote down to the 100.1 tick
+        let fill = &trade_state.get_trades_history()[0];
+        assert_eq!(fill.status, "filled");
+        assert!((fill.quantity - 0.001).abs() < 1e-12);
+        assert!((fill.price - 100.1).abs() < 1e-9);
+        assert!((strategy.position - 0.001).abs() < 1e-12);
+        assert_eq!(strategy.position, trade_state.get_position("BTCUSDT"));
+    }
+
+    #[test]
+    fn test_position_held_past_max_hold_is_force_closed() {
+        let config = BacktestConfig {
+            max_hold_ms: 1000,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+
+        // AlwaysBuy never sells; the book at 2000 is 1000ms after the opening fill
+        for time in [1000, 1500, 2000, 2500] {
+            engine.step(&deep_book(1, time), &mut strategy, &mut executor, &mut trade_state, None);
+        }
+
+        let trades = trade_state.get_all_trades();
+        let sides: Vec<&str> = trades.iter().map(|t| t.side.as_str()).collect();
+        assert_eq!(sides, ["Buy", "Buy", "Sell", "Buy"]);
+        assert_eq!(trades[2].time, 2000);
+        assert!((trades[2].quantity - 0.02).abs() < 1e-12);
+        assert!(trades[2].reduce_only);
+        assert!((trade_state.get_position("BTCUSDT") - 0.01).abs() < 1e-12);
+        assert_eq!(trade_state.position_entry_time("BTCUSDT"), Some(2500));
+    }
+
+    #[test]
+    fn test_untradeable_residual_falls_through_to_strategy() {
+        let instruments = crate::trading::InstrumentSpecRegistry::new()
+            .with_spec("BTCUSDT", crate::trading::InstrumentSpec { min_notional: 5.0, ..Default::default() });
+        let config = BacktestConfig { max_hold_ms: 1000, slippage_bps: 0.0, instruments, ..deterministic_config() };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        // A 0.01 residual from an earlier partial fill, worth about 1.0 against the 5.0 minimum
+        let mut residual = Trade::new(0, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 0.01);
+        residual.status = "filled".to_string();
+        trade_state.add(residual);
+
+        let mut strategy = FnStrategy::new("big_buyer", |book, _| {
+            book.best_ask().map(|(ask, _)| Trade::new(book.current_time, book.symbol.clone(), "Buy".to_string(), ask, 1.0)).into_iter().collect()
+        });
+        // No exit is sent for the residual, and the strategy still trades
+        engine.step(&deep_book(1, 2000), &mut strategy, &mut executor, &mut trade_state, None);
+        let trades = trade_state.get_all_trades();
+        assert_eq!(trades.len(), 2);
+        assert!(trades.iter().all(|t| !t.reduce_only));
+        assert!((trade_state.get_position("BTCUSDT") - 1.01).abs() < 1e-12);
+
+        // Once the position is large enough to trade, the forced exit closes all of it
+        engine.step(&deep_book(1, 3000), &mut strategy, &mut executor, &mut trade_state, None);
+        assert!(trade_state.get_all_trades()[2].reduce_only);
+        assert!(trade_state.get_position("BTCUSDT").abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dashboard_margin_uses_engine_margin_rate() {
+        let config = BacktestConfig {
+            margin_rate: 0.2,
+            slippage_bps: 0.0,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        engine.process_orderbook(&deep_book(1, 1000), &mut strategy, &mut executor, &mut trade_state);
+
+        let mut dashboard = engine.dashboard(trade_state);
+        assert_eq!(dashboard.margin_rate(), 0.2);
+        // 0.01 marked at the 100.05 mid, 20% of it held as margin
+        let metrics = dashboard.get_capital_metrics("BTCUSDT");
+        assert!((metrics.peak_margin_requirement - 0.01 * 100.05 * 0.2).abs() < 1e-9);
+
+        assert_eq!(BacktestConfig::default().margin_rate, crate::trading::DEFAULT_MARGIN_RATE);
+    }
+
+    #[test]
+    fn test_percent_of_equity_size_scales_with_balance() {
+        let first_quantity = |initial_equity: f64| {
+            let config = BacktestConfig {
+                sizing_mode: SizingMode::PercentOfEquity(1.0),
+                initial_equity,
+                ..deterministic_config()
+            };
+            let engine = BacktestEngine::new(config.clone());
+            let mut strategy = AlwaysBuy { position: 0.0 };
+            let mut executor = BacktestTradeEmitter::new(config);
+            let mut trade_state = TradeState::new();
+            engine.process_orderbook(&deep_book(1, 1000), &mut strategy, &mut executor, &mut trade_state);
+            trade_state.get_all_trades()[0].quantity
+        };
+
+        let small = first_quantity(10_000.0);
+        let large = first_quantity(20_000.0);
+
+        assert!((small - 100.0 / 100.1).abs() < 1e-9);
+        assert!((large - 2.0 * small).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_percent_of_equity_marks_every_symbol() {
+        let instruments = crate::trading::InstrumentSpecRegistry::new()
+            .with_spec("ETHUSDT", crate::trading::InstrumentSpec { contract_size: 10.0, ..Default::default() });
+        let config = BacktestConfig {
+            sizing_mode: SizingMode::PercentOfEquity(10.0),
+            initial_equity: 10_000.0,
+            instruments,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        let mut eth = Trade::new(500, "ETHUSDT".to_string(), "Buy".to_string(), 2_000.0, 1.0);
+        eth.status = "filled".to_string();
+        eth.fee = 5.0;
+        trade_state.add(eth);
+        trade_state.mark("ETHUSDT", 2_100.0);
+
+        engine.process_orderbook(&deep_book(1, 1000), &mut AlwaysBuy { position: 0.0 }, &mut executor, &mut trade_state);
+
+        // The ETH long is up 100 on 10 contracts per unit, less its 5 fee: equity 10_995
+        let btc = &trade_state.get_all_trades()[1];
+        assert!((btc.quantity - 1_099.5 / 100.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_monte_carlo_runs_every_seed() {
+        let dir = TestDir::new("monte_carlo");
+        let path = dir.join("BTCUSDT_mc.jsonl");
+        let lines: Vec<String> = (0..20)
+            .map(|i| format!(r#"{{"ts":{},"data":{{"b":[["{}","1.0"]],"a":[["{}","1.0"]]}}}}"#,
+                             1000 + i * 100, 100.0 + i as f64 * 0.1, 100.1 + i as f64 * 0.1))
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let engine = BacktestEngine::new(BacktestConfig {
+            fill_rate: 0.5,
+            rejection_rate: 0.0,
+            ..Default::default()
+        });
+        let runs = engine.run_monte_carlo(&path, 4, 7, || Box::new(AlwaysBuy { position: 0.0 })).unwrap();
+        let seeds: Vec<u64> = runs.iter().map(|(seed, _)| *seed).collect();
+        assert_eq!(seeds, vec![7, 8, 9, 10]);
+
+        let report = crate::pnl::PnlReport::new();
+        let summary = MonteCarloSummary::from_trade_states(&runs, &report, crate::pnl::Method::Fifo);
+        assert_eq!(summary.runs.len(), 4);
+        assert!(summary.min <= summary.mean && summary.mean <= summary.max);
+        assert!(summary.std >= 0.0);
+        assert!(summary.to_table().contains("Monte Carlo over 4 execution seeds"));
+
+        // The same seed replays the same fills
+        let again = engine.run_monte_carlo(&path, 1, 7, || Box::new(AlwaysBuy { position: 0.0 })).unwrap();
+        assert_eq!(again[0].1.get_trades_history().len(), runs[0].1.get_trades_history().len());
+    }
+
+    #[test]
+    fn test_decision_log_records_strategy_and_engine_reasons() {
+        let config = BacktestConfig {
+            min_spread_pct: 0.001,
+            record_decisions: true,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        // Buys on odd ticks and reports a cooldown on even ones
+        let mut tick = 0;
+        let mut strategy = FnStrategy::new("scripted", move |book, _| {
+            tick += 1;
+            if tick % 2 == 0 { Vec::new() } else { buy_at_ask(book) }
+        }).with_reasons("OPEN_BUY", "VOLATILITY_COOLDOWN: 1.0s remaining");
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = engine.new_trade_state();
+
+        let book = |spread: f64, time: i64| OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.0 + spread, 1.0)], time);
+        for (spread, time) in [(1.0, 1000), (1.0, 2000), (0.01, 3000), (1.0, 4000), (1.0, 5000)] {
+            engine.process_orderbook(&book(spread, time), &mut strategy, &mut executor, &mut trade_state);
+        }
+
+        let log = trade_state.decision_log().unwrap();
+        let outcomes: Vec<(i64, bool, &str)> = log.decisions().iter()
+            .map(|d| (d.time, d.traded, d.reason_kind()))
+            .collect();
+        assert_eq!(outcomes, vec![
+            (1000, true, "OPEN_BUY"),
+            (2000, false, "VOLATILITY_COOLDOWN"),
+            (3000, false, "SPREAD_TOO_TIGHT"),
+            (4000, false, "VOLATILITY_COOLDOWN"),
+            (5000, true, "OPEN_BUY"),
+        ]);
+        assert_eq!(log.reason_counts(), vec![
+            ("OPEN_BUY".to_string(), 2),
+            ("VOLATILITY_COOLDOWN".to_string(), 2),
+            ("SPREAD_TOO_TIGHT".to_string(), 1),
+        ]);
+        assert!(log.summary().starts_with("Decisions: 5 ticks, 2 traded, 3 skipped"));
+
+        // Off by default
+        assert!(BacktestEngine::new(deterministic_config()).new_trade_state().decision_log().is_none());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "future_stamped proposed a trade at 2000 from a book at 1000")]
+    fn test_strict_causality_rejects_future_trade() {
+        let config = BacktestConfig {
+            strict_causality: true,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+
+        // Stamps its orders one second after the book it saw
+        let mut strategy = FnStrategy::new("future_stamped", |book, _| {
+            let mut trades = buy_at_ask(book);
+            trades.iter_mut().for_each(|trade| trade.time += 1000);
+            trades
+        });
+        engine.process_orderbook(&deep_book(1, 1000), &mut strategy, &mut executor, &mut trade_state);
+    }
+
+    #[test]
+    fn test_feature_source_drives_strategy() {
+        let dir = TestDir::new("features");
+        let path = dir.join("signal.csv");
+        std::fs::write(&path, "timestamp,signal,funding\n1500,1.0,0.0001\n3500,-1.0,0.0002\n").unwrap();
+
+        let features = FeatureSource::from_csv(&path).unwrap();
+        assert_eq!(features.len(), 2);
+        assert!(features.values_at(1000).is_none());
+        assert_eq!(features.values_at(2000).unwrap()["funding"], 0.0001);
+
+        let engine = BacktestEngine::new(deterministic_config()).with_feature_source(features);
+        // Buys only while the external "signal" feature is positive
+        let mut strategy = FnStrategy::new("signal_follower", |book, features| {
+            if features.get("signal").copied().unwrap_or(0.0) > 0.0 { buy_at_ask(book) } else { Vec::new() }
+        });
+        let mut executor = BacktestTradeEmitter::new(deterministic_config());
+        let mut trade_state = TradeState::new();
+
+        for time in [1000, 2000, 3000, 4000] {
+            engine.process_orderbook(&deep_book(1, time), &mut strategy, &mut executor, &mut trade_state);
+        }
+
+        // Signal is positive from 1500 until 3500
+        let times: Vec<i64> = trade_state.get_all_trades().iter().map(|t| t.time).collect();
+        assert_eq!(times, vec![2000, 3000]);
+    }
+
+    /// In-memory data source standing in for one file
+    struct VecSource {
+        books: Vec<OrderBook>,
+        index: usize,
+    }
+
+    impl DataSource for VecSource {
+        fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+            let book = self.books.get(self.index).cloned();
+            self.index += 1;
+            Ok(book)
+        }
+
+        fn reset(&mut self) -> Result<()> {
+            self.index = 0;
+            Ok(())
+        }
+
+        fn total_count(&self) -> Option<usize> {
+            Some(self.books.len())
+        }
+    }
+
+    /// Strategy that buys when flat and sells when long
+    struct RoundTrip {
+        position: f64,
+    }
+
+    impl Strategy for RoundTrip {
+        fn name(&self) -> &str {
+            "round_trip"
+        }
+
+        fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade> {
+            let (side, price) = if self.position > 0.0 {
+                ("Sell", order_book.best_bid()?.0)
+            } else {
+                ("Buy", order_book.best_ask()?.0)
+            };
+            Some(Trade::new(order_book.current_time, order_book.symbol.clone(), side.to_string(), price, 0.01))
+        }
+
+        fn update_position(&mut self, trade: &Trade, filled: bool) {
+            if filled {
+                self.position += if trade.side == "Buy" { trade.quantity } else { -trade.quantity };
+            }
+        }
+
+        fn get_position(&self, _symbol: &str) -> f64 {
+            self.position
+        }
+
+        fn reset(&mut self) {
+            self.position = 0.0;
+        }
+    }
+
+    fn run_two_files(cross_file_state: CrossFileState) -> TradeState {
+        let config = BacktestConfig {
+            slippage_bps: 0.0,
+            cross_file_state,
+            ..deterministic_config()
+        };
+        let file = |book: OrderBook| Box::new(VecSource { books: vec![book], index: 0 }) as Box<dyn DataSource>;
+        let mut data_source = MultiFileDataSource::from_sources(vec![
+            file(OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.1, 1.0)], 1000)),
+            file(OrderBook::new("BTCUSDT".to_string(), vec![(101.0, 1.0)], vec![(101.1, 1.0)], 2000)),
+        ]);
+        let engine = BacktestEngine::new(config.clone());
+        let mut strategy = RoundTrip { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+
+        let processed = engine
+            .process_files(&mut data_source, &mut strategy, &mut executor, &mut trade_state, None, &ProgressBar::hidden())
+            .unwrap();
+        assert_eq!(processed, 2);
+        trade_state
+    }
+
+    #[test]
+    fn test_position_carries_across_file_boundary() {
+        // Opened in file 1, closed in file 2
+        let preserved = run_two_files(CrossFileState::Preserve);
+        let sides: Vec<&str> = preserved.get_all_trades().iter().map(|t| t.side.as_str()).collect();
+        assert_eq!(sides, vec!["Buy", "Sell"]);
+        assert_eq!(preserved.get_position("BTCUSDT"), 0.0);
+        assert!((preserved.net_cash_flow() - 0.009).abs() < 1e-9);
+
+        // A reset strategy forgets the long and buys again
+        let reset = run_two_files(CrossFileState::Reset);
+        let sides: Vec<&str> = reset.get_all_trades().iter().map(|t| t.side.as_str()).collect();
+        assert_eq!(sides, vec!["Buy", "Buy"]);
+        assert!((reset.get_position("BTCUSDT") - 0.02).abs() < 1e-12);
+    }
+
+    /// Strategy that always bids at the best bid
+    fn join_bid() -> impl Strategy {
+        FnStrategy::new("join_bid", |book, _| book.best_bid().map(|(bid, _)| order(book, "Buy", bid)).into_iter().collect())
+    }
+
+    #[test]
+    fn test_requoted_bid_follows_book_and_fills_when_touched() {
+        let config = BacktestConfig {
+            requote_on_move: true,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        let mut quotes = QuoteSimulator::new();
+        let book = |bid: f64, time: i64| OrderBook::new("BTCUSDT".to_string(), vec![(bid, 1.0)], vec![(bid + 0.1, 1.0)], time);
+
+        // Bid 100.0 is quoted, then moved up to 100.2 as the book rises
+        engine.step(&book(100.0, 1000), &mut join_bid(), &mut executor, &mut trade_state, Some(&mut quotes));
+        engine.step(&book(100.2, 2000), &mut join_bid(), &mut executor, &mut trade_state, Some(&mut quotes));
+        assert!(trade_state.get_all_trades().is_empty());
+        assert_eq!(quotes.resting("Buy").unwrap().price, 100.2);
+
+        // The book drops and its ask trades through the resting bid
+        engine.step(&book(100.0, 3000), &mut join_bid(), &mut executor, &mut trade_state, Some(&mut quotes));
+
+        let trades = trade_state.get_all_trades();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].status, "filled");
+        assert_eq!(trades[0].price, 100.2);
+        assert_eq!(trades[0].time, 3000);
+
+        let stats = quotes.stats();
+        assert_eq!((stats.placed, stats.cancelled, stats.filled), (3, 1, 1));
+        assert!((stats.fill_ratio() - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(stats.uptime_ms, 2000);
+    }
+
+    /// Maker that joins both sides of the book every tick
+    fn two_sided() -> impl Strategy {
+        FnStrategy::new("two_sided", |book, _| {
+            book.best_bid().map(|(bid, _)| order(book, "Buy", bid)).into_iter()
+                .chain(book.best_ask().map(|(ask, _)| order(book, "Sell", ask)))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_quotes_are_snapped_and_checked_like_taker_orders() {
+        let instruments = crate::trading::InstrumentSpecRegistry::new()
+            .with_spec("BTCUSDT", crate::trading::InstrumentSpec { tick_size: 0.5, min_notional: 1.0, ..Default::default() });
+        let config = BacktestConfig { requote_on_move: true, instruments, ..deterministic_config() };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        let mut quotes = QuoteSimulator::new();
+        let book = |bid: f64, time: i64| OrderBook::new("BTCUSDT".to_string(), vec![(bid, 1.0)], vec![(100.5, 1.0)], time);
+
+        // The 100.2 bid rests on the 100.0 tick
+        engine.step(&book(100.2, 1000), &mut join_bid(), &mut executor, &mut trade_state, Some(&mut quotes));
+        assert_eq!(quotes.resting("Buy").unwrap().price, 100.0);
+
+        // At 99.0 the 0.01 quote is below the 1.0 minimum notional: rejected, and the old bid pulled
+        engine.step(&book(99.0, 2000), &mut join_bid(), &mut executor, &mut trade_state, Some(&mut quotes));
+        assert!(quotes.resting("Buy").is_none());
+        let trades = trade_state.get_all_trades();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].status, "rejected");
+    }
+
+    #[test]
+    fn test_two_sided_maker_posts_both_sides() {
+        let config = BacktestConfig {
+            requote_on_move: true,
+            ..deterministic_config()
+        };
+        let engine = BacktestEngine::new(config.clone());
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = TradeState::new();
+        let mut quotes = QuoteSimulator::new();
+
+        engine.step(&deep_book(1, 1000), &mut two_sided(), &mut executor, &mut trade_state, Some(&mut quotes));
+        assert_eq!(quotes.resting("Buy").unwrap().price, 100.0);
+        assert_eq!(quotes.resting("Sell").unwrap().price, 100.1);
+        assert_eq!(quotes.stats().placed, 2);
+
+        // Without quoting, both orders go to the executor in the same tick
+        let mut trade_state = TradeState::new();
+        engine.step(&deep_book(1, 2000), &mut two_sided(), &mut executor, &mut trade_state, None);
+        let sides: Vec<&str> = trade_state.get_all_trades().iter().map(|t| t.side.as_str()).collect();
+        assert_eq!(sides, ["Buy", "Sell"]);
+        assert!(trade_state.get_all_trades().iter().all(|t| t.time == 2000));
+    }
+
+    #[test]
+    fn test_duplicate_update_ids_are_skipped() {
+        let engine = BacktestEngine::new(BacktestConfig {
+            dedup_update_ids: true,
+            ..deterministic_config()
+        });
+        let books = [(1000, 7), (1000, 7), (1100, 6), (1200, 8)]
+            .map(|(time, update_id)| deep_book(1, time).with_update_id(update_id));
+        let mut data_source = engine.filtered(Box::new(VecSource { books: books.to_vec(), index: 0 }));
+
+        let mut replayed = Vec::new();
+        while let Some(book) = data_source.next_orderbook().unwrap() {
+            replayed.push(book.update_id);
+        }
+        assert_eq!(replayed, vec![Some(7), Some(8)]);
+
+        // Off by default
+        let mut data_source = BacktestEngine::new(deterministic_config())
+            .filtered(Box::new(VecSource { books: books.to_vec(), index: 0 }));
+        let mut count = 0;
+        while data_source.next_orderbook().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_store_all_books_keeps_non_trading_ticks() {
+        let run = |store_all_books: bool| {
+            let config = BacktestConfig {
+                store_all_books,
+                min_spread_pct: 0.002,
+                ..deterministic_config()
+            };
+            let engine = BacktestEngine::new(config.clone());
+            let mut strategy = AlwaysBuy { position: 0.0 };
+            let mut executor = BacktestTradeEmitter::new(config);
+            let mut trade_state = TradeState::new();
+            // Only the wide book at 2000 clears the spread gate
+            let wide = OrderBook::new("BTCUSDT".to_string(), vec![(99.8, 1.0)], vec![(100.2, 1.0)], 2000);
+            for book in [deep_book(1, 1000), wide, deep_book(1, 3000)] {
+                engine.step(&book, &mut strategy, &mut executor, &mut trade_state, None);
+            }
+            assert_eq!(trade_state.get_all_trades().len(), 1);
+            trade_state.get_orderbooks().iter().map(|book| book.current_time).collect::<Vec<_>>()
+        };
+
+        assert_eq!(run(false), vec![2000]);
+        assert_eq!(run(true), vec![1000, 2000, 3000]);
+    }
+
+    /// Strategy that sits out its first `warmup` books, then buys on every book
+    fn warmed_up(warmup: usize) -> impl Strategy {
+        let mut seen = 0;
+        FnStrategy::new("warmed_up", move |book, _| {
+            seen += 1;
+            if seen <= warmup { Vec::new() } else { buy_at_ask(book) }
+        })
+    }
+
+    #[test]
+    fn test_trade_ramp_reports_warmup() {
+        let engine = BacktestEngine::new(deterministic_config());
+        let mut strategy = warmed_up(30);
+        // 40 books one second apart, starting at t = 5s
+        let books = (0..40).map(|i| deep_book(1, 5_000 + i * 1_000));
+        let trade_state = engine.run_order_books(books, &mut strategy);
+
+        assert_eq!(trade_state.book_span(), Some((5_000, 44_000)));
+        let ramp = trade_state.trade_ramp().unwrap();
+        assert_eq!(ramp.time_to_first_trade_ms, Some(30_000));
+        assert_eq!(ramp.quartile_fills, [0, 0, 0, 10]);
+
+        let idle = engine.run_order_books((0..5).map(|i| deep_book(1, i * 1_000)), &mut warmed_up(10));
+        assert_eq!(idle.trade_ramp().unwrap().time_to_first_trade_ms, None);
+    }
+
+    #[test]
+    fn test_slippage_cost_is_attributed_in_report() {
+        let config = BacktestConfig { slippage_bps: 10.0, ..deterministic_config() };
+        let engine = BacktestEngine::new(config);
+        let books = (0..3).map(|i| deep_book(1, i * 1_000));
+        let trade_state = engine.run_order_books(books, &mut AlwaysBuy { position: 0.0 });
+
+        // Each 0.01 buy at 100.1 fills 10 bps higher; history keeps the quoted price
+        let fills = trade_state.get_trades_history();
+        assert_eq!(fills.len(), 3);
+        assert!(fills.iter().all(|t| t.price == 100.1));
+        let per_fill = 100.1 * 0.001 * 0.01;
+        for fill in &fills {
+            assert!((trade_state.slippage_costs()[&fill.id] - per_fill).abs() < 1e-9);
+        }
+
+        let report = crate::pnl::PnlReport::new().with_slippage_costs(trade_state.slippage_costs().clone());
+        let costs = report.cost_attribution(trade_state.get_all_trades(), crate::pnl::Method::Fifo);
+        assert!((costs.slippage_cost - 3.0 * per_fill).abs() < 1e-9);
+        assert!(costs.fees > 0.0);
+        assert!((costs.frictionless_pnl - costs.slippage_cost - costs.fees - costs.net_pnl).abs() < 1e-9);
+    }
+
+    /// Strategy that quotes a bid one tick above its own ask
+    fn crossed() -> impl Strategy {
+        FnStrategy::new("crossed", |book, _| vec![order(book, "Buy", 100.1), order(book, "Sell", 100.0)])
+    }
+
+    #[test]
+    fn test_self_cross_is_suppressed_and_reported() {
+        let config = BacktestConfig { record_decisions: true, ..deterministic_config() };
+        let engine = BacktestEngine::new(config.clone());
+        let trade_state = engine.run_order_books((0..3).map(|i| deep_book(1, i * 1_000)), &mut crossed());
+
+        assert!(trade_state.get_all_trades().is_empty());
+        assert_eq!(trade_state.self_cross_count(), 6);
+        // One decision per tick, not per discarded order
+        assert_eq!(trade_state.decision_log().unwrap().reason_counts(), vec![("SELF_CROSS".to_string(), 3)]);
+
+        // Allowed self-crosses still count, but both sides execute
+        let engine = BacktestEngine::new(BacktestConfig { allow_self_cross: true, ..config });
+        let trade_state = engine.run_order_books((0..3).map(|i| deep_book(1, i * 1_000)), &mut crossed());
+        assert_eq!(trade_state.get_all_trades().len(), 6);
+        assert_eq!(trade_state.self_cross_count(), 6);
+    }
+
+    /// Executor that fills a fraction of every order
+    struct PartialFiller {
+        fraction: f64,
+    }
+
+    impl TradeEmitter for PartialFiller {
+        fn execute_trade(&mut self, trade: Option<Trade>) -> Option<Trade> {
+            let mut trade = trade?;
+            trade.quantity *= self.fraction;
+            trade.status = "partially_filled".to_string();
+            Some(trade)
+        }
+    }
+
+    #[test]
+    fn test_partial_fill_updates_strategy_by_filled_quantity() {
+        let engine = BacktestEngine::new(BacktestConfig { slippage_bps: 0.0, ..deterministic_config() });
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = PartialFiller { fraction: 0.25 };
+        let mut trade_state = engine.new_trade_state();
+        for time in [1_000, 2_000] {
+            engine.process_orderbook(&deep_book(1, time), &mut strategy, &mut executor, &mut trade_state);
+        }
+
+        // Each 0.01 order executes 0.0025
+        assert!((strategy.position - 0.005).abs() < 1e-12);
+        let fills = trade_state.get_trades_history();
+        assert_eq!(fills.len(), 2);
+        assert!(fills.iter().all(|t| (t.quantity - 0.0025).abs() < 1e-12 && t.price == 100.1));
+        assert!((trade_state.get_position("BTCUSDT") - 0.005).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_depth_limited_fill_feeds_back_executed_quantity() {
+        let config = BacktestConfig { slippage_bps: 0.0, fill_from_depth: true, ..deterministic_config() };
+        let engine = BacktestEngine::new(config.clone());
+        let mut strategy = AlwaysBuy { position: 0.0 };
+        let mut executor = BacktestTradeEmitter::new(config);
+        let mut trade_state = engine.new_trade_state();
+        let thin_book = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.1, 0.004)], 1_000);
+        engine.process_orderbook(&thin_book, &mut strategy, &mut executor, &mut trade_state);
+
+        assert!((strategy.position - 0.004).abs() < 1e-12);
+        assert!((trade_state.get_position("BTCUSDT") - 0.004).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parameter_sweep_runs_every_combination_sorted_by_net_pnl() {
+        let dir = TestDir::new("sweep");
+        let path = dir.join("BTCUSDT_sweep.jsonl");
+        let lines: Vec<String> = (0..50)
+            .map(|i| format!(r#"{{"ts":{},"data":{{"b":[["{}","1.0"]],"a":[["{}","1.0"]]}}}}"#,
+                             1000 + i * 100, 100.0 + (i % 7) as f64 * 0.1, 100.1 + (i % 7) as f64 * 0.1))
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let engine = BacktestEngine::new(deterministic_config());
+        let sweep = vec![ParamRange::new("obi_threshold", 0.1, 0.3, 0.1), ParamRange::new("take_profit_bps", 10.0, 20.0, 10.0)];
+        let results = engine.run_parameter_sweep(&path, &GptMarketMakerConfig::default(), &sweep).unwrap();
+
+        assert_eq!(results.len(), 6);
+        assert!(results.windows(2).all(|w| w[0].net_pnl >= w[1].net_pnl));
+        assert!(results.iter().all(|r| r.params.len() == 2 && r.max_drawdown >= 0.0));
+        assert!(engine.run_parameter_sweep(&path, &GptMarketMakerConfig::default(), &[ParamRange::new("bogus", 1.0, 2.0, 1.0)]).is_err());
+    }
+}
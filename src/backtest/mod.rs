@@ -1,5 +1,15 @@
 pub mod trade_dashboard;
 pub mod engine;
+pub mod aggregate;
+pub mod monte_carlo;
+pub mod sweep;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_sink;
 
-pub use trade_dashboard::TradeDashboard;
-pub use engine::BacktestEngine;
\ No newline at end of file
+pub use trade_dashboard::{TradeDashboard, MarkoutSummary, Excursion, ExcursionSummary, InventoryRisk};
+pub use engine::BacktestEngine;
+pub use aggregate::{AggregateResult, SessionSplit, SessionSummary};
+pub use monte_carlo::MonteCarloSummary;
+pub use sweep::{ParamRange, SweepResult};
+#[cfg(feature = "sqlite")]
+pub use sqlite_sink::{RunRecord, SqliteSink};
\ No newline at end of file
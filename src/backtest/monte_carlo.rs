@@ -0,0 +1,85 @@
+use comfy_table::Table;
+
+use crate::core::TradeState;
+use crate::pnl::{Method, PnlReport};
+
+/// Runs whose P&L standard deviation exceeds this share of |mean P&L| are flagged as luck-dependent
+const LUCK_DISPERSION_THRESHOLD: f64 = 0.5;
+
+/// Net P&L spread across backtests of the same data under different execution seeds
+#[derive(Debug, Clone)]
+pub struct MonteCarloSummary {
+    /// `(seed, net P&L)` per run, in seed order
+    pub runs: Vec<(u64, f64)>,
+    pub mean: f64,
+    /// Population standard deviation of the run P&Ls
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MonteCarloSummary {
+    pub fn from_runs(runs: Vec<(u64, f64)>) -> Self {
+        let pnls: Vec<f64> = runs.iter().map(|(_, pnl)| *pnl).collect();
+        if pnls.is_empty() {
+            return Self { runs, mean: 0.0, std: 0.0, min: 0.0, max: 0.0 };
+        }
+
+        let mean = pnls.iter().sum::<f64>() / pnls.len() as f64;
+        let variance = pnls.iter().map(|pnl| (pnl - mean).powi(2)).sum::<f64>() / pnls.len() as f64;
+        Self {
+            mean,
+            std: variance.sqrt(),
+            min: pnls.iter().copied().fold(f64::INFINITY, f64::min),
+            max: pnls.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            runs,
+        }
+    }
+
+    /// Summarize seeded runs by their headline P&L net of commission and borrow fees
+    pub fn from_trade_states(runs: &[(u64, TradeState)], report: &PnlReport, method: Method) -> Self {
+        Self::from_runs(runs.iter()
+            .map(|(seed, trade_state)| {
+                let trades = trade_state.get_all_trades();
+                let result = report.calculate(trades, method);
+                (*seed, report.headline_pnl(&result) - report.commission(trades) - report.borrow_cost(trades))
+            })
+            .collect())
+    }
+
+    /// Standard deviation relative to |mean| (infinite when the mean is zero and runs differ)
+    pub fn dispersion(&self) -> f64 {
+        if self.std == 0.0 {
+            0.0
+        } else {
+            self.std / self.mean.abs()
+        }
+    }
+
+    /// Whether the spread across seeds is wide enough that the result mostly reflects execution luck
+    pub fn is_luck_dependent(&self) -> bool {
+        self.dispersion() > LUCK_DISPERSION_THRESHOLD
+    }
+
+    /// Distribution table plus a warning when the spread is wide
+    pub fn to_table(&self) -> String {
+        let mut table = Table::new();
+        table.set_header(vec!["Runs", "Mean P&L", "Std", "Min", "Max"]);
+        table.add_row(vec![
+            self.runs.len().to_string(),
+            format!("${:.2}", self.mean),
+            format!("${:.2}", self.std),
+            format!("${:.2}", self.min),
+            format!("${:.2}", self.max),
+        ]);
+
+        let mut output = format!("\n=== Monte Carlo over {} execution seeds ===\n{}", self.runs.len(), table);
+        if self.is_luck_dependent() {
+            output.push_str(&format!(
+                "\nWarning: P&L std is {:.0}% of |mean|; the result depends heavily on execution luck",
+                self.dispersion() * 100.0
+            ));
+        }
+        output
+    }
+}
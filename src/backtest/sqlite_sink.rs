@@ -0,0 +1,234 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection};
+
+use crate::backtest::{AggregateResult, TradeDashboard};
+use crate::core::{Result, Trade};
+use crate::trading::BacktestConfig;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    config_hash TEXT NOT NULL,
+    params TEXT NOT NULL,
+    pnl REAL NOT NULL,
+    sharpe REAL NOT NULL,
+    max_drawdown REAL NOT NULL,
+    fees REAL NOT NULL,
+    seed INTEGER,
+    timestamp_ms INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS runs_config_hash ON runs (config_hash);
+CREATE TABLE IF NOT EXISTS trades (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    time INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    price REAL NOT NULL,
+    quantity REAL NOT NULL,
+    status TEXT NOT NULL,
+    trade_id TEXT NOT NULL,
+    reduce_only INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_run_id ON trades (run_id);
+";
+
+/// One backtest run as stored in the `runs` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunRecord {
+    /// FNV-1a hash of `params`, so runs with identical settings group together
+    pub config_hash: String,
+    /// Backtest and strategy settings as JSON
+    pub params: String,
+    /// Realized plus unrealized P&L across all symbols
+    pub pnl: f64,
+    /// Portfolio Sharpe of equity increments (not annualized)
+    pub sharpe: f64,
+    /// Sum of the per-symbol max drawdowns
+    pub max_drawdown: f64,
+    pub fees: f64,
+    pub seed: Option<u64>,
+    /// Wall-clock time the run was recorded, in Unix milliseconds
+    pub timestamp_ms: i64,
+}
+
+impl RunRecord {
+    /// Summarize the run in `dashboard`. `params` is the JSON of every setting that shaped it,
+    /// e.g. the backtest config together with the strategy config.
+    pub fn from_dashboard(dashboard: &mut TradeDashboard, config: &BacktestConfig, params: serde_json::Value) -> Self {
+        let aggregate = AggregateResult::from_dashboard(dashboard, 1);
+        let max_drawdown = aggregate.per_symbol.keys()
+            .map(|symbol| dashboard.get_capital_metrics(symbol).max_drawdown)
+            .sum();
+        // serde_json objects keep keys sorted, so equal settings always hash the same
+        let params = params.to_string();
+        Self {
+            config_hash: format!("{:016x}", fnv1a(params.as_bytes())),
+            params,
+            pnl: aggregate.total_pnl,
+            sharpe: aggregate.portfolio_sharpe,
+            max_drawdown,
+            fees: aggregate.per_symbol.values().map(|r| r.total_fees).sum(),
+            seed: config.seed,
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64),
+        }
+    }
+}
+
+/// Appends runs and their trades to a SQLite database for querying across sweeps
+pub struct SqliteSink {
+    conn: Connection,
+}
+
+impl SqliteSink {
+    /// Open or create the database at `path`, creating the tables if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Sink over an in-memory database
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Insert a run and its trades in one transaction, returning the run's id
+    pub fn insert_run(&mut self, run: &RunRecord, trades: &[Trade]) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO runs (config_hash, params, pnl, sharpe, max_drawdown, fees, seed, timestamp_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                run.config_hash,
+                run.params,
+                run.pnl,
+                run.sharpe,
+                run.max_drawdown,
+                run.fees,
+                run.seed.map(|seed| seed as i64),
+                run.timestamp_ms,
+            ],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO trades (run_id, time, symbol, side, price, quantity, status, trade_id, reduce_only)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for trade in trades {
+                insert.execute(params![
+                    run_id,
+                    trade.time,
+                    trade.symbol,
+                    trade.side,
+                    trade.price,
+                    trade.quantity,
+                    trade.status,
+                    trade.id,
+                    trade.reduce_only,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+
+    /// The run stored under `run_id`
+    pub fn run(&self, run_id: i64) -> Result<RunRecord> {
+        Ok(self.conn.query_row(
+            "SELECT config_hash, params, pnl, sharpe, max_drawdown, fees, seed, timestamp_ms FROM runs WHERE id = ?1",
+            params![run_id],
+            |row| {
+                Ok(RunRecord {
+                    config_hash: row.get(0)?,
+                    params: row.get(1)?,
+                    pnl: row.get(2)?,
+                    sharpe: row.get(3)?,
+                    max_drawdown: row.get(4)?,
+                    fees: row.get(5)?,
+                    seed: row.get::<_, Option<i64>>(6)?.map(|seed| seed as u64),
+                    timestamp_ms: row.get(7)?,
+                })
+            },
+        )?)
+    }
+
+    /// Trades stored for `run_id`, in insertion order
+    pub fn trades(&self, run_id: i64) -> Result<Vec<Trade>> {
+        let mut query = self.conn.prepare(
+            "SELECT time, symbol, side, price, quantity, status, trade_id, reduce_only
+             FROM trades WHERE run_id = ?1 ORDER BY rowid",
+        )?;
+        let trades = query
+            .query_map(params![run_id], |row| {
+                Ok(Trade {
+                    time: row.get(0)?,
+                    symbol: row.get(1)?,
+                    side: row.get(2)?,
+                    price: row.get(3)?,
+                    quantity: row.get(4)?,
+                    status: row.get(5)?,
+                    id: row.get(6)?,
+                    reduce_only: row.get(7)?,
+                    liquidity: None,
+                    fee: 0.0,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(trades)
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and Rust versions unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TradeState;
+
+    fn filled(side: &str, price: f64, time: i64) -> Trade {
+        let mut trade = Trade::new(time, "BTCUSDT".to_string(), side.to_string(), price, 1.0);
+        trade.status = "filled".to_string();
+        trade
+    }
+
+    #[test]
+    fn test_insert_run_and_read_it_back() {
+        let config = BacktestConfig { seed: Some(7), ..Default::default() };
+        let mut trade_state = TradeState::new();
+        trade_state.add(filled("Buy", 100.0, 1_000));
+        trade_state.add(filled("Sell", 110.0, 2_000));
+        let mut dashboard = TradeDashboard::from_config(trade_state, &config);
+
+        let params = serde_json::json!({ "backtest": config, "strategy": { "take_profit_bps": 20.0 } });
+        let run = RunRecord::from_dashboard(&mut dashboard, &config, params.clone());
+        assert!((run.pnl - 10.0).abs() < 1e-9);
+        assert_eq!(run.seed, Some(7));
+        assert_eq!(run.config_hash, RunRecord::from_dashboard(&mut dashboard, &config, params).config_hash);
+
+        let mut sink = SqliteSink::in_memory().unwrap();
+        let trades = dashboard.trade_state.get_all_trades().clone();
+        let run_id = sink.insert_run(&run, &trades).unwrap();
+        assert_eq!(sink.run(run_id).unwrap(), run);
+
+        let stored = sink.trades(run_id).unwrap();
+        assert_eq!(stored.len(), 2);
+        for (stored, trade) in stored.iter().zip(&trades) {
+            assert_eq!((stored.time, &stored.side, stored.price, &stored.status, &stored.id),
+                       (trade.time, &trade.side, trade.price, &trade.status, &trade.id));
+        }
+
+        let second = sink.insert_run(&run, &[]).unwrap();
+        assert_ne!(second, run_id);
+        assert!(sink.trades(second).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+use serde_json::Value;
+
+use crate::core::{Result, TradeError};
+use crate::strategy::GptMarketMakerConfig;
+
+/// Values of one `GptMarketMakerConfig` field to sweep: `start`, `start + step`, ... up to `stop`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamRange {
+    /// Config field name, e.g. `obi_threshold`
+    pub field: String,
+    pub start: f64,
+    pub stop: f64,
+    pub step: f64,
+}
+
+impl ParamRange {
+    pub fn new(field: &str, start: f64, stop: f64, step: f64) -> Self {
+        Self { field: field.to_string(), start, stop, step }
+    }
+
+    /// Every value of the range, `stop` included when the steps land on it
+    pub fn values(&self) -> Result<Vec<f64>> {
+        if self.step <= 0.0 || self.stop < self.start {
+            return Err(TradeError::InvalidTradeParameters(format!(
+                "Sweep range for {} needs step > 0 and stop >= start, got {}..{} by {}",
+                self.field, self.start, self.stop, self.step
+            )));
+        }
+        let steps = ((self.stop - self.start) / self.step + 1e-9).floor() as usize;
+        Ok((0..=steps).map(|i| self.start + i as f64 * self.step).collect())
+    }
+}
+
+/// Outcome of one parameter combination of a sweep
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepResult {
+    /// `(field, value)` per swept field, in sweep order
+    pub params: Vec<(String, f64)>,
+    /// Headline P&L, realized plus open positions marked at the last mid
+    pub total_pnl: f64,
+    /// `total_pnl` net of commission, fees and slippage
+    pub net_pnl: f64,
+    /// Largest peak-to-trough fall of the equity curve, in dollars
+    pub max_drawdown: f64,
+    /// Filled trades
+    pub trade_count: usize,
+}
+
+/// Cartesian product of the ranges' values, first range varying slowest
+pub(crate) fn combinations(sweep: &[ParamRange]) -> Result<Vec<Vec<(String, f64)>>> {
+    let mut combinations = vec![Vec::new()];
+    for range in sweep {
+        let values = range.values()?;
+        combinations = combinations.into_iter()
+            .flat_map(|params: Vec<(String, f64)>| values.iter().map(move |value| {
+                let mut params = params.clone();
+                params.push((range.field.clone(), *value));
+                params
+            }))
+            .collect();
+    }
+    Ok(combinations)
+}
+
+/// `base` with each named field set to its value; integer fields are rounded
+pub(crate) fn apply_params(base: &GptMarketMakerConfig, params: &[(String, f64)]) -> Result<GptMarketMakerConfig> {
+    let invalid = |msg: String| TradeError::InvalidTradeParameters(msg);
+    let mut config = serde_json::to_value(base).map_err(|e| invalid(e.to_string()))?;
+    for (field, value) in params {
+        let slot = config.get_mut(field.as_str())
+            .ok_or_else(|| invalid(format!("Unknown strategy parameter: {}", field)))?;
+        let replacement = match &*slot {
+            Value::Number(number) if !number.is_f64() => Value::from(value.round() as i64),
+            Value::Number(_) => Value::from(*value),
+            _ => return Err(invalid(format!("Strategy parameter {} is not numeric", field))),
+        };
+        *slot = replacement;
+    }
+    serde_json::from_value(config).map_err(|e| invalid(e.to_string()))
+}
+
+/// Largest fall from a running peak of `curve`, starting from flat
+pub(crate) fn max_drawdown(curve: &[(i64, f64)]) -> f64 {
+    let mut peak: f64 = 0.0;
+    curve.iter().fold(0.0, |drawdown: f64, &(_, equity)| {
+        peak = peak.max(equity);
+        drawdown.max(peak - equity)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combinations_cover_the_grid() {
+        let sweep = vec![ParamRange::new("obi_threshold", 0.1, 0.3, 0.1), ParamRange::new("vwap_window", 50.0, 100.0, 50.0)];
+        let combinations = combinations(&sweep).unwrap();
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(combinations[1], vec![("obi_threshold".to_string(), 0.1), ("vwap_window".to_string(), 100.0)]);
+
+        let config = apply_params(&GptMarketMakerConfig::default(), &combinations[5]).unwrap();
+        assert!((config.obi_threshold - 0.3).abs() < 1e-12);
+        assert_eq!(config.vwap_window, 100);
+
+        assert!(apply_params(&GptMarketMakerConfig::default(), &[("no_such_field".to_string(), 1.0)]).is_err());
+        assert!(ParamRange::new("obi_threshold", 0.3, 0.1, 0.1).values().is_err());
+    }
+
+    #[test]
+    fn test_max_drawdown_from_running_peak() {
+        assert_eq!(max_drawdown(&[(1, 5.0), (2, 2.0), (3, 8.0), (4, 7.0)]), 3.0);
+        assert_eq!(max_drawdown(&[(1, -4.0), (2, 1.0)]), 4.0);
+        assert_eq!(max_drawdown(&[]), 0.0);
+    }
+}
\ No newline at end of file
@@ -1,8 +1,63 @@
 use crate::core::{Trade, OrderBook, PnLResult, ClosedTrade, CapitalMetrics, TradeState};
+use crate::pnl::{IncludeUnrealized, Record, QUANTITY_EPSILON, snap_quantity};
+use crate::trading::{BacktestConfig, InstrumentSpecRegistry};
+use crate::trading::metrics::fee_to_pnl_ratio;
 use std::collections::HashMap;
 use log::info;
 use comfy_table::Table;
 
+/// Average markout of fills on one side at one horizon
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkoutSummary {
+    pub horizon_ms: i64,
+    pub side: String,
+    /// Number of fills with a book available at the horizon
+    pub fills: usize,
+    /// Average mid move in the fill's favor, in price units per unit quantity
+    pub avg_markout: f64,
+    /// Average markout in basis points of the fill price
+    pub avg_markout_bps: f64,
+}
+
+/// Best and worst unrealized P&L of one closed lot over its holding window
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excursion {
+    /// Side of the opening fill
+    pub side: String,
+    pub quantity: f64,
+    pub open_time: i64,
+    pub open_price: f64,
+    pub close_time: i64,
+    pub close_price: f64,
+    /// Maximum favorable excursion: best unrealized profit per unit quantity (>= 0)
+    pub mfe: f64,
+    /// Maximum adverse excursion: worst unrealized loss per unit quantity, as a positive number
+    pub mae: f64,
+}
+
+/// Average excursions across a symbol's closed lots
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExcursionSummary {
+    pub trades: usize,
+    pub avg_mfe: f64,
+    pub avg_mae: f64,
+    /// Averages in basis points of the entry price
+    pub avg_mfe_bps: f64,
+    pub avg_mae_bps: f64,
+}
+
+/// Inventory risk at one stored book: the exposure carried into the recent volatility
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryRisk {
+    pub time: i64,
+    /// Net position after the fills up to `time`
+    pub inventory: f64,
+    /// Standard deviation of mid changes between the books in the trailing window
+    pub volatility: f64,
+    /// `|inventory| * volatility` in account currency: the one-sigma move of the position
+    pub risk: f64,
+}
+
 pub struct TradeDashboard {
     pub trade_state: TradeState,
     positions: HashMap<String, f64>,
@@ -13,6 +68,11 @@ pub struct TradeDashboard {
     margin_history: Vec<f64>,
     open_positions_value_history: Vec<f64>,
     margin_rate: f64,
+    risk_aversion: f64,
+    mark_to_market_every_tick: bool,
+    include_unrealized: IncludeUnrealized,
+    instruments: InstrumentSpecRegistry,
+    inventory_risk_window_ms: i64,
 }
 
 impl TradeDashboard {
@@ -27,9 +87,47 @@ impl TradeDashboard {
             margin_history: Vec::new(),
             open_positions_value_history: Vec::new(),
             margin_rate,
+            risk_aversion: 0.0,
+            mark_to_market_every_tick: false,
+            include_unrealized: IncludeUnrealized::default(),
+            instruments: InstrumentSpecRegistry::default(),
+            inventory_risk_window_ms: 0,
         }
     }
 
+    /// Dashboard using the margin rate and instruments of the config the backtest ran with
+    pub fn from_config(trade_state: TradeState, config: &BacktestConfig) -> Self {
+        Self::new(trade_state, config.margin_rate).with_instruments(config.instruments.clone())
+    }
+
+    pub fn margin_rate(&self) -> f64 {
+        self.margin_rate
+    }
+
+    /// Choose whether the headline "Total PnL" marks open positions
+    pub fn with_include_unrealized(mut self, include_unrealized: IncludeUnrealized) -> Self {
+        self.include_unrealized = include_unrealized;
+        self
+    }
+
+    /// Scale P&L by each symbol's contract size
+    pub fn with_instruments(mut self, instruments: InstrumentSpecRegistry) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    /// Enable the inventory penalty with the given risk aversion (0.0 disables it)
+    pub fn with_risk_aversion(mut self, risk_aversion: f64) -> Self {
+        self.risk_aversion = risk_aversion;
+        self
+    }
+
+    /// Report the peak rolling inventory risk over windows of `window_ms` in the P&L summary (0 disables it)
+    pub fn with_inventory_risk_window(mut self, window_ms: i64) -> Self {
+        self.inventory_risk_window_ms = window_ms;
+        self
+    }
+
     pub fn pnl(&mut self, symbol: &str) -> HashMap<String, PnLResult> {
         let mut pnl_results = HashMap::new();
         
@@ -43,8 +141,10 @@ impl TradeDashboard {
     }
 
     fn process_trades(&self, trades: &[&Trade], symbol: &str) -> PnLResult {
+        let contract_size = self.instruments.get(symbol).contract_size;
         let mut total_pnl = 0.0;
         let mut closed_trades = Vec::new();
+        let mut pnl_records = Vec::new();
         let mut positions: HashMap<String, Vec<(f64, f64)>> = HashMap::new(); // symbol -> Vec<(quantity, price)>
         
         for trade in trades {
@@ -60,13 +160,18 @@ impl TradeDashboard {
                 let mut remaining_quantity = trade.quantity;
                 let mut i = 0;
                 
-                while remaining_quantity > 0.0 && i < pos_list.len() {
+                while remaining_quantity > QUANTITY_EPSILON && i < pos_list.len() {
                     let (pos_quantity, pos_price) = pos_list[i];
                     
-                    if pos_quantity > 0.0 {
+                    if pos_quantity > QUANTITY_EPSILON {
                         let close_quantity = remaining_quantity.min(pos_quantity);
-                        let pnl = (trade.price - pos_price) * close_quantity;
+                        let pnl = (trade.price - pos_price) * close_quantity * contract_size;
                         total_pnl += pnl;
+                        pnl_records.push(Record {
+                            timestamp: trade.time,
+                            symbol: trade.symbol.clone(),
+                            profit: pnl,
+                        });
                         
                         closed_trades.push(ClosedTrade {
                             open_side: "Buy".to_string(),
@@ -77,10 +182,10 @@ impl TradeDashboard {
                             pnl,
                         });
                         
-                        pos_list[i].0 -= close_quantity;
-                        remaining_quantity -= close_quantity;
+                        pos_list[i].0 = snap_quantity(pos_list[i].0 - close_quantity);
+                        remaining_quantity = snap_quantity(remaining_quantity - close_quantity);
                         
-                        if pos_list[i].0 == 0.0 {
+                        if pos_list[i].0 < QUANTITY_EPSILON {
                             pos_list.remove(i);
                         } else {
                             i += 1;
@@ -99,8 +204,8 @@ impl TradeDashboard {
         
         for (_, pos_list) in &positions {
             for (quantity, price) in pos_list {
-                if *quantity > 0.0 {
-                    unrealized_pnl += (last_price - price) * quantity;
+                if *quantity > QUANTITY_EPSILON {
+                    unrealized_pnl += (last_price - price) * quantity * contract_size;
                     remaining_shares += quantity;
                 }
             }
@@ -110,8 +215,436 @@ impl TradeDashboard {
             total_pnl,
             unrealized_pnl,
             closed_trades,
-            total_fees: 0.0,
+            total_fees: trades.iter().filter(|t| t.symbol == symbol).map(|t| t.fee).sum(),
             remaining_shares,
+            pnl_records,
+        }
+    }
+
+    /// Mark open positions at every stored order book instead of only at fills
+    pub fn with_mark_to_market_every_tick(mut self, enabled: bool) -> Self {
+        self.mark_to_market_every_tick = enabled;
+        self
+    }
+
+    /// Equity curve (realized + unrealized P&L) for a symbol as `(timestamp, equity)` points.
+    ///
+    /// By default a point is emitted at every fill, with open inventory marked at the latest
+    /// known mid price. With `mark_to_market_every_tick` enabled a point is also emitted at
+    /// every stored order book, which exposes drawdowns on open positions between fills.
+    pub fn equity_curve(&self, symbol: &str) -> Vec<(i64, f64)> {
+        let trades: Vec<&Trade> = self.trade_state.get_trades_history()
+            .into_iter()
+            .filter(|t| t.symbol == symbol)
+            .collect();
+        let orderbooks: Vec<&OrderBook> = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol)
+            .collect();
+
+        let mut curve = Vec::new();
+        let mut quantity = 0.0;
+        let mut avg_price = 0.0;
+        let mut realized = 0.0;
+        let mut mark_price: Option<f64> = None;
+        let mut book_idx = 0;
+
+        let contract_size = self.instruments.get(symbol).contract_size;
+        let unrealized = |quantity: f64, avg_price: f64, mark: f64| (mark - avg_price) * quantity * contract_size;
+
+        for trade in trades {
+            // Books up to and including the fill time move the mark first
+            while book_idx < orderbooks.len() && orderbooks[book_idx].current_time <= trade.time {
+                let mid = orderbooks[book_idx].mid_price();
+                if mid > 0.0 {
+                    mark_price = Some(mid);
+                    if self.mark_to_market_every_tick && quantity.abs() >= QUANTITY_EPSILON {
+                        curve.push((orderbooks[book_idx].current_time, realized + unrealized(quantity, avg_price, mid)));
+                    }
+                }
+                book_idx += 1;
+            }
+
+            let signed_quantity = if trade.side.to_lowercase() == "buy" {
+                trade.quantity
+            } else {
+                -trade.quantity
+            };
+
+            if quantity.abs() < QUANTITY_EPSILON || quantity.signum() == signed_quantity.signum() {
+                // Opening or adding to the position
+                let total_quantity = quantity.abs() + signed_quantity.abs();
+                avg_price = (quantity.abs() * avg_price + signed_quantity.abs() * trade.price) / total_quantity;
+                quantity += signed_quantity;
+            } else {
+                // Reducing, closing or flipping the position
+                let closed = quantity.abs().min(signed_quantity.abs());
+                realized += (trade.price - avg_price) * closed * quantity.signum() * contract_size;
+                quantity += signed_quantity;
+                if quantity.abs() < QUANTITY_EPSILON {
+                    quantity = 0.0;
+                    avg_price = 0.0;
+                } else if quantity.signum() == signed_quantity.signum() {
+                    avg_price = trade.price;
+                }
+            }
+
+            let mark = mark_price.unwrap_or(trade.price);
+            curve.push((trade.time, realized + unrealized(quantity, avg_price, mark)));
+        }
+
+        if self.mark_to_market_every_tick {
+            for orderbook in &orderbooks[book_idx..] {
+                let mid = orderbook.mid_price();
+                if mid > 0.0 && quantity.abs() >= QUANTITY_EPSILON {
+                    curve.push((orderbook.current_time, realized + unrealized(quantity, avg_price, mid)));
+                }
+            }
+        }
+
+        curve
+    }
+
+    /// Markout (adverse selection) analysis for a symbol's fills.
+    ///
+    /// For each fill the markout at horizon `h` is the mid-price move from the fill price
+    /// to the mid `h` milliseconds later, signed in the fill's favor (positive when the
+    /// price moves up after a buy). Fills whose horizon runs past the stored books are
+    /// skipped. Negative averages on both sides indicate toxic flow.
+    pub fn markout_analysis(&self, symbol: &str, horizons: &[i64]) -> Vec<MarkoutSummary> {
+        let mut mids: Vec<(i64, f64)> = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol)
+            .map(|ob| (ob.current_time, ob.mid_price()))
+            .filter(|(_, mid)| *mid > 0.0)
+            .collect();
+        mids.sort_by_key(|(time, _)| *time);
+
+        let last_book_time = match mids.last() {
+            Some((time, _)) => *time,
+            None => return Vec::new(),
+        };
+
+        // Latest mid at or before the given time
+        let mid_at = |time: i64| -> Option<f64> {
+            let idx = mids.partition_point(|(t, _)| *t <= time);
+            if idx == 0 { None } else { Some(mids[idx - 1].1) }
+        };
+
+        let trades = self.trade_state.get_trades_history();
+        let mut summaries = Vec::new();
+
+        for &horizon in horizons {
+            for side in ["Buy", "Sell"] {
+                let sign = if side == "Buy" { 1.0 } else { -1.0 };
+                let mut total = 0.0;
+                let mut total_bps = 0.0;
+                let mut fills = 0;
+
+                for trade in trades.iter().filter(|t| t.symbol == symbol && t.side.eq_ignore_ascii_case(side)) {
+                    let target = trade.time + horizon;
+                    if target > last_book_time {
+                        continue;
+                    }
+                    if let Some(mid) = mid_at(target) {
+                        let markout = sign * (mid - trade.price);
+                        total += markout;
+                        total_bps += markout / trade.price * 10_000.0;
+                        fills += 1;
+                    }
+                }
+
+                let (avg_markout, avg_markout_bps) = if fills > 0 {
+                    (total / fills as f64, total_bps / fills as f64)
+                } else {
+                    (0.0, 0.0)
+                };
+
+                summaries.push(MarkoutSummary {
+                    horizon_ms: horizon,
+                    side: side.to_string(),
+                    fills,
+                    avg_markout,
+                    avg_markout_bps,
+                });
+            }
+        }
+
+        summaries
+    }
+
+    /// Maximum favorable and adverse excursion of each closed lot of a symbol.
+    ///
+    /// Fills are matched FIFO into lots, and each lot is marked at the stored mids from
+    /// its entry to its exit, and at the exit price itself. Stored books only cover the
+    /// ticks trades were made on unless `store_all_books` was set.
+    pub fn excursions(&self, symbol: &str) -> Vec<Excursion> {
+        let mut mids: Vec<(i64, f64)> = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol)
+            .map(|ob| (ob.current_time, ob.mid_price()))
+            .filter(|(_, mid)| *mid > 0.0)
+            .collect();
+        mids.sort_by_key(|(time, _)| *time);
+
+        // Open lots as (signed quantity, price, time), oldest first
+        let mut lots: Vec<(f64, f64, i64)> = Vec::new();
+        let mut excursions = Vec::new();
+
+        for trade in self.trade_state.get_trades_history().into_iter().filter(|t| t.symbol == symbol) {
+            let mut remaining = if trade.side.eq_ignore_ascii_case("buy") { trade.quantity } else { -trade.quantity };
+
+            while remaining.abs() >= QUANTITY_EPSILON && lots.first().is_some_and(|lot| lot.0.signum() != remaining.signum()) {
+                let (lot_quantity, open_price, open_time) = lots[0];
+                let closed = lot_quantity.abs().min(remaining.abs());
+                let sign = lot_quantity.signum();
+
+                let start = mids.partition_point(|(time, _)| *time < open_time);
+                let end = mids.partition_point(|(time, _)| *time <= trade.time);
+                let moves = mids[start..end].iter()
+                    .map(|(_, mid)| sign * (mid - open_price))
+                    .chain(std::iter::once(sign * (trade.price - open_price)));
+                let (best, worst) = moves.fold((0.0f64, 0.0f64), |(best, worst), m| (best.max(m), worst.min(m)));
+
+                excursions.push(Excursion {
+                    side: if sign > 0.0 { "Buy" } else { "Sell" }.to_string(),
+                    quantity: closed,
+                    open_time,
+                    open_price,
+                    close_time: trade.time,
+                    close_price: trade.price,
+                    mfe: best,
+                    mae: -worst,
+                });
+
+                lots[0].0 = snap_quantity(lot_quantity - sign * closed);
+                remaining = snap_quantity(remaining + sign * closed);
+                if lots[0].0.abs() < QUANTITY_EPSILON {
+                    lots.remove(0);
+                }
+            }
+
+            if remaining.abs() >= QUANTITY_EPSILON {
+                lots.push((remaining, trade.price, trade.time));
+            }
+        }
+
+        excursions
+    }
+
+    /// Average MFE and MAE across a symbol's closed lots. An average MFE well above the
+    /// realized profit suggests exits come too early; a large MAE that losers run.
+    pub fn excursion_summary(&self, symbol: &str) -> ExcursionSummary {
+        let excursions = self.excursions(symbol);
+        let count = excursions.len();
+        let average = |value: &dyn Fn(&Excursion) -> f64| {
+            if count == 0 { 0.0 } else { excursions.iter().map(value).sum::<f64>() / count as f64 }
+        };
+        ExcursionSummary {
+            trades: count,
+            avg_mfe: average(&|e| e.mfe),
+            avg_mae: average(&|e| e.mae),
+            avg_mfe_bps: average(&|e| e.mfe / e.open_price * 10_000.0),
+            avg_mae_bps: average(&|e| e.mae / e.open_price * 10_000.0),
+        }
+    }
+
+    pub fn print_excursion_summary(&self, symbol: &str, summary: &ExcursionSummary) {
+        let mut table = Table::new();
+        table.set_header(vec!["Closed lots", "Avg MFE", "Avg MAE", "Avg MFE (bps)", "Avg MAE (bps)"]);
+        table.add_row(vec![
+            summary.trades.to_string(),
+            format!("{:.4}", summary.avg_mfe),
+            format!("{:.4}", summary.avg_mae),
+            format!("{:.2}", summary.avg_mfe_bps),
+            format!("{:.2}", summary.avg_mae_bps),
+        ]);
+
+        info!("\nEXCURSION ANALYSIS FOR {}", symbol);
+        info!("{}", table);
+    }
+
+    pub fn print_markout_analysis(&self, symbol: &str, summaries: &[MarkoutSummary]) {
+        let mut table = Table::new();
+        table.set_header(vec!["Horizon", "Side", "Fills", "Avg markout", "Avg markout (bps)"]);
+        for summary in summaries {
+            table.add_row(vec![
+                format!("{}ms", summary.horizon_ms),
+                summary.side.clone(),
+                summary.fills.to_string(),
+                format!("{:.4}", summary.avg_markout),
+                format!("{:.2}", summary.avg_markout_bps),
+            ]);
+        }
+
+        info!("\nMARKOUT ANALYSIS FOR {}", symbol);
+        info!("{}", table);
+    }
+
+    /// Information coefficient of a signal: the correlation between `signal` on each stored
+    /// `symbol` book and the mid return from that book to the mid `horizon_ms` later.
+    ///
+    /// Measures whether the signal predicts short-horizon moves, independent of execution.
+    /// Books where the signal is None or whose horizon runs past the stored books are
+    /// skipped. None without two pairs or when either series is constant.
+    pub fn information_coefficient<F>(&self, symbol: &str, horizon_ms: i64, signal: F) -> Option<f64>
+    where
+        F: Fn(&OrderBook) -> Option<f64>,
+    {
+        let mut books: Vec<&OrderBook> = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol && ob.mid_price() > 0.0)
+            .collect();
+        books.sort_by_key(|ob| ob.current_time);
+        let last_book_time = books.last()?.current_time;
+
+        let pairs: Vec<(f64, f64)> = books.iter()
+            .filter(|ob| ob.current_time + horizon_ms <= last_book_time)
+            .filter_map(|ob| {
+                let value = signal(ob)?;
+                // Latest book at or before the horizon; never before `ob` itself
+                let idx = books.partition_point(|b| b.current_time <= ob.current_time + horizon_ms);
+                Some((value, books[idx - 1].mid_price() / ob.mid_price() - 1.0))
+            })
+            .collect();
+        correlation(&pairs)
+    }
+
+    /// Log the information coefficient of the built-in book signals at `horizon_ms`
+    pub fn print_information_coefficients(&self, symbol: &str, horizon_ms: i64) {
+        let signals: [(&str, fn(&OrderBook) -> Option<f64>); 2] = [
+            ("Order book imbalance", |ob| Some(ob.order_book_imbalance())),
+            ("Microprice offset", |ob| {
+                let ((bid, bid_qty), (ask, ask_qty)) = (ob.best_bid()?, ob.best_ask()?);
+                let microprice = (bid * ask_qty + ask * bid_qty) / (bid_qty + ask_qty);
+                Some(microprice / ob.mid_price() - 1.0)
+            }),
+        ];
+
+        let mut table = Table::new();
+        table.set_header(vec!["Signal".to_string(), format!("IC at {}ms", horizon_ms)]);
+        for (name, signal) in signals {
+            let ic = self.information_coefficient(symbol, horizon_ms, signal);
+            table.add_row(vec![name.to_string(), ic.map_or("n/a".to_string(), |ic| format!("{:.4}", ic))]);
+        }
+
+        info!("\nSIGNAL INFORMATION COEFFICIENTS FOR {}", symbol);
+        info!("{}", table);
+    }
+
+    /// Avellaneda-Stoikov style inventory penalty for a symbol.
+    ///
+    /// Integrates `risk_aversion * sigma^2 * q^2` over time, where `q` is the inventory
+    /// held between fills and `sigma^2` is the mid-price variance per second estimated
+    /// from the stored order books. Returns 0.0 when the penalty is disabled.
+    pub fn inventory_penalty(&self, symbol: &str) -> f64 {
+        if self.risk_aversion <= 0.0 {
+            return 0.0;
+        }
+
+        let variance_rate = self.mid_variance_rate(symbol);
+        if variance_rate <= 0.0 {
+            return 0.0;
+        }
+
+        let trades: Vec<&Trade> = self.trade_state.get_trades_history()
+            .into_iter()
+            .filter(|t| t.symbol == symbol)
+            .collect();
+        if trades.is_empty() {
+            return 0.0;
+        }
+
+        // Inventory held after the last fill is carried until the last stored book
+        let end_time = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol)
+            .map(|ob| ob.current_time)
+            .fold(trades[trades.len() - 1].time, i64::max);
+
+        let mut inventory = 0.0;
+        let mut inventory_sq_seconds = 0.0;
+        for (i, trade) in trades.iter().enumerate() {
+            if trade.side.to_lowercase() == "buy" {
+                inventory += trade.quantity;
+            } else {
+                inventory -= trade.quantity;
+            }
+
+            let next_time = trades.get(i + 1).map(|t| t.time).unwrap_or(end_time);
+            let held_seconds = (next_time - trade.time).max(0) as f64 / 1000.0;
+            inventory_sq_seconds += inventory * inventory * held_seconds;
+        }
+
+        self.risk_aversion * variance_rate * inventory_sq_seconds
+    }
+
+    /// Rolling inventory risk at every stored `symbol` book: the net position times the standard
+    /// deviation of mid changes over the trailing `window_ms`. Peaks mark where the strategy
+    /// carried exposure into volatile conditions.
+    pub fn rolling_inventory_risk(&self, symbol: &str, window_ms: i64) -> Vec<InventoryRisk> {
+        let mut mids: Vec<(i64, f64)> = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol && ob.mid_price() > 0.0)
+            .map(|ob| (ob.current_time, ob.mid_price()))
+            .collect();
+        mids.sort_by_key(|(time, _)| *time);
+        let fills: Vec<&Trade> = self.trade_state.get_trades_history()
+            .into_iter()
+            .filter(|t| t.symbol == symbol)
+            .collect();
+
+        let mut series = Vec::with_capacity(mids.len());
+        let mut inventory = 0.0;
+        let mut next_fill = 0;
+        let mut window_start = 0;
+        for (i, &(time, _)) in mids.iter().enumerate() {
+            while let Some(fill) = fills.get(next_fill).filter(|f| f.time <= time) {
+                inventory += if fill.side.eq_ignore_ascii_case("buy") { fill.quantity } else { -fill.quantity };
+                next_fill += 1;
+            }
+            inventory = snap_quantity(inventory);
+            while time - mids[window_start].0 > window_ms {
+                window_start += 1;
+            }
+
+            let moves: Vec<f64> = mids[window_start..=i].windows(2).map(|w| w[1].1 - w[0].1).collect();
+            let volatility = if moves.is_empty() {
+                0.0
+            } else {
+                let mean = moves.iter().sum::<f64>() / moves.len() as f64;
+                (moves.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / moves.len() as f64).sqrt()
+            };
+            series.push(InventoryRisk {
+                time,
+                inventory,
+                volatility,
+                risk: self.instruments.notional(symbol, volatility, inventory.abs()),
+            });
+        }
+        series
+    }
+
+    /// Moment of highest rolling inventory risk; None when no book carried any
+    pub fn peak_inventory_risk(&self, symbol: &str, window_ms: i64) -> Option<InventoryRisk> {
+        self.rolling_inventory_risk(symbol, window_ms).into_iter()
+            .filter(|point| point.risk > 0.0)
+            .max_by(|a, b| a.risk.total_cmp(&b.risk))
+    }
+
+    /// Realized variance of the mid price per second for a symbol
+    fn mid_variance_rate(&self, symbol: &str) -> f64 {
+        let mids: Vec<(i64, f64)> = self.trade_state.get_orderbooks().iter()
+            .filter(|ob| ob.symbol == symbol)
+            .map(|ob| (ob.current_time, ob.mid_price()))
+            .filter(|(_, mid)| *mid > 0.0)
+            .collect();
+
+        if mids.len() < 2 {
+            return 0.0;
+        }
+
+        let squared_moves: f64 = mids.windows(2).map(|w| (w[1].1 - w[0].1).powi(2)).sum();
+        let elapsed_seconds = (mids[mids.len() - 1].0 - mids[0].0) as f64 / 1000.0;
+
+        if elapsed_seconds <= 0.0 {
+            0.0
+        } else {
+            squared_moves / elapsed_seconds
         }
     }
 
@@ -181,9 +714,9 @@ impl TradeDashboard {
             };
             
             // Calculate new average price
-            let new_avg_price = if quantity == 0.0 {
+            let new_avg_price = if quantity.abs() < QUANTITY_EPSILON {
                 trade.price
-            } else if (quantity > 0.0 && trade_quantity > 0.0) || (quantity < 0.0 && trade_quantity < 0.0) {
+            } else if quantity.signum() == trade_quantity.signum() {
                 let total_value = quantity.abs() * avg_price + trade_quantity.abs() * trade.price;
                 let total_quantity = quantity.abs() + trade_quantity.abs();
                 total_value / total_quantity
@@ -194,7 +727,7 @@ impl TradeDashboard {
             // Update quantity
             let new_quantity = quantity + trade_quantity;
             
-            if new_quantity.abs() < 1e-8 {
+            if new_quantity.abs() < QUANTITY_EPSILON {
                 self.positions.insert(trade.symbol.clone(), 0.0);
                 self.avg_prices.insert(trade.symbol.clone(), 0.0);
             } else {
@@ -213,7 +746,7 @@ impl TradeDashboard {
         let mut total_open_positions_value = 0.0;
         
         for (symbol, quantity) in &self.positions {
-            if quantity.abs() < 1e-8 {
+            if quantity.abs() < QUANTITY_EPSILON {
                 continue;
             }
             
@@ -222,16 +755,12 @@ impl TradeDashboard {
             let avg_price = self.avg_prices.get(symbol).unwrap_or(&0.0);
             
             // Unrealized PnL
-            let unrealized_pnl = if *quantity > 0.0 {
-                (current_price - avg_price) * quantity
-            } else {
-                (avg_price - current_price) * quantity.abs()
-            };
+            let unrealized_pnl = self.instruments.notional(symbol, (current_price - avg_price) * quantity.signum(), quantity.abs());
             
             total_unrealized_pnl += unrealized_pnl;
             
             // Open positions value
-            let position_value = quantity.abs() * current_price;
+            let position_value = self.instruments.notional(symbol, *current_price, quantity.abs());
             total_open_positions_value += position_value;
             
             // Margin requirement
@@ -262,6 +791,8 @@ impl TradeDashboard {
                 average_capital_utilization: 0.0,
                 peak_margin_requirement: 0.0,
                 max_unrealized_loss: 0.0,
+                margin_hours: 0.0,
+                pnl_per_margin_hour: 0.0,
             };
         }
         
@@ -272,6 +803,14 @@ impl TradeDashboard {
         let peak_margin_requirement = self.margin_history.iter().fold(0.0_f64, |a, &b| a.max(b));
         let max_unrealized_loss = self.pnl_history.iter().fold(0.0_f64, |a, &b| a.min(b));
         
+        // Each margin sample holds until the next one
+        let margin_hours = self.margin_history.iter()
+            .zip(self.timestamp_history.windows(2))
+            .map(|(margin, times)| margin * (times[1] - times[0]) as f64 / 3_600_000.0)
+            .sum::<f64>();
+        let realized_pnl = self.process_trades(&self.trade_state.get_trades_history(), symbol).total_pnl;
+        let pnl_per_margin_hour = if margin_hours > 0.0 { realized_pnl / margin_hours } else { 0.0 };
+        
         CapitalMetrics {
             max_required_capital,
             max_drawdown: max_drawdown.abs(),
@@ -279,6 +818,8 @@ impl TradeDashboard {
             average_capital_utilization,
             peak_margin_requirement,
             max_unrealized_loss: max_unrealized_loss.abs(),
+            margin_hours,
+            pnl_per_margin_hour,
         }
     }
 
@@ -294,15 +835,39 @@ impl TradeDashboard {
         let total_pnl = pnl_result.total_pnl;
         let total_unrealized_pnl = pnl_result.unrealized_pnl;
         let total_pnl_with_unrealized = total_pnl + total_unrealized_pnl;
+        let headline_pnl = self.include_unrealized.total(total_pnl, total_unrealized_pnl);
         let total_fees = pnl_result.total_fees;
+        let turnover: f64 = self.trade_state.get_trades_history().iter()
+            .filter(|t| t.symbol == symbol)
+            .map(|t| self.instruments.notional(symbol, t.price, t.quantity))
+            .sum();
+        let fee_ratio = fee_to_pnl_ratio(total_fees, headline_pnl);
         
         let mut table = Table::new();
         table.set_header(vec!["Metric", "Value"]);
         table.add_row(vec!["Total realized PnL", &format!("${:.2}", total_pnl)]);
         table.add_row(vec!["Trading fees", &format!("${:.2}", total_fees)]);
         table.add_row(vec!["Net realized PnL", &format!("${:.2}", total_pnl)]);
-        table.add_row(vec!["Unrealized PnL", &format!("${:.2}", total_unrealized_pnl)]);
-        table.add_row(vec!["Total PnL", &format!("${:.2}", total_pnl_with_unrealized)]);
+        if self.include_unrealized == IncludeUnrealized::Yes {
+            table.add_row(vec!["Unrealized PnL", &format!("${:.2}", total_unrealized_pnl)]);
+        }
+        table.add_row(vec!["Total PnL", &format!("${:.2}", headline_pnl)]);
+        let inventory_penalty = self.inventory_penalty(symbol);
+        if self.risk_aversion > 0.0 {
+            table.add_row(vec!["Inventory penalty", &format!("${:.2}", inventory_penalty)]);
+            table.add_row(vec!["Penalized PnL", &format!("${:.2}", headline_pnl - inventory_penalty)]);
+        }
+        let peak_risk = (self.inventory_risk_window_ms > 0)
+            .then(|| self.peak_inventory_risk(symbol, self.inventory_risk_window_ms))
+            .flatten();
+        if self.inventory_risk_window_ms > 0 {
+            let peak = peak_risk.as_ref().map_or("none".to_string(), |peak| {
+                format!("${:.2} at {} ({} held, {:.4} vol)", peak.risk, peak.time, peak.inventory, peak.volatility)
+            });
+            table.add_row(vec!["Peak inventory risk", &peak]);
+        }
+        table.add_row(vec!["Turnover", &format!("${:.2}", turnover)]);
+        table.add_row(vec!["Fees / gross PnL", &format!("{:.4}", fee_ratio)]);
         table.add_row(vec!["Fill rate", &format!("{:.2}%", costs.get("fill_rate").unwrap_or(&0.0) * 100.0)]);
         table.add_row(vec!["Buy trades", &costs.get("buy_trades").unwrap_or(&0.0).to_string()]);
         table.add_row(vec!["Sell trades", &costs.get("sell_trades").unwrap_or(&0.0).to_string()]);
@@ -315,6 +880,15 @@ impl TradeDashboard {
         summary.insert("total_fees", total_fees);
         summary.insert("unrealized_pnl", total_unrealized_pnl);
         summary.insert("total_pnl_with_unrealized", total_pnl_with_unrealized);
+        summary.insert("headline_pnl", headline_pnl);
+        summary.insert("inventory_penalty", inventory_penalty);
+        summary.insert("penalized_pnl", headline_pnl - inventory_penalty);
+        if let Some(peak) = &peak_risk {
+            summary.insert("peak_inventory_risk", peak.risk);
+            summary.insert("peak_inventory_risk_time", peak.time as f64);
+        }
+        summary.insert("turnover", turnover);
+        summary.insert("fee_to_pnl_ratio", fee_ratio);
         summary.insert("buy_trades", *costs.get("buy_trades").unwrap_or(&0.0));
         summary.insert("sell_trades", *costs.get("sell_trades").unwrap_or(&0.0));
         summary.insert("fill_rate", *costs.get("fill_rate").unwrap_or(&0.0));
@@ -332,12 +906,33 @@ impl TradeDashboard {
             table.add_row(vec!["Max open positions value", &format!("${:.2}", metrics.max_open_positions_value)]);
             table.add_row(vec!["Average capital utilization", &format!("${:.2}", metrics.average_capital_utilization)]);
             table.add_row(vec!["Max unrealized loss", &format!("${:.2}", metrics.max_unrealized_loss)]);
+            table.add_row(vec!["Margin-hours (notional × h)", &format!("{:.2}", metrics.margin_hours)]);
+            table.add_row(vec!["P&L per margin-hour", &format!("{:.4}", metrics.pnl_per_margin_hour)]);
             
             info!("\nCAPITAL REQUIREMENTS FOR {}", symbol);
             info!("{}", table);
         }
     }
 
+    /// Machine-readable summary of `symbol`: P&L, execution counts and its capital metrics
+    /// (`null` when `capital_metrics` has no entry for it)
+    pub fn to_json(&self, symbol: &str, pnl_results: &HashMap<String, PnLResult>, capital_metrics: &HashMap<String, CapitalMetrics>) -> serde_json::Value {
+        let costs = self.calculate_trading_costs(symbol);
+        let count = |key: &str| *costs.get(key).unwrap_or(&0.0) as usize;
+        let pnl_result = pnl_results.get(symbol);
+        serde_json::json!({
+            "symbol": symbol,
+            "total_pnl": pnl_result.map_or(0.0, |r| r.total_pnl),
+            "unrealized_pnl": pnl_result.map_or(0.0, |r| r.unrealized_pnl),
+            "total_fees": pnl_result.map_or(0.0, |r| r.total_fees),
+            "fill_rate": *costs.get("fill_rate").unwrap_or(&0.0),
+            "buy_trades": count("buy_trades"),
+            "sell_trades": count("sell_trades"),
+            "closed_trades": pnl_result.map_or(0, |r| r.closed_trades.len()),
+            "capital_metrics": capital_metrics.get(symbol),
+        })
+    }
+
     pub fn to_console(&self, symbol: &str, pnl_results: &HashMap<String, PnLResult>, capital_metrics: &HashMap<String, CapitalMetrics>) {
         info!("\nComplete summary for {}:", symbol);
         
@@ -347,4 +942,296 @@ impl TradeDashboard {
             self.print_capital_metrics(capital_metrics);
         }
     }
-}
\ No newline at end of file
+}
+/// Pearson correlation of the pairs; None with fewer than two or when either side is constant
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance_x: f64 = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let variance_y: f64 = pairs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    (variance_x > 0.0 && variance_y > 0.0).then(|| covariance / (variance_x * variance_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled_trade(side: &str, price: f64, quantity: f64, time: i64) -> Trade {
+        let mut trade = Trade::new(time, "BTCUSDT".to_string(), side.to_string(), price, quantity);
+        trade.status = "filled".to_string();
+        trade
+    }
+
+    fn book(mid: f64, time: i64) -> OrderBook {
+        OrderBook::new(
+            "BTCUSDT".to_string(),
+            vec![(mid - 0.5, 1.0)],
+            vec![(mid + 0.5, 1.0)],
+            time,
+        )
+    }
+
+    fn dashboard_with(trades: Vec<Trade>) -> TradeDashboard {
+        let mut trade_state = TradeState::new();
+        for trade in trades {
+            trade_state.add(trade);
+        }
+        for (mid, time) in [(100.0, 0), (102.0, 5_000), (101.0, 10_000)] {
+            trade_state.add_orderbook(book(mid, time));
+        }
+        TradeDashboard::new(trade_state, 0.05).with_risk_aversion(0.1)
+    }
+
+    #[test]
+    fn test_inventory_penalty_prefers_smaller_inventory() {
+        // Both runs realize $10, but the second parks twice the inventory
+        let mut small = dashboard_with(vec![
+            filled_trade("Buy", 100.0, 1.0, 0),
+            filled_trade("Sell", 110.0, 1.0, 10_000),
+        ]);
+        let mut large = dashboard_with(vec![
+            filled_trade("Buy", 100.0, 2.0, 0),
+            filled_trade("Sell", 105.0, 2.0, 10_000),
+        ]);
+
+        let small_pnl = small.pnl("BTCUSDT");
+        let large_pnl = large.pnl("BTCUSDT");
+        assert_eq!(small_pnl["BTCUSDT"].total_pnl, large_pnl["BTCUSDT"].total_pnl);
+
+        let small_summary = small.print_pnl_metrics("BTCUSDT", &small_pnl);
+        let large_summary = large.print_pnl_metrics("BTCUSDT", &large_pnl);
+
+        assert!(large.inventory_penalty("BTCUSDT") > small.inventory_penalty("BTCUSDT"));
+        assert!(large_summary["penalized_pnl"] < small_summary["penalized_pnl"]);
+    }
+
+    #[test]
+    fn test_tick_level_equity_captures_dip() {
+        let mut trade_state = TradeState::new();
+        trade_state.add(filled_trade("Buy", 100.0, 1.0, 0));
+        trade_state.add(filled_trade("Sell", 100.0, 1.0, 3_000));
+        for (mid, time) in [(100.0, 0), (90.0, 1_000), (100.0, 2_000), (100.0, 3_000)] {
+            trade_state.add_orderbook(book(mid, time));
+        }
+
+        let dashboard = TradeDashboard::new(trade_state, 0.05);
+        let per_trade_low = dashboard.equity_curve("BTCUSDT").iter().map(|(_, e)| *e).fold(0.0, f64::min);
+        assert_eq!(per_trade_low, 0.0);
+
+        let dashboard = dashboard.with_mark_to_market_every_tick(true);
+        let curve = dashboard.equity_curve("BTCUSDT");
+        let tick_low = curve.iter().map(|(_, e)| *e).fold(0.0, f64::min);
+        assert_eq!(tick_low, -10.0);
+        assert!(curve.contains(&(1_000, -10.0)));
+        assert_eq!(curve.last().unwrap().1, 0.0);
+    }
+
+    #[test]
+    fn test_markout_on_known_price_path() {
+        let mut trade_state = TradeState::new();
+        // Buy right before the price drops, sell right before it recovers
+        trade_state.add(filled_trade("Buy", 100.0, 1.0, 0));
+        trade_state.add(filled_trade("Sell", 98.0, 1.0, 1_000));
+        for (mid, time) in [(100.0, 0), (99.0, 100), (98.0, 1_000), (99.0, 1_100), (100.0, 2_000)] {
+            trade_state.add_orderbook(book(mid, time));
+        }
+
+        let dashboard = TradeDashboard::new(trade_state, 0.05);
+        let summaries = dashboard.markout_analysis("BTCUSDT", &[100, 1_000, 5_000]);
+        let find = |horizon: i64, side: &str| {
+            summaries.iter().find(|s| s.horizon_ms == horizon && s.side == side).unwrap().clone()
+        };
+
+        assert_eq!(find(100, "Buy").avg_markout, -1.0);
+        assert_eq!(find(1_000, "Buy").avg_markout, -2.0);
+        assert_eq!(find(100, "Sell").avg_markout, -1.0);
+        assert_eq!(find(1_000, "Sell").avg_markout, -2.0);
+        assert!((find(1_000, "Buy").avg_markout_bps + 200.0).abs() < 1e-9);
+
+        // Horizon beyond the stored books has no observations
+        assert_eq!(find(5_000, "Buy").fills, 0);
+    }
+
+    #[test]
+    fn test_inventory_penalty_disabled_by_default() {
+        let trade_state = dashboard_with(vec![filled_trade("Buy", 100.0, 1.0, 0)]).trade_state;
+        let dashboard = TradeDashboard::new(trade_state, 0.05);
+        assert_eq!(dashboard.inventory_penalty("BTCUSDT"), 0.0);
+    }
+
+    #[test]
+    fn test_pnl_per_margin_hour() {
+        const HOUR: i64 = 3_600_000;
+        let mut trade_state = TradeState::new();
+        // Margin is 5 for the first hour, 10 for the next two: 25 margin-hours for $20
+        for trade in [
+            filled_trade("Buy", 100.0, 1.0, 0),
+            filled_trade("Buy", 100.0, 1.0, HOUR),
+            filled_trade("Sell", 110.0, 2.0, 3 * HOUR),
+        ] {
+            trade_state.add(trade);
+        }
+        let mut dashboard = TradeDashboard::new(trade_state, 0.05);
+
+        let metrics = dashboard.get_capital_metrics("BTCUSDT");
+        assert!((metrics.margin_hours - 25.0).abs() < 1e-9);
+        assert!((metrics.pnl_per_margin_hour - 0.8).abs() < 1e-9);
+
+        let mut idle = TradeDashboard::new(TradeState::new(), 0.05);
+        let metrics = idle.get_capital_metrics("BTCUSDT");
+        assert_eq!(metrics.margin_hours, 0.0);
+        assert_eq!(metrics.pnl_per_margin_hour, 0.0);
+    }
+
+    #[test]
+    fn test_equity_and_capital_scale_by_contract_size() {
+        let trade_state = dashboard_with(vec![
+            filled_trade("Buy", 100.0, 1.0, 0),
+            filled_trade("Sell", 110.0, 1.0, 10_000),
+        ])
+        .trade_state;
+        let instruments = InstrumentSpecRegistry::new()
+            .with_spec("BTCUSDT", crate::trading::InstrumentSpec { contract_size: 10.0, ..Default::default() });
+        let mut dashboard = TradeDashboard::new(trade_state, 0.05).with_instruments(instruments);
+
+        let total_pnl = dashboard.pnl("BTCUSDT")["BTCUSDT"].total_pnl;
+        assert_eq!(total_pnl, 100.0);
+        assert_eq!(dashboard.equity_curve("BTCUSDT").last().unwrap().1, total_pnl);
+
+        let metrics = dashboard.get_capital_metrics("BTCUSDT");
+        assert_eq!(metrics.max_open_positions_value, 1000.0);
+        assert!((metrics.peak_margin_requirement - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_excursions_on_known_price_path() {
+        let mut trade_state = TradeState::new();
+        // Long from 100 to 102: peaks at 105, troughs at 97
+        trade_state.add(filled_trade("Buy", 100.0, 1.0, 0));
+        trade_state.add(filled_trade("Sell", 102.0, 1.0, 4_000));
+        // Short from 102 to 101 after the exit: only the later books count
+        trade_state.add(filled_trade("Sell", 102.0, 1.0, 5_000));
+        trade_state.add(filled_trade("Buy", 101.0, 1.0, 6_000));
+        for (mid, time) in [(100.0, 0), (105.0, 1_000), (97.0, 2_000), (102.0, 4_000), (103.0, 5_500), (101.0, 6_000)] {
+            trade_state.add_orderbook(book(mid, time));
+        }
+
+        let dashboard = TradeDashboard::new(trade_state, 0.05);
+        let excursions = dashboard.excursions("BTCUSDT");
+        assert_eq!(excursions.len(), 2);
+        assert_eq!((excursions[0].side.as_str(), excursions[0].mfe, excursions[0].mae), ("Buy", 5.0, 3.0));
+        assert_eq!((excursions[1].side.as_str(), excursions[1].mfe, excursions[1].mae), ("Sell", 1.0, 1.0));
+
+        let summary = dashboard.excursion_summary("BTCUSDT");
+        assert_eq!(summary.trades, 2);
+        assert_eq!(summary.avg_mfe, 3.0);
+        assert_eq!(summary.avg_mae, 2.0);
+        assert!((summary.avg_mfe_bps - (500.0 + 1.0 / 102.0 * 10_000.0) / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_information_coefficient_of_perfect_signal() {
+        let mids = [100.0, 101.0, 100.5, 102.0, 101.0, 101.5, 103.0, 102.0];
+        let mut trade_state = TradeState::new();
+        for (i, mid) in mids.iter().enumerate() {
+            trade_state.add_orderbook(book(*mid, i as i64 * 1_000));
+        }
+        let dashboard = TradeDashboard::new(trade_state, 0.05);
+
+        // A signal that is an affine function of the next 1s return predicts it perfectly
+        let future_return = |ob: &OrderBook| {
+            let i = (ob.current_time / 1_000) as usize;
+            mids.get(i + 1).map(|next| next / mids[i] - 1.0)
+        };
+        let ic = dashboard.information_coefficient("BTCUSDT", 1_000, |ob| future_return(ob).map(|r| 3.0 * r + 0.5)).unwrap();
+        assert!((ic - 1.0).abs() < 1e-9);
+        let ic = dashboard.information_coefficient("BTCUSDT", 1_000, |ob| future_return(ob).map(|r| -r)).unwrap();
+        assert!((ic + 1.0).abs() < 1e-9);
+
+        // A constant signal carries no information
+        assert_eq!(dashboard.information_coefficient("BTCUSDT", 1_000, |_| Some(1.0)), None);
+        assert_eq!(dashboard.information_coefficient("ETHUSDT", 1_000, |_| Some(1.0)), None);
+    }
+
+    #[test]
+    fn test_peak_inventory_risk_on_scripted_path() {
+        let mut trade_state = TradeState::new();
+        // Flat through the early swings, long 2 through the calm, long 3 into the late swings
+        trade_state.add(filled_trade("Buy", 100.0, 2.0, 5_000));
+        trade_state.add(filled_trade("Buy", 100.0, 1.0, 7_000));
+        trade_state.add(filled_trade("Sell", 100.0, 3.0, 10_000));
+        let mids = [100.0, 104.0, 98.0, 103.0, 100.0, 100.5, 100.0, 100.0, 102.0, 99.0, 101.0, 101.0];
+        for (i, mid) in mids.iter().enumerate() {
+            trade_state.add_orderbook(book(*mid, i as i64 * 1_000));
+        }
+        let mut dashboard = TradeDashboard::new(trade_state, 0.05).with_inventory_risk_window(2_000);
+
+        let series = dashboard.rolling_inventory_risk("BTCUSDT", 2_000);
+        assert_eq!(series.len(), mids.len());
+        assert!(series[..5].iter().all(|p| p.inventory == 0.0 && p.risk == 0.0));
+        assert_eq!((series[5].inventory, series[8].inventory, series[10].inventory), (2.0, 3.0, 0.0));
+        // Window [7s, 9s] holds the moves 0 and +2 then [8s, 10s] the moves +2 and -3
+        assert_eq!(series[8].volatility, 1.0);
+        assert_eq!(series[9].volatility, 2.5);
+        assert_eq!(series[9].risk, 7.5);
+
+        let peak = dashboard.peak_inventory_risk("BTCUSDT", 2_000).unwrap();
+        assert_eq!(peak.time, 9_000);
+        let pnl = dashboard.pnl("BTCUSDT");
+        let summary = dashboard.print_pnl_metrics("BTCUSDT", &pnl);
+        assert_eq!(summary["peak_inventory_risk_time"], 9_000.0);
+
+        assert_eq!(dashboard.peak_inventory_risk("ETHUSDT", 2_000), None);
+    }
+
+    #[test]
+    fn test_json_summary_matches_dashboard() {
+        let mut trade_state = TradeState::new();
+        for trade in [
+            filled_trade("Buy", 100.0, 2.0, 0),
+            filled_trade("Sell", 103.0, 1.0, 1_000),
+            filled_trade("Buy", 101.0, 1.0, 2_000),
+        ] {
+            trade_state.add(trade);
+        }
+        let mut dashboard = TradeDashboard::new(trade_state, 0.05);
+        let pnl_results = dashboard.pnl("BTCUSDT");
+        let capital_metrics = HashMap::from([("BTCUSDT".to_string(), dashboard.get_capital_metrics("BTCUSDT"))]);
+
+        let json = dashboard.to_json("BTCUSDT", &pnl_results, &capital_metrics);
+        let parsed: serde_json::Value = serde_json::from_str(&json.to_string()).unwrap();
+        let pnl = &pnl_results["BTCUSDT"];
+        assert_eq!(parsed["symbol"], "BTCUSDT");
+        assert_eq!(parsed["total_pnl"].as_f64().unwrap(), pnl.total_pnl);
+        assert_eq!(parsed["unrealized_pnl"].as_f64().unwrap(), pnl.unrealized_pnl);
+        assert_eq!(parsed["fill_rate"].as_f64().unwrap(), 1.0);
+        assert_eq!(parsed["buy_trades"], 2);
+        assert_eq!(parsed["sell_trades"], 1);
+        assert_eq!(parsed["closed_trades"], 1);
+
+        let capital: CapitalMetrics = serde_json::from_value(parsed["capital_metrics"].clone()).unwrap();
+        assert_eq!(capital.max_required_capital, capital_metrics["BTCUSDT"].max_required_capital);
+        assert_eq!(capital.margin_hours, capital_metrics["BTCUSDT"].margin_hours);
+
+        let without_capital = dashboard.to_json("BTCUSDT", &pnl_results, &HashMap::new());
+        assert!(without_capital["capital_metrics"].is_null());
+    }
+
+    #[test]
+    fn test_from_config_takes_margin_rate_from_config() {
+        let mut trade_state = TradeState::new();
+        trade_state.add(filled_trade("Buy", 100.0, 2.0, 0));
+        let config = BacktestConfig { margin_rate: 0.1, ..Default::default() };
+
+        let mut dashboard = TradeDashboard::from_config(trade_state, &config);
+        assert_eq!(dashboard.margin_rate(), 0.1);
+        let metrics = dashboard.get_capital_metrics("BTCUSDT");
+        assert!((metrics.peak_margin_requirement - 20.0).abs() < 1e-9);
+        assert!((metrics.max_open_positions_value - 200.0).abs() < 1e-9);
+    }
+}
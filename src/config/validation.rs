@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::core::{Result, TradeError};
-use crate::trading::BacktestConfig;
+use crate::trading::{BacktestConfig, SizingMode};
 use crate::strategy::GptMarketMakerConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,13 +64,69 @@ pub fn validate_config(config: &AppConfig) -> Result<()> {
         ));
     }
     
+    if config.backtest.max_order_volume < 0.0 {
+        return Err(TradeError::InvalidTradeParameters(
+            format!("Max order volume must be non-negative, got {}", config.backtest.max_order_volume)
+        ));
+    }
+    
+    match config.backtest.sizing_mode {
+        SizingMode::PercentOfEquity(pct) | SizingMode::PercentOfTopOfBook(pct) if !(pct > 0.0 && pct <= 100.0) => {
+            return Err(TradeError::InvalidTradeParameters(
+                format!("Sizing percentage must be in (0, 100], got {}", pct)
+            ));
+        }
+        SizingMode::PercentOfEquity(_) if config.backtest.initial_equity <= 0.0 => {
+            return Err(TradeError::InvalidTradeParameters(
+                format!("Initial equity must be positive, got {}", config.backtest.initial_equity)
+            ));
+        }
+        _ => {}
+    }
+    
+    if config.backtest.resample_interval_ms < 0 {
+        return Err(TradeError::InvalidTradeParameters(
+            format!("Resample interval must be non-negative, got {}", config.backtest.resample_interval_ms)
+        ));
+    }
+    
+    if config.backtest.max_hold_ms < 0 {
+        return Err(TradeError::InvalidTradeParameters(
+            format!("Max hold time must be non-negative, got {}", config.backtest.max_hold_ms)
+        ));
+    }
+    
+    if let Some(filter) = &config.backtest.mid_price_filter {
+        if filter.window == 0 || filter.max_deviation_mads <= 0.0 {
+            return Err(TradeError::InvalidTradeParameters(
+                format!("Mid price filter needs a positive window and deviation, got {:?}", filter)
+            ));
+        }
+    }
+    
+    for (side, slippage) in [
+        ("Buy", config.backtest.buy_slippage_bps),
+        ("Sell", config.backtest.sell_slippage_bps),
+    ] {
+        if let Some(bps) = slippage {
+            if bps < 0.0 {
+                return Err(TradeError::InvalidTradeParameters(
+                    format!("{} slippage must be non-negative, got {}", side, bps)
+                ));
+            }
+        }
+    }
+    
     // Validate strategy config
     match config.strategy.name.as_str() {
         "gpt" => {
-            if config.strategy.gpt_market_maker.is_none() {
-                return Err(TradeError::InvalidTradeParameters(
-                    "GPT Market Maker config is required when using 'gpt' strategy".to_string()
-                ));
+            match &config.strategy.gpt_market_maker {
+                Some(gpt_config) => gpt_config.validate()?,
+                None => {
+                    return Err(TradeError::InvalidTradeParameters(
+                        "GPT Market Maker config is required when using 'gpt' strategy".to_string()
+                    ));
+                }
             }
         }
         _ => {
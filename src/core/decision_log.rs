@@ -0,0 +1,90 @@
+use super::errors::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Outcome of consulting the strategy on one order book
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Decision {
+    pub time: i64,
+    pub symbol: String,
+    /// Whether an order was sent to the executor or quote simulator
+    pub traded: bool,
+    /// Strategy or engine reason, e.g. `VOLATILITY_COOLDOWN: 3.0s remaining`
+    pub reason: String,
+}
+
+impl Decision {
+    /// Reason without its detail suffix, used to tally gates (`TAKE_PROFIT: 21.0 bps` -> `TAKE_PROFIT`)
+    pub fn reason_kind(&self) -> &str {
+        self.reason.split(':').next().unwrap_or("").trim()
+    }
+}
+
+/// Per-tick decisions collected over a run
+#[derive(Debug, Clone, Default)]
+pub struct DecisionLog {
+    decisions: Vec<Decision>,
+}
+
+impl DecisionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, time: i64, symbol: &str, traded: bool, reason: &str) {
+        self.decisions.push(Decision {
+            time,
+            symbol: symbol.to_string(),
+            traded,
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Append another run's decisions, e.g. when merging per-file results
+    pub fn extend_from(&mut self, other: &DecisionLog) {
+        self.decisions.extend(other.decisions.iter().cloned());
+    }
+
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
+    /// Number of decisions per reason kind, most frequent first
+    pub fn reason_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for decision in &self.decisions {
+            *counts.entry(decision.reason_kind()).or_default() += 1;
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter()
+            .map(|(reason, count)| (reason.to_string(), count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// Multi-line table of reason frequencies
+    pub fn summary(&self) -> String {
+        let total = self.decisions.len();
+        let traded = self.decisions.iter().filter(|d| d.traded).count();
+        let mut summary = format!("Decisions: {} ticks, {} traded, {} skipped\n", total, traded, total - traded);
+        for (reason, count) in self.reason_counts() {
+            summary.push_str(&format!("  {:<24} {:>8} ({:.1}%)\n", reason, count, count as f64 * 100.0 / total as f64));
+        }
+        summary
+    }
+
+    /// Write every decision to CSV
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "time,symbol,traded,reason")?;
+        for decision in &self.decisions {
+            writeln!(writer, "{},{},{},\"{}\"", decision.time, decision.symbol, decision.traded,
+                     decision.reason.replace('"', "\"\""))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -38,6 +38,10 @@ pub enum TradeError {
     
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
 }
 
 pub type Result<T> = std::result::Result<T, TradeError>;
\ No newline at end of file
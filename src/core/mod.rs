@@ -2,8 +2,10 @@ pub mod models;
 pub mod trade_state;
 pub mod errors;
 pub mod traits;
+pub mod decision_log;
 
 pub use models::*;
-pub use trade_state::TradeState;
+pub use trade_state::{TradeRamp, TradeState};
 pub use errors::{TradeError, Result};
+pub use decision_log::{Decision, DecisionLog};
 pub use traits::{DataSource, TradeExecutor, ExecutionStats};
\ No newline at end of file
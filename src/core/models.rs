@@ -10,6 +10,24 @@ pub struct Trade {
     pub quantity: f64,
     pub status: String,
     pub id: String,
+    /// Only closes existing exposure; in hedge mode this picks the leg opposite to `side`
+    #[serde(default)]
+    pub reduce_only: bool,
+    /// Whether the fill added or took liquidity (None until executed)
+    #[serde(default)]
+    pub liquidity: Option<Liquidity>,
+    /// Exchange fee paid on the fill, in quote currency
+    #[serde(default)]
+    pub fee: f64,
+}
+
+/// Side of the book a fill traded on, which decides its fee rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Liquidity {
+    /// Rested on the book and was traded against
+    Maker,
+    /// Crossed the spread against resting orders
+    Taker,
 }
 
 impl Trade {
@@ -28,16 +46,42 @@ impl Trade {
             quantity,
             status: "pending".to_string(),
             id: Uuid::new_v4().to_string(),
+            reduce_only: false,
+            liquidity: None,
+            fee: 0.0,
         }
     }
+
+    /// Mark the trade as closing existing exposure only
+    pub fn with_reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = reduce_only;
+        self
+    }
+}
+
+/// How book levels are weighted when computing order book imbalance
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ImbalanceWeighting {
+    /// Sum of raw level quantities
+    #[default]
+    Quantity,
+    /// Sum of price * quantity per level
+    Notional,
 }
 
+/// Order book snapshot with every level the data source provided
+///
+/// Loaders and the engine pass books through untruncated, so strategies see the
+/// complete book; only the copies kept for P&L marking are cut to `stored_book_depth`.
+/// The top-5 helpers below are conveniences, not a limit on what is available.
 #[derive(Debug, Clone)]
 pub struct OrderBook {
     pub symbol: String,
     pub bids: Vec<(f64, f64)>, // (price, quantity)
     pub asks: Vec<(f64, f64)>, // (price, quantity)
     pub current_time: i64,
+    /// Exchange sequence number of the update, when the data source carries one
+    pub update_id: Option<i64>,
 }
 
 impl OrderBook {
@@ -47,21 +91,48 @@ impl OrderBook {
             bids,
             asks,
             current_time,
+            update_id: None,
+        }
+    }
+
+    pub fn with_update_id(mut self, update_id: i64) -> Self {
+        self.update_id = Some(update_id);
+        self
+    }
+
+    /// Copy of the book keeping only the top `depth` levels on each side
+    pub fn truncated(&self, depth: usize) -> OrderBook {
+        OrderBook {
+            symbol: self.symbol.clone(),
+            bids: self.bids.iter().take(depth).cloned().collect(),
+            asks: self.asks.iter().take(depth).cloned().collect(),
+            current_time: self.current_time,
+            update_id: self.update_id,
         }
     }
 
+    /// Best bid as `(price, quantity)`, or `None` when the bid side is empty
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.first().copied()
+    }
+
+    /// Best ask as `(price, quantity)`, or `None` when the ask side is empty
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.first().copied()
+    }
+
     pub fn mid_price(&self) -> f64 {
-        if self.bids.is_empty() || self.asks.is_empty() {
-            return 0.0;
+        match (self.best_bid(), self.best_ask()) {
+            (Some((bid, _)), Some((ask, _))) => (bid + ask) / 2.0,
+            _ => 0.0,
         }
-        (self.bids[0].0 + self.asks[0].0) / 2.0
     }
 
     pub fn spread_abs(&self) -> f64 {
-        if self.bids.is_empty() || self.asks.is_empty() {
-            return 0.0;
+        match (self.best_bid(), self.best_ask()) {
+            (Some((bid, _)), Some((ask, _))) => ask - bid,
+            _ => 0.0,
         }
-        self.asks[0].0 - self.bids[0].0
     }
 
     pub fn spread_pct(&self) -> f64 {
@@ -73,12 +144,21 @@ impl OrderBook {
     }
 
     pub fn order_book_imbalance(&self) -> f64 {
+        self.order_book_imbalance_weighted(ImbalanceWeighting::Quantity)
+    }
+
+    /// Imbalance over the top 5 levels, in [-1, 1], using the given weighting
+    pub fn order_book_imbalance_weighted(&self, weighting: ImbalanceWeighting) -> f64 {
         if self.bids.is_empty() || self.asks.is_empty() {
             return 0.0;
         }
         
-        let bid_vol = self.bids.iter().take(5).map(|(_, v)| v).sum::<f64>();
-        let ask_vol = self.asks.iter().take(5).map(|(_, v)| v).sum::<f64>();
+        let weight = |(price, quantity): &(f64, f64)| match weighting {
+            ImbalanceWeighting::Quantity => *quantity,
+            ImbalanceWeighting::Notional => price * quantity,
+        };
+        let bid_vol = self.bids.iter().take(5).map(weight).sum::<f64>();
+        let ask_vol = self.asks.iter().take(5).map(weight).sum::<f64>();
         
         if bid_vol + ask_vol == 0.0 {
             return 0.0;
@@ -87,6 +167,24 @@ impl OrderBook {
         (bid_vol - ask_vol) / (bid_vol + ask_vol)
     }
 
+    /// Quantity summed over every bid level
+    pub fn total_bid_volume(&self) -> f64 {
+        self.bids.iter().map(|(_, quantity)| quantity).sum()
+    }
+
+    /// Quantity summed over every ask level
+    pub fn total_ask_volume(&self) -> f64 {
+        self.asks.iter().map(|(_, quantity)| quantity).sum()
+    }
+
+    /// Quantity resting at exactly `price` on either side, searching all levels
+    pub fn level_at_price(&self, price: f64) -> Option<f64> {
+        self.bids.iter()
+            .chain(self.asks.iter())
+            .find(|(level_price, _)| *level_price == price)
+            .map(|(_, quantity)| *quantity)
+    }
+
     pub fn avg_top_bid_depth(&self) -> f64 {
         if self.bids.is_empty() {
             return 0.0;
@@ -95,6 +193,18 @@ impl OrderBook {
     }
 }
 
+/// A trade joined with the order book that triggered it
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeContext {
+    pub trade: Trade,
+    pub book_time: i64,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    pub mid_price: f64,
+    pub spread_pct: f64,
+    pub imbalance: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClosedTrade {
     pub open_side: String,
@@ -112,9 +222,11 @@ pub struct PnLResult {
     pub closed_trades: Vec<ClosedTrade>,
     pub total_fees: f64,
     pub remaining_shares: f64,
+    /// Realized P&L per closing fill, in processing order
+    pub pnl_records: Vec<crate::pnl::Record>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapitalMetrics {
     pub max_required_capital: f64,
     pub max_drawdown: f64,
@@ -122,4 +234,46 @@ pub struct CapitalMetrics {
     pub average_capital_utilization: f64,
     pub peak_margin_requirement: f64,
     pub max_unrealized_loss: f64,
-}
\ No newline at end of file
+    /// Time integral of margin requirement, in notional × hours
+    pub margin_hours: f64,
+    /// Realized P&L per margin-dollar-hour (0.0 when no margin was used)
+    pub pnl_per_margin_hour: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_levels_on_degenerate_books() {
+        let bids_only = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 2.0), (99.5, 1.0)], vec![], 1000);
+        assert_eq!(bids_only.best_bid(), Some((100.0, 2.0)));
+        assert_eq!(bids_only.best_ask(), None);
+        assert_eq!(bids_only.mid_price(), 0.0);
+        assert_eq!(bids_only.spread_abs(), 0.0);
+
+        let asks_only = OrderBook::new("BTCUSDT".to_string(), vec![], vec![(100.5, 3.0)], 1000);
+        assert_eq!(asks_only.best_bid(), None);
+        assert_eq!(asks_only.best_ask(), Some((100.5, 3.0)));
+
+        let empty = OrderBook::new("BTCUSDT".to_string(), vec![], vec![], 1000);
+        assert_eq!(empty.best_bid(), None);
+        assert_eq!(empty.best_ask(), None);
+        assert_eq!(empty.spread_pct(), 0.0);
+    }
+
+    #[test]
+    fn test_full_book_helpers_cover_every_level() {
+        // 200 levels a side with a large wall deep in the bids
+        let mut bids: Vec<(f64, f64)> = (0..200).map(|i| (100.0 - i as f64 * 0.5, 1.0)).collect();
+        bids[150].1 = 500.0;
+        let asks: Vec<(f64, f64)> = (0..200).map(|i| (100.5 + i as f64 * 0.5, 2.0)).collect();
+        let book = OrderBook::new("BTCUSDT".to_string(), bids, asks, 1000);
+
+        assert_eq!(book.total_bid_volume(), 199.0 + 500.0);
+        assert_eq!(book.total_ask_volume(), 400.0);
+        assert_eq!(book.level_at_price(25.0), Some(500.0));
+        assert_eq!(book.level_at_price(150.0), Some(2.0));
+        assert_eq!(book.level_at_price(100.25), None);
+    }
+}
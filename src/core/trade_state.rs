@@ -1,24 +1,122 @@
-use super::models::{Trade, OrderBook};
-use chrono::Utc;
+use super::errors::Result;
+use super::models::{Trade, OrderBook, TradeContext};
+use super::decision_log::DecisionLog;
+use crate::utils::TimestampFormatter;
+use crate::pnl::snap_quantity;
+use std::collections::{BTreeMap, HashMap};
 use log::{debug, warn};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// When trading started within the data and how fills spread across it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeRamp {
+    /// Milliseconds from the first book to the first fill (None without fills)
+    pub time_to_first_trade_ms: Option<i64>,
+    /// Fills in each quarter of the book time span, earliest first
+    pub quartile_fills: [usize; 4],
+}
+
+/// Net position of a symbol, the time of the fill that opened it, its cash flow and its
+/// latest mark, updated fill by fill
+#[derive(Debug, Clone, Copy, Default)]
+struct RunningPosition {
+    quantity: f64,
+    entry_time: Option<i64>,
+    /// Sells add and buys subtract price × quantity, before contract size and fees
+    cash: f64,
+    /// Latest book mid, or the last fill price when no book was marked since
+    mark: f64,
+}
 
 pub struct TradeState {
     all_trades: Vec<Trade>,
-    orderbooks: Vec<OrderBook>
+    /// Running position per symbol over the filled trades
+    positions: HashMap<String, RunningPosition>,
+    /// Exchange fees charged on the filled trades
+    fees: f64,
+    orderbooks: Vec<OrderBook>,
+    decisions: Option<DecisionLog>,
+    /// Times of the first and last book seen, stored or not
+    book_span: Option<(i64, i64)>,
+    /// Dollar cost of execution slippage per filled trade id
+    slippage_costs: HashMap<String, f64>,
+    /// Proposed orders that would have traded against the strategy's own order
+    self_crosses: usize,
+    /// Time of the first book of each input file, in order
+    file_starts: Vec<i64>,
 }
 
 impl TradeState {
     pub fn new() -> Self {
         Self {
             all_trades: Vec::new(),
-            orderbooks: Vec::new()
+            positions: HashMap::new(),
+            fees: 0.0,
+            orderbooks: Vec::new(),
+            decisions: None,
+            book_span: None,
+            slippage_costs: HashMap::new(),
+            self_crosses: 0,
+            file_starts: Vec::new(),
         }
     }
 
+    /// Start collecting per-tick strategy decisions
+    pub fn enable_decision_log(&mut self) {
+        self.decisions.get_or_insert_with(DecisionLog::new);
+    }
+
+    /// Decisions collected so far, if the log is enabled
+    pub fn decision_log(&self) -> Option<&DecisionLog> {
+        self.decisions.as_ref()
+    }
+
+    pub fn decision_log_mut(&mut self) -> Option<&mut DecisionLog> {
+        self.decisions.as_mut()
+    }
+
     pub fn add(&mut self, trade: Trade) {
+        self.apply_fill(&trade);
         self.all_trades.push(trade);
     }
 
+    /// Move the running position of a filled trade's symbol. The entry time resets whenever
+    /// the position goes flat or flips side.
+    fn apply_fill(&mut self, trade: &Trade) {
+        if trade.status != "filled" {
+            return;
+        }
+        let signed = match trade.side.as_str() {
+            "Buy" => trade.quantity,
+            "Sell" => -trade.quantity,
+            _ => return,
+        };
+        let open = self.positions.entry(trade.symbol.clone()).or_default();
+        let next = snap_quantity(open.quantity + signed);
+        if next == 0.0 {
+            open.entry_time = None;
+        } else if open.quantity == 0.0 || next.signum() != open.quantity.signum() {
+            open.entry_time = Some(trade.time);
+        }
+        open.quantity = next;
+        open.cash -= signed * trade.price;
+        open.mark = trade.price;
+        self.fees += trade.fee;
+    }
+
+    /// Rebuild the running positions from the history, after a trade's status changed
+    fn replay_fills(&mut self) {
+        self.positions.clear();
+        self.fees = 0.0;
+        let trades = std::mem::take(&mut self.all_trades);
+        for trade in &trades {
+            self.apply_fill(trade);
+        }
+        self.all_trades = trades;
+    }
+
     pub fn get_trades_history(&self) -> Vec<&Trade> {
         self.all_trades
             .iter()
@@ -31,35 +129,81 @@ impl TradeState {
     }
 
     pub fn change_status(&mut self, trade_id: &str, new_status: String) -> bool {
-        for trade in &mut self.all_trades {
-            if trade.id == trade_id {
-                let old_status = trade.status.clone();
-                trade.status = new_status.clone();
-                debug!("Trade {} status changed from {} to {}", trade_id, old_status, new_status);
-                return true;
-            }
+        let Some(trade) = self.all_trades.iter_mut().find(|trade| trade.id == trade_id) else {
+            warn!("Trade with ID {} not found", trade_id);
+            return false;
+        };
+        let old_status = std::mem::replace(&mut trade.status, new_status.clone());
+        debug!("Trade {} status changed from {} to {}", trade_id, old_status, new_status);
+        if (old_status == "filled") != (new_status == "filled") {
+            self.replay_fills();
         }
-        warn!("Trade with ID {} not found", trade_id);
-        false
+        true
     }
 
     pub fn get_position(&self, symbol: &str) -> f64 {
-        let mut position = 0.0;
+        self.positions.get(symbol).map_or(0.0, |open| open.quantity)
+    }
+
+    /// Mark `symbol`'s position at `mid`; the engine calls this with every book
+    pub fn mark(&mut self, symbol: &str, mid: f64) {
+        if mid <= 0.0 {
+            return;
+        }
+        if let Some(open) = self.positions.get_mut(symbol) {
+            open.mark = mid;
+        }
+    }
+
+    /// Per-symbol P&L of the fills so far, in price × quantity units before contract size and
+    /// fees: the cash flow plus the open position at its latest mark
+    pub fn marked_pnl(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.positions.iter().map(|(symbol, open)| (symbol.as_str(), open.cash + open.quantity * open.mark))
+    }
+
+    /// Open `(long, short)` quantities of a hedge-mode account, where reduce-only
+    /// trades close the leg opposite to their side
+    pub fn get_hedged_position(&self, symbol: &str) -> (f64, f64) {
+        let (mut long, mut short) = (0.0_f64, 0.0_f64);
         for trade in &self.all_trades {
             if trade.symbol != symbol || trade.status != "filled" {
                 continue;
             }
-            if trade.side == "Buy" {
-                position += trade.quantity;
-            } else if trade.side == "Sell" {
-                position -= trade.quantity;
+            match (trade.side.as_str(), trade.reduce_only) {
+                ("Buy", false) => long += trade.quantity,
+                ("Sell", true) => long = (long - trade.quantity).max(0.0),
+                ("Sell", false) => short += trade.quantity,
+                ("Buy", true) => short = (short - trade.quantity).max(0.0),
+                _ => {}
             }
         }
-        position
+        (long, short)
     }
 
+    /// Net cash from filled trades across all symbols (sells add, buys subtract)
+    pub fn net_cash_flow(&self) -> f64 {
+        self.positions.values().map(|open| open.cash).sum()
+    }
+
+    /// Time of the fill that opened the current net position in `symbol`, or None when flat.
+    /// Resets whenever the position goes flat or flips side.
+    pub fn position_entry_time(&self, symbol: &str) -> Option<i64> {
+        self.positions.get(symbol).and_then(|open| open.entry_time)
+    }
+
+    /// Simulation time: the latest book the engine has seen, or the latest trade when no book
+    /// was noted. Ages are measured against it rather than the wall clock, which during a
+    /// replay of historical data would be days or years ahead.
+    pub fn current_time(&self) -> i64 {
+        match self.book_span {
+            Some((_, end)) => end,
+            None => self.all_trades.iter().map(|t| t.time).max().unwrap_or(0),
+        }
+    }
+
+    /// Milliseconds of simulation time since the symbol's last fill (0 without fills)
     pub fn get_position_age(&self, symbol: &str) -> i64 {
-        let now = Utc::now().timestamp_millis();
+        let now = self.current_time();
         let mut last_time = 0;
         
         for trade in self.all_trades.iter().rev() {
@@ -76,8 +220,9 @@ impl TradeState {
         }
     }
 
+    /// Fills of the symbol within `window_ms` of simulation time
     pub fn get_recent_fills(&self, symbol: &str, window_ms: i64) -> Vec<String> {
-        let now = Utc::now().timestamp_millis();
+        let now = self.current_time();
         let mut result = Vec::new();
         
         for trade in self.all_trades.iter().rev() {
@@ -88,18 +233,302 @@ impl TradeState {
         result
     }
 
+    /// Record what slippage cost a fill against its quoted price. History keeps the quoted
+    /// price, so P&L computed from it is before slippage.
+    pub fn record_slippage(&mut self, trade_id: &str, cost: f64) {
+        *self.slippage_costs.entry(trade_id.to_string()).or_insert(0.0) += cost;
+    }
+
+    /// Slippage cost per filled trade id
+    pub fn slippage_costs(&self) -> &HashMap<String, f64> {
+        &self.slippage_costs
+    }
+
+    /// Exchange fees charged on the filled trades
+    pub fn total_fees(&self) -> f64 {
+        self.fees
+    }
+
+    /// Count a proposed order that crossed another order of the same strategy
+    pub fn record_self_cross(&mut self) {
+        self.self_crosses += 1;
+    }
+
+    /// Number of proposed orders that crossed another order of the same strategy
+    pub fn self_cross_count(&self) -> usize {
+        self.self_crosses
+    }
+
+    /// Extend the data span to cover a book at `time`; the engine calls this for every book
+    pub fn note_book_time(&mut self, time: i64) {
+        self.book_span = Some(match self.book_span {
+            Some((start, end)) => (start.min(time), end.max(time)),
+            None => (time, time),
+        });
+    }
+
+    /// Mark the first book of an input file; multi-file runs call this at each file boundary
+    pub fn note_file_start(&mut self, time: i64) {
+        self.file_starts.push(time);
+    }
+
+    /// Time of the first book of each input file
+    pub fn file_starts(&self) -> &[i64] {
+        &self.file_starts
+    }
+
+    /// Times of the first and last book of the run
+    pub fn book_span(&self) -> Option<(i64, i64)> {
+        self.book_span
+    }
+
+    /// Time to the first fill and fills per quarter of the data, to check how much of
+    /// the data a strategy's warm-up used. None before any book was seen.
+    pub fn trade_ramp(&self) -> Option<TradeRamp> {
+        let (start, end) = self.book_span?;
+        let fills = self.get_trades_history();
+        let span = (end - start + 1) as i128;
+        let mut quartile_fills = [0; 4];
+        for trade in &fills {
+            let quartile = ((trade.time - start).max(0) as i128 * 4 / span).min(3) as usize;
+            quartile_fills[quartile] += 1;
+        }
+        Some(TradeRamp {
+            time_to_first_trade_ms: fills.iter().map(|t| t.time).min().map(|first| first - start),
+            quartile_fills,
+        })
+    }
+
     pub fn add_orderbook(&mut self, orderbook: OrderBook) {
         self.orderbooks.push(orderbook);
     }
 
+    /// Mid of the last stored book per symbol, a mark for positions still open at the end
+    pub fn last_mids(&self) -> HashMap<String, f64> {
+        self.orderbooks.iter()
+            .map(|book| (book.symbol.clone(), book.mid_price()))
+            .collect()
+    }
+
     pub fn get_orderbooks(&self) -> &Vec<OrderBook> {
         &self.orderbooks
     }
 
+    /// Beta of `symbol`'s mid-price returns to `reference`'s, from the stored order books.
+    ///
+    /// Each `symbol` book is paired with the latest `reference` mid at or before its time, so
+    /// the series need not have the same sample counts. None without two aligned returns or
+    /// when the reference doesn't move.
+    pub fn beta_to_reference(&self, symbol: &str, reference: &str) -> Option<f64> {
+        let mids = |name: &str| -> BTreeMap<i64, f64> {
+            self.orderbooks.iter()
+                .filter(|book| book.symbol == name && book.mid_price() > 0.0)
+                .map(|book| (book.current_time, book.mid_price()))
+                .collect()
+        };
+        let reference_mids = mids(reference);
+        let aligned: Vec<(f64, f64)> = mids(symbol).into_iter()
+            .filter_map(|(time, mid)| {
+                reference_mids.range(..=time).next_back().map(|(_, reference_mid)| (mid, *reference_mid))
+            })
+            .collect();
+
+        let returns: Vec<(f64, f64)> = aligned.windows(2)
+            .map(|w| (w[1].0 / w[0].0 - 1.0, w[1].1 / w[0].1 - 1.0))
+            .collect();
+        if returns.len() < 2 {
+            return None;
+        }
+
+        let n = returns.len() as f64;
+        let mean_symbol = returns.iter().map(|(r, _)| r).sum::<f64>() / n;
+        let mean_reference = returns.iter().map(|(_, r)| r).sum::<f64>() / n;
+        let covariance = returns.iter()
+            .map(|(rs, rr)| (rs - mean_symbol) * (rr - mean_reference))
+            .sum::<f64>() / n;
+        let variance = returns.iter().map(|(_, rr)| (rr - mean_reference).powi(2)).sum::<f64>() / n;
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
     pub fn get_failed_trades(&self) -> Vec<&Trade> {
         self.all_trades
             .iter()
             .filter(|t| t.status != "filled")
             .collect()
     }
-}
\ No newline at end of file
+
+    /// Pair each trade with the order book it was proposed on: the latest stored book
+    /// of its symbol at or before the trade time.
+    ///
+    /// Trades with no such book are left out.
+    pub fn trades_with_context(&self) -> Vec<TradeContext> {
+        let mut books_by_symbol: HashMap<&str, Vec<&OrderBook>> = HashMap::new();
+        for book in &self.orderbooks {
+            books_by_symbol.entry(book.symbol.as_str()).or_default().push(book);
+        }
+        for books in books_by_symbol.values_mut() {
+            books.sort_by_key(|book| book.current_time);
+        }
+
+        self.all_trades
+            .iter()
+            .filter_map(|trade| {
+                let books = books_by_symbol.get(trade.symbol.as_str())?;
+                let before = books.partition_point(|book| book.current_time <= trade.time);
+                let book = books[..before].last()?;
+                Some(TradeContext {
+                    trade: trade.clone(),
+                    book_time: book.current_time,
+                    best_bid: book.best_bid().map(|(price, _)| price).unwrap_or(0.0),
+                    best_ask: book.best_ask().map(|(price, _)| price).unwrap_or(0.0),
+                    mid_price: book.mid_price(),
+                    spread_pct: book.spread_pct(),
+                    imbalance: book.order_book_imbalance(),
+                })
+            })
+            .collect()
+    }
+
+    /// Write `trades_with_context` to CSV when the path ends in `.csv`, JSON otherwise
+    pub fn export_trades_with_context<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.export_trades_with_context_formatted(path, &TimestampFormatter::epoch_millis())
+    }
+
+    /// Same as `export_trades_with_context`, with trade and book times rendered by `times`
+    pub fn export_trades_with_context_formatted<P: AsRef<Path>>(&self, path: P, times: &TimestampFormatter) -> Result<()> {
+        let path = path.as_ref();
+        let contexts = self.trades_with_context();
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        let is_csv = path.extension()
+            .map(|ext| ext.eq_ignore_ascii_case("csv"))
+            .unwrap_or(false);
+
+        if is_csv {
+            writeln!(writer, "id,time,symbol,side,price,quantity,status,book_time,best_bid,best_ask,mid_price,spread_pct,imbalance")?;
+            for ctx in &contexts {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                    ctx.trade.id, times.format_precise(ctx.trade.time), ctx.trade.symbol, ctx.trade.side,
+                    ctx.trade.price, ctx.trade.quantity, ctx.trade.status, times.format_precise(ctx.book_time),
+                    ctx.best_bid, ctx.best_ask, ctx.mid_price, ctx.spread_pct, ctx.imbalance
+                )?;
+            }
+        } else if times.is_epoch_millis() {
+            serde_json::to_writer_pretty(&mut writer, &contexts)?;
+        } else {
+            let mut value = serde_json::to_value(&contexts)?;
+            for (entry, ctx) in value.as_array_mut().into_iter().flatten().zip(&contexts) {
+                entry["book_time"] = times.format_precise(ctx.book_time).into();
+                entry["trade"]["time"] = times.format_precise(ctx.trade.time).into();
+            }
+            serde_json::to_writer_pretty(&mut writer, &value)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trades_with_context_pairs_by_timestamp() {
+        let mut state = TradeState::new();
+        for (time, bid, ask) in [(1000, 99.0, 101.0), (2000, 104.0, 105.0), (3000, 97.0, 98.0)] {
+            state.add(Trade::new(time, "BTCUSDT".to_string(), "Buy".to_string(), ask, 0.01));
+            state.add_orderbook(OrderBook::new(
+                "BTCUSDT".to_string(),
+                vec![(bid, 3.0)],
+                vec![(ask, 1.0)],
+                time,
+            ));
+        }
+
+        let contexts = state.trades_with_context();
+        assert_eq!(contexts.len(), 3);
+        for ctx in &contexts {
+            assert_eq!(ctx.trade.time, ctx.book_time);
+            assert_eq!(ctx.trade.price, ctx.best_ask);
+            assert!((ctx.mid_price - (ctx.best_bid + ctx.best_ask) / 2.0).abs() < 1e-12);
+            assert!((ctx.imbalance - 0.5).abs() < 1e-12);
+        }
+        assert_eq!(contexts[1].best_bid, 104.0);
+    }
+
+    #[test]
+    fn test_beta_to_reference_aligns_on_timestamps() {
+        let book = |symbol: &str, mid: f64, time: i64| {
+            OrderBook::new(symbol.to_string(), vec![(mid - 0.5, 1.0)], vec![(mid + 0.5, 1.0)], time)
+        };
+        let mut state = TradeState::new();
+        // Reference returns +1%, -2%, +3%; the symbol moves twice as much
+        let reference = [100.0, 101.0, 98.98, 101.9494];
+        let mut symbol_mid = 50.0;
+        for (i, mid) in reference.iter().enumerate() {
+            state.add_orderbook(book("BTCUSDT", *mid, i as i64 * 1000));
+            // Extra reference ticks between samples must not shift the alignment
+            state.add_orderbook(book("BTCUSDT", mid * 1.5, i as i64 * 1000 + 700));
+            if i > 0 {
+                symbol_mid *= 1.0 + 2.0 * (reference[i] / reference[i - 1] - 1.0);
+            }
+            state.add_orderbook(book("ETHUSDT", symbol_mid, i as i64 * 1000 + 500));
+        }
+
+        let beta = state.beta_to_reference("ETHUSDT", "BTCUSDT").unwrap();
+        assert!((beta - 2.0).abs() < 1e-9, "beta {}", beta);
+        assert!((state.beta_to_reference("BTCUSDT", "BTCUSDT").unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(state.beta_to_reference("SOLUSDT", "BTCUSDT"), None);
+    }
+
+    #[test]
+    fn test_running_position_follows_fills_and_status_changes() {
+        let mut state = TradeState::new();
+        let fill = |time: i64, side: &str, price: f64, quantity: f64| {
+            let mut trade = Trade::new(time, "BTCUSDT".to_string(), side.to_string(), price, quantity);
+            trade.status = "filled".to_string();
+            trade.fee = 0.1;
+            trade
+        };
+        state.add(fill(1000, "Buy", 100.0, 2.0));
+        state.add(fill(2000, "Sell", 110.0, 1.0));
+        let pending = Trade::new(3000, "BTCUSDT".to_string(), "Sell".to_string(), 120.0, 1.0);
+        let pending_id = pending.id.clone();
+        state.add(pending);
+
+        assert_eq!(state.get_position("BTCUSDT"), 1.0);
+        assert_eq!(state.position_entry_time("BTCUSDT"), Some(1000));
+        assert!((state.net_cash_flow() + 90.0).abs() < 1e-9);
+        assert!((state.total_fees() - 0.2).abs() < 1e-9);
+        // One left, marked at the last fill and then at the book's mid
+        assert_eq!(state.marked_pnl().collect::<Vec<_>>(), vec![("BTCUSDT", 20.0)]);
+        state.mark("BTCUSDT", 105.0);
+        assert_eq!(state.marked_pnl().collect::<Vec<_>>(), vec![("BTCUSDT", 15.0)]);
+
+        // Filling the pending sell later closes the position
+        assert!(state.change_status(&pending_id, "filled".to_string()));
+        assert_eq!(state.get_position("BTCUSDT"), 0.0);
+        assert_eq!(state.position_entry_time("BTCUSDT"), None);
+        assert!((state.net_cash_flow() - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_position_age_uses_simulation_time() {
+        // A fill from 2021 replayed years later
+        let fill_time = 1_609_459_200_000;
+        let mut state = TradeState::new();
+        let mut fill = Trade::new(fill_time, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 1.0);
+        fill.status = "filled".to_string();
+        state.add(fill);
+        assert_eq!(state.get_position_age("BTCUSDT"), 0);
+
+        state.note_book_time(fill_time + 5_000);
+        assert_eq!(state.get_position_age("BTCUSDT"), 5_000);
+        assert_eq!(state.get_recent_fills("BTCUSDT", 10_000).len(), 1);
+        assert!(state.get_recent_fills("BTCUSDT", 1_000).is_empty());
+        assert_eq!(state.get_position_age("ETHUSDT"), 0);
+    }
+}
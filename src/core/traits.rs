@@ -29,4 +29,8 @@ pub struct ExecutionStats {
     pub rejected_trades: usize,
     pub partial_fills: usize,
     pub total_slippage: f64,
+    /// Exchange fees charged on fills
+    pub total_fees: f64,
+    pub maker_fills: usize,
+    pub taker_fills: usize,
 }
\ No newline at end of file
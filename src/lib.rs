@@ -9,12 +9,12 @@ pub mod reader;
 
 // Re-export commonly used types
 pub use core::{
-    Trade, OrderBook, PnLResult, ClosedTrade, CapitalMetrics,
+    Trade, OrderBook, TradeContext, PnLResult, ClosedTrade, CapitalMetrics,
     TradeState, TradeError, Result, DataSource, TradeExecutor, ExecutionStats
 };
 pub use strategy::{Strategy, GptMarketMaker, GptMarketMakerConfig};
 pub use backtest::{TradeDashboard, BacktestEngine};
-pub use utils::{FileDataSource, ParquetDataSource, OrderBookMessage, MultiFileDataSource};
-pub use trading::{TradeEmitter, BacktestTradeEmitter, BacktestConfig};
+pub use utils::{open_data_source, FileDataSource, ParquetDataSource, CsvDataSource, OrderBookMessage, MultiFileDataSource, ResamplingDataSource};
+pub use trading::{TradeEmitter, BacktestTradeEmitter, BacktestConfig, SizingMode, CrossFileState};
 pub use config::{AppConfig, validate_config};
 
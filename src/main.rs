@@ -3,26 +3,38 @@ use env_logger;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
-use std::fs;
 use regex::Regex;
+use chrono::FixedOffset;
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
 use rayon::prelude::*;
 
 use happytest::{
-    utils::extract_symbol_from_filename, BacktestConfig, BacktestEngine, TradeDashboard,
-    pnl::{PnlReport, Method}, TradeState,
+    BacktestConfig, BacktestEngine, SizingMode, CrossFileState, TradeDashboard,
+    backtest::{AggregateResult, MonteCarloSummary, SessionSplit},
+    trading::{InstrumentSpecRegistry, DEFAULT_MARGIN_RATE},
+    utils::{FeatureSource, MidPriceFilterConfig, SymbolExtractor, TimeFormat, TimestampFormatter, init_global_thread_pool, looks_like_path, resolve_input_files},
+    pnl::{PnlReport, Method, IncludeUnrealized, PositionMode}, PnLResult, TradeState,
+    strategy::{BuyAndHoldStrategy, NoopStrategy, Strategy},
 };
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// File path or regex pattern for orderbook data files (JSONL or Parquet)
+    /// File path or regex pattern for orderbook data files (JSONL, Parquet or CSV)
     /// Examples: 
-    ///   - Single file: data.jsonl or data.parquet
+    ///   - Single file: data.jsonl, data.parquet or data.csv
     ///   - Pattern: BTCUSDT_202509.*_mainnet.parquet
     #[arg(short, long)]
     file: String,
     
+    /// Symbol to trade, bypassing filename parsing
+    #[arg(long, conflicts_with = "symbol_regex")]
+    symbol_override: Option<String>,
+
+    /// Regex whose capture groups, concatenated, give the symbol (e.g. '^([A-Z]+)_([A-Z]+)_')
+    #[arg(long)]
+    symbol_regex: Option<String>,
+
     /// Directory to search for files when using regex patterns
     #[arg(short = 'd', long, default_value = "./data")]
     directory: String,
@@ -35,16 +47,193 @@ struct Args {
     #[arg(long, default_value_t = 2.0)]
     slippage_bps: f64,
 
+    /// Slippage for buys in basis points (defaults to --slippage-bps)
+    #[arg(long)]
+    buy_slippage_bps: Option<f64>,
+
+    /// Slippage for sells in basis points (defaults to --slippage-bps)
+    #[arg(long)]
+    sell_slippage_bps: Option<f64>,
+
+    /// Exchange fee on fills that rest inside the spread, in basis points (negative = rebate)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    maker_fee_bps: f64,
+
+    /// Exchange fee on fills that cross the spread, in basis points
+    #[arg(long, default_value_t = 0.0)]
+    taker_fee_bps: f64,
+
+    /// Fill crossing orders only up to the quantity resting at the levels they reach
+    #[arg(long, default_value_t = false)]
+    fill_from_depth: bool,
+
     /// Order rejection rate (0.0-1.0)
     #[arg(long, default_value_t = 0.01)]
     rejection_rate: f64,
 
-    /// Margin requirement rate (0.0-1.0)
-    #[arg(long, default_value_t = 0.05)]
+    /// Margin requirement rate (0.0-1.0), used for capital metrics
+    #[arg(long, default_value_t = DEFAULT_MARGIN_RATE)]
     margin_rate: f64,
 
-    /// Treat regex-matched files as a single continuous range (for backtesting multiple periods)
-    #[arg(long, default_value_t = true)]
+    /// Minimum book spread as a fraction of mid required to trade (0 = no gate)
+    #[arg(long, default_value_t = 0.0)]
+    min_spread_pct: f64,
+
+    /// Rolling window for the mid-price spike filter (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    mid_filter_window: usize,
+
+    /// Reject mids more than this many median absolute deviations from the rolling median
+    #[arg(long, default_value_t = 5.0)]
+    mid_filter_mads: f64,
+
+    /// Resample order books to a fixed grid of this many milliseconds (0 = raw updates)
+    #[arg(long, default_value_t = 0)]
+    resample_ms: i64,
+
+    /// Cap on strategy order quantity (0 = no cap; size is set by the strategy's --fix-order-volume)
+    #[arg(long, default_value_t = 0.0)]
+    max_order_volume: f64,
+
+    /// Size each order as this percentage of account equity instead of --fix-order-volume
+    #[arg(long, conflicts_with = "size_pct_of_book")]
+    size_pct_of_equity: Option<f64>,
+
+    /// Size each order as this percentage of the opposite best level's quantity
+    #[arg(long)]
+    size_pct_of_book: Option<f64>,
+
+    /// Starting account balance used by --size-pct-of-equity
+    #[arg(long, default_value_t = 10_000.0)]
+    initial_equity: f64,
+
+    /// Fail on trades stamped after the order book that produced them (look-ahead guard)
+    #[arg(long, default_value_t = false)]
+    strict_causality: bool,
+
+    /// Reset the strategy at each file boundary of a range run instead of carrying inventory over
+    #[arg(long, default_value_t = false)]
+    reset_between_files: bool,
+
+    /// Rest orders as maker quotes, cancel-replacing them as the book moves
+    #[arg(long, default_value_t = false)]
+    requote_on_move: bool,
+    
+    /// Execute crossing buy and sell orders of the strategy instead of suppressing the self-match
+    #[arg(long, default_value_t = false)]
+    allow_self_cross: bool,
+
+    /// Record why the strategy traded or skipped on every tick and write the decisions to this CSV
+    #[arg(long)]
+    decision_log: Option<String>,
+
+    /// Number of book levels per side kept for P&L marking (0 = full depth)
+    #[arg(long, default_value_t = 0)]
+    stored_book_depth: usize,
+
+    /// Keep every order book, not just those trades were made on, for tick-level marking and markouts
+    #[arg(long, default_value_t = false)]
+    store_all_books: bool,
+
+    /// Recover the complete row groups of Parquet files left without a footer by an interrupted capture
+    #[arg(long, default_value_t = false)]
+    lenient_parquet: bool,
+
+    /// Instrument specs (contract, tick and lot size, min notional) per symbol from a JSON or CSV file
+    #[arg(long)]
+    instruments_file: Option<PathBuf>,
+
+    /// Risk aversion for the inventory penalty in the P&L summary (0 = disabled)
+    #[arg(long, default_value_t = 0.0)]
+    risk_aversion: f64,
+    
+    /// Flag the peak of |inventory| x volatility over windows of this many milliseconds in the P&L summary (0 = off)
+    #[arg(long, default_value_t = 0)]
+    inventory_risk_window_ms: i64,
+
+    /// Mark open positions at every order book when building the equity curve; implies --store-all-books
+    #[arg(long, default_value_t = false)]
+    mark_to_market_every_tick: bool,
+
+    /// Markout horizons in milliseconds for adverse-selection analysis (e.g. 100,1000); implies --store-all-books
+    #[arg(long, value_delimiter = ',')]
+    markout_horizons: Vec<i64>,
+
+    /// Horizon in milliseconds for the information coefficient of the book signals (pair with --store-all-books)
+    #[arg(long)]
+    ic_horizon_ms: Option<i64>,
+
+    /// Write the realized plus unrealized P&L curve of per-file runs (--aggregate-files false), continued from file to file, to this CSV
+    #[arg(long)]
+    stitched_equity: Option<String>,
+
+    /// Print realized P&L by hour of day in the --utc-offset-minutes timezone
+    #[arg(long, default_value_t = false)]
+    pnl_by_hour: bool,
+
+    /// Report average maximum favorable/adverse excursion of closed trades (pair with --store-all-books)
+    #[arg(long, default_value_t = false)]
+    excursions: bool,
+
+    /// Export each trade with its triggering order book to this path (.csv or .json)
+    #[arg(long)]
+    export_trade_context: Option<String>,
+
+    /// Write each symbol's P&L, execution and capital metrics to this JSON file
+    #[arg(long, value_name = "PATH")]
+    output_json: Option<String>,
+
+    /// Report realized P&L only, without marking open positions
+    #[arg(long, default_value_t = false)]
+    exclude_unrealized: bool,
+
+    /// CSV of timestamped external features (timestamp column plus one column per feature)
+    #[arg(long)]
+    features: Option<String>,
+
+    /// Closed trades per symbol needed before drawdown, Sharpe and the console chart are reported
+    #[arg(long, default_value_t = 2)]
+    min_closed_trades: usize,
+
+    /// Report each symbol's mid-price return beta to this symbol in the multi-file summary
+    #[arg(long, value_name = "SYMBOL")]
+    beta_reference: Option<String>,
+
+    /// Also run buy-and-hold of this quantity over each file and report the strategy's alpha against it (per-file runs only)
+    #[arg(long, value_name = "QTY")]
+    benchmark_quantity: Option<f64>,
+
+    /// Break the multi-file summary into sessions: day (UTC calendar day) or file
+    #[arg(long, value_name = "SPLIT")]
+    sessions: Option<SessionSplit>,
+
+    /// Borrow fee charged on short positions, in bps of entry notional per day held (0 = none)
+    #[arg(long, default_value_t = 0.0)]
+    short_borrow_bps_per_day: f64,
+
+    /// Timestamp style for chart axes and exports: auto (date only on multi-day spans), time, datetime or epoch
+    #[arg(long, default_value = "auto")]
+    time_format: TimeFormat,
+
+    /// Timezone for formatted timestamps, in minutes east of UTC
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-1439..=1439))]
+    utc_offset_minutes: i32,
+
+    /// Keep long and short legs of a symbol separate; only reduce-only trades close a leg
+    #[arg(long, default_value_t = false)]
+    hedge_mode: bool,
+
+    /// Skip PNG chart generation
+    #[arg(long, default_value_t = false)]
+    no_charts: bool,
+
+    /// P&L chart bucket size in milliseconds (default: fitted to the run's time span)
+    #[arg(long)]
+    chart_bucket_ms: Option<i64>,
+
+    /// Treat regex-matched files as a single continuous range (for backtesting multiple periods);
+    /// pass `--aggregate-files false` to backtest each file on its own
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
     aggregate_files: bool,
     
     /// Process files in parallel (only when not aggregating)
@@ -55,6 +244,27 @@ struct Args {
     #[arg(long, default_value_t = 0)]
     workers: usize,
     
+    /// Skip duplicate or out-of-sequence books by their exchange update_id
+    #[arg(long, default_value_t = false)]
+    dedup_update_ids: bool,
+    
+    /// Force-close positions held longer than this many milliseconds of book time (0 = no limit)
+    #[arg(long, default_value_t = 0)]
+    max_hold_ms: i64,
+    
+    /// Seed for the execution model's fill and rejection draws (random when omitted)
+    #[arg(long)]
+    seed: Option<u64>,
+    
+    /// Backtest each file under N execution seeds in parallel and report the P&L spread
+    #[arg(long, value_name = "N")]
+    monte_carlo: Option<usize>,
+    
+    /// Append the run's summary metrics and trades to this SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    sqlite: Option<String>,
+    
     /// Strategy selection and configuration
     #[command(subcommand)]
     strategy: StrategyCommand,
@@ -64,44 +274,203 @@ struct Args {
 enum StrategyCommand {
     /// GPT Market Maker strategy
     Gpt(happytest::strategy::GptMarketMakerArgs),
+    /// Never trade: zero-P&L reference and engine overhead baseline
+    Noop,
+    /// Buy once on the first tick and hold: market-return reference
+    BuyAndHold(happytest::strategy::BuyAndHoldArgs),
 }
 
-/// Find files matching a regex pattern in a directory
-fn find_matching_files(directory: &Path, pattern: &str) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
-    let regex = Regex::new(pattern)?;
-    let mut matching_files = Vec::new();
-    
-    // Read directory entries
-    for entry in fs::read_dir(directory)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        // Skip directories
-        if path.is_dir() {
-            continue;
+impl StrategyCommand {
+    /// Strategy selected on the command line, trading `symbol`
+    fn build(&self, symbol: String) -> Box<dyn Strategy> {
+        match self {
+            StrategyCommand::Gpt(gpt_args) => gpt_args.build_strategy(symbol),
+            StrategyCommand::Noop => Box::new(NoopStrategy),
+            StrategyCommand::BuyAndHold(hold_args) => hold_args.build_strategy(symbol),
         }
-        
-        // Get filename and check if it matches the pattern
-        if let Some(filename) = path.file_name() {
-            if let Some(filename_str) = filename.to_str() {
-                if regex.is_match(filename_str) {
-                    matching_files.push(path);
-                }
-            }
+    }
+}
+
+/// Write the run's decision log to `--decision-log`, if requested
+fn save_decision_log(args: &Args, trade_state: &TradeState) -> Result<(), Box<dyn std::error::Error>> {
+    if let (Some(path), Some(decisions)) = (&args.decision_log, trade_state.decision_log()) {
+        decisions.save_csv(path)?;
+        println!("Decision log written to {} ({} ticks)", path, decisions.decisions().len());
+    }
+    Ok(())
+}
+
+/// Print when the first fill happened and how fills spread over the data, for the diagnostic block
+fn print_trade_ramp(trade_state: &TradeState) {
+    let Some(ramp) = trade_state.trade_ramp() else { return };
+    match ramp.time_to_first_trade_ms {
+        Some(ms) => println!("Time to first trade: {:.1}s", ms as f64 / 1000.0),
+        None => println!("Time to first trade: no fills"),
+    }
+    let [q1, q2, q3, q4] = ramp.quartile_fills;
+    println!("Fills by quartile: {} / {} / {} / {}", q1, q2, q3, q4);
+}
+
+/// Append the run and its trades to the `--sqlite` database, if requested
+#[cfg(feature = "sqlite")]
+fn save_sqlite_run(
+    args: &Args,
+    backtest_config: &BacktestConfig,
+    dashboard: &mut TradeDashboard,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use happytest::backtest::{RunRecord, SqliteSink};
+
+    let Some(path) = &args.sqlite else { return Ok(()) };
+    let strategy = match &args.strategy {
+        StrategyCommand::Gpt(gpt_args) => serde_json::json!(gpt_args.config()),
+        StrategyCommand::Noop => serde_json::json!("noop"),
+        StrategyCommand::BuyAndHold(hold_args) => serde_json::json!({ "buy_and_hold": hold_args.quantity }),
+    };
+    let params = serde_json::json!({ "backtest": backtest_config, "strategy": strategy });
+    let run = RunRecord::from_dashboard(dashboard, backtest_config, params);
+    let run_id = SqliteSink::open(path)?.insert_run(&run, dashboard.trade_state.get_all_trades())?;
+    println!("Run {} appended to {}", run_id, path);
+    Ok(())
+}
+
+/// Run buy-and-hold of `--benchmark-quantity` over `file_path` and print `strategy`'s P&L
+/// (realized plus marked) against it, if requested
+fn print_benchmark(
+    args: &Args,
+    backtest_config: &BacktestConfig,
+    file_path: &Path,
+    strategy: &PnLResult,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(quantity) = args.benchmark_quantity else { return Ok(()) };
+    let trade_state = build_engine(args, backtest_config)?
+        .run_backtest_with_custom_strategy(file_path, Box::new(BuyAndHoldStrategy::new(quantity)))?;
+    let benchmark = PnlReport::new()
+        .with_mark_prices(trade_state.last_mids())
+        .with_instruments(backtest_config.instruments.clone())
+        .calculate(trade_state.get_all_trades(), Method::Fifo);
+
+    let strategy_pnl = strategy.total_pnl + strategy.unrealized_pnl;
+    let benchmark_pnl = benchmark.total_pnl + benchmark.unrealized_pnl;
+    println!("Buy-and-hold benchmark ({} units): ${:.2}, strategy: ${:.2}, alpha: ${:.2}",
+             quantity, benchmark_pnl, strategy_pnl, strategy_pnl - benchmark_pnl);
+    Ok(())
+}
+
+/// Write the equity curve stitched across per-file runs to `--stitched-equity`, if requested
+fn save_stitched_equity(args: &Args, curve: &[(i64, f64)]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &args.stitched_equity {
+        let mut csv = String::from("time,equity\n");
+        for (time, equity) in curve {
+            csv.push_str(&format!("{},{}\n", time, equity));
         }
+        std::fs::write(path, csv)?;
+        println!("Stitched equity curve written to {} ({} points)", path, curve.len());
     }
-    
-    // Sort files for consistent processing order
-    matching_files.sort();
-    
-    Ok(matching_files)
+    Ok(())
+}
+
+/// Reject flags that the aggregated and parallel multi-file runs would ignore
+fn check_multi_file_flags(args: &Args) -> Result<(), String> {
+    if args.stitched_equity.is_some() {
+        return Err("--stitched-equity stitches per-file runs; pass --aggregate-files false".to_string());
+    }
+    if args.benchmark_quantity.is_some() {
+        return Err("--benchmark-quantity runs buy-and-hold over one file; pass --aggregate-files false".to_string());
+    }
+    Ok(())
+}
+
+/// Write the per-symbol JSON summaries to `--output-json`, if requested
+fn save_output_json(args: &Args, summaries: &serde_json::Map<String, serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &args.output_json {
+        std::fs::write(path, serde_json::to_string_pretty(summaries)?)?;
+        println!("JSON results written to {} ({} symbols)", path, summaries.len());
+    }
+    Ok(())
+}
+
+fn utc_offset(args: &Args) -> FixedOffset {
+    FixedOffset::east_opt(args.utc_offset_minutes * 60).expect("offset range is validated by clap")
+}
+
+/// Export timestamp formatter resolved over the span of the recorded trades
+fn export_time_formatter(args: &Args, trade_state: &TradeState) -> TimestampFormatter {
+    let times = trade_state.get_all_trades().iter().map(|t| t.time);
+    let start = times.clone().min().unwrap_or(0);
+    let end = times.max().unwrap_or(0);
+    args.time_format.formatter(utc_offset(args), start, end)
+}
+
+fn position_mode(args: &Args) -> PositionMode {
+    if args.hedge_mode {
+        PositionMode::Hedging
+    } else {
+        PositionMode::Netting
+    }
+}
+
+/// Symbol extraction from `--symbol-override` or `--symbol-regex`, defaulting to the filename prefix
+fn symbol_extractor(args: &Args) -> Result<SymbolExtractor, regex::Error> {
+    Ok(match (&args.symbol_override, &args.symbol_regex) {
+        (Some(symbol), _) => SymbolExtractor::Override(symbol.clone()),
+        (None, Some(pattern)) => SymbolExtractor::Regex(Regex::new(pattern)?),
+        (None, None) => SymbolExtractor::FirstSegment,
+    })
 }
 
-/// Process a single file with the backtest engine
+fn build_engine(args: &Args, backtest_config: &BacktestConfig) -> Result<BacktestEngine, Box<dyn std::error::Error>> {
+    let engine = BacktestEngine::new(backtest_config.clone()).with_symbol_extractor(symbol_extractor(args)?);
+    match &args.features {
+        Some(path) => Ok(engine.with_feature_source(FeatureSource::from_csv(path)?)),
+        None => Ok(engine),
+    }
+}
+
+fn include_unrealized(args: &Args) -> IncludeUnrealized {
+    if args.exclude_unrealized {
+        IncludeUnrealized::No
+    } else {
+        IncludeUnrealized::Yes
+    }
+}
+
+/// Print the lowest point of `symbol`'s tick-level equity curve, if requested
+fn print_lowest_equity(args: &Args, dashboard: &TradeDashboard, symbol: &str) {
+    if !args.mark_to_market_every_tick {
+        return;
+    }
+    let equity_curve = dashboard.equity_curve(symbol);
+    let lowest_equity = equity_curve.iter().map(|(_, equity)| *equity).fold(f64::INFINITY, f64::min);
+    if lowest_equity.is_finite() {
+        println!("Lowest mark-to-market equity ({}): ${:.2} ({} points)", symbol, lowest_equity, equity_curve.len());
+    }
+}
+
+/// Print the requested fill and signal analyses of `symbol`
+fn print_analyses(args: &Args, dashboard: &TradeDashboard, symbol: &str) {
+    if !args.markout_horizons.is_empty() {
+        let markouts = dashboard.markout_analysis(symbol, &args.markout_horizons);
+        dashboard.print_markout_analysis(symbol, &markouts);
+    }
+
+    if args.excursions {
+        let summary = dashboard.excursion_summary(symbol);
+        dashboard.print_excursion_summary(symbol, &summary);
+    }
+
+    if let Some(horizon_ms) = args.ic_horizon_ms {
+        dashboard.print_information_coefficients(symbol, horizon_ms);
+    }
+}
+
+/// Backtest one file. Its equity curve is appended to `stitched_equity`, continuing from
+/// the previous file's final value, and its JSON summary is stored in `json_summaries`.
 fn process_single_file(
     file_path: &Path,
     args: &Args,
     backtest_config: &BacktestConfig,
+    stitched_equity: &mut Vec<(i64, f64)>,
+    json_summaries: &mut serde_json::Map<String, serde_json::Value>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("\n{}", "=".repeat(60));
     println!("Processing file: {:?}", file_path);
@@ -124,28 +493,26 @@ fn process_single_file(
         .ok_or("Invalid file path")?
         .to_str()
         .ok_or("Invalid filename encoding")?;
-    let symbol = extract_symbol_from_filename(filename);
+    let symbol = symbol_extractor(args)?.extract(filename);
 
     // Create strategy from command line arguments
-    let strategy = match &args.strategy {
-        StrategyCommand::Gpt(gpt_args) => {
-            gpt_args.build_strategy(symbol.clone())
-        }
-    };
+    let strategy = args.strategy.build(symbol.clone());
 
     // Create backtest engine
-    let engine = BacktestEngine::new(backtest_config.clone());
+    let engine = build_engine(args, backtest_config)?;
     
     spinner.finish_with_message("✅ Strategy initialized");
 
     // Run backtest with the constructed strategy
     let trade_state = engine.run_backtest_with_custom_strategy(file_path, strategy)?;
+    save_decision_log(args, &trade_state)?;
 
     // Create dashboard for analysis
-    let mut dashboard = TradeDashboard::new(
-        trade_state,
-        backtest_config.margin_rate,
-    );
+    let mut dashboard = TradeDashboard::from_config(trade_state, backtest_config)
+        .with_risk_aversion(args.risk_aversion)
+        .with_inventory_risk_window(args.inventory_risk_window_ms)
+        .with_mark_to_market_every_tick(args.mark_to_market_every_tick)
+        .with_include_unrealized(include_unrealized(args));
 
     // Calculate PnL
     let pnl_results = dashboard.pnl(&symbol);
@@ -164,24 +531,49 @@ fn process_single_file(
         println!("Closed positions: {}", result.closed_trades.len());
     }
     
+    print_trade_ramp(&dashboard.trade_state);
+
     // Get capital metrics for Max DD
     let capital_metrics_temp = dashboard.get_capital_metrics(&symbol);
     println!("Max Drawdown: ${:.2}", capital_metrics_temp.max_drawdown);
+    print_lowest_equity(args, &dashboard, &symbol);
     
     println!("======================");
 
+    if let Some(path) = &args.export_trade_context {
+        let times = export_time_formatter(args, &dashboard.trade_state);
+        dashboard.trade_state.export_trades_with_context_formatted(path, &times)?;
+        println!("Trade context exported to {}", path);
+    }
+
     // Use PnlReport to display results in a nice table
-    let pnl_report = PnlReport::new();
+    let pnl_report = PnlReport::new()
+        .with_min_closed_trades(args.min_closed_trades)
+        .with_position_mode(position_mode(args))
+        .with_time_format(args.time_format, utc_offset(args))
+        .with_include_unrealized(include_unrealized(args))
+        .with_mark_prices(dashboard.trade_state.last_mids())
+        .with_slippage_costs(dashboard.trade_state.slippage_costs().clone())
+        .with_instruments(backtest_config.instruments.clone())
+        .with_short_borrow_bps_per_day(args.short_borrow_bps_per_day);
     let all_trades = dashboard.trade_state.get_all_trades();
+    let offset = stitched_equity.last().map_or(0.0, |(_, equity)| *equity);
+    stitched_equity.extend(pnl_report.equity_curve_continued(all_trades, Method::Fifo, offset));
     let report = pnl_report.report(all_trades, Method::Fifo);
     println!("{}", report);
+    print_benchmark(args, backtest_config, file_path, &pnl_report.calculate(all_trades, Method::Fifo))?;
+    if args.pnl_by_hour {
+        println!("{}", pnl_report.hourly_pnl_table(all_trades, Method::Fifo));
+    }
     
-    // Generate P&L graphs (PNG files)
-    let output_name = format!("{}_{}", 
-        file_path.file_stem().unwrap_or_default().to_str().unwrap_or("output"),
-        "graph"
-    );
-    pnl_report.graph_by_minute(all_trades, Method::Fifo, None, Some(&output_name))?;
+    // Generate P&L graphs (PNG files); failures are logged, not fatal
+    if !args.no_charts {
+        let output_name = format!("{}_{}", 
+            file_path.file_stem().unwrap_or_default().to_str().unwrap_or("output"),
+            "graph"
+        );
+        pnl_report.try_graph_with_aggregation(all_trades, Method::Fifo, None, Some(&output_name), args.chart_bucket_ms);
+    }
     
     // Display P&L graph in console
     pnl_report.display_console_graph(all_trades, Method::Fifo)?;
@@ -196,6 +588,11 @@ fn process_single_file(
 
     log::info!("============================================================");
     dashboard.to_console(&symbol, &pnl_results, &capital_metrics_map);
+    json_summaries.insert(symbol.clone(), dashboard.to_json(&symbol, &pnl_results, &capital_metrics_map));
+    print_analyses(args, &dashboard, &symbol);
+
+    #[cfg(feature = "sqlite")]
+    save_sqlite_run(args, backtest_config, &mut dashboard)?;
     
     Ok(())
 }
@@ -222,11 +619,12 @@ fn process_files_as_range(
     spinner.enable_steady_tick(std::time::Duration::from_millis(80));
     
     // Extract symbols from all files to show what we're processing
+    let symbols = symbol_extractor(args)?;
     let mut file_symbols = Vec::new();
     println!("Files to process:");
     for (i, path) in file_paths.iter().enumerate() {
         if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-            let sym = extract_symbol_from_filename(filename);
+            let sym = symbols.extract(filename);
             file_symbols.push(sym.clone());
             println!("  {}. {} (Symbol: {})", i + 1, path.display(), sym);
         } else {
@@ -238,25 +636,23 @@ fn process_files_as_range(
     let symbol = "MULTI".to_string();
 
     // Create strategy from command line arguments
-    let strategy = match &args.strategy {
-        StrategyCommand::Gpt(gpt_args) => {
-            gpt_args.build_strategy(symbol.clone())
-        }
-    };
+    let strategy = args.strategy.build(symbol.clone());
 
     // Create backtest engine
-    let engine = BacktestEngine::new(backtest_config.clone());
+    let engine = build_engine(args, backtest_config)?;
     
     spinner.finish_with_message(format!("✅ Strategy initialized, {} files ready", file_paths.len()));
 
     // Run backtest with multiple files as a single continuous data source
     let trade_state = engine.run_backtest_with_multiple_files(file_paths, strategy)?;
+    save_decision_log(args, &trade_state)?;
 
     // Create dashboard for analysis
-    let mut dashboard = TradeDashboard::new(
-        trade_state,
-        backtest_config.margin_rate,
-    );
+    let mut dashboard = TradeDashboard::from_config(trade_state, backtest_config)
+        .with_risk_aversion(args.risk_aversion)
+        .with_inventory_risk_window(args.inventory_risk_window_ms)
+        .with_mark_to_market_every_tick(args.mark_to_market_every_tick)
+        .with_include_unrealized(include_unrealized(args));
 
     // Get all unique symbols from trades
     let all_trades = dashboard.trade_state.get_all_trades();
@@ -291,12 +687,15 @@ fn process_files_as_range(
         }
     }
     
+    print_trade_ramp(&dashboard.trade_state);
+
     // Get capital metrics for all symbols
     let mut max_drawdown_total = 0.0;
     for sym in &unique_symbols {
         let capital_metrics_temp = dashboard.get_capital_metrics(sym);
         println!("Max Drawdown for {}: ${:.2}", sym, capital_metrics_temp.max_drawdown);
         max_drawdown_total += capital_metrics_temp.max_drawdown;
+        print_lowest_equity(args, &dashboard, sym);
     }
     if !unique_symbols.is_empty() {
         println!("Total Max Drawdown: ${:.2}", max_drawdown_total);
@@ -304,11 +703,41 @@ fn process_files_as_range(
     
     println!("======================");
 
+    if let Some(path) = &args.export_trade_context {
+        let times = export_time_formatter(args, &dashboard.trade_state);
+        dashboard.trade_state.export_trades_with_context_formatted(path, &times)?;
+        println!("Trade context exported to {}", path);
+    }
+
+    // Combined report across all files and symbols
+    let mut aggregate = AggregateResult::from_dashboard(&mut dashboard, file_paths.len());
+    if let Some(reference) = &args.beta_reference {
+        aggregate = aggregate.with_beta_reference(&dashboard.trade_state, reference);
+    }
+    if let Some(split) = args.sessions {
+        aggregate = aggregate.with_sessions(&dashboard.trade_state, split);
+    }
+    println!("{}", aggregate.to_table());
+    if args.sessions.is_some() {
+        println!("{}", aggregate.sessions_table());
+    }
+
     // Use PnlReport to display results in a nice table
-    let pnl_report = PnlReport::new();
+    let pnl_report = PnlReport::new()
+        .with_min_closed_trades(args.min_closed_trades)
+        .with_position_mode(position_mode(args))
+        .with_time_format(args.time_format, utc_offset(args))
+        .with_include_unrealized(include_unrealized(args))
+        .with_mark_prices(dashboard.trade_state.last_mids())
+        .with_slippage_costs(dashboard.trade_state.slippage_costs().clone())
+        .with_instruments(backtest_config.instruments.clone())
+        .with_short_borrow_bps_per_day(args.short_borrow_bps_per_day);
     let all_trades = dashboard.trade_state.get_all_trades();
     let report = pnl_report.report(all_trades, Method::Fifo);
     println!("{}", report);
+    if args.pnl_by_hour {
+        println!("{}", pnl_report.hourly_pnl_table(all_trades, Method::Fifo));
+    }
     
     // Generate P&L graphs (PNG files)
     // let output_name = format!("aggregated_{}_{}",
@@ -339,17 +768,54 @@ fn process_files_as_range(
 
     log::info!("============================================================");
     // Print console output for each symbol
+    let mut json_summaries = serde_json::Map::new();
     for sym in &unique_symbols {
         if let Some(result) = all_pnl_results.get(sym) {
             let mut sym_pnl_results = HashMap::new();
             sym_pnl_results.insert(sym.clone(), result.clone());
             dashboard.to_console(sym, &sym_pnl_results, &capital_metrics_map);
+            json_summaries.insert(sym.clone(), dashboard.to_json(sym, &sym_pnl_results, &capital_metrics_map));
+            print_analyses(args, &dashboard, sym);
         }
     }
+    save_output_json(args, &json_summaries)?;
+
+    #[cfg(feature = "sqlite")]
+    save_sqlite_run(args, backtest_config, &mut dashboard)?;
     
     Ok(())
 }
 
+/// Run one file under `runs` consecutive seeds and print the net P&L distribution
+fn process_monte_carlo(
+    file_path: &Path,
+    runs: usize,
+    args: &Args,
+    backtest_config: &BacktestConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = file_path
+        .file_name()
+        .ok_or("Invalid file path")?
+        .to_str()
+        .ok_or("Invalid filename encoding")?;
+    let symbol = symbol_extractor(args)?.extract(filename);
+    let base_seed = args.seed.unwrap_or(0);
+    println!("Monte Carlo: {} runs of {} with seeds {}..{}", runs, file_path.display(), base_seed, base_seed + runs as u64);
+    
+    let engine = build_engine(args, backtest_config)?;
+    let results = engine.run_monte_carlo(file_path, runs, base_seed, || args.strategy.build(symbol.clone()))?;
+    
+    let pnl_report = PnlReport::new()
+        .with_position_mode(position_mode(args))
+        .with_time_format(args.time_format, utc_offset(args))
+        .with_include_unrealized(include_unrealized(args))
+        .with_instruments(backtest_config.instruments.clone())
+        .with_short_borrow_bps_per_day(args.short_borrow_bps_per_day);
+    let summary = MonteCarloSummary::from_trade_states(&results, &pnl_report, Method::Fifo);
+    println!("{}", summary.to_table());
+    Ok(())
+}
+
 /// Process multiple files in parallel and aggregate results
 fn process_files_parallel(
     file_paths: &[PathBuf],
@@ -360,24 +826,25 @@ fn process_files_parallel(
     println!("Processing {} files in parallel", file_paths.len());
     println!("{}", "=".repeat(60));
     
-    // Set up thread pool
-    if args.workers > 0 {
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(args.workers)
-            .build_global()
-            .unwrap();
-    }
+    // Set up thread pool (a no-op once the global pool exists)
+    init_global_thread_pool(args.workers);
     
-    // Extract symbol from first file
-    let first_file = &file_paths[0];
-    let filename = first_file
-        .file_name()
-        .ok_or("Invalid file path")?
-        .to_str()
-        .ok_or("Invalid filename encoding")?;
-    let symbol = extract_symbol_from_filename(filename);
+    // Extract each file's symbol
+    let extractor = symbol_extractor(args)?;
+    let mut file_symbols = Vec::with_capacity(file_paths.len());
+    for file_path in file_paths {
+        let filename = file_path
+            .file_name()
+            .ok_or("Invalid file path")?
+            .to_str()
+            .ok_or("Invalid filename encoding")?;
+        file_symbols.push(extractor.extract(filename));
+    }
     
-    println!("Symbol: {}", symbol);
+    let mut distinct_symbols = file_symbols.clone();
+    distinct_symbols.sort();
+    distinct_symbols.dedup();
+    println!("Symbols: {}", distinct_symbols.join(", "));
     println!("Files to process in parallel:");
     for (i, path) in file_paths.iter().enumerate() {
         println!("  {}. {}", i + 1, path.display());
@@ -395,19 +862,16 @@ fn process_files_parallel(
     );
     overall_pb.set_message("Processing files in parallel");
     
+    // Shared backtest engine (loads the feature file once)
+    let engine = build_engine(args, backtest_config)?;
+    
     // Process files in parallel
     let results: Vec<_> = file_paths
         .par_iter()
-        .map(|file_path| {
+        .zip(file_symbols.par_iter())
+        .map(|(file_path, symbol)| {
             // Create strategy for this file
-            let strategy = match &args.strategy {
-                StrategyCommand::Gpt(gpt_args) => {
-                    gpt_args.build_strategy(symbol.clone())
-                }
-            };
-            
-            // Create backtest engine
-            let engine = BacktestEngine::new(backtest_config.clone());
+            let strategy = args.strategy.build(symbol.clone());
             
             // Run backtest
             let result = engine.run_backtest_with_custom_strategy(file_path, strategy);
@@ -447,20 +911,61 @@ fn process_files_parallel(
         for trade in trade_state.get_all_trades() {
             merged_trade_state.add(trade.clone());
         }
+        // Merge slippage costs
+        for (trade_id, cost) in trade_state.slippage_costs() {
+            merged_trade_state.record_slippage(trade_id, *cost);
+        }
+        // Merge self-cross counts
+        for _ in 0..trade_state.self_cross_count() {
+            merged_trade_state.record_self_cross();
+        }
+        // Merge the data span; each file's first book starts a session
+        if let Some((start, end)) = trade_state.book_span() {
+            merged_trade_state.note_file_start(start);
+            merged_trade_state.note_book_time(start);
+            merged_trade_state.note_book_time(end);
+        }
         // Merge orderbooks
         for orderbook in trade_state.get_orderbooks() {
             merged_trade_state.add_orderbook(orderbook.clone());
         }
+        // Merge decisions
+        if let Some(decisions) = trade_state.decision_log() {
+            merged_trade_state.enable_decision_log();
+            if let Some(merged) = merged_trade_state.decision_log_mut() {
+                merged.extend_from(decisions);
+            }
+        }
+    }
+    if let Some(decisions) = merged_trade_state.decision_log() {
+        print!("{}", decisions.summary());
     }
+    save_decision_log(args, &merged_trade_state)?;
     
     // Create dashboard for analysis
-    let mut dashboard = TradeDashboard::new(
-        merged_trade_state,
-        backtest_config.margin_rate,
-    );
+    let mut dashboard = TradeDashboard::from_config(merged_trade_state, backtest_config)
+        .with_risk_aversion(args.risk_aversion)
+        .with_inventory_risk_window(args.inventory_risk_window_ms)
+        .with_mark_to_market_every_tick(args.mark_to_market_every_tick)
+        .with_include_unrealized(include_unrealized(args));
     
-    // Calculate PnL
-    let pnl_results = dashboard.pnl(&symbol);
+    // Combined report across all files and symbols
+    let mut aggregate = AggregateResult::from_dashboard(&mut dashboard, file_paths.len());
+    if let Some(reference) = &args.beta_reference {
+        aggregate = aggregate.with_beta_reference(&dashboard.trade_state, reference);
+    }
+    if let Some(split) = args.sessions {
+        aggregate = aggregate.with_sessions(&dashboard.trade_state, split);
+    }
+    let mut symbols: Vec<String> = aggregate.per_symbol.keys().cloned().collect();
+    symbols.sort();
+    let pnl_results = &aggregate.per_symbol;
+    
+    // Get capital metrics
+    let mut capital_metrics_map = HashMap::new();
+    for symbol in &symbols {
+        capital_metrics_map.insert(symbol.clone(), dashboard.get_capital_metrics(symbol));
+    }
     
     // Print diagnostic info
     println!("\n=== AGGREGATED DIAGNOSTIC INFO ===");
@@ -472,36 +977,56 @@ fn process_files_parallel(
         "Filled trades: {}",
         dashboard.trade_state.get_trades_history().len()
     );
-    if let Some(result) = pnl_results.get(&symbol) {
-        println!("Closed positions: {}", result.closed_trades.len());
+    for symbol in &symbols {
+        println!("Closed positions ({}): {}", symbol, pnl_results[symbol].closed_trades.len());
+        println!("Max Drawdown ({}): ${:.2}", symbol, capital_metrics_map[symbol].max_drawdown);
+        print_lowest_equity(args, &dashboard, symbol);
     }
     
-    // Get capital metrics for Max DD
-    let capital_metrics_temp = dashboard.get_capital_metrics(&symbol);
-    println!("Max Drawdown: ${:.2}", capital_metrics_temp.max_drawdown);
+    print_trade_ramp(&dashboard.trade_state);
     
     println!("Files processed: {}", file_paths.len());
     println!("===================================");
     
+    println!("{}", aggregate.to_table());
+    if args.sessions.is_some() {
+        println!("{}", aggregate.sessions_table());
+    }
+
     // Use PnlReport to display results in a nice table
-    let pnl_report = PnlReport::new();
+    let pnl_report = PnlReport::new()
+        .with_min_closed_trades(args.min_closed_trades)
+        .with_position_mode(position_mode(args))
+        .with_time_format(args.time_format, utc_offset(args))
+        .with_include_unrealized(include_unrealized(args))
+        .with_mark_prices(dashboard.trade_state.last_mids())
+        .with_slippage_costs(dashboard.trade_state.slippage_costs().clone())
+        .with_instruments(backtest_config.instruments.clone())
+        .with_short_borrow_bps_per_day(args.short_borrow_bps_per_day);
     let all_trades = dashboard.trade_state.get_all_trades();
     let report = pnl_report.report(all_trades, Method::Fifo);
     println!("{}", report);
+    if args.pnl_by_hour {
+        println!("{}", pnl_report.hourly_pnl_table(all_trades, Method::Fifo));
+    }
     
     // Display P&L graph in console
     pnl_report.display_console_graph(all_trades, Method::Fifo)?;
     
-    // Get capital metrics
-    let capital_metrics = dashboard.get_capital_metrics(&symbol);
-    let mut capital_metrics_map = HashMap::new();
-    capital_metrics_map.insert(symbol.clone(), capital_metrics);
-    
-    // Print metrics to console
-    let _metrics_summary = dashboard.print_pnl_metrics(&symbol, &pnl_results);
-    
-    log::info!("============================================================");
-    dashboard.to_console(&symbol, &pnl_results, &capital_metrics_map);
+    // Print metrics and summaries for every traded symbol
+    let mut json_summaries = serde_json::Map::new();
+    for symbol in &symbols {
+        let _metrics_summary = dashboard.print_pnl_metrics(symbol, pnl_results);
+        
+        log::info!("============================================================");
+        dashboard.to_console(symbol, pnl_results, &capital_metrics_map);
+        json_summaries.insert(symbol.clone(), dashboard.to_json(symbol, pnl_results, &capital_metrics_map));
+        print_analyses(args, &dashboard, symbol);
+    }
+    save_output_json(args, &json_summaries)?;
+
+    #[cfg(feature = "sqlite")]
+    save_sqlite_run(args, backtest_config, &mut dashboard)?;
     
     Ok(())
 }
@@ -516,45 +1041,74 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let backtest_config = BacktestConfig {
         fill_rate: args.fill_rate,
         slippage_bps: args.slippage_bps,
+        buy_slippage_bps: args.buy_slippage_bps,
+        sell_slippage_bps: args.sell_slippage_bps,
         rejection_rate: args.rejection_rate,
         margin_rate: args.margin_rate,
-        min_spread_pct: 0.0005, // Default value, could be made a CLI arg if needed
-        spread_percent: 0.005, // Default value, could be made a CLI arg if needed
-        max_order_volume: 0.0,
+        min_spread_pct: args.min_spread_pct,
+        max_order_volume: args.max_order_volume,
+        stored_book_depth: args.stored_book_depth,
+        // Markouts and tick-level marking need every book, not just those trades were made on
+        store_all_books: args.store_all_books || args.mark_to_market_every_tick || !args.markout_horizons.is_empty(),
+        lenient_parquet: args.lenient_parquet,
+        instruments: match &args.instruments_file {
+            Some(path) => InstrumentSpecRegistry::from_file(path)?,
+            None => InstrumentSpecRegistry::default(),
+        },
+        mid_price_filter: (args.mid_filter_window > 0).then(|| MidPriceFilterConfig {
+            window: args.mid_filter_window,
+            max_deviation_mads: args.mid_filter_mads,
+        }),
+        resample_interval_ms: args.resample_ms,
+        sizing_mode: match (args.size_pct_of_equity, args.size_pct_of_book) {
+            (Some(pct), _) => SizingMode::PercentOfEquity(pct),
+            (None, Some(pct)) => SizingMode::PercentOfTopOfBook(pct),
+            (None, None) => SizingMode::AbsoluteQuantity,
+        },
+        initial_equity: args.initial_equity,
+        strict_causality: args.strict_causality,
+        requote_on_move: args.requote_on_move,
+        record_decisions: args.decision_log.is_some(),
+        seed: args.seed,
+        max_hold_ms: args.max_hold_ms,
+        dedup_update_ids: args.dedup_update_ids,
+        allow_self_cross: args.allow_self_cross,
+        maker_fee_bps: args.maker_fee_bps,
+        taker_fee_bps: args.taker_fee_bps,
+        fill_from_depth: args.fill_from_depth,
+        cross_file_state: if args.reset_between_files { CrossFileState::Reset } else { CrossFileState::Preserve },
     };
 
-    // Determine if the input is a file path or a regex pattern
-    let file_path = Path::new(&args.file);
-    
-    let files_to_process = if file_path.exists() && file_path.is_file() {
-        // Single file mode
-        vec![file_path.to_path_buf()]
-    } else {
-        // Pattern mode - search for matching files
-        let search_dir = Path::new(&args.directory);
-        
-        if !search_dir.exists() || !search_dir.is_dir() {
-            return Err(format!("Directory '{}' does not exist or is not a directory", args.directory).into());
-        }
-        
+    // Reject inconsistent strategy parameters before touching any data
+    if let StrategyCommand::Gpt(gpt_args) = &args.strategy {
+        gpt_args.config().validate()?;
+    }
+
+    // Use the input as a file when it exists or looks like a path, otherwise as a pattern
+    let is_pattern = !Path::new(&args.file).is_file() && !looks_like_path(&args.file);
+    if is_pattern {
         println!("Searching for files matching pattern '{}' in directory '{}'", args.file, args.directory);
-        
-        let matching_files = find_matching_files(search_dir, &args.file)?;
-        
-        if matching_files.is_empty() {
-            return Err(format!("No files found matching pattern '{}'", args.file).into());
-        }
-        
-        println!("\nFound {} matching files:", matching_files.len());
-        for (i, file) in matching_files.iter().enumerate() {
+    }
+    let files_to_process = resolve_input_files(&args.file, Path::new(&args.directory))?;
+    if is_pattern {
+        println!("\nFound {} matching files:", files_to_process.len());
+        for (i, file) in files_to_process.iter().enumerate() {
             println!("  {}. {}", i + 1, file.display());
         }
-        
-        matching_files
-    };
+    }
+
+    if args.monte_carlo.is_none() && files_to_process.len() > 1 && (args.parallel || args.aggregate_files) {
+        check_multi_file_flags(&args)?;
+    }
 
-    // Process files based on aggregate_files and parallel flags
-    if files_to_process.len() > 1 {
+    // Process files based on monte_carlo, aggregate_files and parallel flags
+    if let Some(runs) = args.monte_carlo {
+        for file_path in &files_to_process {
+            if let Err(e) = process_monte_carlo(file_path, runs, &args, &backtest_config) {
+                eprintln!("Error running Monte Carlo on {:?}: {}", file_path, e);
+            }
+        }
+    } else if files_to_process.len() > 1 {
         if args.parallel {
             // Process files in parallel and aggregate results (overrides aggregate_files)
             if let Err(e) = process_files_parallel(&files_to_process, &args, &backtest_config) {
@@ -567,20 +1121,31 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
             }
         } else {
             // Process each file individually (sequential)
+            let mut stitched_equity = Vec::new();
+            let mut json_summaries = serde_json::Map::new();
             for file_path in &files_to_process {
-                if let Err(e) = process_single_file(file_path, &args, &backtest_config) {
+                if let Err(e) = process_single_file(file_path, &args, &backtest_config, &mut stitched_equity, &mut json_summaries) {
                     eprintln!("Error processing file {:?}: {}", file_path, e);
                     // Continue with next file instead of failing completely
                 }
             }
+            if let Some((_, equity)) = stitched_equity.last() {
+                println!("\nCumulative P&L across {} files: ${:.2}", files_to_process.len(), equity);
+            }
+            save_stitched_equity(&args, &stitched_equity)?;
+            save_output_json(&args, &json_summaries)?;
         }
     } else {
         // Single file - process normally
+        let mut stitched_equity = Vec::new();
+        let mut json_summaries = serde_json::Map::new();
         for file_path in &files_to_process {
-            if let Err(e) = process_single_file(file_path, &args, &backtest_config) {
+            if let Err(e) = process_single_file(file_path, &args, &backtest_config, &mut stitched_equity, &mut json_summaries) {
                 eprintln!("Error processing file {:?}: {}", file_path, e);
             }
         }
+        save_stitched_equity(&args, &stitched_equity)?;
+        save_output_json(&args, &json_summaries)?;
     }
 
     let total_time = main_start.elapsed();
@@ -1,24 +1,97 @@
 use crate::core::{Trade, PnLResult};
+use crate::trading::InstrumentSpecRegistry;
+use crate::trading::metrics::{annualized_sharpe, calmar_ratio, fee_to_pnl_ratio, streaks};
+use crate::utils::{TimeFormat, TimestampFormatter};
 use crate::pnl::{
-    models::Method,
+    models::{Method, BootstrapResult, CostAttribution, HourlyPnl, IncludeUnrealized, PositionMode, Record, QUANTITY_EPSILON, snap_quantity},
     fifo::FifoProcessor,
+    lifo::LifoProcessor,
     position::PositionProcessor,
+    incremental::IncrementalPnl,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use chrono::FixedOffset;
 use comfy_table::Table;
+use log::warn;
 use plotters::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+/// Seed used by `PnlReport::bootstrap_pnl` so repeated runs give the same interval
+const BOOTSTRAP_SEED: u64 = 42;
 
 /// Trait for calculation
 pub trait Processor {
     /// Process trades and calculate P&L
     fn process(&self, trades: &[Trade], method: Method) -> PnLResult;
+    
+    /// Process trades, marking open positions at `mark_prices` where a symbol has one
+    /// and at its last trade price otherwise
+    fn process_with_mark(&self, trades: &[Trade], method: Method, mark_prices: &HashMap<String, f64>) -> PnLResult;
+    
+    /// Realized P&L per closing fill, in processing order
+    fn pnl_records(&self, trades: &[Trade], method: Method) -> Vec<Record> {
+        self.process(trades, method).pnl_records
+    }
+    
+    /// P&L of each symbol's trades on their own
+    fn process_by_symbol(&self, trades: &[Trade], method: Method) -> HashMap<String, PnLResult> {
+        let mut trades_by_symbol: HashMap<&str, Vec<Trade>> = HashMap::new();
+        for trade in trades {
+            trades_by_symbol.entry(trade.symbol.as_str()).or_default().push(trade.clone());
+        }
+        trades_by_symbol.into_iter()
+            .map(|(symbol, symbol_trades)| (symbol.to_string(), self.process(&symbol_trades, method)))
+            .collect()
+    }
 }
 
+/// Recompute P&L from already executed trades without re-running the simulation
+///
+/// Lets a previous run's trades be re-evaluated under a different method or
+/// commission rate (a percentage, e.g. 0.03 for 0.03%). The commission, plus any
+/// maker/taker fees recorded on the trades, is reported in `total_fees`.
+pub fn recompute_from_trades(trades: &[Trade], method: Method, commission_rate: f64) -> PnLResult {
+    let report = PnlReport::with_commission(commission_rate);
+    let mut result = report.calculate(trades, method);
+    result.total_fees = report.commission(trades);
+    result
+}
+
+/// Milliseconds per day, the accrual period of `short_borrow_bps_per_day`
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// Milliseconds per hour, the width of a `pnl_by_hour` bucket
+const MS_PER_HOUR: i64 = 3_600_000;
+
+/// Most points `auto_aggregation_ms` lets a chart have; it picks the finest bucket that
+/// stays under, which leaves at least `AUTO_CHART_MAX_POINTS / 2.5` points
+const AUTO_CHART_MAX_POINTS: i64 = 250;
+
+/// Round bucket sizes `auto_aggregation_ms` chooses from, no more than 2.5x apart
+const AUTO_BUCKETS_MS: [i64; 18] = [
+    100, 250, 500, 1_000, 2_000, 5_000, 10_000, 15_000, 30_000, 60_000,
+    120_000, 300_000, 600_000, 1_800_000, 3_600_000, 7_200_000, 14_400_000, 28_800_000,
+];
+
+/// Closed trades needed per symbol before risk metrics are reported
+const DEFAULT_MIN_CLOSED_TRADES: usize = 2;
+
 /// Main PnL report generator that delegates to specific implementations
 pub struct PnlReport {
     fifo_processor: FifoProcessor,
+    lifo_processor: LifoProcessor,
     position_processor: PositionProcessor,
     commission_rate: f64,  // Commission rate as a percentage (e.g., 0.03 for 0.03%)
+    include_unrealized: IncludeUnrealized,
+    instruments: InstrumentSpecRegistry,
+    min_closed_trades: usize,
+    time_format: TimeFormat,
+    utc_offset: FixedOffset,
+    short_borrow_bps_per_day: f64,
+    mark_prices: HashMap<String, f64>,
+    slippage_costs: HashMap<String, f64>,
+    open_exposure_columns: bool,
 }
 
 impl PnlReport {
@@ -29,9 +102,124 @@ impl PnlReport {
     pub fn with_commission(commission_rate: f64) -> Self {
         Self {
             fifo_processor: FifoProcessor::new(),
+            lifo_processor: LifoProcessor::new(),
             position_processor: PositionProcessor::new(),
             commission_rate,
+            include_unrealized: IncludeUnrealized::default(),
+            instruments: InstrumentSpecRegistry::default(),
+            min_closed_trades: DEFAULT_MIN_CLOSED_TRADES,
+            time_format: TimeFormat::default(),
+            utc_offset: FixedOffset::east_opt(0).unwrap(),
+            short_borrow_bps_per_day: 0.0,
+            mark_prices: HashMap::new(),
+            slippage_costs: HashMap::new(),
+            open_exposure_columns: true,
+        }
+    }
+    
+    /// Scale P&L and commission by each symbol's contract size
+    pub fn with_instruments(mut self, instruments: InstrumentSpecRegistry) -> Self {
+        self.instruments = instruments;
+        self
+    }
+    
+    /// Choose whether `report` and `display_console_graph` mark open positions
+    pub fn with_include_unrealized(mut self, include_unrealized: IncludeUnrealized) -> Self {
+        self.include_unrealized = include_unrealized;
+        self
+    }
+    
+    /// Net opposite trades per symbol, or keep hedge-mode long and short legs apart
+    pub fn with_position_mode(mut self, mode: PositionMode) -> Self {
+        self.fifo_processor = FifoProcessor::new().with_position_mode(mode);
+        self.lifo_processor = LifoProcessor::new().with_position_mode(mode);
+        self.position_processor = PositionProcessor::new().with_position_mode(mode);
+        self
+    }
+    
+    /// Minimum closed trades per symbol before drawdown, Sharpe and the console chart are shown
+    pub fn with_min_closed_trades(mut self, min_closed_trades: usize) -> Self {
+        self.min_closed_trades = min_closed_trades;
+        self
+    }
+    
+    /// Render chart time axes with `time_format`, at `utc_offset` from UTC
+    pub fn with_time_format(mut self, time_format: TimeFormat, utc_offset: FixedOffset) -> Self {
+        self.time_format = time_format;
+        self.utc_offset = utc_offset;
+        self
+    }
+    
+    /// Charge short positions `bps` of their entry notional per day held, deducted from net P&L at close
+    pub fn with_short_borrow_bps_per_day(mut self, bps: f64) -> Self {
+        self.short_borrow_bps_per_day = bps;
+        self
+    }
+    
+    /// Mark open positions at these per-symbol prices (e.g. the last book mid) instead of
+    /// the symbol's last trade price
+    pub fn with_mark_prices(mut self, mark_prices: HashMap<String, f64>) -> Self {
+        self.mark_prices = mark_prices;
+        self
+    }
+    
+    /// Charge each filled trade the slippage cost recorded for its id (see
+    /// `TradeState::slippage_costs`); trade prices are taken as the quoted, pre-slippage prices
+    pub fn with_slippage_costs(mut self, slippage_costs: HashMap<String, f64>) -> Self {
+        self.slippage_costs = slippage_costs;
+        self
+    }
+    
+    /// Show or hide the report's open notional and unrealized P&L columns. Symbols that end
+    /// with open inventory are flagged below the table either way.
+    pub fn with_open_exposure_columns(mut self, show: bool) -> Self {
+        self.open_exposure_columns = show;
+        self
+    }
+    
+    /// Price the symbol's open position is marked at: its configured mark, else its last filled trade
+    fn mark_price(&self, symbol: &str, trades: &[Trade]) -> Option<f64> {
+        self.mark_prices.get(symbol).copied().or_else(|| {
+            trades.iter()
+                .filter(|t| t.symbol == symbol && t.status.to_lowercase() == "filled")
+                .max_by_key(|t| t.time)
+                .map(|t| t.price)
+        })
+    }
+    
+    /// Notice for a symbol that ended with open inventory, if any
+    fn open_inventory_notice(symbol: &str, result: &PnLResult, open_notional: f64) -> Option<String> {
+        (result.remaining_shares != 0.0).then(|| format!(
+            "Open inventory for {}: {} left open (${:.2} notional, unrealized ${:.2})",
+            symbol, result.remaining_shares, open_notional, result.unrealized_pnl
+        ))
+    }
+    
+    /// Report row with the open exposure cells spliced in after Net P&L when they are shown
+    fn report_row(&self, mut row: Vec<String>, open_notional: String, unrealized: String) -> Vec<String> {
+        if self.open_exposure_columns {
+            row.splice(6..6, [open_notional, unrealized]);
         }
+        row
+    }
+    
+    /// Axis label formatter for a chart spanning `start_ms..=end_ms`
+    pub(crate) fn time_formatter(&self, start_ms: i64, end_ms: i64) -> TimestampFormatter {
+        self.time_format.formatter(self.utc_offset, start_ms, end_ms)
+    }
+    
+    /// Notice for a symbol with too few closed trades for meaningful metrics, if any
+    fn insufficient_data_notice(&self, symbol: &str, result: &PnLResult) -> Option<String> {
+        let closed = result.closed_trades.len();
+        (closed < self.min_closed_trades).then(|| format!(
+            "Insufficient data for {}: {} closed trade(s), need at least {}; drawdown and Sharpe omitted",
+            symbol, closed, self.min_closed_trades
+        ))
+    }
+    
+    /// Headline P&L of a result under the configured `IncludeUnrealized` setting
+    pub fn headline_pnl(&self, result: &PnLResult) -> f64 {
+        self.include_unrealized.total(result.total_pnl, result.unrealized_pnl)
     }
     
     /// Calculate P&L metrics from trading logs using specified method
@@ -43,6 +231,17 @@ impl PnlReport {
     /// # Returns
     /// * `PnLResult` - Complete P&L result including realized and unrealized P&L
     pub fn calculate(&self, trades: &[Trade], method: Method) -> PnLResult {
+        self.calculate_marked(trades, method, &self.mark_prices)
+    }
+    
+    /// `calculate` with open lots marked to market at `last_prices`; a symbol without a price
+    /// is marked at its last filled trade
+    pub fn calculate_with_unrealized(&self, trades: &[Trade], method: Method, last_prices: &HashMap<String, f64>) -> PnLResult {
+        self.calculate_marked(trades, method, last_prices)
+    }
+    
+    /// `calculate` with open positions marked at `mark_prices` instead of the configured marks
+    fn calculate_marked(&self, trades: &[Trade], method: Method, mark_prices: &HashMap<String, f64>) -> PnLResult {
         // Filter only filled orders (actual trades)
         let filled_orders: Vec<&Trade> = trades.iter()
             .filter(|t| t.status.to_lowercase() == "filled")
@@ -55,22 +254,261 @@ impl PnlReport {
                 closed_trades: Vec::new(),
                 total_fees: 0.0,
                 remaining_shares: 0.0,
+                pnl_records: Vec::new(),
             };
         }
         
-        // Convert to owned trades for processing
-        let filled_trades: Vec<Trade> = filled_orders.into_iter().cloned().collect();
+        // Convert to owned trades for processing, in underlying units so
+        // P&L scales with contract size
+        let filled_trades: Vec<Trade> = filled_orders.into_iter()
+            .map(|t| {
+                let mut trade = t.clone();
+                trade.quantity *= self.instruments.get(&t.symbol).contract_size;
+                trade
+            })
+            .collect();
         
-        // Process trades based on selected method
-        let result = match method {
-            Method::Fifo => self.fifo_processor.process_realized(&filled_trades),
-            Method::Position => self.position_processor.process_position(&filled_trades),
+        // Process trades based on selected method, marking open positions
+        let mut result = match method {
+            Method::Fifo => self.fifo_processor.process_marked(&filled_trades, mark_prices),
+            Method::Lifo => self.lifo_processor.process_marked(&filled_trades, mark_prices),
+            Method::Position => self.position_processor.process_position_marked(&filled_trades, mark_prices),
         };
+        result.total_fees = Self::exchange_fees(trades);
+        result
+    }
+    
+    /// Commission charged on the filled volume of the given trades, plus the maker/taker
+    /// fees the executor recorded on them
+    pub fn commission(&self, trades: &[Trade]) -> f64 {
+        let total_volume = trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .map(|t| self.instruments.notional(&t.symbol, t.price, t.quantity))
+            .sum::<f64>();
+        total_volume * (self.commission_rate / 100.0) + Self::exchange_fees(trades)
+    }
+    
+    /// Maker/taker fees recorded on the filled trades
+    fn exchange_fees(trades: &[Trade]) -> f64 {
+        trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .map(|t| t.fee)
+            .sum()
+    }
+    
+    /// Slippage cost of the filled trades
+    pub fn slippage_cost(&self, trades: &[Trade]) -> f64 {
+        trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .filter_map(|t| self.slippage_costs.get(&t.id))
+            .sum()
+    }
+    
+    /// Split net P&L into the frictionless P&L at quoted prices and the slippage and fees paid
+    pub fn cost_attribution(&self, trades: &[Trade], method: Method) -> CostAttribution {
+        let frictionless_pnl = self.headline_pnl(&self.calculate(trades, method));
+        let slippage_cost = self.slippage_cost(trades);
+        let fees = self.commission(trades) + self.borrow_cost(trades);
+        CostAttribution {
+            frictionless_pnl,
+            slippage_cost,
+            fees,
+            net_pnl: frictionless_pnl - slippage_cost - fees,
+        }
+    }
+    
+    /// Total executed notional of the filled trades
+    pub fn turnover(&self, trades: &[Trade]) -> f64 {
+        trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .map(|t| self.instruments.notional(&t.symbol, t.price, t.quantity))
+            .sum()
+    }
+    
+    /// Commission and borrow fees as a share of headline gross P&L (see `fee_to_pnl_ratio`)
+    pub fn fee_to_pnl_ratio(&self, trades: &[Trade], method: Method) -> f64 {
+        let gross_pnl = self.headline_pnl(&self.calculate(trades, method));
+        fee_to_pnl_ratio(self.commission(trades) + self.borrow_cost(trades), gross_pnl)
+    }
+    
+    /// Borrow fees accrued by short lots closed within the given trades
+    ///
+    /// Shorts are matched FIFO against later buys and charged on their entry notional
+    /// for the time between the opening sell and the closing buy. Shorts still open are not charged.
+    pub fn borrow_cost(&self, trades: &[Trade]) -> f64 {
+        if self.short_borrow_bps_per_day <= 0.0 {
+            return 0.0;
+        }
+        let mut filled: Vec<&Trade> = trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .collect();
+        filled.sort_by_key(|t| t.time);
         
-        // For now, we'll skip unrealized PnL calculation as it requires open trades tracking
-        // This can be enhanced later to maintain state of open positions
+        // Open lots per symbol as (time, price, signed quantity), oldest first
+        let mut lots: HashMap<&str, VecDeque<(i64, f64, f64)>> = HashMap::new();
+        let mut cost = 0.0;
+        for trade in filled {
+            let signed = if trade.side.eq_ignore_ascii_case("buy") { trade.quantity } else { -trade.quantity };
+            let queue = lots.entry(trade.symbol.as_str()).or_default();
+            let mut remaining = signed;
+            while let Some(lot) = queue.front_mut() {
+                if remaining.abs() < QUANTITY_EPSILON || lot.2.signum() == remaining.signum() {
+                    break;
+                }
+                let matched = lot.2.abs().min(remaining.abs());
+                if lot.2 < 0.0 {
+                    let days = (trade.time - lot.0).max(0) as f64 / MS_PER_DAY;
+                    cost += self.instruments.notional(&trade.symbol, lot.1, matched)
+                        * self.short_borrow_bps_per_day / 10_000.0 * days;
+                }
+                lot.2 = snap_quantity(lot.2 - matched * lot.2.signum());
+                remaining = snap_quantity(remaining - matched * remaining.signum());
+                if lot.2.abs() < QUANTITY_EPSILON {
+                    queue.pop_front();
+                }
+            }
+            if remaining.abs() >= QUANTITY_EPSILON {
+                queue.push_back((trade.time, trade.price, remaining));
+            }
+        }
+        cost
+    }
+    
+    /// Running commission after each filled trade, as `(time, fees)` in time order
+    ///
+    /// This is the gross P&L needed to break even; the last value equals `commission`.
+    pub fn cumulative_fees(&self, trades: &[Trade]) -> Vec<(i64, f64)> {
+        let mut filled: Vec<&Trade> = trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .collect();
+        filled.sort_by_key(|t| t.time);
         
-        result
+        let mut fees = 0.0;
+        filled.into_iter()
+            .map(|trade| {
+                fees += self.commission(std::slice::from_ref(trade));
+                (trade.time, fees)
+            })
+            .collect()
+    }
+    
+    /// Cumulative P&L (realized + unrealized, all symbols) after each filled trade,
+    /// as `(time, pnl)` in time order with one point per timestamp
+    pub fn equity_curve(&self, trades: &[Trade], method: Method) -> Vec<(i64, f64)> {
+        self.equity_curve_continued(trades, method, 0.0)
+    }
+    
+    /// `equity_curve` starting from `start_offset` instead of zero. Passing the previous
+    /// file's final value stitches per-file curves into one series without resets.
+    pub fn equity_curve_continued(&self, trades: &[Trade], method: Method, start_offset: f64) -> Vec<(i64, f64)> {
+        let mut filled: Vec<&Trade> = trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .collect();
+        filled.sort_by_key(|t| t.time);
+        
+        let mut accumulators: HashMap<&str, IncrementalPnl> = HashMap::new();
+        let mut curve: Vec<(i64, f64)> = Vec::new();
+        for trade in filled {
+            let mut scaled = trade.clone();
+            scaled.quantity *= self.instruments.get(&trade.symbol).contract_size;
+            accumulators.entry(trade.symbol.as_str())
+                .or_insert_with(|| IncrementalPnl::new(method))
+                .push(&scaled);
+            let equity = start_offset + accumulators.values().map(IncrementalPnl::total).sum::<f64>();
+            
+            match curve.last_mut() {
+                Some(last) if last.0 == trade.time => last.1 = equity,
+                _ => curve.push((trade.time, equity)),
+            }
+        }
+        curve
+    }
+    
+    /// Value of a `cumulative_fees` line at `time`
+    fn fees_at(fee_line: &[(i64, f64)], time: i64) -> f64 {
+        let paid = fee_line.partition_point(|(fee_time, _)| *fee_time <= time);
+        paid.checked_sub(1).map(|i| fee_line[i].1).unwrap_or(0.0)
+    }
+    
+    /// Bootstrap confidence interval for total realized P&L
+    ///
+    /// Resamples the closed-trade P&Ls with replacement `iterations` times and reports
+    /// the mean and 5th/95th percentiles of the resampled totals.
+    pub fn bootstrap_pnl(&self, trades: &[Trade], method: Method, iterations: usize) -> BootstrapResult {
+        self.bootstrap_pnl_with_seed(trades, method, iterations, BOOTSTRAP_SEED)
+    }
+    
+    /// Same as `bootstrap_pnl` with an explicit RNG seed
+    pub fn bootstrap_pnl_with_seed(
+        &self,
+        trades: &[Trade],
+        method: Method,
+        iterations: usize,
+        seed: u64,
+    ) -> BootstrapResult {
+        let result = self.calculate(trades, method);
+        let pnls: Vec<f64> = result.closed_trades.iter().map(|t| t.pnl).collect();
+        let point_estimate = result.total_pnl;
+        
+        if pnls.is_empty() || iterations == 0 {
+            return BootstrapResult {
+                point_estimate,
+                mean: point_estimate,
+                p5: point_estimate,
+                p95: point_estimate,
+                iterations: 0,
+            };
+        }
+        
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut totals = Vec::with_capacity(iterations);
+        for _ in 0..iterations {
+            let mut total = 0.0;
+            for _ in 0..pnls.len() {
+                total += pnls[rng.gen_range(0..pnls.len())];
+            }
+            totals.push(total);
+        }
+        totals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        
+        let percentile = |p: f64| totals[((p / 100.0) * (totals.len() - 1) as f64).round() as usize];
+        
+        BootstrapResult {
+            point_estimate,
+            mean: totals.iter().sum::<f64>() / totals.len() as f64,
+            p5: percentile(5.0),
+            p95: percentile(95.0),
+            iterations,
+        }
+    }
+    
+    /// Realized P&L and closed-trade count for each hour of day at `tz`, by close time.
+    ///
+    /// Multi-day runs are folded into the same 24 buckets, hour 0 first.
+    pub fn pnl_by_hour(&self, trades: &[Trade], method: Method, tz: FixedOffset) -> Vec<HourlyPnl> {
+        let mut buckets: Vec<HourlyPnl> = (0..24).map(|hour| HourlyPnl { hour, pnl: 0.0, trades: 0 }).collect();
+        let offset_ms = tz.local_minus_utc() as i64 * 1000;
+        for record in self.calculate(trades, method).pnl_records {
+            let hour = (record.timestamp + offset_ms).rem_euclid(MS_PER_DAY as i64) / MS_PER_HOUR;
+            let bucket = &mut buckets[hour as usize];
+            bucket.pnl += record.profit;
+            bucket.trades += 1;
+        }
+        buckets
+    }
+    
+    /// Console table of `pnl_by_hour` in the report's UTC offset
+    pub fn hourly_pnl_table(&self, trades: &[Trade], method: Method) -> String {
+        let mut table = Table::new();
+        table.set_header(vec!["Hour", "Trades", "Realized P&L"]);
+        for bucket in self.pnl_by_hour(trades, method, self.utc_offset) {
+            table.add_row(vec![
+                format!("{:02}:00", bucket.hour),
+                bucket.trades.to_string(),
+                format!("${:.2}", bucket.pnl),
+            ]);
+        }
+        format!("\n=== Realized P&L by hour of day (UTC{}) ===\n{}", self.utc_offset, table)
     }
     
     /// Generate a tabular report of P&L by symbol
@@ -87,15 +525,22 @@ impl PnlReport {
         
         // Create table
         let mut table = Table::new();
-        table.set_header(vec![
-            "Symbol",
-            "Trades",
-            "Gross P&L",
-            "Commission",
-            "Net P&L",
-            "Max Drawdown %",
-            "Sharpe Ratio",
-        ]);
+        table.set_header(self.report_row(
+            [
+                "Symbol",
+                "Trades",
+                "Gross P&L",
+                "Commission",
+                "Slippage",
+                "Net P&L",
+                "Max Drawdown %",
+                "Sharpe Ratio",
+                "Calmar (MAR)",
+                "Win/Loss Streak",
+            ].map(String::from).to_vec(),
+            "Open Notional".to_string(),
+            "Unrealized P&L".to_string(),
+        ));
         
         // Sort symbols for consistent output
         let mut symbols: Vec<String> = trades_by_symbol.keys().cloned().collect();
@@ -104,44 +549,89 @@ impl PnlReport {
         let mut total_trades = 0;
         let mut total_gross_pnl = 0.0;
         let mut total_commission = 0.0;
+        let mut total_borrow = 0.0;
+        let mut total_slippage = 0.0;
         let mut total_net_pnl = 0.0;
+        let mut total_open_notional = 0.0;
+        let mut total_unrealized = 0.0;
         let mut max_drawdown_sum = 0.0;
         let mut sharpe_sum = 0.0;
         let mut symbol_count = 0;
+        let mut calmar_sum = 0.0;
+        let mut calmar_count = 0;
+        let mut longest_win = 0;
+        let mut longest_loss = 0;
+        let mut notices = Vec::new();
         
         // Process each symbol
         for symbol in symbols {
             if let Some(symbol_trades) = trades_by_symbol.get(&symbol) {
                 let result = self.calculate(symbol_trades, method);
-                let gross_pnl = result.total_pnl + result.unrealized_pnl;
+                let gross_pnl = self.headline_pnl(&result);
+                
+                // Calculate commission and short borrow fees
+                let commission = self.commission(symbol_trades);
+                let borrow = self.borrow_cost(symbol_trades);
+                let slippage = self.slippage_cost(symbol_trades);
+                let net_pnl = gross_pnl - commission - borrow - slippage;
+                let symbol_streaks = streaks(result.closed_trades.iter().map(|t| t.pnl));
+                let streak = format!("{}/{}", symbol_streaks.longest_win, symbol_streaks.longest_loss);
+                longest_win = longest_win.max(symbol_streaks.longest_win);
+                longest_loss = longest_loss.max(symbol_streaks.longest_loss);
+                let open_notional = self.mark_price(&symbol, symbol_trades)
+                    .map_or(0.0, |price| result.remaining_shares.abs() * price);
+                let open_cells = (format!("${:.2}", open_notional), format!("${:.2}", result.unrealized_pnl));
+                notices.extend(Self::open_inventory_notice(&symbol, &result, open_notional));
                 
-                // Calculate commission
-                let total_volume = symbol_trades.iter()
-                    .filter(|t| t.status.to_lowercase() == "filled")
-                    .map(|t| t.quantity * t.price)
-                    .sum::<f64>();
-                let commission = total_volume * (self.commission_rate / 100.0);
-                let net_pnl = gross_pnl - commission;
+                total_trades += symbol_trades.len();
+                total_gross_pnl += gross_pnl;
+                total_commission += commission;
+                total_borrow += borrow;
+                total_slippage += slippage;
+                total_net_pnl += net_pnl;
+                total_open_notional += open_notional;
+                total_unrealized += result.unrealized_pnl;
+                
+                if let Some(notice) = self.insufficient_data_notice(&symbol, &result) {
+                    table.add_row(self.report_row(vec![
+                        symbol.clone(),
+                        symbol_trades.len().to_string(),
+                        format!("${:.2}", gross_pnl),
+                        format!("${:.2}", commission),
+                        format!("${:.2}", slippage),
+                        format!("${:.2}", net_pnl),
+                        "n/a".to_string(),
+                        "n/a".to_string(),
+                        "n/a".to_string(),
+                        streak,
+                    ], open_cells.0, open_cells.1));
+                    notices.push(notice);
+                    continue;
+                }
                 
                 // Calculate metrics
                 let (max_drawdown, sharpe_ratio) = self.calculate_metrics(symbol_trades, &result);
+                let calmar = self.calculate_calmar(symbol_trades, &result);
                 
-                table.add_row(vec![
+                table.add_row(self.report_row(vec![
                     symbol.clone(),
                     symbol_trades.len().to_string(),
                     format!("${:.2}", gross_pnl),
                     format!("${:.2}", commission),
+                    format!("${:.2}", slippage),
                     format!("${:.2}", net_pnl),
                     format!("{:.2}%", max_drawdown),
                     format!("{:.2}", sharpe_ratio),
-                ]);
+                    Self::format_ratio(calmar),
+                    streak,
+                ], open_cells.0, open_cells.1));
                 
-                total_trades += symbol_trades.len();
-                total_gross_pnl += gross_pnl;
-                total_commission += commission;
-                total_net_pnl += net_pnl;
+                if calmar.is_finite() {
+                    calmar_sum += calmar;
+                    calmar_count += 1;
+                }
                 
-                if !max_drawdown.is_nan() {
+                if max_drawdown.is_finite() && sharpe_ratio.is_finite() {
                     max_drawdown_sum += max_drawdown;
                     sharpe_sum += sharpe_ratio;
                     symbol_count += 1;
@@ -150,32 +640,63 @@ impl PnlReport {
         }
         
         // Calculate averages for metrics
-        let avg_drawdown = if symbol_count > 0 { max_drawdown_sum / symbol_count as f64 } else { 0.0 };
-        let avg_sharpe = if symbol_count > 0 { sharpe_sum / symbol_count as f64 } else { 0.0 };
+        let (avg_drawdown, avg_sharpe) = if symbol_count > 0 {
+            (
+                format!("{:.2}%", max_drawdown_sum / symbol_count as f64),
+                format!("{:.2}", sharpe_sum / symbol_count as f64),
+            )
+        } else {
+            ("n/a".to_string(), "n/a".to_string())
+        };
+        let avg_calmar = if calmar_count > 0 {
+            format!("{:.2}", calmar_sum / calmar_count as f64)
+        } else {
+            "n/a".to_string()
+        };
         
         // Add separator
-        table.add_row(vec![
+        let rule = "─────────────".to_string();
+        table.add_row(self.report_row(vec![
             "─────────".to_string(),
             "─────────".to_string(),
-            "─────────────".to_string(),
-            "─────────────".to_string(),
-            "─────────────".to_string(),
-            "─────────────".to_string(),
-            "─────────────".to_string(),
-        ]);
+            rule.clone(),
+            rule.clone(),
+            rule.clone(),
+            rule.clone(),
+            rule.clone(),
+            rule.clone(),
+            rule.clone(),
+            rule.clone(),
+        ], rule.clone(), rule));
         
         // Add totals row
-        table.add_row(vec![
+        table.add_row(self.report_row(vec![
             "TOTAL".to_string(),
             total_trades.to_string(),
             format!("${:.2}", total_gross_pnl),
             format!("${:.2}", total_commission),
+            format!("${:.2}", total_slippage),
             format!("${:.2}", total_net_pnl),
-            format!("{:.2}%", avg_drawdown),
-            format!("{:.2}", avg_sharpe),
-        ]);
+            avg_drawdown,
+            avg_sharpe,
+            avg_calmar,
+            format!("{}/{}", longest_win, longest_loss),
+        ], format!("${:.2}", total_open_notional), format!("${:.2}", total_unrealized)));
         
-        format!("\n=== P&L Summary by Symbol ===\n{}", table)
+        let mut output = format!("\n=== P&L Summary by Symbol ===\n{}", table);
+        output.push_str(&format!("\nTurnover: ${:.2}, fees / gross P&L: {}",
+                                 self.turnover(trades),
+                                 Self::format_ratio(fee_to_pnl_ratio(total_commission + total_borrow, total_gross_pnl))));
+        output.push_str(&format!("\nFrictionless P&L: ${:.2} = net ${:.2} + slippage ${:.2} + fees ${:.2}",
+                                 total_gross_pnl, total_net_pnl, total_slippage, total_commission + total_borrow));
+        if self.short_borrow_bps_per_day > 0.0 {
+            output.push_str(&format!("\nShort borrow cost ({} bps/day, included in Net P&L): ${:.2}",
+                                     self.short_borrow_bps_per_day, total_borrow));
+        }
+        for notice in notices {
+            output.push_str(&format!("\n{}", notice));
+        }
+        output
     }
     
     /// Generate P&L graphs for each symbol (without aggregation)
@@ -230,6 +751,10 @@ impl PnlReport {
                 timestamps.push(symbol_trades[i-1].time);
             }
             
+            // Gross P&L needed to cover commissions so far
+            let fee_line = self.cumulative_fees(&symbol_trades);
+            let total_fees = fee_line.last().map(|(_, fees)| *fees).unwrap_or(0.0);
+            
             // Create the chart
             let filename = format!("{}/{}{}.png", output_dir, prefix, symbol);
             let root = BitMapBackend::new(&filename, (1024, 768)).into_drawing_area();
@@ -237,7 +762,7 @@ impl PnlReport {
             
             // Find min and max values for the chart
             let min_pnl = cumulative_pnl.iter().cloned().fold(f64::INFINITY, f64::min);
-            let max_pnl = cumulative_pnl.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let max_pnl = cumulative_pnl.iter().cloned().fold(total_fees, f64::max);
             let pnl_range = if (max_pnl - min_pnl).abs() < 1.0 {
                 min_pnl - 100.0..max_pnl + 100.0
             } else {
@@ -247,6 +772,7 @@ impl PnlReport {
             let min_time = *timestamps.first().unwrap_or(&0);
             let max_time = *timestamps.last().unwrap_or(&1);
             
+            let time_labels = self.time_formatter(min_time, max_time);
             let mut chart = ChartBuilder::on(&root)
                 .caption(&format!("P&L Chart for {}", symbol), ("sans-serif", 40).into_font())
                 .margin(10)
@@ -257,11 +783,7 @@ impl PnlReport {
             chart.configure_mesh()
                 .x_desc("Time")
                 .y_desc("P&L ($)")
-                .x_label_formatter(&|x| {
-                    chrono::DateTime::from_timestamp_millis(*x)
-                        .map(|dt| dt.format("%H:%M").to_string())
-                        .unwrap_or_else(|| x.to_string())
-                })
+                .x_label_formatter(&|x| time_labels.format(*x))
                 .draw()?;
             
             // Draw the P&L line
@@ -282,6 +804,16 @@ impl PnlReport {
                 &BLACK.mix(0.3),
             ))?;
             
+            // Fee breakeven: P&L above this line is edge net of commission
+            chart.draw_series(DashedLineSeries::new(
+                fee_line,
+                6,
+                4,
+                RED.mix(0.6).into(),
+            ))?
+            .label("Cumulative fees (breakeven)")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], RED.mix(0.6)));
+            
             // Draw points for each trade
             chart.draw_series(PointSeries::of_element(
                 data.clone(),
@@ -347,6 +879,7 @@ impl PnlReport {
                 global_min_pnl * 1.1..global_max_pnl * 1.1
             };
             
+            let time_labels = self.time_formatter(global_min_time, global_max_time);
             let mut chart = ChartBuilder::on(&root)
                 .caption("Combined P&L Chart - All Symbols", ("sans-serif", 45).into_font())
                 .margin(15)
@@ -357,11 +890,7 @@ impl PnlReport {
             chart.configure_mesh()
                 .x_desc("Time")
                 .y_desc("P&L ($)")
-                .x_label_formatter(&|x| {
-                    chrono::DateTime::from_timestamp_millis(*x)
-                        .map(|dt| dt.format("%H:%M").to_string())
-                        .unwrap_or_else(|| x.to_string())
-                })
+                .x_label_formatter(&|x| time_labels.format(*x))
                 .draw()?;
             
             // Define colors for different symbols
@@ -457,39 +986,10 @@ impl PnlReport {
             // Sort trades by time
             symbol_trades.sort_by_key(|t| t.time);
             
-            // Aggregate trades by time buckets
-            let mut aggregated_data: Vec<(i64, Vec<Trade>)> = Vec::new();
-            
-            for trade in symbol_trades.iter() {
-                let bucket_time = (trade.time / aggregation_ms) * aggregation_ms;
-                
-                if let Some(last) = aggregated_data.last_mut() {
-                    if last.0 == bucket_time {
-                        last.1.push(trade.clone());
-                    } else {
-                        aggregated_data.push((bucket_time, vec![trade.clone()]));
-                    }
-                } else {
-                    aggregated_data.push((bucket_time, vec![trade.clone()]));
-                }
-            }
-            
-            // Calculate cumulative P&L for each time bucket
-            let mut cumulative_pnl = Vec::new();
-            let mut timestamps = Vec::new();
-            let mut all_trades_so_far = Vec::new();
-            
-            for (bucket_time, bucket_trades) in aggregated_data {
-                // Add trades from this bucket to all trades
-                all_trades_so_far.extend(bucket_trades);
-                
-                // Calculate P&L up to this point
-                let result = self.calculate(&all_trades_so_far, method);
-                let current_pnl = result.total_pnl + result.unrealized_pnl;
-                
-                cumulative_pnl.push(current_pnl);
-                timestamps.push(bucket_time);
-            }
+            let (timestamps, cumulative_pnl): (Vec<i64>, Vec<f64>) =
+                Self::bucketed_cumulative_pnl(&symbol_trades, method, aggregation_ms)
+                    .into_iter()
+                    .unzip();
             
             // Create the chart
             let filename = format!("{}/{}{}.png", output_dir, prefix, symbol);
@@ -508,6 +1008,7 @@ impl PnlReport {
             let min_time = *timestamps.first().unwrap_or(&0);
             let max_time = *timestamps.last().unwrap_or(&1);
             
+            let time_labels = self.time_formatter(min_time, max_time);
             let mut chart = ChartBuilder::on(&root)
                 .caption(&format!("P&L Chart for {} ({}ms aggregation)", symbol, aggregation_ms), ("sans-serif", 40).into_font())
                 .margin(10)
@@ -518,11 +1019,7 @@ impl PnlReport {
             chart.configure_mesh()
                 .x_desc("Time")
                 .y_desc("P&L ($)")
-                .x_label_formatter(&|x| {
-                    chrono::DateTime::from_timestamp_millis(*x)
-                        .map(|dt| dt.format("%H:%M").to_string())
-                        .unwrap_or_else(|| x.to_string())
-                })
+                .x_label_formatter(&|x| time_labels.format(*x))
                 .draw()?;
             
             // Draw the P&L line
@@ -608,6 +1105,7 @@ impl PnlReport {
                 global_min_pnl * 1.1..global_max_pnl * 1.1
             };
             
+            let time_labels = self.time_formatter(global_min_time, global_max_time);
             let mut chart = ChartBuilder::on(&root)
                 .caption(&format!("Combined P&L Chart - All Symbols ({}ms aggregation)", aggregation_ms), ("sans-serif", 45).into_font())
                 .margin(15)
@@ -618,11 +1116,7 @@ impl PnlReport {
             chart.configure_mesh()
                 .x_desc("Time")
                 .y_desc("P&L ($)")
-                .x_label_formatter(&|x| {
-                    chrono::DateTime::from_timestamp_millis(*x)
-                        .map(|dt| dt.format("%H:%M").to_string())
-                        .unwrap_or_else(|| x.to_string())
-                })
+                .x_label_formatter(&|x| time_labels.format(*x))
                 .draw()?;
             
             // Define colors for different symbols
@@ -678,6 +1172,33 @@ impl PnlReport {
         Ok(())
     }
     
+    /// Cumulative P&L (realized + unrealized) at the end of each time bucket
+    ///
+    /// Expects trades for a single symbol sorted by time; the sort must be
+    /// stable so same-timestamp trades keep their execution order within a
+    /// bucket. Uses a running accumulator rather than recomputing the full
+    /// history per bucket.
+    pub fn bucketed_cumulative_pnl(
+        trades: &[Trade],
+        method: Method,
+        aggregation_ms: i64,
+    ) -> Vec<(i64, f64)> {
+        let mut accumulator = IncrementalPnl::new(method);
+        let mut buckets: Vec<(i64, f64)> = Vec::new();
+        
+        for trade in trades {
+            let bucket_time = (trade.time / aggregation_ms) * aggregation_ms;
+            accumulator.push(trade);
+            
+            match buckets.last_mut() {
+                Some(last) if last.0 == bucket_time => last.1 = accumulator.total(),
+                _ => buckets.push((bucket_time, accumulator.total())),
+            }
+        }
+        
+        buckets
+    }
+    
     /// Generate P&L graphs with second-level aggregation
     pub fn graph_by_second(
         &self, 
@@ -700,6 +1221,83 @@ impl PnlReport {
         self.graph_with_aggregation(trades, method, output_dir, prefix, 60000)
     }
     
+    /// Generate P&L graphs with a bucket size fitted to the span of the filled trades
+    /// (see `auto_aggregation_ms`), so short and long runs both give a readable chart
+    pub fn graph_auto(
+        &self,
+        trades: &[Trade],
+        method: Method,
+        output_dir: Option<&str>,
+        prefix: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.graph_with_aggregation(trades, method, output_dir, prefix, Self::auto_aggregation_ms(trades))
+    }
+    
+    /// Finest round bucket size that charts the filled trades' time span in at most
+    /// `AUTO_CHART_MAX_POINTS` points (100 to 250 for all but the shortest and longest spans)
+    pub fn auto_aggregation_ms(trades: &[Trade]) -> i64 {
+        let times = trades.iter()
+            .filter(|t| t.status.to_lowercase() == "filled")
+            .map(|t| t.time);
+        let span = match (times.clone().min(), times.max()) {
+            (Some(start), Some(end)) => end - start,
+            _ => 0,
+        };
+        AUTO_BUCKETS_MS.iter()
+            .copied()
+            .find(|bucket| span / bucket < AUTO_CHART_MAX_POINTS)
+            .unwrap_or(AUTO_BUCKETS_MS[AUTO_BUCKETS_MS.len() - 1])
+    }
+    
+    /// Like `graph_by_minute`, but a rendering failure is logged instead of returned
+    ///
+    /// Charts are cosmetic: a missing font or headless backend should not
+    /// discard an otherwise completed backtest. Returns whether the charts
+    /// were written.
+    pub fn try_graph_by_minute(
+        &self,
+        trades: &[Trade],
+        method: Method,
+        output_dir: Option<&str>,
+        prefix: Option<&str>,
+    ) -> bool {
+        Self::try_render(|| self.graph_by_minute(trades, method, output_dir, prefix))
+    }
+    
+    /// Like `graph_with_aggregation`, with `graph_auto`'s bucket size when `aggregation_ms`
+    /// is None, logging a rendering failure instead of returning it
+    pub fn try_graph_with_aggregation(
+        &self,
+        trades: &[Trade],
+        method: Method,
+        output_dir: Option<&str>,
+        prefix: Option<&str>,
+        aggregation_ms: Option<i64>,
+    ) -> bool {
+        let aggregation_ms = aggregation_ms.unwrap_or_else(|| Self::auto_aggregation_ms(trades));
+        Self::try_render(|| self.graph_with_aggregation(trades, method, output_dir, prefix, aggregation_ms))
+    }
+    
+    /// Run a chart rendering, logging its failure; returns whether it succeeded
+    fn try_render(render: impl FnOnce() -> Result<(), Box<dyn std::error::Error>>) -> bool {
+        // Some plotters font backends panic rather than returning an error
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            render().map_err(|e| e.to_string())
+        }));
+
+        match outcome {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                warn!("Chart generation failed, continuing without charts: {}", e);
+                false
+            }
+            Err(_) => {
+                warn!("Chart generation panicked, continuing without charts");
+                false
+            }
+        }
+    }
+    
     /// Generate P&L graphs with default parameters (./data directory, pnl_ prefix)
     pub fn graph_default(&self, trades: &[Trade], method: Method) -> Result<(), Box<dyn std::error::Error>> {
         self.graph(trades, method, None, None)
@@ -759,6 +1357,11 @@ impl PnlReport {
                 continue;
             }
             
+            if let Some(notice) = self.insufficient_data_notice(symbol, &result) {
+                println!("\n{}", notice);
+                continue;
+            }
+            
             // Create data points for the chart (index, pnl)
             // Aggregate data if there are too many points for console display
             let max_console_points = 100;
@@ -779,13 +1382,20 @@ impl PnlReport {
                     .collect()
             };
             
-            // Calculate commission for net P&L
-            let total_volume = symbol_trades.iter()
-                .filter(|t| t.status.to_lowercase() == "filled")
-                .map(|t| t.quantity * t.price)
-                .sum::<f64>();
-            let commission = total_volume * (self.commission_rate / 100.0);
-            let net_pnl = running_pnl - commission;
+            // Fee breakeven at each sampled closed trade, on the same scale as the P&L
+            let fee_line = self.cumulative_fees(symbol_trades);
+            let fee_points: Vec<f32> = data_points.iter()
+                .map(|(i, _)| {
+                    let time = result.pnl_records.get(*i as usize).map(|r| r.timestamp).unwrap_or(i64::MAX);
+                    Self::fees_at(&fee_line, time) as f32
+                })
+                .collect();
+            
+            // Calculate commission and short borrow fees for net P&L
+            let commission = self.commission(symbol_trades);
+            let borrow = self.borrow_cost(symbol_trades);
+            let gross_pnl = self.headline_pnl(&result);
+            let net_pnl = gross_pnl - commission - borrow;
             
             // Calculate Max Drawdown
             let (max_dd_pct, max_dd_value) = self.calculate_max_drawdown(&cumulative_pnl);
@@ -828,9 +1438,10 @@ impl PnlReport {
                 let samples = chart_width.min(data_points.len());
                 let step = data_points.len() / samples;
                 
-                // Find min/max for scaling
-                let min_val = data_points.iter().map(|(_, y)| *y).fold(f32::INFINITY, f32::min);
-                let max_val = data_points.iter().map(|(_, y)| *y).fold(f32::NEG_INFINITY, f32::max);
+                // Find min/max for scaling, keeping the fee line in view
+                let values = data_points.iter().map(|(_, y)| *y).chain(fee_points.iter().copied());
+                let min_val = values.clone().fold(f32::INFINITY, f32::min);
+                let max_val = values.fold(f32::NEG_INFINITY, f32::max);
                 let range = max_val - min_val;
                 
                 // Print the chart with formatted Y-axis
@@ -846,13 +1457,16 @@ impl PnlReport {
                 for row in 0..chart_height {
                     print!("{:width$} │", "", width = max_label_width + 2);
                     let threshold = max_val - (row as f32 * range / chart_height as f32);
+                    let half_band = range / chart_height as f32 / 2.0;
                     
                     for col in 0..samples {
                         let idx = col * step;
                         if idx < data_points.len() {
                             let val = data_points[idx].1;
-                            if val >= threshold - (range / chart_height as f32 / 2.0) {
+                            if val >= threshold - half_band {
                                 print!("█");
+                            } else if (fee_points[idx] - threshold).abs() <= half_band {
+                                print!("╌");
                             } else {
                                 print!(" ");
                             }
@@ -865,13 +1479,11 @@ impl PnlReport {
                 println!("{:>width$} └{}", format!("${}", min_label), "─".repeat(chart_width), width = max_label_width + 2);
                 
                 // X-axis labels with trades and time
-                let spaces = chart_width.saturating_sub(cumulative_pnl.len().to_string().len()) - 1;
-                
-                println!("{:width$} 0{}{}", "", " ".repeat(spaces), cumulative_pnl.len(), width = max_label_width + 2);
-                println!("{:width$} │{}│", "", " ".repeat(chart_width - 2), width = max_label_width + 2);
-                let time_spaces = spaces.saturating_sub(time_duration_minutes.to_string().len() + 6);
-                println!("{:width$}(0 min){}({} min)", "", " ".repeat(time_spaces), time_duration_minutes, width = max_label_width - 1);
+                for line in Self::x_axis_labels(max_label_width + 3, chart_width, cumulative_pnl.len(), time_duration_minutes) {
+                    println!("{}", line);
+                }
                 println!("\n                     Trades / Time");
+                println!("  █ cumulative P&L   ╌ cumulative fees (breakeven)");
             } else {
                 println!("[Insufficient data points for chart]");
             }
@@ -879,8 +1491,14 @@ impl PnlReport {
             println!("\n{}", "-".repeat(80));
             println!("Summary:");
             println!("  Total Trades: {}", filled_trades.len());
-            println!("  Gross P&L: ${:.2}", running_pnl);
+            if self.include_unrealized == IncludeUnrealized::Yes {
+                println!("  Unrealized P&L: ${:.2}", result.unrealized_pnl);
+            }
+            println!("  Gross P&L: ${:.2}", gross_pnl);
             println!("  Commission ({}%): ${:.2}", self.commission_rate, commission);
+            if self.short_borrow_bps_per_day > 0.0 {
+                println!("  Short borrow ({} bps/day): ${:.2}", self.short_borrow_bps_per_day, borrow);
+            }
             println!("  Net P&L: ${:.2}", net_pnl);
             println!("  Max Drawdown: ${:.2} ({:.2}%)", max_dd_value, max_dd_pct);
             println!("{}", "=".repeat(80));
@@ -925,46 +1543,64 @@ impl PnlReport {
             }
         }
         
-        // Calculate Sharpe Ratio (annualized)
-        let sharpe_ratio = if cumulative_pnl.len() > 2 {
-            // Calculate returns between periods
-            let mut returns = Vec::new();
-            for i in 1..cumulative_pnl.len() {
-                if cumulative_pnl[i-1] != 0.0 {
-                    returns.push((cumulative_pnl[i] - cumulative_pnl[i-1]) / cumulative_pnl[i-1].abs());
-                } else if cumulative_pnl[i] != 0.0 {
-                    // Handle case where previous value is 0
-                    returns.push(if cumulative_pnl[i] > 0.0 { 1.0 } else { -1.0 });
-                }
-            }
-            
-            if !returns.is_empty() {
-                // Calculate mean return
-                let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-                
-                // Calculate standard deviation
-                let variance = returns.iter()
-                    .map(|r| (r - mean_return).powi(2))
-                    .sum::<f64>() / returns.len() as f64;
-                let std_dev = variance.sqrt();
-                
-                // Calculate annualized Sharpe ratio
-                // Assuming daily returns and 252 trading days per year
-                if std_dev > 0.0 {
-                    let annualized_return = mean_return * (252.0_f64).sqrt();
-                    let annualized_std = std_dev * (252.0_f64).sqrt();
-                    annualized_return / annualized_std
-                } else {
-                    0.0
-                }
-            } else {
-                0.0
-            }
+        // Sharpe of per-trade returns on entry notional, annualized by the trade frequency
+        let returns: Vec<f64> = result.closed_trades.iter()
+            .filter(|closed| closed.quantity * closed.open_price > 0.0)
+            .map(|closed| closed.pnl / (closed.quantity * closed.open_price))
+            .collect();
+        let span_ms = filled_trades[filled_trades.len() - 1].time - filled_trades[0].time;
+        let sharpe_ratio = annualized_sharpe(&returns, span_ms);
+        
+        (max_drawdown_pct, sharpe_ratio)
+    }
+    
+    /// Calmar (and MAR) ratio: realized P&L annualized over the filled-trade span, divided by
+    /// the max drawdown of cumulative closed-trade P&L
+    pub fn calculate_calmar(&self, trades: &[Trade], result: &PnLResult) -> f64 {
+        let times = trades.iter().filter(|t| t.status.to_lowercase() == "filled").map(|t| t.time);
+        let span_ms = match (times.clone().min(), times.max()) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0,
+        };
+        
+        let cumulative_pnl: Vec<f64> = result.closed_trades.iter()
+            .scan(0.0, |running, closed| {
+                *running += closed.pnl;
+                Some(*running)
+            })
+            .collect();
+        let (_, max_drawdown) = self.calculate_max_drawdown(&cumulative_pnl);
+        
+        calmar_ratio(result.total_pnl, span_ms, max_drawdown)
+    }
+    
+    /// Ratio for display, with "inf" for unbounded values
+    fn format_ratio(value: f64) -> String {
+        if value.is_infinite() {
+            if value > 0.0 { "inf".to_string() } else { "-inf".to_string() }
         } else {
-            0.0
+            format!("{:.2}", value)
+        }
+    }
+    
+    /// X-axis lines under the console chart: trade counts, tick marks and elapsed minutes.
+    ///
+    /// `axis_column` is the column of the chart's `└` corner. Start labels begin there and end
+    /// labels finish at the chart's last column; when a pair doesn't fit, the end label follows
+    /// the start label after a single space instead of overlapping it.
+    pub(crate) fn x_axis_labels(axis_column: usize, chart_width: usize, trade_count: usize, duration_minutes: i64) -> [String; 3] {
+        let span = chart_width + 1;
+        let spread = |left: &str, right: &str| {
+            let used = left.chars().count() + right.chars().count();
+            let gap = span.saturating_sub(used).max(1);
+            format!("{}{}{}{}", " ".repeat(axis_column), left, " ".repeat(gap), right)
         };
         
-        (max_drawdown_pct, sharpe_ratio)
+        [
+            spread("0", &trade_count.to_string()),
+            spread("│", "│"),
+            spread("(0 min)", &format!("({} min)", duration_minutes)),
+        ]
     }
     
     /// Calculate maximum drawdown from cumulative P&L series
@@ -1004,4 +1640,8 @@ impl Processor for PnlReport {
     fn process(&self, trades: &[Trade], method: Method) -> PnLResult {
         self.calculate(trades, method)
     }
+    
+    fn process_with_mark(&self, trades: &[Trade], method: Method, mark_prices: &HashMap<String, f64>) -> PnLResult {
+        self.calculate_marked(trades, method, mark_prices)
+    }
 }
\ No newline at end of file
@@ -1,16 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use log::warn;
 use crate::core::{Trade, ClosedTrade, PnLResult};
-use crate::pnl::models::Record;
+use crate::pnl::models::{Record, PositionMode, QUANTITY_EPSILON, snap_quantity};
+use crate::pnl::unrealized::{calculate_unrealized_pnl, last_trade_prices};
 
 /// FIFO (First-In-First-Out) processor
-pub struct FifoProcessor;
+///
+/// ```
+/// use happytest::Trade;
+/// use happytest::pnl::FifoProcessor;
+///
+/// let trades = vec![
+///     Trade::new(1_000, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 1.0),
+///     Trade::new(2_000, "BTCUSDT".to_string(), "Sell".to_string(), 105.0, 1.0),
+/// ];
+/// let result = FifoProcessor::new().process_realized(&trades);
+/// assert_eq!(result.total_pnl, 5.0);
+/// assert_eq!(result.closed_trades.len(), 1);
+/// ```
+pub struct FifoProcessor {
+    mode: PositionMode,
+}
 
 impl FifoProcessor {
     pub fn new() -> Self {
-        Self
+        Self { mode: PositionMode::default() }
+    }
+    
+    /// Match lots per net symbol position or per hedge-mode leg
+    pub fn with_position_mode(mut self, mode: PositionMode) -> Self {
+        self.mode = mode;
+        self
     }
     
-    /// Process trades and calculate realized P&L using FIFO method
+    /// Process trades and calculate realized P&L using FIFO method, marking open
+    /// lots at the last trade price of their symbol
     ///
     /// # Arguments
     /// * `trades` - List of Trade objects containing trading logs
@@ -18,142 +42,211 @@ impl FifoProcessor {
     /// # Returns
     /// * `PnLResult` - Object containing open_trades, closed_trades, and pnl_records
     pub fn process_realized(&self, trades: &[Trade]) -> PnLResult {
-        // Dictionary to store open trades by asset
-        let mut open_trades: HashMap<String, Vec<Trade>> = HashMap::new();
+        self.process_marked(trades, &HashMap::new())
+    }
+    
+    /// Like `process_realized`, but marks open lots at `mark_prices` (e.g. the last book mid)
+    /// where a symbol has one, falling back to its last trade price
+    pub fn process_marked(&self, trades: &[Trade], mark_prices: &HashMap<String, f64>) -> PnLResult {
+        match_lots(trades, mark_prices, self.mode, MatchOrder::OldestFirst)
+    }
+}
+
+/// Which open lot a closing trade is matched against first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MatchOrder {
+    /// FIFO
+    OldestFirst,
+    /// LIFO
+    NewestFirst,
+}
+
+/// Match closing trades against a book's open lots in `match_order`, marking the lots left
+/// open at `mark_prices` where a symbol has one and at its last trade price otherwise
+pub(crate) fn match_lots(
+    trades: &[Trade],
+    mark_prices: &HashMap<String, f64>,
+    mode: PositionMode,
+    match_order: MatchOrder,
+) -> PnLResult {
+    // Open lots by asset (and leg in hedge mode)
+    let mut open_trades: HashMap<String, OpenLots> = HashMap::new();
+    
+    // Lists to store closed trades and PnL records
+    let mut closed_trades: Vec<ClosedTrade> = Vec::new();
+    let mut pnl_records = Vec::new();
+    
+    // Process each filled trade chronologically
+    for order in trades {
+        let time = order.time;
+        let symbol = order.symbol.clone();
+        let side = order.side.clone();
+        let price = order.price;
+        let quantity = order.quantity;
+        let is_buy = side.eq_ignore_ascii_case("buy");
         
-        // Lists to store closed trades and PnL records
-        let mut closed_trades: Vec<ClosedTrade> = Vec::new();
-        let mut pnl_records = Vec::new();
+        // Create a new trade
+        let trade = Trade {
+            id: order.id.clone(),
+            time,
+            symbol: symbol.clone(),
+            side: side.clone(),
+            price,
+            quantity,
+            status: order.status.clone(),
+            reduce_only: order.reduce_only,
+            liquidity: order.liquidity,
+            fee: order.fee,
+        };
+        let closes_only = mode.closes_only(order);
         
-        // Process each filled trade chronologically
-        for order in trades {
-            let time = order.time;
-            let symbol = order.symbol.clone();
-            let side = order.side.clone();
-            let price = order.price;
-            let quantity = order.quantity;
+        // Initialize the asset's open lots if they don't exist
+        let asset_trades = open_trades.entry(mode.book_key(order)).or_default();
+        
+        if closes_only && asset_trades.is_flat() {
+            warn!("Reduce-only {} {} {} has no open leg to close, ignoring", side, quantity, symbol);
+            continue;
+        }
+        
+        // A flat book or an order in the lots' direction opens or adds to the position
+        if asset_trades.adds(is_buy) {
+            asset_trades.push(trade, is_buy);
+            continue;
+        }
+        
+        // Process matching trades (opposite sides)
+        let mut remaining_quantity = quantity;
+        
+        // Match with existing open trades in the method's order
+        while remaining_quantity > QUANTITY_EPSILON {
+            let Some(open_trade) = asset_trades.next_lot(match_order) else { break };
             
-            // Create a new trade
-            let trade = Trade {
-                id: order.id.clone(),
-                time,
-                symbol: symbol.clone(),
-                side: side.clone(),
-                price,
-                quantity,
-                status: order.status.clone(),
-            };
+            // Calculate the matched quantity
+            let matched_quantity = remaining_quantity.min(open_trade.quantity);
             
-            // Initialize the asset's open trades list if it doesn't exist
-            let asset_trades = open_trades.entry(symbol.clone()).or_insert_with(Vec::new);
+            // Calculate PnL for this match
+            let pnl = if is_buy {
+                // Current trade is buy, open trade is sell
+                (open_trade.price - price) * matched_quantity
+            } else {
+                // Current trade is sell, open trade is buy
+                (price - open_trade.price) * matched_quantity
+            };
             
-            // If there are no open trades for this asset or the side is the same as the first open trade,
-            // add this trade to the open trades list
-            if asset_trades.is_empty() || asset_trades[0].side == side {
-                asset_trades.push(trade);
-                continue;
-            }
+            // Create a closed trade record
+            let closed_trade = ClosedTrade {
+                quantity: matched_quantity,
+                pnl,
+                open_side: open_trade.side.clone(),
+                close_side: side.clone(),
+                open_price: open_trade.price,
+                close_price: price,
+            };
+            closed_trades.push(closed_trade);
             
-            // Process matching trades (opposite sides)
-            let mut remaining_quantity = quantity;
+            // Add to PnL records for visualization
+            pnl_records.push(Record {
+                timestamp: time,
+                symbol: symbol.clone(),
+                profit: pnl,
+            });
             
-            // Match with existing open trades using FIFO
-            while remaining_quantity > 0.0 && !asset_trades.is_empty() {
-                let open_trade = &mut asset_trades[0];
-                
-                // Calculate the matched quantity
-                let matched_quantity = remaining_quantity.min(open_trade.quantity);
-                
-                // Calculate PnL for this match
-                let pnl = if side.to_lowercase() == "buy" {
-                    // Current trade is buy, open trade is sell
-                    (open_trade.price - price) * matched_quantity
-                } else {
-                    // Current trade is sell, open trade is buy
-                    (price - open_trade.price) * matched_quantity
-                };
-                
-                // Create a closed trade record
-                let closed_trade = ClosedTrade {
-                    quantity: matched_quantity,
-                    pnl,
-                    open_side: open_trade.side.clone(),
-                    close_side: side.clone(),
-                    open_price: open_trade.price,
-                    close_price: price,
-                };
-                closed_trades.push(closed_trade);
-                
-                // Add to PnL records for visualization
-                pnl_records.push(Record {
-                    timestamp: time,
-                    symbol: symbol.clone(),
-                    profit: pnl,
-                });
-                
-                // Update remaining quantities
-                remaining_quantity -= matched_quantity;
-                open_trade.quantity -= matched_quantity;
-                
-                // Remove the open trade if it's fully matched
-                if open_trade.quantity == 0.0 {
-                    asset_trades.remove(0);
-                }
-            }
+            // Update remaining quantities
+            remaining_quantity = snap_quantity(remaining_quantity - matched_quantity);
+            open_trade.quantity = snap_quantity(open_trade.quantity - matched_quantity);
             
-            // If there's still remaining quantity, add it as a new open trade
-            if remaining_quantity > 0.0 {
-                let new_trade = Trade {
-                    id: order.id.clone(),
-                    time,
-                    symbol: symbol.clone(),
-                    side: side.clone(),
-                    price,
-                    quantity: remaining_quantity,
-                    status: order.status.clone(),
-                };
-                asset_trades.push(new_trade);
+            // Remove the open trade if it's fully matched
+            if open_trade.quantity < QUANTITY_EPSILON {
+                asset_trades.pop_matched(match_order);
             }
         }
         
-        // Calculate total realized PnL
-        let total_pnl = closed_trades.iter().map(|t| t.pnl).sum();
-        
-        // Remove empty entries from open_trades
-        open_trades.retain(|_, trades| !trades.is_empty());
-        
-        // Calculate remaining shares and unrealized P&L
-        let mut remaining_shares = 0.0;
-        let mut unrealized_pnl = 0.0;
-        
-        // Get the last price per symbol for unrealized P&L calculation
-        let mut last_prices: HashMap<String, f64> = HashMap::new();
-        for trade in trades.iter().rev() {
-            if !last_prices.contains_key(&trade.symbol) {
-                last_prices.insert(trade.symbol.clone(), trade.price);
-            }
+        // If there's still remaining quantity, the position reversed: it opens the other way
+        if remaining_quantity > QUANTITY_EPSILON && closes_only {
+            warn!("Reduce-only {} {} exceeds the open leg by {}, ignoring the excess", side, symbol, remaining_quantity);
+        } else if remaining_quantity > QUANTITY_EPSILON {
+            let new_trade = Trade {
+                id: order.id.clone(),
+                time,
+                symbol: symbol.clone(),
+                side: side.clone(),
+                price,
+                quantity: remaining_quantity,
+                status: order.status.clone(),
+                reduce_only: order.reduce_only,
+                liquidity: order.liquidity,
+                fee: order.fee,
+            };
+            asset_trades.push(new_trade, is_buy);
         }
-        
-        for (symbol, asset_trades) in &open_trades {
-            let last_price = last_prices.get(symbol).copied().unwrap_or(0.0);
-            for trade in asset_trades {
-                if trade.side.to_lowercase() == "buy" {
-                    remaining_shares += trade.quantity;
-                    unrealized_pnl += (last_price - trade.price) * trade.quantity;
-                } else {
-                    remaining_shares -= trade.quantity;
-                    unrealized_pnl += (trade.price - last_price) * trade.quantity;
-                }
-            }
+    }
+    
+    // Calculate total realized PnL
+    let total_pnl = closed_trades.iter().map(|t| t.pnl).sum();
+    
+    // Keep the books with open lots
+    let open_trades: HashMap<String, Vec<Trade>> = open_trades.into_iter()
+        .filter(|(_, lots)| !lots.is_flat())
+        .map(|(key, lots)| (key, lots.lots.into()))
+        .collect();
+    
+    // Calculate remaining shares and unrealized P&L of the open lots
+    let mut marks = last_trade_prices(trades);
+    marks.extend(mark_prices.iter().map(|(symbol, price)| (symbol.clone(), *price)));
+    let (unrealized_pnl, _, remaining_by_asset) = calculate_unrealized_pnl(&open_trades, &marks);
+    let remaining_shares = remaining_by_asset.values().sum();
+    
+    // Create and return PnLResult object
+    PnLResult {
+        total_pnl,
+        unrealized_pnl,
+        closed_trades,
+        total_fees: 0.0,
+        remaining_shares,
+        pnl_records,
+    }
+}
+
+/// Open lots of one book, oldest first, all in the book's net direction
+#[derive(Default)]
+struct OpenLots {
+    /// Some(true) when the lots are long, Some(false) when short, None when flat
+    long: Option<bool>,
+    lots: VecDeque<Trade>,
+}
+
+impl OpenLots {
+    fn is_flat(&self) -> bool {
+        self.lots.is_empty()
+    }
+
+    /// Whether an order on this side opens or adds to the position rather than closing it
+    fn adds(&self, is_buy: bool) -> bool {
+        self.long.is_none() || self.long == Some(is_buy)
+    }
+
+    fn push(&mut self, trade: Trade, is_buy: bool) {
+        debug_assert!(self.adds(is_buy));
+        self.long = Some(is_buy);
+        self.lots.push_back(trade);
+    }
+
+    /// Lot the next closing trade is matched against
+    fn next_lot(&mut self, match_order: MatchOrder) -> Option<&mut Trade> {
+        match match_order {
+            MatchOrder::OldestFirst => self.lots.front_mut(),
+            MatchOrder::NewestFirst => self.lots.back_mut(),
         }
-        
-        // Create and return PnLResult object
-        PnLResult {
-            total_pnl,
-            unrealized_pnl,
-            closed_trades,
-            total_fees: 0.0,
-            remaining_shares,
+    }
+
+    /// Drop the fully matched `next_lot`
+    fn pop_matched(&mut self, match_order: MatchOrder) {
+        match match_order {
+            MatchOrder::OldestFirst => self.lots.pop_front(),
+            MatchOrder::NewestFirst => self.lots.pop_back(),
+        };
+        if self.lots.is_empty() {
+            self.long = None;
         }
     }
 }
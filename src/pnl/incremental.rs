@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+use crate::core::Trade;
+use crate::pnl::models::{Method, QUANTITY_EPSILON, snap_quantity};
+
+/// Running P&L for a single symbol, updated one trade at a time
+///
+/// Produces the same realized and unrealized totals as `PnlReport::calculate`
+/// over the trades pushed so far, without reprocessing the history on every
+/// step. Non-filled trades are ignored.
+pub struct IncrementalPnl {
+    method: Method,
+    realized: f64,
+    last_price: f64,
+    /// Open FIFO/LIFO lots as (side, price, quantity), oldest first
+    lots: VecDeque<(String, f64, f64)>,
+    /// Net position and average entry price for `Method::Position`
+    position: f64,
+    avg_price: f64,
+}
+
+impl IncrementalPnl {
+    pub fn new(method: Method) -> Self {
+        Self {
+            method,
+            realized: 0.0,
+            last_price: 0.0,
+            lots: VecDeque::new(),
+            position: 0.0,
+            avg_price: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, trade: &Trade) {
+        if trade.status.to_lowercase() != "filled" {
+            return;
+        }
+
+        self.last_price = trade.price;
+        match self.method {
+            Method::Fifo | Method::Lifo => self.push_lots(trade),
+            Method::Position => self.push_position(trade),
+        }
+    }
+
+    fn push_lots(&mut self, trade: &Trade) {
+        let same_side = self.lots.front().map(|(side, _, _)| side.eq_ignore_ascii_case(&trade.side)).unwrap_or(true);
+        if same_side {
+            self.lots.push_back((trade.side.clone(), trade.price, trade.quantity));
+            return;
+        }
+
+        let is_buy = trade.side.to_lowercase() == "buy";
+        let mut remaining = trade.quantity;
+        while remaining > QUANTITY_EPSILON {
+            let lot = if self.method == Method::Lifo { self.lots.back_mut() } else { self.lots.front_mut() };
+            let Some(lot) = lot else { break };
+            let matched = remaining.min(lot.2);
+            self.realized += if is_buy {
+                (lot.1 - trade.price) * matched
+            } else {
+                (trade.price - lot.1) * matched
+            };
+            remaining = snap_quantity(remaining - matched);
+            lot.2 = snap_quantity(lot.2 - matched);
+            if lot.2 < QUANTITY_EPSILON {
+                if self.method == Method::Lifo {
+                    self.lots.pop_back();
+                } else {
+                    self.lots.pop_front();
+                }
+            }
+        }
+
+        if remaining > QUANTITY_EPSILON {
+            self.lots.push_back((trade.side.clone(), trade.price, remaining));
+        }
+    }
+
+    fn push_position(&mut self, trade: &Trade) {
+        let signed = if trade.side.to_lowercase() == "buy" { trade.quantity } else { -trade.quantity };
+
+        if self.position.abs() < QUANTITY_EPSILON {
+            self.position = signed;
+            self.avg_price = trade.price;
+        } else if self.position.signum() == signed.signum() {
+            let total_cost = self.avg_price * self.position.abs() + trade.price * trade.quantity;
+            self.position += signed;
+            self.avg_price = total_cost / self.position.abs();
+        } else {
+            let matched = trade.quantity.min(self.position.abs());
+            self.realized += if self.position > 0.0 {
+                (trade.price - self.avg_price) * matched
+            } else {
+                (self.avg_price - trade.price) * matched
+            };
+            self.position = snap_quantity(self.position + signed.signum() * matched);
+
+            let remaining = snap_quantity(trade.quantity - matched);
+            if remaining > QUANTITY_EPSILON {
+                self.position = signed.signum() * remaining;
+                self.avg_price = trade.price;
+            } else if self.position.abs() < QUANTITY_EPSILON {
+                self.avg_price = 0.0;
+            }
+        }
+    }
+
+    /// Realized P&L so far
+    pub fn realized(&self) -> f64 {
+        self.realized
+    }
+
+    /// Open positions marked at the last filled trade price
+    pub fn unrealized(&self) -> f64 {
+        match self.method {
+            Method::Fifo | Method::Lifo => self.lots.iter()
+                .map(|(side, price, quantity)| {
+                    if side.to_lowercase() == "buy" {
+                        (self.last_price - price) * quantity
+                    } else {
+                        (price - self.last_price) * quantity
+                    }
+                })
+                .sum(),
+            Method::Position => (self.last_price - self.avg_price) * self.position,
+        }
+    }
+
+    /// Realized plus unrealized P&L
+    pub fn total(&self) -> f64 {
+        self.realized() + self.unrealized()
+    }
+}
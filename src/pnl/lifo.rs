@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use crate::core::{Trade, PnLResult};
+use crate::pnl::fifo::{match_lots, MatchOrder};
+use crate::pnl::models::PositionMode;
+
+/// LIFO (Last-In-First-Out) processor: closing trades match the most recently opened lots first
+pub struct LifoProcessor {
+    mode: PositionMode,
+}
+
+impl LifoProcessor {
+    pub fn new() -> Self {
+        Self { mode: PositionMode::default() }
+    }
+    
+    /// Match lots per net symbol position or per hedge-mode leg
+    pub fn with_position_mode(mut self, mode: PositionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    
+    /// Process trades and calculate realized P&L using LIFO method, marking open
+    /// lots at the last trade price of their symbol
+    ///
+    /// A close larger than the newest lot spills over to the next-newest.
+    pub fn process_realized(&self, trades: &[Trade]) -> PnLResult {
+        self.process_marked(trades, &HashMap::new())
+    }
+    
+    /// Like `process_realized`, but marks open lots at `mark_prices` where a symbol has one,
+    /// falling back to its last trade price
+    pub fn process_marked(&self, trades: &[Trade], mark_prices: &HashMap<String, f64>) -> PnLResult {
+        match_lots(trades, mark_prices, self.mode, MatchOrder::NewestFirst)
+    }
+}
+
+impl Default for LifoProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
\ No newline at end of file
@@ -1,8 +1,10 @@
 pub mod models;
 pub mod fifo;
+pub mod lifo;
 pub mod position;
 pub mod unrealized;
 pub mod calculator;
+pub mod incremental;
 
 #[cfg(test)]
 mod tests {
@@ -10,8 +12,10 @@ mod tests {
     mod integration;
 }
 
-pub use models::{Method, Record};
-pub use calculator::{PnlReport, Processor};
+pub use models::{Method, Record, HourlyPnl, BootstrapResult, CostAttribution, IncludeUnrealized, PositionMode, QUANTITY_EPSILON, snap_quantity};
+pub use calculator::{PnlReport, Processor, recompute_from_trades};
 pub use fifo::FifoProcessor;
+pub use lifo::LifoProcessor;
 pub use position::PositionProcessor;
-pub use unrealized::calculate_unrealized_pnl;
\ No newline at end of file
+pub use incremental::IncrementalPnl;
+pub use unrealized::{calculate_unrealized_pnl, last_trade_prices};
\ No newline at end of file
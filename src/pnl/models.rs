@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::core::Trade;
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Method {
     Fifo,
+    /// Closes match the most recently opened lots first
+    Lifo,
     Position,
 }
 
@@ -12,6 +16,69 @@ impl Default for Method {
     }
 }
 
+/// Quantities smaller than this are treated as zero
+///
+/// Repeated partial fills of fractional sizes leave float residuals such as 1e-17
+/// that would otherwise keep fully matched lots open.
+pub const QUANTITY_EPSILON: f64 = 1e-8;
+
+/// `quantity` with residuals below `QUANTITY_EPSILON` zeroed out
+pub fn snap_quantity(quantity: f64) -> f64 {
+    if quantity.abs() < QUANTITY_EPSILON {
+        0.0
+    } else {
+        quantity
+    }
+}
+
+/// How opposite-side trades on the same symbol interact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PositionMode {
+    /// One net position per symbol; a buy after a short reduces the short
+    #[default]
+    Netting,
+    /// Separate long and short legs per symbol; only `reduce_only` trades close a leg
+    Hedging,
+}
+
+impl PositionMode {
+    /// Key of the book a trade is matched in: the symbol, plus the leg when hedging
+    pub fn book_key(self, trade: &Trade) -> String {
+        match self {
+            PositionMode::Netting => trade.symbol.clone(),
+            PositionMode::Hedging => {
+                let long = trade.side.eq_ignore_ascii_case("buy") != trade.reduce_only;
+                format!("{}:{}", trade.symbol, if long { "long" } else { "short" })
+            }
+        }
+    }
+
+    /// Whether any quantity left after closing a leg must be dropped instead of opening the other way
+    pub fn closes_only(self, trade: &Trade) -> bool {
+        self == PositionMode::Hedging && trade.reduce_only
+    }
+}
+
+/// Whether headline P&L figures mark open positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IncludeUnrealized {
+    /// Headline P&L is realized plus open positions marked at the last price
+    #[default]
+    Yes,
+    /// Headline P&L is realized only
+    No,
+}
+
+impl IncludeUnrealized {
+    /// Headline P&L for the given realized and unrealized amounts
+    pub fn total(self, realized: f64, unrealized: f64) -> f64 {
+        match self {
+            IncludeUnrealized::Yes => realized + unrealized,
+            IncludeUnrealized::No => realized,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
     pub timestamp: i64,
@@ -19,6 +86,43 @@ pub struct Record {
     pub profit: f64,
 }
 
+/// Realized P&L of the closing fills in one hour-of-day bucket, across all days of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HourlyPnl {
+    /// Hour of day, 0-23, in the report's timezone
+    pub hour: u32,
+    pub pnl: f64,
+    /// Closed trades (P&L records) in the bucket
+    pub trades: usize,
+}
+
+/// Net P&L split into what the trades earned at their quoted prices and what execution cost
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CostAttribution {
+    /// P&L at quoted prices, had there been no slippage or fees
+    pub frictionless_pnl: f64,
+    /// Slippage per fill times its quantity
+    pub slippage_cost: f64,
+    /// Commission plus short borrow fees
+    pub fees: f64,
+    pub net_pnl: f64,
+}
+
+/// Bootstrap distribution summary of total realized P&L
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapResult {
+    /// Total realized P&L of the actual trade sequence
+    pub point_estimate: f64,
+    /// Mean of the resampled totals
+    pub mean: f64,
+    /// 5th percentile of the resampled totals
+    pub p5: f64,
+    /// 95th percentile of the resampled totals
+    pub p95: f64,
+    /// Number of resamples drawn
+    pub iterations: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct PositionInfo {
     pub quantity: f64,
@@ -1,13 +1,36 @@
 use std::collections::HashMap;
+use log::warn;
 use crate::core::{Trade, ClosedTrade, PnLResult};
-use crate::pnl::models::{Record, PositionInfo};
+use crate::pnl::models::{Record, PositionInfo, PositionMode, QUANTITY_EPSILON, snap_quantity};
+use crate::pnl::unrealized::{calculate_position_unrealized_pnl, last_trade_prices};
 
 /// Position-based processor
-pub struct PositionProcessor;
+///
+/// ```
+/// use happytest::Trade;
+/// use happytest::pnl::PositionProcessor;
+///
+/// let trades = vec![
+///     Trade::new(1_000, "BTCUSDT".to_string(), "Sell".to_string(), 105.0, 2.0),
+///     Trade::new(2_000, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 2.0),
+/// ];
+/// let result = PositionProcessor::new().process_position(&trades);
+/// assert_eq!(result.total_pnl, 10.0);
+/// assert_eq!(result.remaining_shares, 0.0);
+/// ```
+pub struct PositionProcessor {
+    mode: PositionMode,
+}
 
 impl PositionProcessor {
     pub fn new() -> Self {
-        Self
+        Self { mode: PositionMode::default() }
+    }
+    
+    /// Track one net position per symbol or separate hedge-mode legs
+    pub fn with_position_mode(mut self, mode: PositionMode) -> Self {
+        self.mode = mode;
+        self
     }
     
     /// Process trades and calculate realized P&L using Position-based model
@@ -21,7 +44,13 @@ impl PositionProcessor {
     /// # Returns
     /// * `PnLResult` - Object containing open_trades, closed_trades, and pnl_records
     pub fn process_position(&self, trades: &[Trade]) -> PnLResult {
-        // Dictionary to store positions by asset
+        self.process_position_marked(trades, &HashMap::new())
+    }
+    
+    /// Like `process_position`, but marks open positions at `mark_prices` (e.g. the last
+    /// book mid) where a symbol has one, falling back to its last trade price
+    pub fn process_position_marked(&self, trades: &[Trade], mark_prices: &HashMap<String, f64>) -> PnLResult {
+        // Dictionary to store positions by asset (and leg in hedge mode)
         let mut positions: HashMap<String, PositionInfo> = HashMap::new();
         
         // Lists to store closed trades and PnL records
@@ -35,9 +64,10 @@ impl PositionProcessor {
             let side = order.side.clone();
             let price = order.price;
             let quantity = order.quantity;
+            let closes_only = self.mode.closes_only(order);
             
             // Initialize position for the asset if it doesn't exist
-            let pos = positions.entry(symbol.clone()).or_insert_with(|| PositionInfo {
+            let pos = positions.entry(self.mode.book_key(order)).or_insert_with(|| PositionInfo {
                 quantity: 0.0,
                 avg_price: 0.0,
                 total_cost: 0.0,
@@ -46,7 +76,10 @@ impl PositionProcessor {
             });
             
             // Determine if this is increasing or reducing position
-            if pos.quantity == 0.0 {
+            if pos.quantity.abs() < QUANTITY_EPSILON && closes_only {
+                warn!("Reduce-only {} {} {} has no open leg to close, ignoring", side, quantity, symbol);
+                
+            } else if pos.quantity.abs() < QUANTITY_EPSILON {
                 // New position
                 pos.quantity = if side.to_lowercase() == "buy" { quantity } else { -quantity };
                 pos.avg_price = price;
@@ -54,8 +87,8 @@ impl PositionProcessor {
                 pos.side = Some(side.clone());
                 pos.trades.push(order.clone());
                 
-            } else if (pos.quantity > 0.0 && side.to_lowercase() == "buy") || 
-                      (pos.quantity < 0.0 && side.to_lowercase() == "sell") {
+            } else if (pos.quantity > QUANTITY_EPSILON && side.to_lowercase() == "buy") || 
+                      (pos.quantity < -QUANTITY_EPSILON && side.to_lowercase() == "sell") {
                 // Increasing position (same direction)
                 if side.to_lowercase() == "buy" {
                     let new_quantity = pos.quantity + quantity;
@@ -75,11 +108,11 @@ impl PositionProcessor {
                 let mut remaining_quantity = quantity;
                 
                 // Calculate P&L
-                if pos.quantity > 0.0 {  // Long position being reduced
+                if pos.quantity > QUANTITY_EPSILON {  // Long position being reduced
                     let matched_quantity = remaining_quantity.min(pos.quantity);
                     let pnl = (price - pos.avg_price) * matched_quantity;
-                    pos.quantity -= matched_quantity;
-                    remaining_quantity -= matched_quantity;
+                    pos.quantity = snap_quantity(pos.quantity - matched_quantity);
+                    remaining_quantity = snap_quantity(remaining_quantity - matched_quantity);
                     
                     // Create closed trade record
                     let closed_trade = ClosedTrade {
@@ -102,8 +135,8 @@ impl PositionProcessor {
                 } else {  // Short position being reduced
                     let matched_quantity = remaining_quantity.min(pos.quantity.abs());
                     let pnl = (pos.avg_price - price) * matched_quantity;
-                    pos.quantity += matched_quantity;
-                    remaining_quantity -= matched_quantity;
+                    pos.quantity = snap_quantity(pos.quantity + matched_quantity);
+                    remaining_quantity = snap_quantity(remaining_quantity - matched_quantity);
                     
                     // Create closed trade record
                     let closed_trade = ClosedTrade {
@@ -125,7 +158,7 @@ impl PositionProcessor {
                 }
                 
                 // Update position cost basis
-                if pos.quantity != 0.0 {
+                if pos.quantity.abs() >= QUANTITY_EPSILON {
                     pos.total_cost = pos.avg_price * pos.quantity.abs();
                 } else {
                     pos.total_cost = 0.0;
@@ -134,7 +167,9 @@ impl PositionProcessor {
                 }
                 
                 // If position reversed (went from long to short or vice versa)
-                if remaining_quantity > 0.0 {
+                if remaining_quantity > QUANTITY_EPSILON && closes_only {
+                    warn!("Reduce-only {} {} exceeds the open leg by {}, ignoring the excess", side, symbol, remaining_quantity);
+                } else if remaining_quantity > QUANTITY_EPSILON {
                     pos.quantity = if side.to_lowercase() == "buy" { remaining_quantity } else { -remaining_quantity };
                     pos.avg_price = price;
                     pos.total_cost = price * remaining_quantity;
@@ -147,6 +182,9 @@ impl PositionProcessor {
                         price,
                         quantity: remaining_quantity,
                         status: order.status.clone(),
+                        reduce_only: order.reduce_only,
+                        liquidity: order.liquidity,
+                        fee: order.fee,
                     }];
                 }
             }
@@ -159,19 +197,17 @@ impl PositionProcessor {
         let mut remaining_shares = 0.0;
         let mut unrealized_pnl = 0.0;
         
-        // Get the last price from trades for unrealized P&L calculation
-        let last_price = trades.last().map(|t| t.price).unwrap_or(0.0);
+        // Mark each symbol at its supplied price, or else its last trade price
+        let mut marks = last_trade_prices(trades);
+        marks.extend(mark_prices.iter().map(|(symbol, price)| (symbol.clone(), *price)));
         
-        for (_, pos) in &positions {
-            if pos.quantity != 0.0 {
+        for pos in positions.values() {
+            if pos.quantity.abs() >= QUANTITY_EPSILON {
                 remaining_shares += pos.quantity; // Positive for long, negative for short
-                if pos.quantity > 0.0 {
-                    // Long position
-                    unrealized_pnl += (last_price - pos.avg_price) * pos.quantity;
-                } else {
-                    // Short position
-                    unrealized_pnl += (pos.avg_price - last_price) * pos.quantity.abs();
-                }
+                let mark = pos.trades.first()
+                    .and_then(|t| marks.get(&t.symbol).copied())
+                    .unwrap_or(pos.avg_price);
+                unrealized_pnl += calculate_position_unrealized_pnl(pos.quantity, pos.avg_price, mark, pos.quantity > 0.0);
             }
         }
         
@@ -182,6 +218,7 @@ impl PositionProcessor {
             closed_trades,
             total_fees: 0.0,
             remaining_shares,
+            pnl_records,
         }
     }
     
@@ -54,6 +54,9 @@ fn load_trades_from_csv(path: &Path) -> Vec<Trade> {
                             price,
                             quantity,
                             status: "filled".to_string(),
+                            reduce_only: false,
+                            liquidity: None,
+                            fee: 0.0,
                         });
                     }
                 }
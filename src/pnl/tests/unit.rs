@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::core::Trade;
-    use crate::pnl::{PnlReport, Method};
+    use crate::core::{Trade, TradeState};
+    use crate::pnl::{PnlReport, Processor, Method, IncludeUnrealized, IncrementalPnl, PositionMode, recompute_from_trades};
+    use crate::utils::TimeFormat;
+    use chrono::FixedOffset;
     use uuid::Uuid;
     
     fn create_test_trade(
@@ -19,6 +21,9 @@ mod tests {
             price,
             quantity,
             status: "filled".to_string(),
+            reduce_only: false,
+            liquidity: None,
+            fee: 0.0,
         }
     }
     
@@ -192,6 +197,7 @@ mod tests {
         
         assert_eq!(result.total_pnl, 0.0);
         assert_eq!(result.closed_trades.len(), 0);
+        assert_eq!(result.remaining_shares, 0.0);
     }
     
     #[test]
@@ -211,5 +217,663 @@ mod tests {
         
         assert_eq!(result.total_pnl, 0.0);
         assert_eq!(result.closed_trades.len(), 0);
+        assert_eq!(result.remaining_shares, 0.0);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_recompute_with_higher_fee() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+        ];
+        
+        let low_fee = recompute_from_trades(&trades, Method::Fifo, 0.03);
+        let high_fee = recompute_from_trades(&trades, Method::Fifo, 0.10);
+        
+        let net = |r: &crate::core::PnLResult| r.total_pnl + r.unrealized_pnl - r.total_fees;
+        
+        // Filled volume is 210, so the extra 0.07% costs 0.147
+        assert_eq!(low_fee.total_pnl, high_fee.total_pnl);
+        assert!((net(&low_fee) - net(&high_fee) - 0.147).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_bootstrap_interval_brackets_point_estimate() {
+        let round_trips = [(100.0, 105.0), (100.0, 97.0), (100.0, 102.0), (100.0, 104.0),
+                           (100.0, 99.0), (100.0, 106.0), (100.0, 98.0), (100.0, 103.0)];
+        let mut trades = Vec::new();
+        for (i, (open, close)) in round_trips.iter().enumerate() {
+            let ts = (i as i64) * 2000;
+            trades.push(create_test_trade("BTCUSDT", "Buy", *open, 1.0, ts));
+            trades.push(create_test_trade("BTCUSDT", "Sell", *close, 1.0, ts + 1000));
+        }
+        
+        let calculator = PnlReport::new();
+        let bootstrap = calculator.bootstrap_pnl(&trades, Method::Fifo, 1000);
+        
+        assert_eq!(bootstrap.point_estimate, 14.0);
+        assert_eq!(bootstrap.iterations, 1000);
+        assert!(bootstrap.p5 < bootstrap.point_estimate);
+        assert!(bootstrap.point_estimate < bootstrap.p95);
+        assert!(bootstrap.p5 <= bootstrap.mean && bootstrap.mean <= bootstrap.p95);
+        
+        // Seeded, so the interval is reproducible
+        let again = calculator.bootstrap_pnl(&trades, Method::Fifo, 1000);
+        assert_eq!(bootstrap.p5, again.p5);
+        assert_eq!(bootstrap.p95, again.p95);
+    }
+    
+    #[test]
+    fn test_chart_failure_is_not_fatal() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 61000),
+        ];
+        
+        // A regular file where the output directory should be makes rendering fail
+        let dir = crate::utils::test_dir::TestDir::new("chart_blocker");
+        let blocker = dir.join("blocker");
+        std::fs::write(&blocker, b"").unwrap();
+        let output_dir = blocker.join("charts");
+        
+        let calculator = PnlReport::new();
+        let report = calculator.report(&trades, Method::Fifo);
+        let written = calculator.try_graph_by_minute(&trades, Method::Fifo, output_dir.to_str(), None);
+        
+        assert!(!written);
+        assert!(!report.is_empty());
+        assert_eq!(calculator.calculate(&trades, Method::Fifo).total_pnl, 10.0);
+    }
+    
+    #[test]
+    fn test_bucketed_pnl_matches_full_recompute() {
+        // Several fills share the 60s bucket starting at 60_000, including a reversal
+        let mut trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 2.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 104.0, 1.0, 75000),
+            create_test_trade("BTCUSDT", "Buy", 101.0, 1.0, 61000),
+            create_test_trade("BTCUSDT", "Sell", 106.0, 4.0, 90000),
+            create_test_trade("BTCUSDT", "Buy", 103.0, 1.0, 130000),
+        ];
+        trades.sort_by_key(|t| t.time);
+        
+        for method in [Method::Fifo, Method::Position] {
+            let buckets = PnlReport::bucketed_cumulative_pnl(&trades, method, 60000);
+            assert_eq!(buckets.iter().map(|(t, _)| *t).collect::<Vec<_>>(), vec![0, 60000, 120000]);
+            
+            let calculator = PnlReport::new();
+            for (bucket_time, cumulative) in &buckets {
+                let upto: Vec<_> = trades.iter()
+                    .filter(|t| t.time < bucket_time + 60000)
+                    .cloned()
+                    .collect();
+                let full = calculator.calculate(&upto, method);
+                assert!((cumulative - (full.total_pnl + full.unrealized_pnl)).abs() < 1e-9,
+                        "{:?} bucket {}: {} vs {}", method, bucket_time, cumulative, full.total_pnl + full.unrealized_pnl);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_include_unrealized_toggle() {
+        // Realized 10 on the first lot; the 104 lot stays open and is marked at 110
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Buy", 104.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 3000),
+        ];
+        
+        let marked = PnlReport::with_commission(0.0);
+        let realized_only = PnlReport::with_commission(0.0).with_include_unrealized(IncludeUnrealized::No);
+        let result = marked.calculate(&trades, Method::Fifo);
+        
+        assert_eq!(marked.headline_pnl(&result), 16.0);
+        assert_eq!(realized_only.headline_pnl(&result), 10.0);
+        
+        let marked_report = marked.report(&trades, Method::Fifo);
+        let realized_report = realized_only.report(&trades, Method::Fifo);
+        assert!(marked_report.contains("$16.00"));
+        assert!(!realized_report.contains("$16.00"));
+        assert!(realized_report.contains("$10.00"));
+    }
+    
+    #[test]
+    fn test_contract_size_scales_pnl() {
+        use crate::trading::{InstrumentSpec, InstrumentSpecRegistry};
+        
+        let trades = vec![
+            create_test_trade("ETHUSD", "Buy", 100.0, 2.0, 1000),
+            create_test_trade("ETHUSD", "Sell", 110.0, 1.0, 2000),
+        ];
+        let instruments = InstrumentSpecRegistry::new()
+            .with_spec("ETHUSD", InstrumentSpec { contract_size: 10.0, ..Default::default() });
+        
+        let spot = PnlReport::with_commission(0.1);
+        let futures = PnlReport::with_commission(0.1).with_instruments(instruments);
+        let spot_result = spot.calculate(&trades, Method::Fifo);
+        let futures_result = futures.calculate(&trades, Method::Fifo);
+        
+        assert_eq!(spot_result.total_pnl, 10.0);
+        assert_eq!(futures_result.total_pnl, 100.0);
+        assert_eq!(futures_result.unrealized_pnl, 10.0 * spot_result.unrealized_pnl);
+        assert!((futures.commission(&trades) - 10.0 * spot.commission(&trades)).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_pnl_records_sum_to_total() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 2.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Sell", 95.0, 3.0, 3000),
+            create_test_trade("BTCUSDT", "Buy", 90.0, 2.0, 4000),
+        ];
+        
+        let calculator = PnlReport::new();
+        for method in [Method::Fifo, Method::Position] {
+            let result = calculator.calculate(&trades, method);
+            let records_total: f64 = result.pnl_records.iter().map(|r| r.profit).sum();
+            
+            assert!(!result.pnl_records.is_empty());
+            assert!((records_total - result.total_pnl).abs() < 1e-9, "{:?}", method);
+            assert!(result.pnl_records.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        }
+    }
+    
+    #[test]
+    fn test_report_flags_insufficient_data() {
+        let trades = vec![create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000)];
+        
+        let report = PnlReport::new().report(&trades, Method::Fifo);
+        
+        assert!(report.contains("Insufficient data for BTCUSDT: 0 closed trade(s), need at least 2"));
+        assert!(report.contains("n/a"));
+        assert!(!report.to_lowercase().contains("nan"));
+        
+        let lenient = PnlReport::new().with_min_closed_trades(0).report(&trades, Method::Fifo);
+        assert!(!lenient.contains("Insufficient data"));
+    }
+    
+    #[test]
+    fn test_calmar_ratio() {
+        let half_year = 365 * 24 * 3_600_000 / 2;
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 0),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Sell", 95.0, 1.0, 3000),
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 4000),
+            create_test_trade("BTCUSDT", "Sell", 115.0, 1.0, half_year),
+        ];
+        
+        let calculator = PnlReport::new();
+        let result = calculator.calculate(&trades, Method::Fifo);
+        
+        // 20 over half a year annualizes to 40; drawdown is 10 -> 5
+        assert!((calculator.calculate_calmar(&trades, &result) - 8.0).abs() < 1e-9);
+        
+        // No drawdown means an unbounded ratio, shown as "inf"
+        let no_drawdown = &trades[..2];
+        let result = calculator.calculate(no_drawdown, Method::Fifo);
+        assert!(calculator.calculate_calmar(no_drawdown, &result).is_infinite());
+        assert!(calculator.report(&[trades[0].clone(), trades[1].clone(), trades[4].clone(), trades[5].clone()], Method::Fifo).contains("inf"));
+    }
+    
+    #[test]
+    fn test_console_x_axis_labels_align() {
+        let axis_column = 9;
+        let chart_width = 60;
+        let chart_end = axis_column + chart_width + 1;
+        
+        for (trades, minutes) in [(2, 0), (37, 5), (1_000_000, 1_440), (usize::MAX, i64::MAX)] {
+            let lines = PnlReport::x_axis_labels(axis_column, chart_width, trades, minutes);
+            
+            for line in &lines {
+                let chars: Vec<char> = line.chars().collect();
+                assert!(chars[..axis_column].iter().all(|c| *c == ' '), "{:?}", line);
+                assert_ne!(chars[axis_column], ' ', "{:?}", line);
+                assert!(chars.len() >= chart_end, "{:?}", line);
+            }
+            assert!(lines[0].starts_with(&format!("{}0 ", " ".repeat(axis_column))));
+            assert!(lines[0].ends_with(&format!(" {}", trades)));
+            assert!(lines[2].ends_with(&format!(" ({} min)", minutes)));
+            
+            // Labels that fit end exactly at the chart edge
+            if lines[2].chars().count() > chart_end {
+                assert!(lines[2].contains(&format!("(0 min) ({} min)", minutes)));
+            } else {
+                assert_eq!(lines[2].chars().count(), chart_end);
+            }
+        }
+        
+        assert_eq!(PnlReport::x_axis_labels(0, 4, 3, 0)[1], "│   │");
+        assert_eq!(PnlReport::x_axis_labels(0, 0, 3, 0)[0], "0 3");
+    }
+
+    #[test]
+    fn test_hedge_mode_keeps_long_and_short_legs_open() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 105.0, 1.0, 2000),
+        ];
+
+        let netting = PnlReport::new().calculate(&trades, Method::Fifo);
+        assert_eq!(netting.closed_trades.len(), 1);
+        assert_eq!(netting.remaining_shares, 0.0);
+
+        for method in [Method::Fifo, Method::Position] {
+            let hedged = PnlReport::new().with_position_mode(PositionMode::Hedging).calculate(&trades, method);
+            assert!(hedged.closed_trades.is_empty());
+            assert_eq!(hedged.total_pnl, 0.0);
+            // Long marked up 5 at the last price, short flat; the legs offset in net shares
+            assert_eq!(hedged.remaining_shares, 0.0);
+            assert!((hedged.unrealized_pnl - 5.0).abs() < 1e-9);
+        }
+
+        let mut closing = trades.clone();
+        closing.push(create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 3000).with_reduce_only(true));
+        let hedged = PnlReport::new().with_position_mode(PositionMode::Hedging).calculate(&closing, Method::Fifo);
+        assert_eq!(hedged.closed_trades.len(), 1);
+        assert!((hedged.total_pnl - 10.0).abs() < 1e-9);
+        assert_eq!(hedged.remaining_shares, -1.0);
+
+        let mut state = TradeState::new();
+        for trade in trades {
+            state.add(trade);
+        }
+        assert_eq!(state.get_hedged_position("BTCUSDT"), (1.0, 1.0));
+        assert_eq!(state.get_position("BTCUSDT"), 0.0);
+    }
+
+    #[test]
+    fn test_cumulative_fee_line_rises_to_total_fees() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Buy", 105.0, 2.0, 3000),
+            create_test_trade("BTCUSDT", "Sell", 108.0, 2.0, 4000),
+        ];
+        let report = PnlReport::with_commission(0.1);
+        let fee_line = report.cumulative_fees(&trades);
+
+        assert_eq!(fee_line.len(), 4);
+        assert!(fee_line.windows(2).all(|w| w[1].0 >= w[0].0 && w[1].1 > w[0].1));
+        let (_, final_fees) = *fee_line.last().unwrap();
+        assert!((final_fees - report.commission(&trades)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fractional_partial_fills_close_lot_exactly() {
+        let mut trades = vec![create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000)];
+        for i in 0..10 {
+            trades.push(create_test_trade("BTCUSDT", "Sell", 101.0, 0.1, 2000 + i));
+        }
+        // 1.0 - 10 * 0.1 leaves a float residual without snapping
+        assert_ne!((0..10).fold(1.0_f64, |q, _| q - 0.1), 0.0);
+
+        for method in [Method::Fifo, Method::Position] {
+            let result = PnlReport::new().calculate(&trades, method);
+            assert_eq!(result.closed_trades.len(), 10);
+            assert_eq!(result.remaining_shares, 0.0);
+            assert_eq!(result.unrealized_pnl, 0.0);
+            assert!((result.total_pnl - 1.0).abs() < 1e-9);
+        }
+
+        // A later buy opens a fresh lot instead of netting against a residual
+        trades.push(create_test_trade("BTCUSDT", "Buy", 102.0, 0.005, 3000));
+        let result = PnlReport::new().calculate(&trades, Method::Fifo);
+        assert_eq!(result.closed_trades.len(), 10);
+        assert_eq!(result.remaining_shares, 0.005);
+    }
+
+    #[test]
+    fn test_chart_axis_shows_dates_for_multi_day_runs() {
+        // Trades on 2024-01-01 and 2024-01-03 (UTC)
+        let (start, end) = (1_704_103_200_000, 1_704_283_200_000);
+        let report = PnlReport::new();
+        let labels = report.time_formatter(start, end);
+        assert_eq!(labels.format(start), "2024-01-01 10:00");
+        assert_eq!(labels.format(end), "2024-01-03 12:00");
+
+        let intraday = report.time_formatter(start, start + 3_600_000);
+        assert_eq!(intraday.format(start + 3_600_000), "11:00");
+
+        let eastern = PnlReport::new().with_time_format(TimeFormat::DateTime, FixedOffset::west_opt(5 * 3600).unwrap());
+        assert_eq!(eastern.time_formatter(start, start).format(start), "2024-01-01 05:00");
+    }
+
+    #[test]
+    fn test_short_held_for_a_day_pays_borrow() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Sell", 100.0, 1.0, 0),
+            create_test_trade("BTCUSDT", "Buy", 90.0, 1.0, 86_400_000),
+        ];
+        let report = PnlReport::with_commission(0.0).with_short_borrow_bps_per_day(10.0);
+
+        // 10 bps of the $100 entry notional for one day
+        assert!((report.borrow_cost(&trades) - 0.1).abs() < 1e-9);
+        assert_eq!(PnlReport::with_commission(0.0).borrow_cost(&trades), 0.0);
+
+        let summary = report.report(&trades, Method::Fifo);
+        assert!(summary.contains("$9.90"));
+        assert!(summary.contains("Short borrow cost (10 bps/day, included in Net P&L): $0.10"));
+
+        // Longs never pay borrow
+        let longs = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 0),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 86_400_000),
+        ];
+        assert_eq!(report.borrow_cost(&longs), 0.0);
+    }
+
+    #[test]
+    fn test_fees_exceeding_gross_pnl_give_ratio_above_one() {
+        // $0.10 gross on $200.1 turnover; 0.1% commission costs about $0.20
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 100.1, 1.0, 2000),
+        ];
+        let report = PnlReport::with_commission(0.1);
+
+        assert!((report.turnover(&trades) - 200.1).abs() < 1e-9);
+        let ratio = report.fee_to_pnl_ratio(&trades, Method::Fifo);
+        assert!((ratio - 0.2001 / 0.1).abs() < 1e-6, "ratio {}", ratio);
+        assert!(ratio > 1.0);
+        assert!(report.report(&trades, Method::Fifo).contains("Turnover: $200.10, fees / gross P&L: 2.00"));
+
+        // A losing run with fees has no edge left at all
+        let losing = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 99.0, 1.0, 2000),
+        ];
+        assert_eq!(report.fee_to_pnl_ratio(&losing, Method::Fifo), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_pnl_by_hour_buckets_close_times_across_days() {
+        const HOUR: i64 = 3_600_000;
+        const DAY: i64 = 24 * HOUR;
+        let trades = vec![
+            // Closed at 02:30 UTC on day one: +$10
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 2 * HOUR),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2 * HOUR + HOUR / 2),
+            // Closed at 02:10 UTC on day two: -$4, same bucket
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, DAY + 2 * HOUR),
+            create_test_trade("BTCUSDT", "Sell", 96.0, 1.0, DAY + 2 * HOUR + HOUR / 6),
+            // Closed at 15:00 UTC: +$5
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, DAY + 14 * HOUR),
+            create_test_trade("BTCUSDT", "Sell", 105.0, 1.0, DAY + 15 * HOUR),
+        ];
+        let report = PnlReport::new();
+
+        let utc = report.pnl_by_hour(&trades, Method::Fifo, FixedOffset::east_opt(0).unwrap());
+        assert_eq!(utc.len(), 24);
+        assert_eq!((utc[2].trades, utc[2].pnl), (2, 6.0));
+        assert_eq!((utc[15].trades, utc[15].pnl), (1, 5.0));
+        assert_eq!(utc.iter().map(|b| b.trades).sum::<usize>(), 3);
+
+        // At UTC-5 the 02:xx closes land at 21:00 and the 15:00 close at 10:00
+        let new_york = report.pnl_by_hour(&trades, Method::Fifo, FixedOffset::west_opt(5 * 3600).unwrap());
+        assert_eq!((new_york[21].trades, new_york[21].pnl), (2, 6.0));
+        assert_eq!((new_york[10].trades, new_york[10].pnl), (1, 5.0));
+    }
+
+    #[test]
+    fn test_open_position_is_marked_at_supplied_price() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 2.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 101.0, 1.0, 2000),
+            create_test_trade("ETHUSDT", "Sell", 50.0, 1.0, 3000),
+        ];
+        let marks = [("BTCUSDT".to_string(), 110.0), ("ETHUSDT".to_string(), 45.0)].into_iter().collect();
+
+        for method in [Method::Fifo, Method::Position] {
+            // Without marks, each symbol's open lots are marked at its own last trade
+            let unmarked = PnlReport::new().calculate(&trades, method);
+            assert!((unmarked.unrealized_pnl - 1.0).abs() < 1e-9, "{:?}", method);
+
+            // 1 BTC long from 100 marked at 110, 1 ETH short from 50 marked at 45
+            let marked = PnlReport::new().with_mark_prices(marks.clone()).calculate(&trades, method);
+            assert!((marked.unrealized_pnl - 15.0).abs() < 1e-9, "{:?}", method);
+            assert_eq!(marked.total_pnl, unmarked.total_pnl);
+            assert_eq!(marked.remaining_shares, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_fifo_lots_follow_net_direction_after_reversal() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 2.0, 2000),
+            create_test_trade("BTCUSDT", "Buy", 105.0, 1.0, 3000),
+        ];
+        let result = PnlReport::new().calculate(&trades, Method::Fifo);
+
+        // The sell closes the long and flips to 1 short; the second buy closes that short
+        let closed: Vec<_> = result.closed_trades.iter()
+            .map(|t| (t.open_side.as_str(), t.close_side.as_str(), t.quantity, t.pnl))
+            .collect();
+        assert_eq!(closed, vec![("Buy", "Sell", 1.0, 10.0), ("Sell", "Buy", 1.0, 5.0)]);
+        assert_eq!(result.remaining_shares, 0.0);
+        assert_eq!(result.unrealized_pnl, 0.0);
+
+        // Side case doesn't decide direction: a second buy adds to the long
+        let adds = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "BUY", 102.0, 1.0, 2000),
+        ];
+        let result = PnlReport::new().calculate(&adds, Method::Fifo);
+        assert!(result.closed_trades.is_empty());
+        assert_eq!(result.remaining_shares, 2.0);
+    }
+
+    #[test]
+    fn test_auto_aggregation_fits_bucket_to_span() {
+        let over = |span_ms: i64| vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1_000),
+            create_test_trade("BTCUSDT", "Sell", 101.0, 1.0, 1_000 + span_ms),
+        ];
+
+        // A 10 second file charts in sub-second buckets instead of a single minute point
+        let short = PnlReport::auto_aggregation_ms(&over(10_000));
+        assert!(short < 60_000);
+        assert!((100..=250).contains(&(10_000 / short)));
+
+        // A day charts in buckets coarser than a minute
+        let long = PnlReport::auto_aggregation_ms(&over(86_400_000));
+        assert!(long > 60_000);
+        assert!((100..=250).contains(&(86_400_000 / long)));
+
+        assert_eq!(PnlReport::auto_aggregation_ms(&[]), 100);
+    }
+
+    #[test]
+    fn test_equity_curve_continues_across_files() {
+        let first_file = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+        ];
+        let second_file = vec![
+            create_test_trade("BTCUSDT", "Buy", 110.0, 1.0, 3000),
+            create_test_trade("BTCUSDT", "Sell", 105.0, 1.0, 4000),
+            create_test_trade("BTCUSDT", "Buy", 105.0, 1.0, 5000),
+            create_test_trade("BTCUSDT", "Sell", 108.0, 1.0, 6000),
+        ];
+        let report = PnlReport::new();
+
+        let mut stitched = report.equity_curve(&first_file, Method::Fifo);
+        let offset = stitched.last().unwrap().1;
+        stitched.extend(report.equity_curve_continued(&second_file, Method::Fifo, offset));
+
+        let values: Vec<f64> = stitched.iter().map(|(_, equity)| *equity).collect();
+        assert_eq!(values, vec![0.0, 10.0, 10.0, 5.0, 5.0, 8.0]);
+        // Same as one curve over both files: no reset at the boundary
+        let all: Vec<Trade> = first_file.into_iter().chain(second_file).collect();
+        assert_eq!(stitched, report.equity_curve(&all, Method::Fifo));
+    }
+
+    #[test]
+    fn test_processor_trait_object_exposes_realized_and_unrealized() {
+        let report = PnlReport::with_commission(0.0);
+        let processor: &dyn Processor = &report;
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 2.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+            create_test_trade("ETHUSDT", "Buy", 50.0, 1.0, 3000),
+        ];
+        let marks = [("BTCUSDT".to_string(), 120.0), ("ETHUSDT".to_string(), 55.0)].into_iter().collect();
+
+        for method in [Method::Fifo, Method::Position] {
+            let result = processor.process_with_mark(&trades, method, &marks);
+            assert!((result.total_pnl - 10.0).abs() < 1e-9);
+            assert!((result.unrealized_pnl - 25.0).abs() < 1e-9);
+
+            let records = processor.pnl_records(&trades, method);
+            assert_eq!(records.len(), 1);
+            assert_eq!((records[0].timestamp, records[0].profit), (2000, 10.0));
+
+            let by_symbol = processor.process_by_symbol(&trades, method);
+            assert_eq!(by_symbol.len(), 2);
+            assert_eq!(by_symbol["BTCUSDT"].total_pnl, 10.0);
+            assert_eq!(by_symbol["ETHUSDT"].remaining_shares, 1.0);
+        }
+    }
+    
+    #[test]
+    fn test_report_surfaces_open_exposure_of_buy_only_symbol() {
+        let trades = vec![
+            create_test_trade("ETHUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("ETHUSDT", "Buy", 110.0, 1.0, 2000),
+        ];
+        
+        let realized_only = PnlReport::with_commission(0.0).with_include_unrealized(IncludeUnrealized::No);
+        let report = realized_only.report(&trades, Method::Fifo);
+        assert!(report.contains("Open Notional") && report.contains("Unrealized P&L"));
+        assert!(report.contains("$0.00"));
+        assert!(report.contains("$220.00") && report.contains("$10.00"));
+        assert!(report.contains("Open inventory for ETHUSDT: 2 left open ($220.00 notional, unrealized $10.00)"));
+        
+        let compact = realized_only.with_open_exposure_columns(false).report(&trades, Method::Fifo);
+        assert!(!compact.contains("Open Notional"));
+        assert!(compact.contains("Open inventory for ETHUSDT"));
+    }
+    
+    #[test]
+    fn test_lifo_simple_profit() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+        ];
+        
+        let result = PnlReport::new().calculate(&trades, Method::Lifo);
+        
+        assert_eq!(result.total_pnl, 10.0);
+        assert_eq!(result.closed_trades.len(), 1);
+    }
+    
+    #[test]
+    fn test_lifo_multiple_buys() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Buy", 110.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Sell", 120.0, 1.0, 3000),
+        ];
+        
+        let result = PnlReport::new().calculate(&trades, Method::Lifo);
+        
+        // The newest lot (110) closes; the 100 lot stays open, marked at 120
+        assert_eq!(result.total_pnl, 10.0);
+        assert_eq!(result.closed_trades[0].open_price, 110.0);
+        assert_eq!(result.remaining_shares, 1.0);
+        assert_eq!(result.unrealized_pnl, 20.0);
+    }
+    
+    #[test]
+    fn test_lifo_partial_fill_spills_to_older_lot() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Buy", 110.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Sell", 120.0, 1.5, 3000),
+        ];
+        
+        let result = PnlReport::new().calculate(&trades, Method::Lifo);
+        
+        // (120-110) * 1 + (120-100) * 0.5
+        assert_eq!(result.total_pnl, 20.0);
+        let opens: Vec<(f64, f64)> = result.closed_trades.iter().map(|t| (t.open_price, t.quantity)).collect();
+        assert_eq!(opens, vec![(110.0, 1.0), (100.0, 0.5)]);
+        assert_eq!(result.remaining_shares, 0.5);
+    }
+    
+    #[test]
+    fn test_lifo_short_positions_are_symmetric() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Sell", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 90.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "Buy", 80.0, 1.5, 3000),
+        ];
+        
+        let result = PnlReport::new().calculate(&trades, Method::Lifo);
+        
+        // (90-80) * 1 + (100-80) * 0.5
+        assert_eq!(result.total_pnl, 20.0);
+        assert_eq!(result.closed_trades[0].open_price, 90.0);
+        
+        let mut incremental = IncrementalPnl::new(Method::Lifo);
+        trades.iter().for_each(|t| incremental.push(t));
+        assert_eq!(incremental.realized(), result.total_pnl);
+        assert_eq!(incremental.unrealized(), result.unrealized_pnl);
+    }
+    
+    #[test]
+    fn test_incremental_matches_sides_case_insensitively() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 1.0, 1000),
+            create_test_trade("BTCUSDT", "buy", 110.0, 1.0, 2000),
+            create_test_trade("BTCUSDT", "SELL", 120.0, 2.0, 3000),
+        ];
+        
+        for method in [Method::Fifo, Method::Lifo] {
+            let mut incremental = IncrementalPnl::new(method);
+            trades.iter().for_each(|t| incremental.push(t));
+            // Both buys are one open side, closed in full by the sell
+            assert_eq!(incremental.realized(), 30.0, "{:?}", method);
+            assert_eq!(incremental.unrealized(), 0.0, "{:?}", method);
+        }
+    }
+    
+    #[test]
+    fn test_remaining_shares_is_net_open_quantity_across_symbols() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 3.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 110.0, 1.0, 2000),
+            create_test_trade("ETHUSDT", "Sell", 50.0, 0.5, 3000),
+        ];
+        
+        for method in [Method::Fifo, Method::Lifo, Method::Position] {
+            let result = PnlReport::new().calculate(&trades, method);
+            // 2 BTC long net of 0.5 ETH short
+            assert_eq!(result.remaining_shares, 1.5, "{:?}", method);
+            
+            let short_only = PnlReport::new().calculate(&trades[2..], method);
+            assert_eq!(short_only.remaining_shares, -0.5, "{:?}", method);
+        }
+    }
+    
+    #[test]
+    fn test_calculate_with_unrealized_marks_open_long() {
+        let trades = vec![
+            create_test_trade("BTCUSDT", "Buy", 100.0, 2.0, 1000),
+            create_test_trade("BTCUSDT", "Sell", 105.0, 1.0, 2000),
+            create_test_trade("ETHUSDT", "Buy", 50.0, 1.0, 3000),
+            create_test_trade("ETHUSDT", "Buy", 52.0, 1.0, 4000),
+        ];
+        let last_prices = std::collections::HashMap::from([("BTCUSDT".to_string(), 120.0)]);
+        
+        for method in [Method::Fifo, Method::Lifo, Method::Position] {
+            let result = PnlReport::new().calculate_with_unrealized(&trades, method, &last_prices);
+            assert_eq!(result.total_pnl, 5.0, "{:?}", method);
+            // BTC: 1 left at 100 marked at 120; ETH has no price, so it is marked at its last fill of 52
+            assert!((result.unrealized_pnl - 22.0).abs() < 1e-9, "{:?}: {}", method, result.unrealized_pnl);
+        }
+    }
+}
@@ -5,61 +5,52 @@ use crate::core::Trade;
 ///
 /// # Arguments
 /// * `open_trades` - Dictionary of open trades by asset
-/// * `filled_orders` - List of Trade objects containing filled orders
+/// * `mark_prices` - Price to mark each symbol's open lots at; lots of a symbol
+///   without a mark are valued at their entry price
 ///
 /// # Returns
 /// * `(unrealized_pnl, unrealized_pnl_by_asset, remaining_shares_by_asset)`
 pub fn calculate_unrealized_pnl(
     open_trades: &HashMap<String, Vec<Trade>>,
-    filled_orders: &[Trade],
+    mark_prices: &HashMap<String, f64>,
 ) -> (f64, HashMap<String, f64>, HashMap<String, f64>) {
     let mut unrealized_pnl = 0.0;
     let mut unrealized_pnl_by_asset = HashMap::new();
     let mut remaining_shares_by_asset = HashMap::new();
     
-    // Get the last price for each asset
-    let mut last_sell_prices = HashMap::new();
-    let mut last_buy_prices = HashMap::new();
-    
-    for order in filled_orders {
-        let symbol = &order.symbol;
-        let price = order.price;
-        let side = &order.side;
-        
-        if side.to_lowercase() == "buy" {
-            last_buy_prices.insert(symbol.clone(), price);
-        } else {
-            last_sell_prices.insert(symbol.clone(), price);
-        }
-    }
-    
     // Calculate unrealized PnL and count remaining shares
-    for (symbol, trades) in open_trades {
+    for (asset, trades) in open_trades {
         let mut asset_unrealized_pnl = 0.0;
-        let last_buy_price_by_symbol = last_buy_prices.get(symbol).copied().unwrap_or(0.0);
-        let last_sell_price_by_symbol = last_sell_prices.get(symbol).copied().unwrap_or(0.0);
         let mut remaining_shares = 0.0;
         
         for trade in trades {
-            if trade.side.to_uppercase() == "BUY" {
+            let mark = mark_prices.get(&trade.symbol).copied().unwrap_or(trade.price);
+            if trade.side.eq_ignore_ascii_case("buy") {
                 // For buy positions, unrealized PnL is current value - cost
-                asset_unrealized_pnl += (last_sell_price_by_symbol - trade.price) * trade.quantity;
+                asset_unrealized_pnl += (mark - trade.price) * trade.quantity;
                 remaining_shares += trade.quantity;
             } else {
                 // For sell positions, unrealized PnL is proceeds - current value
-                asset_unrealized_pnl += (trade.price - last_buy_price_by_symbol) * trade.quantity;
+                asset_unrealized_pnl += (trade.price - mark) * trade.quantity;
                 remaining_shares -= trade.quantity;  // Negative for short positions
             }
         }
         
         unrealized_pnl += asset_unrealized_pnl;
-        unrealized_pnl_by_asset.insert(symbol.clone(), asset_unrealized_pnl);
-        remaining_shares_by_asset.insert(symbol.clone(), remaining_shares);
+        unrealized_pnl_by_asset.insert(asset.clone(), asset_unrealized_pnl);
+        remaining_shares_by_asset.insert(asset.clone(), remaining_shares);
     }
     
     (unrealized_pnl, unrealized_pnl_by_asset, remaining_shares_by_asset)
 }
 
+/// Price of the last trade per symbol, the default mark for open lots
+pub fn last_trade_prices(trades: &[Trade]) -> HashMap<String, f64> {
+    trades.iter()
+        .map(|trade| (trade.symbol.clone(), trade.price))
+        .collect()
+}
+
 /// Calculate unrealized P&L for a single position
 pub fn calculate_position_unrealized_pnl(
     position_quantity: f64,
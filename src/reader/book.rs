@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::models::OrderbookData;
+
+/// One side of a book: `(price, [price, size])` levels, best first
+#[derive(Debug, Default)]
+struct BookSide {
+    levels: Vec<(f64, [String; 2])>,
+    /// Bids sort by descending price, asks by ascending
+    descending: bool,
+}
+
+impl BookSide {
+    fn new(descending: bool) -> Self {
+        Self { levels: Vec::new(), descending }
+    }
+
+    /// Set a level's size, removing the level when the size is zero
+    fn apply(&mut self, level: &[String; 2]) {
+        let (Ok(price), Ok(size)) = (level[0].parse::<f64>(), level[1].parse::<f64>()) else {
+            return;
+        };
+        let position = self.levels.binary_search_by(|(p, _)| {
+            let order = p.partial_cmp(&price).unwrap_or(Ordering::Equal);
+            if self.descending { order.reverse() } else { order }
+        });
+        match (position, size > 0.0) {
+            (Ok(i), true) => self.levels[i].1 = level.clone(),
+            (Ok(i), false) => {
+                self.levels.remove(i);
+            }
+            (Err(i), true) => self.levels.insert(i, (price, level.clone())),
+            (Err(_), false) => {}
+        }
+    }
+
+    /// Replace every level with `levels`
+    fn reset(&mut self, levels: &[[String; 2]]) {
+        self.levels.clear();
+        for level in levels {
+            self.apply(level);
+        }
+    }
+
+    fn top(&self, depth: usize) -> Vec<[String; 2]> {
+        self.levels.iter().take(depth).map(|(_, level)| level.clone()).collect()
+    }
+}
+
+#[derive(Debug)]
+struct SymbolBook {
+    bids: BookSide,
+    asks: BookSide,
+    /// Update id of the last snapshot or delta applied
+    update_id: i64,
+}
+
+/// Full order books rebuilt from an exchange's snapshot and delta messages, one per symbol.
+///
+/// A snapshot (or a message of unknown type) replaces the symbol's book; a delta updates the
+/// levels it lists, a size of "0" removing the level. Deltas whose update id is not past the
+/// book's last one are stale and skipped.
+#[derive(Debug)]
+pub struct OrderbookState {
+    books: HashMap<String, SymbolBook>,
+    depth: usize,
+}
+
+impl OrderbookState {
+    /// Books reporting the top `depth` levels per side
+    pub fn new(depth: usize) -> Self {
+        Self { books: HashMap::new(), depth }
+    }
+
+    /// Forget every book, so each symbol waits for a fresh snapshot (e.g. after a reconnect)
+    pub fn clear(&mut self) {
+        self.books.clear();
+    }
+
+    /// Apply `update` and return the symbol's full book as a "snapshot" record with the
+    /// update's timestamps. None for a delta on a symbol that has no snapshot yet, or one
+    /// already covered by the book.
+    pub fn apply(&mut self, update: &OrderbookData) -> Option<OrderbookData> {
+        let book = if update.msg_type.as_deref() == Some("delta") {
+            let book = self.books.get_mut(&update.symbol)?;
+            if update.update_id > 0 && update.update_id <= book.update_id {
+                return None;
+            }
+            book.update_id = update.update_id;
+            for level in &update.bids {
+                book.bids.apply(level);
+            }
+            for level in &update.asks {
+                book.asks.apply(level);
+            }
+            book
+        } else {
+            let book = self.books.entry(update.symbol.clone()).or_insert_with(|| SymbolBook {
+                bids: BookSide::new(true),
+                asks: BookSide::new(false),
+                update_id: 0,
+            });
+            book.update_id = update.update_id;
+            book.bids.reset(&update.bids);
+            book.asks.reset(&update.asks);
+            book
+        };
+
+        Some(OrderbookData {
+            symbol: update.symbol.clone(),
+            bids: book.bids.top(self.depth),
+            asks: book.asks.top(self.depth),
+            timestamp: update.timestamp,
+            update_id: update.update_id,
+            fetch_time: update.fetch_time,
+            msg_type: Some("snapshot".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[(&str, &str)]) -> Vec<[String; 2]> {
+        levels.iter().map(|(price, size)| [price.to_string(), size.to_string()]).collect()
+    }
+
+    fn update(msg_type: &str, update_id: i64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> OrderbookData {
+        OrderbookData {
+            symbol: "ETHUSDT".to_string(),
+            bids: levels(bids),
+            asks: levels(asks),
+            timestamp: update_id * 100,
+            update_id,
+            fetch_time: update_id * 100,
+            msg_type: Some(msg_type.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_then_deltas_rebuild_full_book() {
+        let mut state = OrderbookState::new(3);
+
+        let snapshot = update("snapshot", 1,
+            &[("100.0", "1"), ("99.5", "2"), ("99.0", "3")],
+            &[("100.5", "1"), ("101.0", "2")]);
+        let book = state.apply(&snapshot).unwrap();
+        assert_eq!(book.bids, snapshot.bids);
+        assert_eq!(book.msg_type.as_deref(), Some("snapshot"));
+
+        // Resize the best bid, remove 99.5, add a bid between levels and a new best ask
+        let book = state.apply(&update("delta", 2,
+            &[("100.0", "4"), ("99.5", "0"), ("99.8", "1")],
+            &[("100.4", "5")])).unwrap();
+        assert_eq!(book.bids, levels(&[("100.0", "4"), ("99.8", "1"), ("99.0", "3")]));
+        assert_eq!(book.asks, levels(&[("100.4", "5"), ("100.5", "1"), ("101.0", "2")]));
+        assert_eq!((book.update_id, book.timestamp), (2, 200));
+
+        // A new best bid pushes 99.0 out of the top 3; removing an unknown level is a no-op
+        let book = state.apply(&update("delta", 3,
+            &[("100.2", "1")],
+            &[("100.4", "0"), ("102.0", "0")])).unwrap();
+        assert_eq!(book.bids, levels(&[("100.2", "1"), ("100.0", "4"), ("99.8", "1")]));
+        assert_eq!(book.asks, levels(&[("100.5", "1"), ("101.0", "2")]));
+    }
+
+    #[test]
+    fn test_delta_before_snapshot_is_skipped() {
+        let mut state = OrderbookState::new(50);
+        assert!(state.apply(&update("delta", 1, &[("100.0", "1")], &[])).is_none());
+
+        // A later snapshot replaces the book outright
+        state.apply(&update("snapshot", 2, &[("100.0", "1")], &[("100.5", "1")])).unwrap();
+        let book = state.apply(&update("snapshot", 3, &[("99.0", "2")], &[])).unwrap();
+        assert_eq!(book.bids, levels(&[("99.0", "2")]));
+        assert!(book.asks.is_empty());
+    }
+
+    #[test]
+    fn test_stale_deltas_and_cleared_books_are_skipped() {
+        let mut state = OrderbookState::new(50);
+        state.apply(&update("snapshot", 5, &[("100.0", "1")], &[("100.5", "1")])).unwrap();
+
+        // Deltas up to the snapshot's update id are already in it
+        assert!(state.apply(&update("delta", 4, &[("100.0", "0")], &[])).is_none());
+        assert!(state.apply(&update("delta", 5, &[("100.0", "0")], &[])).is_none());
+        let book = state.apply(&update("delta", 6, &[("99.5", "2")], &[])).unwrap();
+        assert_eq!(book.bids, levels(&[("100.0", "1"), ("99.5", "2")]));
+
+        // After a reconnect the old book is gone until the next snapshot
+        state.clear();
+        assert!(state.apply(&update("delta", 7, &[("99.0", "1")], &[])).is_none());
+        let book = state.apply(&update("snapshot", 3, &[("98.0", "1")], &[])).unwrap();
+        assert_eq!(book.bids, levels(&[("98.0", "1")]));
+    }
+}
@@ -0,0 +1,1041 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use log::{debug, error, info, warn};
+use std::fs::create_dir_all;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::interval;
+use tokio_tungstenite::{connect_async, tungstenite::{self, Message}};
+use tokio_util::sync::CancellationToken;
+
+// Import models, storage and connectors
+use super::book::OrderbookState;
+use super::connector::{BybitConnector, ConnectorEvent, ExchangeConnector};
+use super::models::OrderbookData;
+use super::storage::{JsonlWriter, ParquetWriter, StorageWriter, WriterConfig};
+
+/// When the reader closes its output files and starts new ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RolloverPolicy {
+    /// One file set for the whole run
+    #[default]
+    Never,
+    /// New files at each UTC hour boundary
+    Hourly,
+    /// New files at each UTC day boundary
+    Daily,
+    /// New files once this many records have been written
+    MaxRecords(usize),
+}
+
+impl RolloverPolicy {
+    /// Whether files opened at `opened_at_ms` holding `records_written` records should roll at `now_ms`
+    pub fn should_roll(&self, opened_at_ms: i64, now_ms: i64, records_written: usize) -> bool {
+        const HOUR_MS: i64 = 3_600_000;
+        const DAY_MS: i64 = 24 * HOUR_MS;
+
+        match *self {
+            RolloverPolicy::Never => false,
+            RolloverPolicy::Hourly => now_ms.div_euclid(HOUR_MS) != opened_at_ms.div_euclid(HOUR_MS),
+            RolloverPolicy::Daily => now_ms.div_euclid(DAY_MS) != opened_at_ms.div_euclid(DAY_MS),
+            RolloverPolicy::MaxRecords(max_records) => max_records > 0 && records_written >= max_records,
+        }
+    }
+}
+
+/// Per-writer batches of records, so each file gets only the records routed to it, in order
+#[derive(Default)]
+struct WriterBuffers {
+    buffers: Vec<Vec<OrderbookData>>,
+    /// Records received since the last flush, counted once however many writers take them
+    records: usize,
+}
+
+/// Bookkeeping for the currently open file set
+#[derive(Debug, Default)]
+struct RolloverState {
+    opened_at_ms: i64,
+    records_written: usize,
+    part: usize,
+}
+
+/// Longest wait between reconnection attempts
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Wait before reconnecting after `failures` consecutive sessions without data:
+/// 500ms doubling per failure, capped at `MAX_RECONNECT_BACKOFF`
+fn reconnect_backoff(failures: u32) -> Duration {
+    Duration::from_millis(500)
+        .saturating_mul(1 << failures.min(16))
+        .min(MAX_RECONNECT_BACKOFF)
+}
+
+/// Why a WebSocket session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionEnd {
+    /// Duration reached or cancellation requested: the capture is over
+    Stopped,
+    /// The connection failed, dropped or was closed by the server
+    Disconnected,
+}
+
+/// Counts across every session of one capture
+#[derive(Debug, Default)]
+struct CaptureStats {
+    messages: u64,
+    errors: u64,
+    reconnects: u64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// Configuration for the order book reader
+#[derive(Debug, Clone)]
+pub struct ReaderConfig {
+    /// Symbol to fetch data for (e.g., "BTCUSDT", "ETHUSDT")
+    pub symbol: String,
+    /// Interval in seconds between flushes
+    pub interval_seconds: u64,
+    /// Output directory for data files
+    pub output_dir: String,
+    /// Use testnet API
+    pub testnet: bool,
+    /// Orderbook depth to fetch
+    pub depth: u32,
+    /// Duration to run in seconds (0 for infinite)
+    pub duration_seconds: u64,
+    /// Save as Parquet in addition to JSONL
+    pub save_parquet: bool,
+    /// Save as JSONL format
+    pub save_jsonl: bool,
+    /// When to start new output files
+    pub rollover: RolloverPolicy,
+}
+
+impl Default for ReaderConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "ETHUSDT".to_string(),
+            output_dir: "./data".to_string(),
+            testnet: false,
+            depth: 50,
+            duration_seconds: 3600, // 1 hour by default
+            save_parquet: true,     // Enable Parquet by default
+            save_jsonl: true,       // Enable JSONL by default
+            interval_seconds: 10, // Flush every 10 seconds by default
+            rollover: RolloverPolicy::Never,
+        }
+    }
+}
+
+/// Order book capture over WebSocket: `connector` speaks the exchange's protocol, the reader
+/// buffers the parsed books and writes them to the configured files
+pub struct OrderbookReader<C: ExchangeConnector = BybitConnector> {
+    config: ReaderConfig,
+    connector: C,
+    writers: Arc<Mutex<Vec<Box<dyn StorageWriter>>>>,
+    start_time: SystemTime,
+    /// Records awaiting the next flush, one buffer per writer in `writers` order
+    data_buffers: Arc<Mutex<WriterBuffers>>,
+    rollover_state: Arc<Mutex<RolloverState>>,
+    /// Full books rebuilt from the stream's snapshots and deltas
+    book_state: Arc<Mutex<OrderbookState>>,
+}
+
+/// Reader of Bybit's public linear order book stream
+pub type BybitReader = OrderbookReader<BybitConnector>;
+
+impl BybitReader {
+    /// Create a new Bybit reader with the given configuration
+    pub fn new(config: ReaderConfig) -> Result<Self> {
+        Self::with_connector(config, BybitConnector)
+    }
+}
+
+impl<C: ExchangeConnector> OrderbookReader<C> {
+    /// Create a reader capturing from the exchange `connector` speaks to
+    pub fn with_connector(mut config: ReaderConfig, connector: C) -> Result<Self> {
+        // Never record nothing: fall back to JSONL when both formats are off
+        if !config.save_jsonl && !config.save_parquet {
+            warn!("Neither JSONL nor Parquet output is enabled, saving JSONL");
+            config.save_jsonl = true;
+        }
+
+        // Create output directory if it doesn't exist
+        create_dir_all(&config.output_dir).context("Failed to create output directory")?;
+
+        Ok(Self {
+            connector,
+            writers: Arc::new(Mutex::new(Vec::new())),
+            start_time: SystemTime::now(),
+            data_buffers: Arc::new(Mutex::new(WriterBuffers::default())),
+            rollover_state: Arc::new(Mutex::new(RolloverState::default())),
+            book_state: Arc::new(Mutex::new(OrderbookState::new(config.depth as usize))),
+            config,
+        })
+    }
+
+    /// Generate base filename for output files
+    ///
+    /// Fields are joined with `_`, symbol first, so `extract_symbol_from_filename` recovers it;
+    /// the time has no colons so captures are portable.
+    fn generate_base_filename(&self) -> String {
+        let now = Local::now();
+        let date_str = now.format("%Y%m%d_%H%M%S").to_string();
+        let duration_str = if self.config.duration_seconds > 0 {
+            format!("{}s", self.config.duration_seconds)
+        } else {
+            "continuous".to_string()
+        };
+
+        let base = format!(
+            "{}/{}_{}_{}_{}",
+            self.config.output_dir,
+            filename_component(&self.config.symbol),
+            date_str,
+            duration_str,
+            if self.config.testnet {
+                "testnet"
+            } else {
+                "mainnet"
+            }
+        );
+
+        // Rolled files can share a second, so number them
+        if self.config.rollover == RolloverPolicy::Never {
+            base
+        } else {
+            format!("{}_part{:03}", base, self.rollover_state.lock().unwrap().part)
+        }
+    }
+
+    /// Initialize storage writers
+    fn init_writers(&self) -> Result<Vec<Box<dyn StorageWriter>>> {
+        let base_filename = self.generate_base_filename();
+        {
+            let mut state = self.rollover_state.lock().unwrap();
+            state.opened_at_ms = now_ms();
+            state.records_written = 0;
+        }
+        let writer_config = WriterConfig {
+            base_filename: base_filename.clone(),
+            ..Default::default()
+        };
+
+        let mut writers: Vec<Box<dyn StorageWriter>> = Vec::new();
+
+        // Add JSONL writer if enabled
+        if self.config.save_jsonl {
+            let mut jsonl_writer = Box::new(JsonlWriter::new());
+            jsonl_writer.init(writer_config.clone())?;
+            writers.push(jsonl_writer);
+        }
+
+        // Add Parquet writer if enabled
+        if self.config.save_parquet {
+            let mut parquet_writer = Box::new(ParquetWriter::new());
+            parquet_writer.init(writer_config)?;
+            writers.push(parquet_writer);
+        }
+
+        Ok(writers)
+    }
+
+    /// Buffer data for every storage writer it is routed to
+    fn write_data(&self, data: &OrderbookData) -> Result<()> {
+        // Add data to the writers' buffers instead of writing immediately
+        let writers_guard = self.writers.lock().unwrap();
+        let mut buffers_guard = self.data_buffers.lock().unwrap();
+        buffers_guard.buffers.resize_with(writers_guard.len(), Vec::new);
+        for (writer, buffer) in writers_guard.iter().zip(buffers_guard.buffers.iter_mut()) {
+            if writer.accepts(data) {
+                buffer.push(data.clone());
+            }
+        }
+        buffers_guard.records += 1;
+
+        Ok(())
+    }
+    
+    /// Apply a snapshot or delta to the symbol's book and buffer the resulting full book.
+    /// Deltas arriving before the symbol's first snapshot, or already covered by it, are dropped.
+    fn write_update(&self, update: &OrderbookData) -> Result<()> {
+        let book = self.book_state.lock().unwrap().apply(update);
+        match book {
+            Some(book) => self.write_data(&book),
+            None => {
+                debug!("Dropping {} delta {} received before or covered by a snapshot", update.symbol, update.update_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Flush each writer's buffered data to it
+    fn flush_data(&self) -> Result<()> {
+        let (buffers, batch_size) = {
+            let mut buffers_guard = self.data_buffers.lock().unwrap();
+            (std::mem::take(&mut buffers_guard.buffers), std::mem::take(&mut buffers_guard.records))
+        };
+        
+        if batch_size > 0 {
+            // Rolled writers are created in the same order, so buffers still line up
+            self.roll_writers_if_due()?;
+            
+            let mut writers_guard = self.writers.lock().unwrap();
+            
+            for (writer, buffer) in writers_guard.iter_mut().zip(&buffers) {
+                if buffer.is_empty() {
+                    continue;
+                }
+                if let Err(e) = writer.write_batch(buffer) {
+                    error!("Failed to write batch to {}: {}", writer.file_extension(), e);
+                }
+            }
+            
+            self.rollover_state.lock().unwrap().records_written += batch_size;
+            
+            debug!("Flushed batch of {} records to storage", batch_size);
+        }
+        
+        Ok(())
+    }
+
+    /// Close the current files and open the next set if the rollover policy says so.
+    /// Checked before each batch so a finished run never leaves an empty trailing file.
+    fn roll_writers_if_due(&self) -> Result<()> {
+        let due = {
+            let state = self.rollover_state.lock().unwrap();
+            self.config.rollover.should_roll(state.opened_at_ms, now_ms(), state.records_written)
+        };
+        if !due {
+            return Ok(());
+        }
+        
+        self.close_writers()?;
+        let part = {
+            let mut state = self.rollover_state.lock().unwrap();
+            state.part += 1;
+            state.part
+        };
+        let writers = self.init_writers()?;
+        *self.writers.lock().unwrap() = writers;
+        info!("Rolled over to output part {}", part);
+        
+        Ok(())
+    }
+
+    /// Close all storage writers
+    fn close_writers(&self) -> Result<()> {
+        let mut writers_guard = self.writers.lock().unwrap();
+
+        for writer in writers_guard.iter_mut() {
+            if let Err(e) = writer.close() {
+                error!("Failed to close {}: {}", writer.file_extension(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run the WebSocket reader
+    pub async fn run(&self) -> Result<()> {
+        self.run_with_cancellation(CancellationToken::new()).await
+    }
+
+    /// Run the reader with cancellation support
+    pub async fn run_with_cancellation(&self, cancel_token: CancellationToken) -> Result<()> {
+        info!("Starting {} WebSocket reader for symbol: {}", self.connector.name(), self.config.symbol);
+        info!("Flush interval: {} seconds", self.config.interval_seconds);
+        info!(
+            "Duration: {} seconds",
+            if self.config.duration_seconds > 0 {
+                self.config.duration_seconds.to_string()
+            } else {
+                "infinite".to_string()
+            }
+        );
+        info!(
+            "Parquet output: {}",
+            if self.config.save_parquet {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        info!(
+            "JSONL output: {}",
+            if self.config.save_jsonl {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+
+        // Initialize storage writers
+        {
+            let mut writers_guard = self.writers.lock().unwrap();
+            *writers_guard = self.init_writers()?;
+        }
+
+        let ws_url = self.connector.ws_url(self.config.testnet);
+        let ws_url = ws_url.as_str();
+        self.capture(&cancel_token, move || async move {
+            info!("Connecting to WebSocket: {}", ws_url);
+            let (ws_stream, _response) = connect_async(ws_url)
+                .await
+                .context("Failed to connect to WebSocket")?;
+            info!("WebSocket connected successfully");
+            Ok(ws_stream.split())
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Whether the capture is over: cancellation was requested or the overall duration,
+    /// counted from the reader's start rather than the current connection, is reached
+    fn should_stop(&self, cancel_token: &CancellationToken) -> bool {
+        if cancel_token.is_cancelled() {
+            info!("Cancellation requested, stopping reader");
+            return true;
+        }
+        if self.config.duration_seconds > 0 {
+            let elapsed = self.start_time.elapsed().unwrap().as_secs();
+            if elapsed >= self.config.duration_seconds {
+                info!("Duration reached, stopping reader");
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Run sessions over the connections `connect` opens until the duration is reached or
+    /// `cancel_token` fires, reconnecting with exponential backoff whenever a connection fails
+    /// or drops. Buffered records are kept across reconnects; the files are flushed and closed
+    /// once, at the end, including when a session fails with an error.
+    async fn capture<F, Fut, K, S>(&self, cancel_token: &CancellationToken, mut connect: F) -> Result<CaptureStats>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(K, S)>>,
+        K: Sink<Message> + Unpin,
+        K::Error: std::fmt::Display,
+        S: Stream<Item = std::result::Result<Message, tungstenite::Error>> + Unpin,
+    {
+        let mut stats = CaptureStats::default();
+        let mut failures = 0u32;
+        let mut session_error = None;
+
+        while !self.should_stop(cancel_token) {
+            let received = stats.messages;
+            let end = match connect().await {
+                Ok((mut ws_sender, mut ws_receiver)) => {
+                    // Books from the previous connection miss whatever changed while it was down
+                    self.book_state.lock().unwrap().clear();
+                    self.seed_rest_snapshot().await;
+                    match self.session(&mut ws_sender, &mut ws_receiver, cancel_token, &mut stats).await {
+                        Ok(end) => end,
+                        Err(e) => {
+                            session_error = Some(e);
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("{:#}", e);
+                    SessionEnd::Disconnected
+                }
+            };
+            if end == SessionEnd::Stopped || self.should_stop(cancel_token) {
+                break;
+            }
+
+            // A session that delivered data resets the backoff
+            if stats.messages > received {
+                failures = 0;
+            }
+            let delay = reconnect_backoff(failures);
+            failures = failures.saturating_add(1);
+            stats.reconnects += 1;
+            warn!("Connection lost, reconnecting in {:?} (reconnect #{})", delay, stats.reconnects);
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancel_token.cancelled() => {}
+            }
+        }
+
+        // Flush any remaining buffered data
+        if let Err(e) = self.flush_data() {
+            error!("Failed to flush remaining data: {}", e);
+        }
+
+        // Close all writers
+        if let Err(e) = self.close_writers() {
+            error!("Failed to close writers: {}", e);
+        }
+
+        if let Some(e) = session_error {
+            return Err(e);
+        }
+
+        info!(
+            "Reader finished. Total messages: {}, errors: {}, reconnects: {}",
+            stats.messages, stats.errors, stats.reconnects
+        );
+
+        Ok(stats)
+    }
+
+    /// Fetch a full order book snapshot of the configured symbol over the connector's REST API
+    pub async fn fetch_rest_snapshot(&self) -> Result<OrderbookData> {
+        let url = self.connector
+            .rest_snapshot_url(&self.config.symbol, self.config.depth, self.config.testnet)
+            .with_context(|| format!("{} has no REST order book snapshot", self.connector.name()))?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .context("Failed to build HTTP client")?;
+        let body = client.get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to fetch order book snapshot from {}", url))?
+            .text()
+            .await
+            .context("Failed to read order book snapshot")?;
+        self.connector.parse_rest_snapshot(&body, now_ms())
+    }
+
+    /// Buffer a REST snapshot ahead of the stream's updates so a connection made mid-stream
+    /// starts from a full book. Without one the capture carries on with WebSocket data only.
+    async fn seed_rest_snapshot(&self) {
+        if self.connector.rest_snapshot_url(&self.config.symbol, self.config.depth, self.config.testnet).is_none() {
+            return;
+        }
+        match self.fetch_rest_snapshot().await {
+            Ok(snapshot) => {
+                info!("Seeded {} book from REST snapshot (update {})", snapshot.symbol, snapshot.update_id);
+                if let Err(e) = self.write_update(&snapshot) {
+                    error!("Failed to write snapshot: {}", e);
+                }
+            }
+            Err(e) => warn!("No REST snapshot, proceeding with WebSocket data only: {:#}", e),
+        }
+    }
+
+    /// Subscribe over `ws_sender` and buffer every book arriving on `ws_receiver` until the
+    /// connection drops, the duration is reached or `cancel_token` fires
+    async fn session<K, S>(
+        &self,
+        ws_sender: &mut K,
+        ws_receiver: &mut S,
+        cancel_token: &CancellationToken,
+        stats: &mut CaptureStats,
+    ) -> Result<SessionEnd>
+    where
+        K: Sink<Message> + Unpin,
+        K::Error: std::fmt::Display,
+        S: Stream<Item = std::result::Result<Message, tungstenite::Error>> + Unpin,
+    {
+        // Subscribe to orderbook
+        let subscribe_text = self.connector.subscribe_message(std::slice::from_ref(&self.config.symbol), self.config.depth)?;
+        if let Err(e) = ws_sender.send(Message::Text(subscribe_text)).await {
+            error!("Failed to send subscribe message: {}", e);
+            return Ok(SessionEnd::Disconnected);
+        }
+
+        info!("Subscribed to orderbook for {}", self.config.symbol);
+
+        let mut session_errors = 0u64;
+        let mut last_ping = Instant::now();
+        let mut flush_interval = interval(Duration::from_secs(self.config.interval_seconds));
+        flush_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        let end = loop {
+            if self.should_stop(cancel_token) {
+                break SessionEnd::Stopped;
+            }
+
+            // Send ping every 20 seconds
+            if last_ping.elapsed() >= Duration::from_secs(20) {
+                if let Some(ping_text) = self.connector.ping_message()? {
+                    if let Err(e) = ws_sender.send(Message::Text(ping_text)).await {
+                        warn!("Failed to send ping: {}", e);
+                    }
+                }
+                last_ping = Instant::now();
+            }
+
+            tokio::select! {
+                // Handle WebSocket messages
+                msg = ws_receiver.next() => {
+                    match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            match self.connector.parse_message(&text, now_ms()) {
+                                Ok(ConnectorEvent::Orderbook(orderbook_data)) => {
+                                    stats.messages += 1;
+
+                                    // Write the rebuilt full book to storage
+                                    if let Err(e) = self.write_update(&orderbook_data) {
+                                        error!("Failed to write data: {}", e);
+                                        stats.errors += 1;
+                                    }
+
+                                    if stats.messages % 100 == 0 {
+                                        info!(
+                                            "Processed {} orderbook messages, {} errors",
+                                            stats.messages, stats.errors
+                                        );
+                                    }
+                                }
+                                Ok(ConnectorEvent::Subscribed) => info!("Subscription confirmed"),
+                                Ok(ConnectorEvent::SubscriptionFailed(reason)) => warn!("Subscription failed: {}", reason),
+                                Ok(ConnectorEvent::Pong) => debug!("Received pong"),
+                                Ok(ConnectorEvent::Ignored) => {}
+                                Err(e) => {
+                                    debug!("Failed to parse message: {} - Text: {}", e, text);
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) => {
+                            info!("WebSocket closed by server");
+                            break SessionEnd::Disconnected;
+                        }
+                        Some(Ok(Message::Ping(data))) => {
+                            debug!("Received ping, sending pong");
+                            if let Err(e) = ws_sender.send(Message::Pong(data)).await {
+                                warn!("Failed to send pong: {}", e);
+                            }
+                        }
+                        Some(Ok(_)) => {
+                            // Ignore other message types
+                        }
+                        Some(Err(e)) => {
+                            error!("WebSocket error: {}", e);
+                            stats.errors += 1;
+                            session_errors += 1;
+
+                            // Too many errors on this connection: drop it and reconnect
+                            if session_errors >= 10 {
+                                error!("Too many errors, reconnecting");
+                                break SessionEnd::Disconnected;
+                            }
+                        }
+                        None => {
+                            info!("WebSocket stream ended");
+                            break SessionEnd::Disconnected;
+                        }
+                    }
+                }
+                
+                // Periodic flush based on interval_seconds
+                _ = flush_interval.tick() => {
+                    // First flush buffered data
+                    if let Err(e) = self.flush_data() {
+                        error!("Failed to flush data: {}", e);
+                    }
+                    
+                    // Then flush writers
+                    let mut writers_guard = self.writers.lock().unwrap();
+                    for writer in writers_guard.iter_mut() {
+                        if let Err(e) = writer.flush() {
+                            error!("Failed to flush {}: {}", writer.file_extension(), e);
+                        }
+                    }
+                    debug!("Flushed writers after {} seconds", self.config.interval_seconds);
+                }
+                
+                // Check for cancellation
+                _ = cancel_token.cancelled() => {
+                    info!("Cancellation requested during operation");
+                    break SessionEnd::Stopped;
+                }
+            }
+        };
+
+        // Close WebSocket connection
+        if let Err(e) = ws_sender.close().await {
+            warn!("Failed to close WebSocket: {}", e);
+        }
+
+        Ok(end)
+    }
+}
+
+/// `value` with every character other than ASCII letters, digits, `-` and `.` replaced by `-`,
+/// so it is a valid file name everywhere and can't split the name's `_`-separated fields
+fn filename_component(value: &str) -> String {
+    value.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '-' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+
+    #[test]
+    fn test_config_default() {
+        let config = ReaderConfig::default();
+        assert_eq!(config.symbol, "ETHUSDT");
+        assert_eq!(config.interval_seconds, 10);
+        assert_eq!(config.output_dir, "./data");
+        assert!(!config.testnet);
+        assert_eq!(config.depth, 50);
+        assert_eq!(config.duration_seconds, 3600);
+        assert!(config.save_jsonl);
+        assert!(config.save_parquet);
+    }
+
+    /// Scratch capture directory and its path as `ReaderConfig::output_dir` takes it
+    fn test_output_dir(name: &str) -> (TestDir, String) {
+        let dir = TestDir::new(&format!("reader_{}", name));
+        let path = dir.to_string_lossy().into_owned();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_parquet_only_writers() {
+        let (_dir, output_dir) = test_output_dir("parquet_only");
+        let config = ReaderConfig {
+            output_dir: output_dir.clone(),
+            save_jsonl: false,
+            save_parquet: true,
+            ..Default::default()
+        };
+
+        let reader = BybitReader::new(config).unwrap();
+        let mut writers = reader.init_writers().unwrap();
+
+        assert_eq!(writers.len(), 1);
+        assert_eq!(writers[0].file_extension(), "parquet");
+
+        for writer in writers.iter_mut() {
+            writer.close().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_no_output_format_falls_back_to_jsonl() {
+        let (_dir, output_dir) = test_output_dir("no_format");
+        let config = ReaderConfig {
+            output_dir: output_dir.clone(),
+            save_jsonl: false,
+            save_parquet: false,
+            ..Default::default()
+        };
+
+        let reader = BybitReader::new(config).unwrap();
+        let mut writers = reader.init_writers().unwrap();
+
+        assert_eq!(writers.len(), 1);
+        assert_eq!(writers[0].file_extension(), "jsonl");
+
+        for writer in writers.iter_mut() {
+            writer.close().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rollover_by_record_count() {
+        let policy = RolloverPolicy::MaxRecords(1000);
+        assert!(!policy.should_roll(0, 0, 0));
+        assert!(!policy.should_roll(0, 0, 999));
+        assert!(policy.should_roll(0, 0, 1000));
+        assert!(policy.should_roll(0, 0, 1500));
+
+        assert!(!RolloverPolicy::MaxRecords(0).should_roll(0, 0, 1_000_000));
+        assert!(!RolloverPolicy::Never.should_roll(0, i64::MAX, usize::MAX));
+    }
+
+    #[test]
+    fn test_rollover_at_hour_boundary() {
+        let opened = 3_600_000 * 5 + 1_000;
+        assert!(!RolloverPolicy::Hourly.should_roll(opened, 3_600_000 * 6 - 1, 0));
+        assert!(RolloverPolicy::Hourly.should_roll(opened, 3_600_000 * 6, 0));
+        assert!(!RolloverPolicy::Daily.should_roll(opened, 3_600_000 * 6, 0));
+    }
+
+    #[test]
+    fn test_rolled_files_are_numbered() {
+        let (_dir, output_dir) = test_output_dir("rollover");
+        let config = ReaderConfig {
+            output_dir: output_dir.clone(),
+            save_parquet: false,
+            rollover: RolloverPolicy::MaxRecords(2),
+            ..Default::default()
+        };
+        let reader = BybitReader::new(config).unwrap();
+        *reader.writers.lock().unwrap() = reader.init_writers().unwrap();
+
+        let record = OrderbookData {
+            symbol: "ETHUSDT".to_string(),
+            bids: vec![["100.0".to_string(), "1.0".to_string()]],
+            asks: vec![["100.1".to_string(), "1.0".to_string()]],
+            timestamp: 1000,
+            update_id: 1,
+            fetch_time: 1000,
+            msg_type: None,
+        };
+        for _ in 0..3 {
+            reader.write_data(&record).unwrap();
+            reader.write_data(&record).unwrap();
+            reader.flush_data().unwrap();
+        }
+        reader.close_writers().unwrap();
+
+        assert!(reader.generate_base_filename().ends_with("_part002"));
+        let files = std::fs::read_dir(&output_dir).unwrap().count();
+        assert_eq!(files, 3);
+    }
+
+    #[test]
+    fn test_base_filename_is_portable_and_keeps_symbol_first() {
+        let (_dir, output_dir) = test_output_dir("filename");
+        for (symbol, expected) in [("BTCUSDT", "BTCUSDT"), ("BTC_USDT:PERP", "BTC-USDT-PERP")] {
+            let reader = BybitReader::new(ReaderConfig {
+                symbol: symbol.to_string(),
+                output_dir: output_dir.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+
+            let base = reader.generate_base_filename();
+            let file_name = base.rsplit('/').next().unwrap();
+            assert!(!file_name.contains(':'), "{}", file_name);
+            assert_eq!(crate::utils::extract_symbol_from_filename(file_name), expected);
+        }
+    }
+
+    /// Writer that records the symbols of the batches it is given
+    struct RecordingWriter {
+        symbol: &'static str,
+        written: Arc<Mutex<Vec<(i64, String)>>>,
+    }
+
+    impl StorageWriter for RecordingWriter {
+        fn init(&mut self, _config: WriterConfig) -> Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, data: &OrderbookData) -> Result<()> {
+            self.written.lock().unwrap().push((data.timestamp, data.symbol.clone()));
+            Ok(())
+        }
+
+        fn write_batch(&mut self, batch: &[OrderbookData]) -> Result<()> {
+            batch.iter().try_for_each(|data| self.write(data))
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn file_extension(&self) -> &'static str {
+            "test"
+        }
+
+        fn accepts(&self, data: &OrderbookData) -> bool {
+            data.symbol == self.symbol
+        }
+    }
+
+    #[test]
+    fn test_records_are_buffered_per_writer() {
+        let (_dir, output_dir) = test_output_dir("routing");
+        let reader = BybitReader::new(ReaderConfig { output_dir: output_dir.clone(), ..Default::default() }).unwrap();
+        let btc = Arc::new(Mutex::new(Vec::new()));
+        let eth = Arc::new(Mutex::new(Vec::new()));
+        *reader.writers.lock().unwrap() = vec![
+            Box::new(RecordingWriter { symbol: "BTCUSDT", written: btc.clone() }),
+            Box::new(RecordingWriter { symbol: "ETHUSDT", written: eth.clone() }),
+        ];
+
+        for (time, symbol) in [(1, "BTCUSDT"), (2, "ETHUSDT"), (3, "BTCUSDT"), (4, "ETHUSDT"), (5, "ETHUSDT")] {
+            let record = OrderbookData {
+                symbol: symbol.to_string(),
+                bids: vec![["100.0".to_string(), "1.0".to_string()]],
+                asks: vec![["100.1".to_string(), "1.0".to_string()]],
+                timestamp: time,
+                update_id: time,
+                fetch_time: time,
+                msg_type: None,
+            };
+            reader.write_data(&record).unwrap();
+        }
+        {
+            let buffers = reader.data_buffers.lock().unwrap();
+            assert_eq!(buffers.buffers.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 3]);
+            assert_eq!(buffers.records, 5);
+        }
+        reader.flush_data().unwrap();
+
+        let symbols = |written: &Arc<Mutex<Vec<(i64, String)>>>| written.lock().unwrap().clone();
+        assert_eq!(symbols(&btc), vec![(1, "BTCUSDT".to_string()), (3, "BTCUSDT".to_string())]);
+        assert_eq!(symbols(&eth), vec![(2, "ETHUSDT".to_string()), (4, "ETHUSDT".to_string()), (5, "ETHUSDT".to_string())]);
+        assert_eq!(reader.rollover_state.lock().unwrap().records_written, 5);
+        assert!(reader.data_buffers.lock().unwrap().buffers.is_empty());
+    }
+
+    /// Connector for a line protocol of `SYMBOL,timestamp` order book frames
+    struct MockConnector;
+
+    impl ExchangeConnector for MockConnector {
+        fn name(&self) -> &str {
+            "Mock"
+        }
+
+        fn ws_url(&self, _testnet: bool) -> String {
+            "ws://localhost".to_string()
+        }
+
+        fn subscribe_message(&self, symbols: &[String], _depth: u32) -> Result<String> {
+            if symbols.iter().any(|symbol| symbol == "UNLISTED") {
+                anyhow::bail!("cannot subscribe to UNLISTED");
+            }
+            Ok(symbols.join(","))
+        }
+
+        fn ping_message(&self) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn parse_message(&self, text: &str, fetch_time: i64) -> Result<ConnectorEvent> {
+            if text == "ok" {
+                return Ok(ConnectorEvent::Subscribed);
+            }
+            let (symbol, timestamp) = text.split_once(',').context("missing timestamp")?;
+            let timestamp: i64 = timestamp.parse()?;
+            Ok(ConnectorEvent::Orderbook(OrderbookData {
+                symbol: symbol.to_string(),
+                bids: vec![["100.0".to_string(), "1.0".to_string()]],
+                asks: vec![["100.1".to_string(), "1.0".to_string()]],
+                timestamp,
+                update_id: timestamp,
+                fetch_time,
+                msg_type: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_connector_drives_reader_loop() {
+        let (_dir, output_dir) = test_output_dir("mock_connector");
+        let config = ReaderConfig { output_dir: output_dir.clone(), ..Default::default() };
+        let reader = OrderbookReader::with_connector(config, MockConnector).unwrap();
+        let eth = Arc::new(Mutex::new(Vec::new()));
+        *reader.writers.lock().unwrap() = vec![Box::new(RecordingWriter { symbol: "ETHUSDT", written: eth.clone() })];
+
+        let stats = capture_scripted(&reader, vec![vec!["ok", "ETHUSDT,1", "garbage", "BTCUSDT,2", "ETHUSDT,3"]]).await;
+
+        assert_eq!(*eth.lock().unwrap(), vec![(1, "ETHUSDT".to_string()), (3, "ETHUSDT".to_string())]);
+        assert_eq!(reader.rollover_state.lock().unwrap().records_written, 3);
+        assert_eq!(stats.messages, 3);
+    }
+
+    /// Capture over one scripted connection per entry of `sessions`, each ending when its
+    /// frames run out; the connection after the last fails and cancels the capture
+    async fn capture_scripted(reader: &OrderbookReader<MockConnector>, sessions: Vec<Vec<&'static str>>) -> CaptureStats {
+        let cancel_token = CancellationToken::new();
+        let mut sessions = sessions.into_iter();
+        reader.capture(&cancel_token, || {
+            let frames = sessions.next();
+            if frames.is_none() {
+                cancel_token.cancel();
+            }
+            async move {
+                let frames = frames.context("no more scripted sessions")?;
+                let receiver = futures_util::stream::iter(
+                    frames.into_iter().map(|text| Ok(Message::Text(text.to_string()))).collect::<Vec<_>>(),
+                );
+                Ok::<_, anyhow::Error>((futures_util::sink::drain(), receiver))
+            }
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_resubscribes_and_keeps_buffered_records() {
+        let (_dir, output_dir) = test_output_dir("reconnect");
+        let config = ReaderConfig { output_dir: output_dir.clone(), ..Default::default() };
+        let reader = OrderbookReader::with_connector(config, MockConnector).unwrap();
+        let eth = Arc::new(Mutex::new(Vec::new()));
+        *reader.writers.lock().unwrap() = vec![Box::new(RecordingWriter { symbol: "ETHUSDT", written: eth.clone() })];
+
+        let stats = capture_scripted(&reader, vec![vec!["ok", "ETHUSDT,1"], vec!["ok", "ETHUSDT,2", "ETHUSDT,3"]]).await;
+
+        assert_eq!(stats.reconnects, 2);
+        assert_eq!(stats.messages, 3);
+        // The record buffered when the first connection dropped is written ahead of the rest
+        let written: Vec<i64> = eth.lock().unwrap().iter().map(|(timestamp, _)| *timestamp).collect();
+        assert_eq!(written, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_session_error_still_flushes_buffered_records() {
+        let (_dir, output_dir) = test_output_dir("session_error");
+        let config = ReaderConfig { output_dir: output_dir.clone(), symbol: "UNLISTED".to_string(), ..Default::default() };
+        let reader = OrderbookReader::with_connector(config, MockConnector).unwrap();
+        let eth = Arc::new(Mutex::new(Vec::new()));
+        *reader.writers.lock().unwrap() = vec![Box::new(RecordingWriter { symbol: "ETHUSDT", written: eth.clone() })];
+        if let ConnectorEvent::Orderbook(record) = reader.connector.parse_message("ETHUSDT,1", 0).unwrap() {
+            reader.write_data(&record).unwrap();
+        }
+
+        let cancel_token = CancellationToken::new();
+        let result = reader.capture(&cancel_token, || async {
+            Ok::<_, anyhow::Error>((futures_util::sink::drain(), futures_util::stream::empty::<std::result::Result<Message, tungstenite::Error>>()))
+        }).await;
+
+        assert!(result.unwrap_err().to_string().contains("UNLISTED"));
+        assert_eq!(*eth.lock().unwrap(), vec![(1, "ETHUSDT".to_string())]);
+    }
+
+    #[test]
+    fn test_reconnect_backoff_doubles_up_to_cap() {
+        assert_eq!(reconnect_backoff(0), Duration::from_millis(500));
+        assert_eq!(reconnect_backoff(1), Duration::from_secs(1));
+        assert_eq!(reconnect_backoff(3), Duration::from_secs(4));
+        assert_eq!(reconnect_backoff(6), MAX_RECONNECT_BACKOFF);
+        assert_eq!(reconnect_backoff(u32::MAX), MAX_RECONNECT_BACKOFF);
+    }
+
+    #[test]
+    fn test_stream_deltas_are_written_as_full_books() {
+        let (_dir, output_dir) = test_output_dir("book_state");
+        let config = ReaderConfig { output_dir: output_dir.clone(), ..Default::default() };
+        let reader = BybitReader::new(config).unwrap();
+        let eth = Arc::new(Mutex::new(Vec::new()));
+        *reader.writers.lock().unwrap() = vec![Box::new(RecordingWriter { symbol: "ETHUSDT", written: eth.clone() })];
+
+        let message = |msg_type: &str, ts: i64, bids: &str| format!(
+            r#"{{"topic":"orderbook.50.ETHUSDT","type":"{}","ts":{},"data":{{"s":"ETHUSDT","b":{},"a":[],"u":{}}}}}"#,
+            msg_type, ts, bids, ts,
+        );
+        let frames = [
+            message("delta", 1, r#"[["99.0","1"]]"#),
+            message("snapshot", 2, r#"[["100.0","1"],["99.5","2"]]"#),
+            message("delta", 3, r#"[["99.5","0"],["99.8","3"]]"#),
+        ];
+        for frame in &frames {
+            if let ConnectorEvent::Orderbook(update) = reader.connector.parse_message(frame, 0).unwrap() {
+                reader.write_update(&update).unwrap();
+            }
+        }
+
+        // The early delta is dropped; the last record holds the whole book, not just the change
+        let buffered = reader.data_buffers.lock().unwrap().buffers[0].clone();
+        assert_eq!(buffered.iter().map(|book| book.timestamp).collect::<Vec<_>>(), vec![2, 3]);
+        let bids: Vec<&str> = buffered[1].bids.iter().map(|level| level[0].as_str()).collect();
+        assert_eq!(bids, vec!["100.0", "99.8"]);
+    }
+}
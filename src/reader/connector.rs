@@ -0,0 +1,176 @@
+use anyhow::Result;
+
+use super::models::{BybitResponse, OrderbookData, WsRequest, WsResponse};
+
+/// What one text frame from the exchange turned out to be
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectorEvent {
+    /// An order book snapshot or update
+    Orderbook(OrderbookData),
+    /// The exchange confirmed the subscription
+    Subscribed,
+    /// The exchange refused the subscription, with its reason
+    SubscriptionFailed(String),
+    /// Reply to the connector's ping
+    Pong,
+    /// Anything else the reader can skip
+    Ignored,
+}
+
+/// Exchange-specific side of the order book capture: where to connect, how to subscribe and
+/// how to turn the exchange's messages into `OrderbookData`. `OrderbookReader` drives the
+/// socket, buffering and file output for any connector.
+pub trait ExchangeConnector: Send + Sync {
+    /// Exchange name for logs
+    fn name(&self) -> &str;
+
+    /// WebSocket endpoint of the public order book stream
+    fn ws_url(&self, testnet: bool) -> String;
+
+    /// Text frame subscribing to `depth` levels of each symbol's order book
+    fn subscribe_message(&self, symbols: &[String], depth: u32) -> Result<String>;
+
+    /// Application-level keepalive sent every 20 seconds, if the exchange wants one
+    fn ping_message(&self) -> Result<Option<String>>;
+
+    /// Parse a text frame. `fetch_time` is the local receive time in Unix milliseconds, used
+    /// when the message carries no exchange timestamp.
+    fn parse_message(&self, text: &str, fetch_time: i64) -> Result<ConnectorEvent>;
+
+    /// REST endpoint returning a full `depth`-level snapshot of `symbol`'s book, if the
+    /// exchange has one; the reader seeds each connection with it
+    fn rest_snapshot_url(&self, _symbol: &str, _depth: u32, _testnet: bool) -> Option<String> {
+        None
+    }
+
+    /// Parse the body returned by `rest_snapshot_url` into a book with `msg_type` "snapshot"
+    fn parse_rest_snapshot(&self, _body: &str, _fetch_time: i64) -> Result<OrderbookData> {
+        anyhow::bail!("{} has no REST order book snapshot", self.name())
+    }
+}
+
+/// Bybit v5 public linear stream
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BybitConnector;
+
+impl ExchangeConnector for BybitConnector {
+    fn name(&self) -> &str {
+        "Bybit"
+    }
+
+    fn ws_url(&self, testnet: bool) -> String {
+        if testnet {
+            "wss://stream-testnet.bybit.com/v5/public/linear".to_string()
+        } else {
+            "wss://stream.bybit.com/v5/public/linear".to_string()
+        }
+    }
+
+    fn subscribe_message(&self, symbols: &[String], depth: u32) -> Result<String> {
+        Ok(serde_json::to_string(&WsRequest::subscribe(symbols.to_vec(), depth))?)
+    }
+
+    fn ping_message(&self) -> Result<Option<String>> {
+        Ok(Some(serde_json::to_string(&WsRequest::ping())?))
+    }
+
+    fn parse_message(&self, text: &str, fetch_time: i64) -> Result<ConnectorEvent> {
+        let response: WsResponse = serde_json::from_str(text)?;
+
+        match response.op.as_deref() {
+            Some("subscribe") if response.success == Some(true) => return Ok(ConnectorEvent::Subscribed),
+            Some("subscribe") => {
+                return Ok(ConnectorEvent::SubscriptionFailed(response.ret_msg.unwrap_or_default()));
+            }
+            Some("pong") => return Ok(ConnectorEvent::Pong),
+            _ => {}
+        }
+
+        // Only orderbook updates carry a topic
+        match (response.data, response.topic) {
+            (Some(data), Some(_)) => Ok(ConnectorEvent::Orderbook(OrderbookData {
+                symbol: data.s,
+                bids: data.b,
+                asks: data.a,
+                timestamp: response.ts.unwrap_or(fetch_time),
+                update_id: data.u,
+                fetch_time,
+                msg_type: response.msg_type,
+            })),
+            _ => Ok(ConnectorEvent::Ignored),
+        }
+    }
+
+    fn rest_snapshot_url(&self, symbol: &str, depth: u32, testnet: bool) -> Option<String> {
+        let base = if testnet { "https://api-testnet.bybit.com" } else { "https://api.bybit.com" };
+        Some(format!("{}/v5/market/orderbook?category=linear&symbol={}&limit={}", base, symbol, depth))
+    }
+
+    fn parse_rest_snapshot(&self, body: &str, fetch_time: i64) -> Result<OrderbookData> {
+        // Error responses carry an empty result, so check the code before the book fields
+        let value: serde_json::Value = serde_json::from_str(body)?;
+        if value["retCode"].as_i64() != Some(0) {
+            anyhow::bail!("Bybit returned {}: {}", value["retCode"], value["retMsg"].as_str().unwrap_or_default());
+        }
+        let result = serde_json::from_value::<BybitResponse>(value)?.result;
+        Ok(OrderbookData {
+            symbol: result.s,
+            bids: result.b,
+            asks: result.a,
+            timestamp: result.ts,
+            update_id: result.u,
+            fetch_time,
+            msg_type: Some("snapshot".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bybit_connector_parses_stream_messages() {
+        let connector = BybitConnector;
+        assert_eq!(connector.ws_url(true), "wss://stream-testnet.bybit.com/v5/public/linear");
+        assert!(connector.subscribe_message(&["ETHUSDT".to_string()], 50).unwrap().contains("orderbook.50.ETHUSDT"));
+
+        let delta = r#"{"topic":"orderbook.50.ETHUSDT","type":"delta","ts":1756134462072,
+            "data":{"s":"ETHUSDT","b":[["4646.26","6.46"]],"a":[["4646.96","34.95"]],"u":48114057,"seq":1}}"#;
+        match connector.parse_message(delta, 1).unwrap() {
+            ConnectorEvent::Orderbook(data) => {
+                assert_eq!((data.symbol.as_str(), data.timestamp, data.update_id, data.fetch_time),
+                           ("ETHUSDT", 1756134462072, 48114057, 1));
+                assert_eq!(data.msg_type.as_deref(), Some("delta"));
+            }
+            other => panic!("expected an orderbook, got {:?}", other),
+        }
+
+        let subscribed = r#"{"success":true,"ret_msg":"","op":"subscribe"}"#;
+        assert_eq!(connector.parse_message(subscribed, 1).unwrap(), ConnectorEvent::Subscribed);
+        let refused = r#"{"success":false,"ret_msg":"bad topic","op":"subscribe"}"#;
+        assert_eq!(connector.parse_message(refused, 1).unwrap(), ConnectorEvent::SubscriptionFailed("bad topic".to_string()));
+        assert_eq!(connector.parse_message(r#"{"success":true,"op":"pong"}"#, 1).unwrap(), ConnectorEvent::Pong);
+        assert!(connector.parse_message("not json", 1).is_err());
+    }
+
+    #[test]
+    fn test_bybit_rest_snapshot() {
+        let connector = BybitConnector;
+        assert_eq!(connector.rest_snapshot_url("ETHUSDT", 50, false).unwrap(),
+                   "https://api.bybit.com/v5/market/orderbook?category=linear&symbol=ETHUSDT&limit=50");
+        assert!(connector.rest_snapshot_url("ETHUSDT", 50, true).unwrap().starts_with("https://api-testnet.bybit.com/"));
+
+        let body = r#"{"retCode":0,"retMsg":"OK","result":{"s":"ETHUSDT","b":[["4646.26","6.46"]],
+            "a":[["4646.96","34.95"],["4647.07","2.2"]],"ts":1756134462000,"u":48114000},"time":1756134462010}"#;
+        let snapshot = connector.parse_rest_snapshot(body, 7).unwrap();
+        assert_eq!((snapshot.symbol.as_str(), snapshot.timestamp, snapshot.update_id, snapshot.fetch_time),
+                   ("ETHUSDT", 1756134462000, 48114000, 7));
+        assert_eq!(snapshot.asks.len(), 2);
+        assert_eq!(snapshot.msg_type.as_deref(), Some("snapshot"));
+
+        let refused = r#"{"retCode":10001,"retMsg":"params error","result":{},"time":0}"#;
+        let err = connector.parse_rest_snapshot(refused, 7).unwrap_err();
+        assert!(err.to_string().contains("params error"), "{}", err);
+    }
+}
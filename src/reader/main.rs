@@ -1,6 +1,6 @@
 use clap::Parser;
 use env_logger;
-use happytest::reader::{BybitReader, ReaderConfig};
+use happytest::reader::{BybitReader, ReaderConfig, RolloverPolicy};
 use tokio_util::sync::CancellationToken;
 
 #[derive(Parser, Debug)]
@@ -35,13 +35,21 @@ struct Args {
     #[arg(long, default_value_t = 50)]
     depth: u32,
     
-    /// Save as Parquet in addition to JSONL
-    #[arg(long, default_value_t = false)]
+    /// Save as Parquet format (e.g. `--parquet true`)
+    #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
     parquet: bool,
     
-    /// Save as JSONL format
-    #[arg(long, default_value_t = false)]
+    /// Save as JSONL format (e.g. `--jsonl false` for Parquet-only output)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
     jsonl: bool,
+    
+    /// Start new output files on a schedule: never, hourly or daily
+    #[arg(long, default_value = "never")]
+    rollover: String,
+    
+    /// Start new output files after this many records (overrides --rollover)
+    #[arg(long)]
+    rollover_records: Option<usize>,
 }
 
 #[tokio::main]
@@ -65,6 +73,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Depth: {}", args.depth);
     println!("Parquet: {}", if args.parquet { "enabled" } else { "disabled" });
     println!("JSONL: {}", if args.jsonl { "enabled" } else { "disabled" });
+    let rollover = match (args.rollover_records, args.rollover.as_str()) {
+        (Some(records), _) => RolloverPolicy::MaxRecords(records),
+        (None, "never") => RolloverPolicy::Never,
+        (None, "hourly") => RolloverPolicy::Hourly,
+        (None, "daily") => RolloverPolicy::Daily,
+        (None, other) => {
+            eprintln!("Error: Unknown rollover '{}'. Use never, hourly or daily", other);
+            std::process::exit(1);
+        }
+    };
+    println!("Rollover: {:?}", rollover);
     println!("==============================\n");
     
     let config = ReaderConfig {
@@ -76,6 +95,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         duration_seconds: args.duration,
         save_parquet: args.parquet,
         save_jsonl: args.jsonl,
+        rollover,
     };
     
     let reader = BybitReader::new(config)?;
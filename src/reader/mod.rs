@@ -1,8 +1,12 @@
-pub mod bybit;
+pub mod book;
+pub mod capture;
+pub mod connector;
 pub mod converter;
 pub mod models;
 pub mod storage;
 
-pub use bybit::{BybitReader, ReaderConfig};
+pub use book::OrderbookState;
+pub use capture::{BybitReader, OrderbookReader, ReaderConfig, RolloverPolicy};
+pub use connector::{BybitConnector, ConnectorEvent, ExchangeConnector};
 pub use converter::convert_reader_to_backtest;
 pub use models::{OrderbookData, BybitResponse, OrderbookResult};
\ No newline at end of file
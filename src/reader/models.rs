@@ -22,7 +22,7 @@ pub struct OrderbookResult {
 }
 
 /// Orderbook data structure to save
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct OrderbookData {
     pub symbol: String,
     pub bids: Vec<[String; 2]>,
@@ -30,6 +30,9 @@ pub struct OrderbookData {
     pub timestamp: i64,
     pub update_id: i64,
     pub fetch_time: i64,
+    /// "snapshot" for a full book, "delta" for changed levels only; None when unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg_type: Option<String>,
 }
 
 /// WebSocket message structures for Bybit
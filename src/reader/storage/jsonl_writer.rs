@@ -116,4 +116,8 @@ impl StorageWriter for JsonlWriter {
     fn file_extension(&self) -> &'static str {
         "jsonl"
     }
+    
+    fn accepts(&self, data: &OrderbookData) -> bool {
+        self.config.accepts(data)
+    }
 }
\ No newline at end of file
@@ -29,7 +29,7 @@ impl ParquetWriter {
     }
     
     /// Create the schema for Parquet file
-    fn create_schema() -> Schema {
+    pub(crate) fn create_schema() -> Schema {
         Schema::new(vec![
             Field::new("symbol", DataType::Utf8, false),
             Field::new("bids", DataType::Utf8, false), // JSON string of bids
@@ -40,6 +40,13 @@ impl ParquetWriter {
         ])
     }
     
+    /// Properties of written files; `ParquetDataSource::open_lenient` relies on the codec
+    pub(crate) fn writer_properties() -> WriterProperties {
+        WriterProperties::builder()
+            .set_compression(parquet::basic::Compression::SNAPPY)
+            .build()
+    }
+    
     /// Convert buffered data to Arrow arrays
     fn convert_to_arrow_batch(records: &[OrderbookData]) -> Result<RecordBatch> {
         let mut symbol_builder = StringBuilder::new();
@@ -85,6 +92,8 @@ impl ParquetWriter {
             if let Some(writer) = &mut self.writer {
                 let batch = Self::convert_to_arrow_batch(&self.buffer)?;
                 writer.write(&batch).context("Failed to write Parquet batch")?;
+                // End the row group now so a hard kill leaves it on disk for `open_lenient`
+                writer.flush().context("Failed to flush Parquet row group")?;
                 log::debug!("Wrote batch of {} records to Parquet file", self.buffer.len());
                 self.buffer.clear();
             }
@@ -112,12 +121,8 @@ impl StorageWriter for ParquetWriter {
             .context("Failed to create Parquet output file")?;
         
         let schema = Arc::new(Self::create_schema());
-        let props = WriterProperties::builder()
-            .set_compression(parquet::basic::Compression::SNAPPY)
-            .build();
-        
         self.writer = Some(
-            ArrowWriter::try_new(file, schema, Some(props))
+            ArrowWriter::try_new(file, schema, Some(Self::writer_properties()))
                 .context("Failed to create Parquet writer")?
         );
         
@@ -176,4 +181,8 @@ impl StorageWriter for ParquetWriter {
     fn file_extension(&self) -> &'static str {
         "parquet"
     }
+    
+    fn accepts(&self, data: &OrderbookData) -> bool {
+        self.config.accepts(data)
+    }
 }
\ No newline at end of file
@@ -6,6 +6,18 @@ use crate::reader::models::OrderbookData;
 pub struct WriterConfig {
     pub base_filename: String,
     pub buffer_size: usize,
+    /// Only records of this symbol are routed to the writer (None = every record)
+    pub symbol: Option<String>,
+}
+
+impl WriterConfig {
+    /// Whether `data` belongs in this writer's file
+    pub fn accepts(&self, data: &OrderbookData) -> bool {
+        match &self.symbol {
+            Some(symbol) => *symbol == data.symbol,
+            None => true,
+        }
+    }
 }
 
 impl Default for WriterConfig {
@@ -13,6 +25,7 @@ impl Default for WriterConfig {
         Self {
             base_filename: String::new(),
             buffer_size: 1000,
+            symbol: None,
         }
     }
 }
@@ -36,4 +49,9 @@ pub trait StorageWriter: Send {
     
     /// Get the file extension for this writer type
     fn file_extension(&self) -> &'static str;
+    
+    /// Whether the record should be routed to this writer
+    fn accepts(&self, _data: &OrderbookData) -> bool {
+        true
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use clap::Args;
-use crate::strategy::Strategy;
+use crate::core::ImbalanceWeighting;
+use crate::strategy::{BuyAndHoldStrategy, GptMarketMaker, GptMarketMakerConfig, Strategy};
 
 /// Trait for strategy-specific command line arguments
 pub trait StrategyArgs: Args {
@@ -7,10 +8,20 @@ pub trait StrategyArgs: Args {
     fn build_strategy(&self, symbol: String) -> Box<dyn Strategy>;
 }
 
+/// Parse a `bps:fraction` take-profit ladder level
+fn parse_ladder_level(value: &str) -> Result<(f64, f64), String> {
+    let (bps, fraction) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected bps:fraction, got '{}'", value))?;
+    let bps = bps.trim().parse::<f64>().map_err(|e| format!("invalid bps '{}': {}", bps, e))?;
+    let fraction = fraction.trim().parse::<f64>().map_err(|e| format!("invalid fraction '{}': {}", fraction, e))?;
+    Ok((bps, fraction))
+}
+
 /// Command line arguments for GPT Market Maker strategy
 #[derive(Debug, Clone, Args)]
 pub struct GptMarketMakerArgs {
-    /// Fixed order volume for each trade
+    /// Fixed order volume for each trade (capped by --max-order-volume)
     #[arg(long, default_value_t = 0.005)]
     pub fix_order_volume: f64,
 
@@ -58,6 +69,18 @@ pub struct GptMarketMakerArgs {
     #[arg(long, default_value_t = 5.0)]
     pub min_profit_bps: f64,
 
+    /// Laddered take-profit levels as bps:fraction pairs (e.g. 10:0.5,20:0.25); replaces --take-profit-bps
+    #[arg(long, value_delimiter = ',', value_parser = parse_ladder_level)]
+    pub take_profit_ladder: Vec<(f64, f64)>,
+
+    /// Close the whole inventory (or --stop-close-fraction of it) in one order on stop-loss and aggressive close
+    #[arg(long, default_value_t = false)]
+    pub full_size_on_stop: bool,
+
+    /// Share of the inventory closed per order with --full-size-on-stop (0.0-1.0]
+    #[arg(long, default_value_t = 1.0)]
+    pub stop_close_fraction: f64,
+
     /// Volatility window size
     #[arg(long, default_value_t = 30)]
     pub volatility_window: usize,
@@ -81,13 +104,16 @@ pub struct GptMarketMakerArgs {
     /// Momentum cooldown in milliseconds
     #[arg(long, default_value_t = 3000)]
     pub momentum_cooldown_ms: i64,
+
+    /// Weight order book imbalance by notional (price * quantity) instead of quantity
+    #[arg(long, default_value_t = false)]
+    pub notional_imbalance: bool,
 }
 
 impl GptMarketMakerArgs {
-    pub fn build_strategy(&self, symbol: String) -> Box<dyn Strategy> {
-        use crate::strategy::{GptMarketMaker, GptMarketMakerConfig};
-        
-        let config = GptMarketMakerConfig {
+    /// Strategy config assembled from the command line arguments
+    pub fn config(&self) -> GptMarketMakerConfig {
+        GptMarketMakerConfig {
             fix_order_volume: self.fix_order_volume,
             vwap_window: self.vwap_window,
             obi_threshold: self.obi_threshold,
@@ -100,14 +126,38 @@ impl GptMarketMakerArgs {
             inventory_reduction_threshold: self.inventory_reduction_threshold,
             aggressive_close_threshold: self.aggressive_close_threshold,
             min_profit_bps: self.min_profit_bps,
+            take_profit_ladder: self.take_profit_ladder.clone(),
+            full_size_on_stop: self.full_size_on_stop,
+            stop_close_fraction: self.stop_close_fraction,
             volatility_window: self.volatility_window,
             max_volatility_threshold: self.max_volatility_threshold,
             volatility_cooldown_ms: self.volatility_cooldown_ms,
             momentum_window: self.momentum_window,
             momentum_threshold: self.momentum_threshold,
             momentum_cooldown_ms: self.momentum_cooldown_ms,
-        };
-        
-        Box::new(GptMarketMaker::new(symbol, config))
+            imbalance_weighting: if self.notional_imbalance {
+                ImbalanceWeighting::Notional
+            } else {
+                ImbalanceWeighting::Quantity
+            },
+        }
+    }
+
+    pub fn build_strategy(&self, symbol: String) -> Box<dyn Strategy> {
+        Box::new(GptMarketMaker::new(symbol, self.config()))
     }
-}
\ No newline at end of file
+}
+
+/// Command line arguments for the buy-and-hold benchmark strategy
+#[derive(Debug, Clone, Args)]
+pub struct BuyAndHoldArgs {
+    /// Quantity bought on the first tick and held to the end
+    #[arg(long, default_value_t = 0.005)]
+    pub quantity: f64,
+}
+
+impl BuyAndHoldArgs {
+    pub fn build_strategy(&self, _symbol: String) -> Box<dyn Strategy> {
+        Box::new(BuyAndHoldStrategy::new(self.quantity))
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use crate::core::{OrderBook, Trade};
 
 /// Base trait for all trading strategies
@@ -5,10 +6,27 @@ pub trait Strategy: Send + Sync {
     /// Get the strategy name for identification
     fn name(&self) -> &str;
     
-    /// Propose a trade based on the current order book
+    /// Receive the external feature values in effect for the next order book.
+    /// Called before `propose_trade` when the engine has a feature source.
+    fn on_features(&mut self, _features: &HashMap<String, f64>) {}
+    
+    /// Propose a trade based on the current order book, which holds every level the data source provided
     fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade>;
     
-    /// Update internal position tracking after trade execution
+    /// Propose every order for the current order book, e.g. a bid and an ask for a two-sided quote.
+    /// The engine calls this; the default wraps `propose_trade`.
+    fn propose_trades(&mut self, order_book: &OrderBook) -> Vec<Trade> {
+        self.propose_trade(order_book).into_iter().collect()
+    }
+    
+    /// Why the latest `propose_trade` call did or didn't return a trade, if the strategy reports it
+    fn decision_reason(&self) -> Option<&str> {
+        None
+    }
+    
+    /// Update internal position tracking after trade execution. `filled` is true for full and
+    /// partial fills, with `trade.quantity` the executed quantity; `trade.status` tells
+    /// `filled`, `partially_filled`, `unfilled` and `rejected` apart.
     fn update_position(&mut self, trade: &Trade, filled: bool);
     
     /// Get current net position for a symbol
@@ -0,0 +1,109 @@
+use crate::core::{OrderBook, Trade};
+use crate::strategy::Strategy;
+
+/// Strategy that never trades. Measures engine overhead and pins P&L at zero.
+#[derive(Debug, Clone, Default)]
+pub struct NoopStrategy;
+
+impl Strategy for NoopStrategy {
+    fn name(&self) -> &str {
+        "noop"
+    }
+
+    fn propose_trade(&mut self, _order_book: &OrderBook) -> Option<Trade> {
+        None
+    }
+
+    fn decision_reason(&self) -> Option<&str> {
+        Some("NOOP")
+    }
+
+    fn update_position(&mut self, _trade: &Trade, _filled: bool) {}
+
+    fn get_position(&self, _symbol: &str) -> f64 {
+        0.0
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// Strategy that buys `quantity` at the ask on the first tick and holds it to the end,
+/// the market-return benchmark for other strategies. An unfilled buy is retried on the next tick.
+#[derive(Debug, Clone)]
+pub struct BuyAndHoldStrategy {
+    quantity: f64,
+    position: f64,
+}
+
+impl BuyAndHoldStrategy {
+    pub fn new(quantity: f64) -> Self {
+        Self { quantity, position: 0.0 }
+    }
+}
+
+impl Strategy for BuyAndHoldStrategy {
+    fn name(&self) -> &str {
+        "buy_and_hold"
+    }
+
+    fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade> {
+        if self.position > 0.0 {
+            return None;
+        }
+        let (best_ask, _) = order_book.best_ask()?;
+        Some(Trade::new(order_book.current_time, order_book.symbol.clone(), "Buy".to_string(), best_ask, self.quantity))
+    }
+
+    fn decision_reason(&self) -> Option<&str> {
+        Some(if self.position > 0.0 { "HOLD" } else { "OPEN_BUY" })
+    }
+
+    fn update_position(&mut self, trade: &Trade, filled: bool) {
+        if filled {
+            self.position += trade.quantity;
+        }
+    }
+
+    fn get_position(&self, _symbol: &str) -> f64 {
+        self.position
+    }
+
+    fn reset(&mut self) {
+        self.position = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::BacktestEngine;
+    use crate::trading::BacktestConfig;
+
+    fn books() -> impl Iterator<Item = OrderBook> {
+        (0..5).map(|i| {
+            let mid = 100.0 + i as f64;
+            OrderBook::new("BTCUSDT".to_string(), vec![(mid - 0.5, 1.0)], vec![(mid + 0.5, 1.0)], i * 1_000)
+        })
+    }
+
+    fn engine() -> BacktestEngine {
+        BacktestEngine::new(BacktestConfig { fill_rate: 1.0, rejection_rate: 0.0, ..Default::default() })
+    }
+
+    #[test]
+    fn test_noop_never_trades() {
+        let trade_state = engine().run_order_books(books(), &mut NoopStrategy);
+        assert!(trade_state.get_all_trades().is_empty());
+    }
+
+    #[test]
+    fn test_buy_and_hold_opens_once() {
+        let mut strategy = BuyAndHoldStrategy::new(0.5);
+        let trade_state = engine().run_order_books(books(), &mut strategy);
+
+        let trades = trade_state.get_all_trades();
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].side.as_str(), trades[0].price, trades[0].quantity, trades[0].time), ("Buy", 100.5, 0.5, 0));
+        assert_eq!(strategy.get_position("BTCUSDT"), 0.5);
+    }
+}
@@ -1,10 +1,14 @@
-use crate::core::{Trade, OrderBook};
+use crate::core::{Trade, OrderBook, ImbalanceWeighting, Result, TradeError};
 use crate::strategy::Strategy;
+use std::borrow::Cow;
 use std::collections::VecDeque;
-use log::info;
+use log::Level;
+use crate::utils::logging::{log_close, log_event, log_risk};
+use crate::pnl::QUANTITY_EPSILON;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GptMarketMakerConfig {
+    /// Quantity of every order; `BacktestConfig::max_order_volume` can only cap it
     pub fix_order_volume: f64,
     pub vwap_window: usize,
     pub obi_threshold: f64,
@@ -18,6 +22,18 @@ pub struct GptMarketMakerConfig {
     pub inventory_reduction_threshold: f64,
     pub aggressive_close_threshold: f64,
     pub min_profit_bps: f64,
+    /// Laddered take-profit as `(take_profit_bps, fraction)` levels in ascending bps. Each level
+    /// closes `fraction` of the position held when the ladder started; replaces `take_profit_bps`
+    /// when non-empty, and any remainder is left to the other exits.
+    #[serde(default)]
+    pub take_profit_ladder: Vec<(f64, f64)>,
+    /// Close `stop_close_fraction` of the inventory in one order on a stop-loss or aggressive
+    /// close, instead of `fix_order_volume` slices
+    #[serde(default)]
+    pub full_size_on_stop: bool,
+    /// Share of the inventory closed per order when `full_size_on_stop` is set
+    #[serde(default = "default_stop_close_fraction")]
+    pub stop_close_fraction: f64,
     // Volatility detection parameters
     pub volatility_window: usize,
     pub max_volatility_threshold: f64,
@@ -26,6 +42,13 @@ pub struct GptMarketMakerConfig {
     pub momentum_window: usize,
     pub momentum_threshold: f64,
     pub momentum_cooldown_ms: i64,
+    // Order book imbalance weighting
+    #[serde(default)]
+    pub imbalance_weighting: ImbalanceWeighting,
+}
+
+fn default_stop_close_fraction() -> f64 {
+    1.0
 }
 
 impl Default for GptMarketMakerConfig {
@@ -43,16 +66,144 @@ impl Default for GptMarketMakerConfig {
             inventory_reduction_threshold: 0.7,
             aggressive_close_threshold: 0.9,
             min_profit_bps: 5.0,
+            take_profit_ladder: Vec::new(),
+            full_size_on_stop: false,
+            stop_close_fraction: default_stop_close_fraction(),
             volatility_window: 30,
             max_volatility_threshold: 0.0001,
             volatility_cooldown_ms: 5000,
             momentum_window: 10,
             momentum_threshold: 0.0015,
             momentum_cooldown_ms: 3000,
+            imbalance_weighting: ImbalanceWeighting::Quantity,
         }
     }
 }
 
+impl GptMarketMakerConfig {
+    /// Check parameter constraints before a run.
+    ///
+    /// A zero window, for example, would keep the VWAP from ever becoming
+    /// ready and silently disable trading.
+    pub fn validate(&self) -> Result<()> {
+        fn invalid(msg: String) -> Result<()> {
+            Err(TradeError::InvalidTradeParameters(msg))
+        }
+
+        for (name, window) in [
+            ("vwap_window", self.vwap_window),
+            ("volatility_window", self.volatility_window),
+            ("momentum_window", self.momentum_window),
+        ] {
+            if window == 0 {
+                return invalid(format!("{} must be greater than 0", name));
+            }
+        }
+
+        for (name, value) in [
+            ("fix_order_volume", self.fix_order_volume),
+            ("max_inventory", self.max_inventory),
+            ("take_profit_bps", self.take_profit_bps),
+            ("stop_loss_bps", self.stop_loss_bps),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                return invalid(format!("{} must be greater than 0, got {}", name, value));
+            }
+        }
+
+        for (name, value) in [
+            ("limit_order_spread_bps", self.limit_order_spread_bps),
+            ("min_profit_bps", self.min_profit_bps),
+            ("max_volatility_threshold", self.max_volatility_threshold),
+            ("momentum_threshold", self.momentum_threshold),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                return invalid(format!("{} must be non-negative, got {}", name, value));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.obi_threshold) {
+            return invalid(format!(
+                "obi_threshold must be between 0.0 and 1.0, got {}",
+                self.obi_threshold
+            ));
+        }
+
+        for (name, value) in [
+            ("inventory_reduction_threshold", self.inventory_reduction_threshold),
+            ("aggressive_close_threshold", self.aggressive_close_threshold),
+            ("stop_close_fraction", self.stop_close_fraction),
+        ] {
+            if !(value > 0.0 && value <= 1.0) {
+                return invalid(format!("{} must be in (0.0, 1.0], got {}", name, value));
+            }
+        }
+
+        if self.aggressive_close_threshold < self.inventory_reduction_threshold {
+            return invalid(format!(
+                "aggressive_close_threshold ({}) must not be below inventory_reduction_threshold ({})",
+                self.aggressive_close_threshold, self.inventory_reduction_threshold
+            ));
+        }
+
+        if self.min_profit_bps >= self.take_profit_bps {
+            return invalid(format!(
+                "min_profit_bps ({}) must be below take_profit_bps ({})",
+                self.min_profit_bps, self.take_profit_bps
+            ));
+        }
+
+        let mut previous_bps = 0.0;
+        let mut total_fraction = 0.0;
+        for &(bps, fraction) in &self.take_profit_ladder {
+            if bps.is_nan() || bps <= previous_bps {
+                return invalid(format!(
+                    "take_profit_ladder levels must be positive and ascending, got {} after {}",
+                    bps, previous_bps
+                ));
+            }
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                return invalid(format!("take_profit_ladder fraction must be in (0.0, 1.0], got {}", fraction));
+            }
+            previous_bps = bps;
+            total_fraction += fraction;
+        }
+        if total_fraction > 1.0 + 1e-9 {
+            return invalid(format!("take_profit_ladder fractions sum to {}, more than 1.0", total_fraction));
+        }
+
+        if self.max_position_age_ms <= 0 {
+            return invalid(format!(
+                "max_position_age_ms must be greater than 0, got {}",
+                self.max_position_age_ms
+            ));
+        }
+
+        for (name, value) in [
+            ("volatility_cooldown_ms", self.volatility_cooldown_ms),
+            ("momentum_cooldown_ms", self.momentum_cooldown_ms),
+        ] {
+            if value < 0 {
+                return invalid(format!("{} must be non-negative, got {}", name, value));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Which exit rule asked to reduce the position
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CloseKind {
+    TakeProfit,
+    /// Index into `take_profit_ladder`
+    TakeProfitLevel(usize),
+    StopLoss,
+    PositionAge,
+    InventoryReduction,
+    AggressiveClose,
+}
+
 #[derive(Debug, Clone)]
 struct Position {
     quantity: f64,
@@ -87,6 +238,15 @@ pub struct GptMarketMaker {
     last_high_volatility_time: i64,
     momentum_prices: VecDeque<f64>,
     last_strong_momentum_time: i64,
+    /// Take-profit ladder levels already filled for the current position
+    tp_levels_hit: usize,
+    /// Position size the ladder fractions apply to, fixed when the first level triggers
+    tp_ladder_base: f64,
+    /// Ladder level of the outstanding close proposal, settled by `update_position`
+    pending_tp_level: Option<usize>,
+    /// Reason behind the latest `propose_trade` outcome; fixed reasons are borrowed so
+    /// recording one costs no allocation per tick
+    decision: Cow<'static, str>,
 }
 
 impl GptMarketMaker {
@@ -107,6 +267,10 @@ impl GptMarketMaker {
             last_high_volatility_time: 0,
             momentum_prices: VecDeque::with_capacity(momentum_window),
             last_strong_momentum_time: 0,
+            tp_levels_hit: 0,
+            tp_ladder_base: 0.0,
+            pending_tp_level: None,
+            decision: Cow::Borrowed(""),
         }
     }
 
@@ -134,18 +298,7 @@ impl GptMarketMaker {
     }
 
     fn compute_obi(&self, order_book: &OrderBook) -> f64 {
-        if order_book.bids.is_empty() || order_book.asks.is_empty() {
-            return 0.0;
-        }
-
-        let bid_vol: f64 = order_book.bids.iter().take(5).map(|(_, v)| v).sum();
-        let ask_vol: f64 = order_book.asks.iter().take(5).map(|(_, v)| v).sum();
-
-        if bid_vol + ask_vol == 0.0 {
-            return 0.0;
-        }
-
-        (bid_vol - ask_vol) / (bid_vol + ask_vol)
+        order_book.order_book_imbalance_weighted(self.config.imbalance_weighting)
     }
 
     fn calculate_volatility(&self) -> f64 {
@@ -153,22 +306,16 @@ impl GptMarketMaker {
             return 0.0;
         }
 
-        let prices: Vec<f64> = self.price_history.iter().cloned().collect();
-        let mut returns = Vec::new();
-        
-        for i in 1..prices.len() {
-            let ret = (prices[i] - prices[i - 1]) / prices[i - 1];
-            returns.push(ret);
-        }
-
-        if returns.is_empty() {
-            return 0.0;
-        }
+        // Returns are recomputed on the fly rather than collected, as this runs every tick
+        let returns = || self.price_history.iter()
+            .zip(self.price_history.iter().skip(1))
+            .map(|(previous, price)| (price - previous) / previous);
+        let count = (self.price_history.len() - 1) as f64;
 
-        let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-        let variance = returns.iter()
+        let mean_return = returns().sum::<f64>() / count;
+        let variance = returns()
             .map(|r| (r - mean_return).powi(2))
-            .sum::<f64>() / returns.len() as f64;
+            .sum::<f64>() / count;
         
         variance.sqrt()
     }
@@ -178,38 +325,39 @@ impl GptMarketMaker {
             return 0.0;
         }
 
-        let prices: Vec<f64> = self.momentum_prices.iter().cloned().collect();
-        (prices[prices.len() - 1] - prices[0]) / prices[0]
+        let first = self.momentum_prices[0];
+        (self.momentum_prices[self.momentum_prices.len() - 1] - first) / first
     }
 
-    fn check_market_conditions(&mut self, current_time: i64) -> (bool, String) {
+    /// Why opening new positions is paused at `current_time`, if it is
+    fn check_market_conditions(&mut self, current_time: i64) -> Option<String> {
         // Check volatility cooldown
         if current_time - self.last_high_volatility_time < self.config.volatility_cooldown_ms {
             let time_left = (self.config.volatility_cooldown_ms - (current_time - self.last_high_volatility_time)) as f64 / 1000.0;
-            return (false, format!("VOLATILITY_COOLDOWN: {:.1}s remaining", time_left));
+            return Some(format!("VOLATILITY_COOLDOWN: {:.1}s remaining", time_left));
         }
 
         // Check momentum cooldown
         if current_time - self.last_strong_momentum_time < self.config.momentum_cooldown_ms {
             let time_left = (self.config.momentum_cooldown_ms - (current_time - self.last_strong_momentum_time)) as f64 / 1000.0;
-            return (false, format!("MOMENTUM_COOLDOWN: {:.1}s remaining", time_left));
+            return Some(format!("MOMENTUM_COOLDOWN: {:.1}s remaining", time_left));
         }
 
         // Calculate current volatility - only check if we have enough data
         let volatility = self.calculate_volatility();
         if volatility > 0.0 && volatility > self.config.max_volatility_threshold {
             self.last_high_volatility_time = current_time;
-            return (false, format!("HIGH_VOLATILITY: {:.4} > {:.4}", volatility, self.config.max_volatility_threshold));
+            return Some(format!("HIGH_VOLATILITY: {:.4} > {:.4}", volatility, self.config.max_volatility_threshold));
         }
 
         // Calculate current momentum - only check if we have enough data  
         let momentum = self.calculate_momentum();
         if momentum != 0.0 && momentum.abs() > self.config.momentum_threshold {
             self.last_strong_momentum_time = current_time;
-            return (false, format!("STRONG_MOMENTUM: {:.4} > {:.4}", momentum, self.config.momentum_threshold));
+            return Some(format!("STRONG_MOMENTUM: {:.4} > {:.4}", momentum, self.config.momentum_threshold));
         }
 
-        (true, "OK".to_string())
+        None
     }
 
     fn calculate_average_entry_price(&self) -> f64 {
@@ -231,9 +379,25 @@ impl GptMarketMaker {
         }
     }
 
-    fn should_close_position(&self, mid_price: f64, current_time: i64) -> (bool, String) {
+    fn should_close_position(&self, mid_price: f64, current_time: i64) -> Option<(CloseKind, String)> {
         if self.net_inventory == 0.0 {
-            return (false, String::new());
+            return None;
+        }
+
+        // Weight each lot by its share of the lots' total size rather than of the net inventory,
+        // which a dust residual or lots on both sides would turn into exploding weights
+        let lot_quantity: f64 = self.positions.iter().map(|pos| pos.quantity).sum();
+        if lot_quantity < QUANTITY_EPSILON {
+            return None;
+        }
+        let net_side = if self.net_inventory > 0.0 { "Buy" } else { "Sell" };
+        if self.net_inventory.abs() < QUANTITY_EPSILON || self.positions.iter().any(|pos| pos.side != net_side) {
+            log_risk("inventory_inconsistency", &[
+                ("symbol", self.symbol.clone()),
+                ("net_inventory", self.net_inventory.to_string()),
+                ("lot_quantity", lot_quantity.to_string()),
+                ("lots", self.positions.len().to_string()),
+            ]);
         }
 
         let mut total_pnl_bps = 0.0;
@@ -241,7 +405,7 @@ impl GptMarketMaker {
 
         for pos in &self.positions {
             let pnl_bps = pos.get_pnl_bps(mid_price);
-            total_pnl_bps += pnl_bps * (pos.quantity / self.net_inventory.abs());
+            total_pnl_bps += pnl_bps * (pos.quantity / lot_quantity);
 
             let age = pos.get_age_ms(current_time);
             oldest_position_age = oldest_position_age.max(age);
@@ -250,42 +414,61 @@ impl GptMarketMaker {
         let inventory_ratio = self.net_inventory.abs() / self.config.max_inventory;
 
         // Check various closing conditions
-        if total_pnl_bps >= self.config.take_profit_bps {
-            return (true, format!("TAKE_PROFIT: {:.1} bps", total_pnl_bps));
+        if self.config.take_profit_ladder.is_empty() {
+            if total_pnl_bps >= self.config.take_profit_bps {
+                return Some((CloseKind::TakeProfit, format!("TAKE_PROFIT: {:.1} bps", total_pnl_bps)));
+            }
+        } else if let Some(&(level_bps, _)) = self.config.take_profit_ladder.get(self.tp_levels_hit) {
+            if total_pnl_bps >= level_bps {
+                return Some((
+                    CloseKind::TakeProfitLevel(self.tp_levels_hit),
+                    format!("TAKE_PROFIT_L{}: {:.1} bps", self.tp_levels_hit + 1, total_pnl_bps),
+                ));
+            }
         }
 
         if total_pnl_bps <= -self.config.stop_loss_bps {
-            return (true, format!("STOP_LOSS: {:.1} bps", total_pnl_bps));
+            return Some((CloseKind::StopLoss, format!("STOP_LOSS: {:.1} bps", total_pnl_bps)));
         }
 
         if oldest_position_age > self.config.max_position_age_ms {
-            return (true, format!("POSITION_AGE: {:.1}s", oldest_position_age as f64 / 1000.0));
+            return Some((CloseKind::PositionAge, format!("POSITION_AGE: {:.1}s", oldest_position_age as f64 / 1000.0)));
         }
 
         if inventory_ratio >= self.config.inventory_reduction_threshold {
             if total_pnl_bps >= self.config.min_profit_bps {
-                return (true, format!("INVENTORY_REDUCTION: {:.1}% full, {:.1} bps profit", inventory_ratio * 100.0, total_pnl_bps));
+                return Some((CloseKind::InventoryReduction, format!("INVENTORY_REDUCTION: {:.1}% full, {:.1} bps profit", inventory_ratio * 100.0, total_pnl_bps)));
             }
         }
 
         if inventory_ratio >= self.config.aggressive_close_threshold {
             if total_pnl_bps >= -self.config.min_profit_bps {
-                return (true, format!("AGGRESSIVE_CLOSE: {:.1}% full, {:.1} bps", inventory_ratio * 100.0, total_pnl_bps));
+                return Some((CloseKind::AggressiveClose, format!("AGGRESSIVE_CLOSE: {:.1}% full, {:.1} bps", inventory_ratio * 100.0, total_pnl_bps)));
             }
         }
 
-        (false, String::new())
+        None
     }
 
-    pub fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade> {
-        if order_book.bids.is_empty() || order_book.asks.is_empty() {
-            return None;
+    fn log_open(&self, trade: &Trade, obi: f64, vwap: f64) {
+        if !log::log_enabled!(Level::Debug) {
+            return;
         }
+        log_event(Level::Debug, "open_order", &[
+            ("symbol", trade.symbol.clone()),
+            ("side", trade.side.clone()),
+            ("qty", trade.quantity.to_string()),
+            ("price", format!("{:.4}", trade.price)),
+            ("obi", format!("{:.3}", obi)),
+            ("vwap", format!("{:.4}", vwap)),
+            ("inventory", self.net_inventory.to_string()),
+        ]);
+    }
 
-        let best_bid = order_book.bids[0].0;
-        let bid_vol = order_book.bids[0].1;
-        let best_ask = order_book.asks[0].0;
-        let ask_vol = order_book.asks[0].1;
+    pub fn propose_trade(&mut self, order_book: &OrderBook) -> Option<Trade> {
+        self.decision = Cow::Borrowed("ONE_SIDED_BOOK");
+        let (best_bid, bid_vol) = order_book.best_bid()?;
+        let (best_ask, ask_vol) = order_book.best_ask()?;
 
         let mid_price = (best_bid + best_ask) / 2.0;
         let current_time = order_book.current_time;
@@ -304,17 +487,18 @@ impl GptMarketMaker {
         // Update VWAP
         let vwap = self.update_vwap(mid_price, bid_vol + ask_vol);
         if vwap.is_none() {
+            self.decision = Cow::Borrowed("VWAP_WARMUP");
             return None;
         }
         let vwap = vwap.unwrap();
 
         // Check market conditions
-        let (can_trade, market_condition) = self.check_market_conditions(current_time);
+        let pause_reason = self.check_market_conditions(current_time);
 
         // Check if we should close positions
-        let (should_close, close_reason) = self.should_close_position(mid_price, current_time);
+        let close_signal = self.should_close_position(mid_price, current_time);
 
-        if should_close && self.net_inventory != 0.0 {
+        if let Some((close_kind, close_reason)) = close_signal {
             let (side, limit_price) = if self.net_inventory > 0.0 {
                 // We're long, so sell to close
                 let price = if self.config.use_limit_orders {
@@ -333,7 +517,21 @@ impl GptMarketMaker {
                 ("Buy", price)
             };
 
-            let quantity = self.config.fix_order_volume.min(self.net_inventory.abs());
+            let quantity = match close_kind {
+                CloseKind::TakeProfitLevel(level) => {
+                    if level == 0 {
+                        self.tp_ladder_base = self.net_inventory.abs();
+                    }
+                    self.pending_tp_level = Some(level);
+                    let (_, fraction) = self.config.take_profit_ladder[level];
+                    (self.tp_ladder_base * fraction).min(self.net_inventory.abs())
+                }
+                CloseKind::StopLoss | CloseKind::AggressiveClose if self.config.full_size_on_stop => {
+                    let inventory = self.net_inventory.abs();
+                    (inventory * self.config.stop_close_fraction).max(self.config.fix_order_volume).min(inventory)
+                }
+                _ => self.config.fix_order_volume.min(self.net_inventory.abs()),
+            };
 
             let trade = Trade::new(
                 current_time,
@@ -341,17 +539,21 @@ impl GptMarketMaker {
                 side.to_string(),
                 limit_price,
                 quantity,
-            );
+            )
+            .with_reduce_only(true);
 
-            info!("GPT Maker CLOSING: {} {} @ {:.4} (reason: {}, inventory: {})",
-                side, quantity, limit_price, close_reason, self.net_inventory);
+            log_close(&trade, &close_reason, self.net_inventory);
+            self.decision = Cow::Owned(close_reason);
 
             return Some(trade);
         }
 
         // Check if we can open new positions
-        if !can_trade {
-            info!("GPT Maker PAUSED: {}", market_condition);
+        if let Some(market_condition) = pause_reason {
+            if log::log_enabled!(Level::Debug) {
+                log_event(Level::Debug, "paused", &[("symbol", order_book.symbol.clone()), ("condition", market_condition.clone())]);
+            }
+            self.decision = Cow::Owned(market_condition);
             return None;
         }
 
@@ -378,8 +580,8 @@ impl GptMarketMaker {
                 self.config.fix_order_volume,
             );
 
-            info!("GPT Maker OPENING: Buy {} @ {:.4} (OBI: {:.3}, VWAP: {:.4}, inventory: {})",
-                self.config.fix_order_volume, limit_price, obi, vwap, self.net_inventory);
+            self.log_open(&trade, obi, vwap);
+            self.decision = Cow::Borrowed("OPEN_BUY");
 
             Some(trade)
         } else if obi < -adjusted_obi_threshold && 
@@ -400,16 +602,18 @@ impl GptMarketMaker {
                 self.config.fix_order_volume,
             );
 
-            info!("GPT Maker OPENING: Sell {} @ {:.4} (OBI: {:.3}, VWAP: {:.4}, inventory: {})",
-                self.config.fix_order_volume, limit_price, obi, vwap, self.net_inventory);
+            self.log_open(&trade, obi, vwap);
+            self.decision = Cow::Borrowed("OPEN_SELL");
 
             Some(trade)
         } else {
+            self.decision = Cow::Borrowed("NO_SIGNAL");
             None
         }
     }
 
     pub fn update_position(&mut self, trade: &Trade, filled: bool) {
+        let pending_tp_level = self.pending_tp_level.take();
         if !filled {
             return;
         }
@@ -463,6 +667,15 @@ impl GptMarketMaker {
 
         // Recalculate average entry price
         self.avg_entry_price = self.calculate_average_entry_price();
+
+        // A filled ladder close moves on to the next level; a flat book restarts the ladder
+        if let Some(level) = pending_tp_level.filter(|_| is_closing) {
+            self.tp_levels_hit = level + 1;
+        }
+        if self.net_inventory.abs() < 1e-12 {
+            self.tp_levels_hit = 0;
+            self.tp_ladder_base = 0.0;
+        }
     }
 }
 
@@ -475,6 +688,10 @@ impl Strategy for GptMarketMaker {
         self.propose_trade(order_book)
     }
 
+    fn decision_reason(&self) -> Option<&str> {
+        Some(self.decision.as_ref())
+    }
+
     fn update_position(&mut self, trade: &Trade, filled: bool) {
         self.update_position(trade, filled)
     }
@@ -495,5 +712,218 @@ impl Strategy for GptMarketMaker {
         self.last_high_volatility_time = 0;
         self.momentum_prices.clear();
         self.last_strong_momentum_time = 0;
+        self.tp_levels_hit = 0;
+        self.tp_ladder_base = 0.0;
+        self.pending_tp_level = None;
+        self.decision = Cow::Borrowed("");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_invalid(config: GptMarketMakerConfig, expected: &str) {
+        match config.validate() {
+            Err(TradeError::InvalidTradeParameters(msg)) => {
+                assert!(msg.contains(expected), "unexpected message: {}", msg)
+            }
+            other => panic!("expected InvalidTradeParameters, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_imbalance_weighting_modes() {
+        // More size on the bid, but the ask levels carry more notional
+        let book = OrderBook::new(
+            "BTCUSDT".to_string(),
+            vec![(100.0, 3.0)],
+            vec![(200.0, 2.0)],
+            1000,
+        );
+
+        let quantity = GptMarketMaker::new("BTCUSDT".to_string(), GptMarketMakerConfig::default());
+        let notional = GptMarketMaker::new(
+            "BTCUSDT".to_string(),
+            GptMarketMakerConfig { imbalance_weighting: ImbalanceWeighting::Notional, ..Default::default() },
+        );
+
+        assert!((quantity.compute_obi(&book) - 0.2).abs() < 1e-12);
+        assert!((notional.compute_obi(&book) - (-100.0 / 700.0)).abs() < 1e-12);
+        assert_eq!(book.order_book_imbalance(), quantity.compute_obi(&book));
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(GptMarketMakerConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_zero_windows_rejected() {
+        let config = GptMarketMakerConfig { vwap_window: 0, ..Default::default() };
+        assert_invalid(config, "vwap_window must be greater than 0");
+
+        let config = GptMarketMakerConfig { volatility_window: 0, ..Default::default() };
+        assert_invalid(config, "volatility_window must be greater than 0");
+
+        let config = GptMarketMakerConfig { momentum_window: 0, ..Default::default() };
+        assert_invalid(config, "momentum_window must be greater than 0");
+    }
+
+    #[test]
+    fn test_non_positive_sizes_and_targets_rejected() {
+        let config = GptMarketMakerConfig { fix_order_volume: 0.0, ..Default::default() };
+        assert_invalid(config, "fix_order_volume must be greater than 0");
+
+        let config = GptMarketMakerConfig { max_inventory: -1.0, ..Default::default() };
+        assert_invalid(config, "max_inventory must be greater than 0");
+
+        let config = GptMarketMakerConfig { take_profit_bps: 0.0, ..Default::default() };
+        assert_invalid(config, "take_profit_bps must be greater than 0");
+
+        let config = GptMarketMakerConfig { stop_loss_bps: f64::NAN, ..Default::default() };
+        assert_invalid(config, "stop_loss_bps must be greater than 0");
+    }
+
+    #[test]
+    fn test_negative_bps_and_thresholds_rejected() {
+        let config = GptMarketMakerConfig { limit_order_spread_bps: -1.0, ..Default::default() };
+        assert_invalid(config, "limit_order_spread_bps must be non-negative");
+
+        let config = GptMarketMakerConfig { max_volatility_threshold: -0.1, ..Default::default() };
+        assert_invalid(config, "max_volatility_threshold must be non-negative");
+
+        let config = GptMarketMakerConfig { momentum_threshold: -0.1, ..Default::default() };
+        assert_invalid(config, "momentum_threshold must be non-negative");
+    }
+
+    #[test]
+    fn test_threshold_ranges_rejected() {
+        let config = GptMarketMakerConfig { obi_threshold: 1.5, ..Default::default() };
+        assert_invalid(config, "obi_threshold must be between 0.0 and 1.0");
+
+        let config = GptMarketMakerConfig { inventory_reduction_threshold: 0.0, ..Default::default() };
+        assert_invalid(config, "inventory_reduction_threshold must be in (0.0, 1.0]");
+
+        let config = GptMarketMakerConfig { aggressive_close_threshold: 1.2, ..Default::default() };
+        assert_invalid(config, "aggressive_close_threshold must be in (0.0, 1.0]");
+    }
+
+    #[test]
+    fn test_inconsistent_combinations_rejected() {
+        let config = GptMarketMakerConfig {
+            inventory_reduction_threshold: 0.9,
+            aggressive_close_threshold: 0.7,
+            ..Default::default()
+        };
+        assert_invalid(config, "must not be below inventory_reduction_threshold");
+
+        let config = GptMarketMakerConfig {
+            min_profit_bps: 25.0,
+            take_profit_bps: 20.0,
+            ..Default::default()
+        };
+        assert_invalid(config, "min_profit_bps (25) must be below take_profit_bps (20)");
+    }
+
+    #[test]
+    fn test_non_positive_durations_rejected() {
+        let config = GptMarketMakerConfig { max_position_age_ms: 0, ..Default::default() };
+        assert_invalid(config, "max_position_age_ms must be greater than 0");
+
+        let config = GptMarketMakerConfig { volatility_cooldown_ms: -1, ..Default::default() };
+        assert_invalid(config, "volatility_cooldown_ms must be non-negative");
+
+        let config = GptMarketMakerConfig { momentum_cooldown_ms: -1, ..Default::default() };
+        assert_invalid(config, "momentum_cooldown_ms must be non-negative");
+    }
+
+    #[test]
+    fn test_take_profit_ladder_scales_out() {
+        let config = GptMarketMakerConfig {
+            vwap_window: 1,
+            use_limit_orders: false,
+            take_profit_ladder: vec![(10.0, 0.5), (20.0, 0.25)],
+            ..Default::default()
+        };
+        let mut maker = GptMarketMaker::new("BTCUSDT".to_string(), config);
+        let mut entry = Trade::new(0, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 1.0);
+        entry.status = "filled".to_string();
+        maker.update_position(&entry, true);
+
+        let book = |mid: f64, time: i64| {
+            OrderBook::new("BTCUSDT".to_string(), vec![(mid - 0.01, 1.0)], vec![(mid + 0.01, 1.0)], time)
+        };
+        let fill = |maker: &mut GptMarketMaker, order_book: OrderBook| {
+            let trade = maker.propose_trade(&order_book)?;
+            maker.update_position(&trade, true);
+            Some((trade.side, trade.quantity))
+        };
+
+        // 11 bps: first level sells half of the 1.0 position
+        assert_eq!(fill(&mut maker, book(100.11, 1000)), Some(("Sell".to_string(), 0.5)));
+        assert_eq!(fill(&mut maker, book(100.12, 2000)), None);
+        // 21 bps: second level sells a quarter of the original position
+        assert_eq!(fill(&mut maker, book(100.21, 3000)), Some(("Sell".to_string(), 0.25)));
+        // Ladder exhausted: the runner stays open
+        assert_eq!(fill(&mut maker, book(100.40, 4000)), None);
+        assert!((maker.net_inventory - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_take_profit_ladder_validation() {
+        let descending = GptMarketMakerConfig { take_profit_ladder: vec![(20.0, 0.5), (10.0, 0.5)], ..Default::default() };
+        assert_invalid(descending, "positive and ascending");
+        let oversized = GptMarketMakerConfig { take_profit_ladder: vec![(10.0, 0.75), (20.0, 0.5)], ..Default::default() };
+        assert_invalid(oversized, "sum to 1.25");
+    }
+
+    #[test]
+    fn test_full_size_on_stop_closes_whole_position() {
+        let stop_close = |full_size_on_stop: bool| {
+            let config = GptMarketMakerConfig {
+                vwap_window: 1,
+                use_limit_orders: false,
+                full_size_on_stop,
+                ..Default::default()
+            };
+            let mut maker = GptMarketMaker::new("BTCUSDT".to_string(), config);
+            let mut entry = Trade::new(0, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 2.0);
+            entry.status = "filled".to_string();
+            maker.update_position(&entry, true);
+
+            // 60 bps below entry trips the 50 bps stop
+            let book = OrderBook::new("BTCUSDT".to_string(), vec![(99.39, 1.0)], vec![(99.41, 1.0)], 1000);
+            let trade = maker.propose_trade(&book).unwrap();
+            assert!(maker.decision_reason().unwrap().starts_with("STOP_LOSS"));
+            (trade.side, trade.quantity, trade.reduce_only)
+        };
+
+        assert_eq!(stop_close(false), ("Sell".to_string(), 0.005, true));
+        assert_eq!(stop_close(true), ("Sell".to_string(), 2.0, true));
+    }
+
+    #[test]
+    fn test_close_decision_with_dust_net_inventory() {
+        let mut maker = GptMarketMaker::new("BTCUSDT".to_string(), GptMarketMakerConfig::default());
+        let lot = |side: &str, quantity: f64, entry_price: f64| Position {
+            quantity,
+            entry_price,
+            entry_time: 0,
+            side: side.to_string(),
+        };
+        // Lots left on both sides net out to a float residual
+        maker.positions = vec![lot("Buy", 0.01, 100.0), lot("Sell", 0.01, 100.1)];
+        maker.net_inventory = 1e-15;
+
+        // Weighted by lot size the lots average about 5 bps, short of the 20 bps take-profit;
+        // weights of 0.01 / 1e-15 would have blown it up into a take-profit
+        assert_eq!(maker.should_close_position(100.05, 1_000).map(|(kind, _)| kind), None);
+
+        // A real loss on the lots still stops out
+        maker.positions = vec![lot("Buy", 0.01, 100.0)];
+        let (kind, reason) = maker.should_close_position(99.0, 1_000).unwrap();
+        assert_eq!(kind, CloseKind::StopLoss);
+        assert_eq!(reason, "STOP_LOSS: -100.0 bps");
+    }
+}
@@ -1,7 +1,9 @@
 pub mod base;
 pub mod gpt_market_maker;
 pub mod args;
+pub mod benchmark;
 
 pub use base::Strategy;
 pub use gpt_market_maker::{GptMarketMaker, GptMarketMakerConfig};
-pub use args::{StrategyArgs, GptMarketMakerArgs};
\ No newline at end of file
+pub use args::{StrategyArgs, GptMarketMakerArgs, BuyAndHoldArgs};
+pub use benchmark::{NoopStrategy, BuyAndHoldStrategy};
\ No newline at end of file
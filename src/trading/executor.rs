@@ -1,21 +1,155 @@
-use crate::core::{Trade, TradeExecutor, ExecutionStats, Result};
-use log::info;
+use crate::core::{Liquidity, OrderBook, Trade, TradeExecutor, ExecutionStats, Result};
+use std::collections::HashMap;
+use crate::trading::instrument::InstrumentSpecRegistry;
+use crate::utils::MidPriceFilterConfig;
+use log::Level;
+use crate::utils::logging::{log_event, log_fill, log_risk};
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 
 pub trait TradeEmitter {
     fn execute_trade(&mut self, trade: Option<Trade>) -> Option<Trade>;
+
+    /// Show the emitter the book the next orders execute against
+    fn observe_book(&mut self, _order_book: &OrderBook) {}
+
+    /// Execute `trade` against `order_book`. Emitters that model depth may fill only part of
+    /// the order, returning the executed quantity with status `"partially_filled"`.
+    fn execute_trade_against_book(&mut self, trade: Option<Trade>, order_book: &OrderBook) -> Option<Trade> {
+        self.observe_book(order_book);
+        self.execute_trade(trade)
+    }
+}
+
+/// Quantity resting at the levels `trade` crosses: asks at or below a buy's price,
+/// bids at or above a sell's
+fn crossing_depth(order_book: &OrderBook, trade: &Trade) -> f64 {
+    if trade.side == "Buy" {
+        order_book.asks.iter().filter(|(price, _)| *price <= trade.price).map(|(_, quantity)| quantity).sum()
+    } else {
+        order_book.bids.iter().filter(|(price, _)| *price >= trade.price).map(|(_, quantity)| quantity).sum()
+    }
+}
+
+/// How the engine turns a strategy's proposed order into a quantity
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum SizingMode {
+    /// Use the quantity proposed by the strategy as-is
+    #[default]
+    AbsoluteQuantity,
+    /// Notional of this percentage of current equity (e.g. 2.0 = 2%)
+    PercentOfEquity(f64),
+    /// This percentage of the quantity resting at the opposite best level
+    PercentOfTopOfBook(f64),
+}
+
+/// What happens to strategy state when a multi-file run crosses into the next file
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum CrossFileState {
+    /// Keep strategy state and inventory, treating the files as one continuous capture
+    #[default]
+    Preserve,
+    /// Reset the strategy at each file boundary; filled inventory stays in the trade history
+    Reset,
+}
+
+/// Share of open position value held as margin, used for the dashboard's capital metrics
+pub const DEFAULT_MARGIN_RATE: f64 = 0.05;
+
+fn default_margin_rate() -> f64 {
+    DEFAULT_MARGIN_RATE
+}
+
+fn default_initial_equity() -> f64 {
+    10_000.0
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BacktestConfig {
     pub fill_rate: f64,
     pub slippage_bps: f64,
+    /// Slippage applied to buys; falls back to `slippage_bps` when unset
+    #[serde(default)]
+    pub buy_slippage_bps: Option<f64>,
+    /// Slippage applied to sells; falls back to `slippage_bps` when unset
+    #[serde(default)]
+    pub sell_slippage_bps: Option<f64>,
     pub rejection_rate: f64,
+    /// Margin requirement as a fraction of open position value (see `DEFAULT_MARGIN_RATE`).
+    /// The single source for `TradeDashboard::from_config` capital metrics.
+    #[serde(default = "default_margin_rate")]
     pub margin_rate: f64,
+    /// Minimum book spread as a fraction of mid (e.g. 0.0005 = 5 bps) required to trade.
+    /// Opening orders proposed on tighter books are discarded; reduce-only orders and closes of
+    /// the current position still go through. 0.0 disables the gate.
     pub min_spread_pct: f64,
-    pub spread_percent: f64,
+    /// Cap on order quantity after sizing (0.0 = no cap). Order size itself comes from
+    /// the strategy, e.g. `GptMarketMakerConfig::fix_order_volume`, unless `sizing_mode` overrides it.
+    #[serde(default)]
     pub max_order_volume: f64,
+    /// Number of levels per side kept for books stored in `TradeState` (0 = full depth)
+    #[serde(default)]
+    pub stored_book_depth: usize,
+    /// Store every book in `TradeState`, not only those a trade was made on, for tick-level
+    /// marking and markouts
+    #[serde(default)]
+    pub store_all_books: bool,
+    /// Open Parquet files with `ParquetDataSource::open_lenient`, salvaging complete row
+    /// groups from captures that were cut short
+    #[serde(default)]
+    pub lenient_parquet: bool,
+    /// Per-symbol contract size and order constraints
+    #[serde(default)]
+    pub instruments: InstrumentSpecRegistry,
+    /// Drop books whose mid is an outlier against recent mids (None = no filtering)
+    #[serde(default)]
+    pub mid_price_filter: Option<MidPriceFilterConfig>,
+    /// Resample books to a fixed grid of this many milliseconds (0 = raw updates)
+    #[serde(default)]
+    pub resample_interval_ms: i64,
+    /// How order quantities are resolved at execution time
+    #[serde(default)]
+    pub sizing_mode: SizingMode,
+    /// Starting account balance used for `SizingMode::PercentOfEquity`
+    #[serde(default = "default_initial_equity")]
+    pub initial_equity: f64,
+    /// Check that proposed trades are never stamped after the book that produced them.
+    /// Violations panic in debug builds and are discarded with a warning in release.
+    #[serde(default)]
+    pub strict_causality: bool,
+    /// Strategy state handling at file boundaries in multi-file runs
+    #[serde(default)]
+    pub cross_file_state: CrossFileState,
+    /// Rest strategy orders as maker quotes that are cancel-replaced as the book moves,
+    /// instead of executing them immediately
+    #[serde(default)]
+    pub requote_on_move: bool,
+    /// Collect a `DecisionLog` of per-tick strategy outcomes in the returned `TradeState`
+    #[serde(default)]
+    pub record_decisions: bool,
+    /// Seed for the fill and rejection draws (None = fresh entropy per executor)
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Force-close a position once it has been open this many milliseconds of book time (0 = no limit)
+    #[serde(default)]
+    pub max_hold_ms: i64,
+    /// Skip books whose `update_id` doesn't advance past the last one seen in the same file
+    #[serde(default)]
+    pub dedup_update_ids: bool,
+    /// Execute a strategy's buy and sell that cross each other on the same book instead of
+    /// suppressing the self-match. Self-crosses are reported either way.
+    #[serde(default)]
+    pub allow_self_cross: bool,
+    /// Fee on fills that rested on the book, in basis points of notional (negative = rebate)
+    #[serde(default)]
+    pub maker_fee_bps: f64,
+    /// Fee on fills that crossed the spread, in basis points of notional
+    #[serde(default)]
+    pub taker_fee_bps: f64,
+    /// Fill orders that cross the spread only up to the quantity resting at the levels they
+    /// reach, reporting the rest as a partial fill
+    #[serde(default)]
+    pub fill_from_depth: bool,
 }
 
 impl Default for BacktestConfig {
@@ -23,39 +157,136 @@ impl Default for BacktestConfig {
         Self {
             fill_rate: 0.95,
             slippage_bps: 0.5,
+            buy_slippage_bps: None,
+            sell_slippage_bps: None,
             rejection_rate: 0.02,
-            margin_rate: 0.1,
-            min_spread_pct: 0.1,
-            spread_percent: 0.10,
+            margin_rate: DEFAULT_MARGIN_RATE,
+            min_spread_pct: 0.0,
             max_order_volume: 0.0,
+            stored_book_depth: 0,
+            store_all_books: false,
+            lenient_parquet: false,
+            instruments: InstrumentSpecRegistry::default(),
+            mid_price_filter: None,
+            resample_interval_ms: 0,
+            sizing_mode: SizingMode::AbsoluteQuantity,
+            initial_equity: default_initial_equity(),
+            strict_causality: false,
+            cross_file_state: CrossFileState::Preserve,
+            requote_on_move: false,
+            record_decisions: false,
+            seed: None,
+            max_hold_ms: 0,
+            dedup_update_ids: false,
+            allow_self_cross: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            fill_from_depth: false,
         }
     }
 }
 
+impl BacktestConfig {
+    /// Slippage in basis points for the given order side
+    pub fn slippage_bps_for(&self, side: &str) -> f64 {
+        let side_specific = if side == "Buy" { self.buy_slippage_bps } else { self.sell_slippage_bps };
+        side_specific.unwrap_or(self.slippage_bps)
+    }
+
+    /// Exchange fee in basis points for a fill with the given liquidity
+    pub fn fee_bps_for(&self, liquidity: Liquidity) -> f64 {
+        match liquidity {
+            Liquidity::Maker => self.maker_fee_bps,
+            Liquidity::Taker => self.taker_fee_bps,
+        }
+    }
+
+    /// Tag a fill with its liquidity and charge the matching fee on its notional
+    pub fn charge_fee(&self, trade: &mut Trade, liquidity: Liquidity) {
+        let notional = self.instruments.notional(&trade.symbol, trade.price, trade.quantity);
+        trade.liquidity = Some(liquidity);
+        trade.fee = notional * self.fee_bps_for(liquidity) / 10_000.0;
+    }
+
+    /// Snap an order onto its instrument's tick and lot grid. Returns false, logging why, when
+    /// it rounds to zero lots or falls below the instrument's minimum notional.
+    pub fn snap_to_grid(&self, trade: &mut Trade) -> bool {
+        let spec = self.instruments.get(&trade.symbol);
+        trade.price = spec.round_price(trade.price, &trade.side);
+        trade.quantity = spec.round_quantity(trade.quantity);
+        if spec.lot_size > 0.0 && trade.quantity <= 0.0 {
+            log_risk("lot_size_reject", &[
+                ("symbol", trade.symbol.clone()),
+                ("lot_size", spec.lot_size.to_string()),
+            ]);
+            return false;
+        }
+        
+        // Orders below the instrument's minimum notional never reach the book
+        if self.instruments.notional(&trade.symbol, trade.price, trade.quantity) < spec.min_notional {
+            log_risk("min_notional_reject", &[
+                ("symbol", trade.symbol.clone()),
+                ("qty", trade.quantity.to_string()),
+                ("price", trade.price.to_string()),
+                ("min_notional", spec.min_notional.to_string()),
+            ]);
+            return false;
+        }
+        true
+    }
+}
+
 pub struct BacktestTradeEmitter {
     config: BacktestConfig,
     rng: StdRng,
     stats: ExecutionStats,
+    /// Best bid and ask of the last book observed per symbol
+    touch: HashMap<String, (Option<f64>, Option<f64>)>,
 }
 
 impl BacktestTradeEmitter {
     pub fn new(config: BacktestConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
         Self {
             config,
-            rng: StdRng::from_entropy(),
+            rng,
             stats: ExecutionStats::default(),
+            touch: HashMap::new(),
         }
     }
-}
 
-impl TradeEmitter for BacktestTradeEmitter {
-    fn execute_trade(&mut self, trade: Option<Trade>) -> Option<Trade> {
+    /// Limit orders priced inside the last observed spread rest on the book and are makers;
+    /// orders reaching the opposite touch, or placed without a book, take liquidity
+    fn liquidity_of(&self, trade: &Trade) -> Liquidity {
+        let Some((best_bid, best_ask)) = self.touch.get(&trade.symbol) else { return Liquidity::Taker };
+        let crosses = if trade.side == "Buy" {
+            best_ask.is_some_and(|ask| trade.price >= ask)
+        } else {
+            best_bid.is_some_and(|bid| trade.price <= bid)
+        };
+        if crosses { Liquidity::Taker } else { Liquidity::Maker }
+    }
+
+    /// Execute an order, capping a crossing order at the depth of `order_book` when
+    /// `fill_from_depth` is on
+    fn execute(&mut self, trade: Option<Trade>, order_book: Option<&OrderBook>) -> Option<Trade> {
         if let Some(mut trade) = trade {
             self.stats.total_trades += 1;
             let random_value: f64 = self.rng.gen();
             
+            let spec = self.config.instruments.get(&trade.symbol);
+            if !self.config.snap_to_grid(&mut trade) {
+                trade.status = "rejected".to_string();
+                self.stats.rejected_trades += 1;
+                return Some(trade);
+            }
+            
             // Check for rejection
             if random_value < self.config.rejection_rate {
+                log_event(Level::Debug, "reject", &[("symbol", trade.symbol.clone()), ("id", trade.id.clone())]);
                 trade.status = "rejected".to_string();
                 self.stats.rejected_trades += 1;
                 return Some(trade);
@@ -63,8 +294,24 @@ impl TradeEmitter for BacktestTradeEmitter {
             
             // Check for fill
             if random_value < self.config.fill_rate {
+                let liquidity = self.liquidity_of(&trade);
+                
+                // Take no more than the crossed levels hold
+                let mut partial = false;
+                if let (true, Liquidity::Taker, Some(order_book)) = (self.config.fill_from_depth, liquidity, order_book) {
+                    let available = spec.round_quantity(crossing_depth(order_book, &trade));
+                    if available <= 0.0 {
+                        trade.status = "unfilled".to_string();
+                        return Some(trade);
+                    }
+                    if available < trade.quantity {
+                        trade.quantity = available;
+                        partial = true;
+                    }
+                }
+                
                 // Apply slippage
-                let slippage_factor = 1.0 + (self.config.slippage_bps / 10000.0);
+                let slippage_factor = 1.0 + (self.config.slippage_bps_for(&trade.side) / 10000.0);
                 
                 let original_price = trade.price;
                 if trade.side == "Buy" {
@@ -76,10 +323,21 @@ impl TradeEmitter for BacktestTradeEmitter {
                 let slippage = (trade.price - original_price).abs();
                 self.stats.total_slippage += slippage;
                 
-                trade.status = "filled".to_string();
-                self.stats.filled_trades += 1;
-                info!("Trade executed: {} {} @ {} - Status: {}", 
-                    trade.side, trade.quantity, trade.price, trade.status);
+                self.config.charge_fee(&mut trade, liquidity);
+                self.stats.total_fees += trade.fee;
+                match liquidity {
+                    Liquidity::Maker => self.stats.maker_fills += 1,
+                    Liquidity::Taker => self.stats.taker_fills += 1,
+                }
+                
+                if partial {
+                    trade.status = "partially_filled".to_string();
+                    self.stats.partial_fills += 1;
+                } else {
+                    trade.status = "filled".to_string();
+                    self.stats.filled_trades += 1;
+                }
+                log_fill(&trade);
             } else {
                 trade.status = "unfilled".to_string();
             }
@@ -91,6 +349,22 @@ impl TradeEmitter for BacktestTradeEmitter {
     }
 }
 
+impl TradeEmitter for BacktestTradeEmitter {
+    fn execute_trade(&mut self, trade: Option<Trade>) -> Option<Trade> {
+        self.execute(trade, None)
+    }
+
+    fn execute_trade_against_book(&mut self, trade: Option<Trade>, order_book: &OrderBook) -> Option<Trade> {
+        self.observe_book(order_book);
+        self.execute(trade, Some(order_book))
+    }
+
+    fn observe_book(&mut self, order_book: &OrderBook) {
+        let touch = (order_book.best_bid().map(|(price, _)| price), order_book.best_ask().map(|(price, _)| price));
+        self.touch.insert(order_book.symbol.clone(), touch);
+    }
+}
+
 impl TradeExecutor for BacktestTradeEmitter {
     fn execute_trade(&mut self, trade: Trade) -> Result<Trade> {
         Ok(TradeEmitter::execute_trade(self, Some(trade)).unwrap())
@@ -99,4 +373,147 @@ impl TradeExecutor for BacktestTradeEmitter {
     fn get_stats(&self) -> ExecutionStats {
         self.stats.clone()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn always_fill(buy_slippage_bps: Option<f64>, sell_slippage_bps: Option<f64>) -> BacktestConfig {
+        BacktestConfig {
+            fill_rate: 1.0,
+            rejection_rate: 0.0,
+            slippage_bps: 1.0,
+            buy_slippage_bps,
+            sell_slippage_bps,
+            ..Default::default()
+        }
+    }
+
+    fn execute(emitter: &mut BacktestTradeEmitter, side: &str) -> Trade {
+        let trade = Trade::new(1000, "BTCUSDT".to_string(), side.to_string(), 100.0, 1.0);
+        TradeEmitter::execute_trade(emitter, Some(trade)).unwrap()
+    }
+
+    #[test]
+    fn test_asymmetric_slippage() {
+        let mut emitter = BacktestTradeEmitter::new(always_fill(Some(10.0), Some(2.0)));
+
+        let buy = execute(&mut emitter, "Buy");
+        let sell = execute(&mut emitter, "Sell");
+
+        assert!((buy.price - 100.0 * 1.001).abs() < 1e-9);
+        assert!((sell.price - 100.0 / 1.0002).abs() < 1e-9);
+        assert!((buy.price - 100.0).abs() > (100.0 - sell.price).abs());
+    }
+
+    #[test]
+    fn test_below_min_notional_rejected() {
+        use crate::trading::instrument::InstrumentSpec;
+
+        let mut config = always_fill(None, None);
+        config.instruments.insert("BTCUSDT", InstrumentSpec { min_notional: 150.0, ..Default::default() });
+        let mut emitter = BacktestTradeEmitter::new(config);
+
+        // 1.0 @ 100 is 100 notional, below the 150 minimum
+        let small = execute(&mut emitter, "Buy");
+        assert_eq!(small.status, "rejected");
+
+        let trade = Trade::new(1000, "BTCUSDT".to_string(), "Buy".to_string(), 100.0, 2.0);
+        let large = TradeEmitter::execute_trade(&mut emitter, Some(trade)).unwrap();
+        assert_eq!(large.status, "filled");
+    }
+
+    #[test]
+    fn test_instrument_file_rounds_orders_to_grid() {
+        use crate::trading::instrument::InstrumentSpecRegistry;
+        use crate::utils::test_dir::TestDir;
+
+        let dir = TestDir::new("instruments");
+        let path = dir.join("instruments.csv");
+        std::fs::write(&path, "symbol,tick_size,lot_size,min_notional\nBTCUSDT,0.1,0.001,5\nETHUSDT,0.01,,\n").unwrap();
+        let instruments = InstrumentSpecRegistry::from_file(&path).unwrap();
+        assert_eq!(instruments.get("ETHUSDT").lot_size, 0.0);
+        assert!(!instruments.contains("SOLUSDT"));
+
+        let mut config = always_fill(Some(0.0), Some(0.0));
+        config.instruments = instruments;
+        let mut emitter = BacktestTradeEmitter::new(config);
+        let mut order = |side: &str, price: f64, quantity: f64| {
+            let trade = Trade::new(1000, "BTCUSDT".to_string(), side.to_string(), price, quantity);
+            TradeEmitter::execute_trade(&mut emitter, Some(trade)).unwrap()
+        };
+
+        // Buys round down and sells up to the 0.1 tick; quantities down to the 0.001 lot
+        let buy = order("Buy", 100.07, 0.12345);
+        assert!((buy.price - 100.0).abs() < 1e-9 && (buy.quantity - 0.123).abs() < 1e-12);
+        let sell = order("Sell", 100.01, 0.1);
+        assert!((sell.price - 100.1).abs() < 1e-9 && (sell.quantity - 0.1).abs() < 1e-12);
+        assert_eq!(order("Buy", 100.0, 0.0005).status, "rejected");
+    }
+
+    #[test]
+    fn test_side_slippage_defaults_to_symmetric() {
+        let mut emitter = BacktestTradeEmitter::new(always_fill(None, None));
+
+        let buy = execute(&mut emitter, "Buy");
+        let sell = execute(&mut emitter, "Sell");
+
+        assert!((buy.price - 100.0 * 1.0001).abs() < 1e-9);
+        assert!((sell.price - 100.0 / 1.0001).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_maker_and_taker_fills_pay_their_fee() {
+        let mut config = always_fill(Some(0.0), Some(0.0));
+        config.maker_fee_bps = -1.0;
+        config.taker_fee_bps = 5.0;
+        let mut emitter = BacktestTradeEmitter::new(config);
+        let order = |emitter: &mut BacktestTradeEmitter, side: &str, price: f64| {
+            let trade = Trade::new(1000, "BTCUSDT".to_string(), side.to_string(), price, 1.0);
+            TradeEmitter::execute_trade(emitter, Some(trade)).unwrap()
+        };
+
+        // Without a book there is nothing to rest on
+        let blind = order(&mut emitter, "Buy", 100.0);
+        assert_eq!(blind.liquidity, Some(Liquidity::Taker));
+
+        emitter.observe_book(&OrderBook::new("BTCUSDT".to_string(), vec![(99.0, 1.0)], vec![(101.0, 1.0)], 1000));
+        let resting = order(&mut emitter, "Buy", 100.0);
+        let crossing = order(&mut emitter, "Sell", 99.0);
+        assert_eq!((resting.liquidity, resting.fee), (Some(Liquidity::Maker), -0.01));
+        assert_eq!(crossing.liquidity, Some(Liquidity::Taker));
+        assert!((crossing.fee - 0.0495).abs() < 1e-12);
+
+        let stats = emitter.get_stats();
+        assert_eq!((stats.maker_fills, stats.taker_fills), (1, 2));
+        assert!((stats.total_fees - (0.05 - 0.01 + 0.0495)).abs() < 1e-12);
+
+        let result = crate::pnl::PnlReport::with_commission(0.0).calculate(&[resting, crossing], crate::pnl::Method::Fifo);
+        assert!((result.total_fees - 0.0395).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_crossing_order_fills_only_the_depth_it_reaches() {
+        let book = OrderBook::new("BTCUSDT".to_string(), vec![(100.0, 1.0)], vec![(100.1, 0.3), (100.2, 0.5), (100.5, 2.0)], 1000);
+        let buy = |quantity: f64| Trade::new(1000, "BTCUSDT".to_string(), "Buy".to_string(), 100.2, quantity);
+
+        let mut config = always_fill(Some(0.0), Some(0.0));
+        config.fill_from_depth = true;
+        let mut emitter = BacktestTradeEmitter::new(config.clone());
+        let partial = emitter.execute_trade_against_book(Some(buy(2.0)), &book).unwrap();
+        assert_eq!(partial.status, "partially_filled");
+        assert!((partial.quantity - 0.8).abs() < 1e-12);
+        let full = emitter.execute_trade_against_book(Some(buy(0.5)), &book).unwrap();
+        assert_eq!((full.status.as_str(), full.quantity), ("filled", 0.5));
+        let stats = emitter.get_stats();
+        assert_eq!((stats.partial_fills, stats.filled_trades), (1, 1));
+
+        // Resting orders and runs without the depth limit fill in full
+        let resting = Trade::new(1000, "BTCUSDT".to_string(), "Sell".to_string(), 101.0, 5.0);
+        assert_eq!(emitter.execute_trade_against_book(Some(resting), &book).unwrap().quantity, 5.0);
+        config.fill_from_depth = false;
+        let unlimited = BacktestTradeEmitter::new(config).execute_trade_against_book(Some(buy(2.0)), &book).unwrap();
+        assert_eq!((unlimited.status.as_str(), unlimited.quantity), ("filled", 2.0));
+    }
+}
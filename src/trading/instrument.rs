@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use log::warn;
+
+use crate::core::errors::{Result, TradeError};
+
+/// Tolerance, in steps, for prices and quantities that are already on the grid
+const GRID_EPSILON: f64 = 1e-9;
+
+/// Contract and order constraints for a single instrument
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstrumentSpec {
+    /// Units of the underlying per contract (1.0 for spot)
+    pub contract_size: f64,
+    /// Minimum price increment (0.0 = unconstrained)
+    pub tick_size: f64,
+    /// Minimum quantity increment (0.0 = unconstrained)
+    pub lot_size: f64,
+    /// Minimum order notional in quote currency (0.0 = no minimum)
+    pub min_notional: f64,
+}
+
+impl Default for InstrumentSpec {
+    fn default() -> Self {
+        Self {
+            contract_size: 1.0,
+            tick_size: 0.0,
+            lot_size: 0.0,
+            min_notional: 0.0,
+        }
+    }
+}
+
+impl InstrumentSpec {
+    /// Price moved onto the tick grid in the direction that doesn't improve the order:
+    /// down for buys, up for sells
+    pub fn round_price(&self, price: f64, side: &str) -> f64 {
+        if self.tick_size <= 0.0 {
+            return price;
+        }
+        let ticks = price / self.tick_size;
+        let ticks = if side.eq_ignore_ascii_case("buy") {
+            (ticks + GRID_EPSILON).floor()
+        } else {
+            (ticks - GRID_EPSILON).ceil()
+        };
+        ticks * self.tick_size
+    }
+
+    /// Quantity rounded down to a whole number of lots
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        if self.lot_size <= 0.0 {
+            return quantity;
+        }
+        (quantity / self.lot_size + GRID_EPSILON).floor() * self.lot_size
+    }
+}
+
+/// Symbol to `InstrumentSpec` lookup; unknown symbols get the default spec
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InstrumentSpecRegistry {
+    specs: HashMap<String, InstrumentSpec>,
+}
+
+impl InstrumentSpecRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_spec(mut self, symbol: &str, spec: InstrumentSpec) -> Self {
+        self.insert(symbol, spec);
+        self
+    }
+
+    pub fn insert(&mut self, symbol: &str, spec: InstrumentSpec) {
+        self.specs.insert(symbol.to_string(), spec);
+    }
+
+    /// Load specs from a JSON object keyed by symbol, or from a CSV file (by `.csv` extension)
+    /// with a `symbol` column and any of `contract_size`, `tick_size`, `lot_size`, `min_notional`.
+    /// Fields left out keep their `InstrumentSpec::default` values.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let is_csv = path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            Self::from_csv(&content)
+        } else {
+            Ok(serde_json::from_str(&content)?)
+        }
+    }
+
+    fn from_csv(content: &str) -> Result<Self> {
+        let invalid = |msg: String| TradeError::DataLoadingError(format!("Invalid instrument spec CSV: {}", msg));
+        let mut lines = content.lines().map(str::trim).filter(|line| !line.is_empty());
+        let header: Vec<&str> = lines.next().unwrap_or_default().split(',').map(str::trim).collect();
+        let symbol_column = header.iter().position(|name| *name == "symbol")
+            .ok_or_else(|| invalid("missing 'symbol' column".to_string()))?;
+
+        let mut registry = Self::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let mut spec = InstrumentSpec::default();
+            for (name, value) in header.iter().zip(&fields) {
+                let field = match *name {
+                    "contract_size" => &mut spec.contract_size,
+                    "tick_size" => &mut spec.tick_size,
+                    "lot_size" => &mut spec.lot_size,
+                    "min_notional" => &mut spec.min_notional,
+                    _ => continue,
+                };
+                if !value.is_empty() {
+                    *field = value.parse()
+                        .map_err(|_| invalid(format!("{} '{}' is not a number in line '{}'", name, value, line)))?;
+                }
+            }
+            let symbol = fields.get(symbol_column).filter(|symbol| !symbol.is_empty())
+                .ok_or_else(|| invalid(format!("no symbol in line '{}'", line)))?;
+            registry.insert(symbol, spec);
+        }
+        Ok(registry)
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.specs.contains_key(symbol)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// Warn when specs were configured but `symbol` has none, so it trades unconstrained
+    pub fn warn_if_missing(&self, symbol: &str) {
+        if !self.is_empty() && !self.contains(symbol) {
+            warn!("No instrument spec for {}; using defaults (contract size 1, no tick, lot or notional limits)", symbol);
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> InstrumentSpec {
+        self.specs.get(symbol).copied().unwrap_or_default()
+    }
+
+    /// Quote-currency notional of `quantity` contracts at `price`
+    pub fn notional(&self, symbol: &str, price: f64, quantity: f64) -> f64 {
+        price * quantity * self.get(symbol).contract_size
+    }
+}
@@ -1,5 +1,103 @@
 use crate::core::ClosedTrade;
 
+const YEAR_MS: f64 = 365.0 * 24.0 * 3_600_000.0;
+
+/// Calmar ratio: P&L annualized over `span_ms` divided by the max drawdown (both in currency).
+///
+/// Backtests are far shorter than the 36-month window Calmar conventionally uses, so
+/// this is also the MAR ratio (full-history return over drawdown). A profitable run
+/// with no drawdown yields `f64::INFINITY`; an empty span or non-positive P&L without
+/// drawdown yields 0.0.
+pub fn calmar_ratio(total_pnl: f64, span_ms: i64, max_drawdown: f64) -> f64 {
+    if span_ms <= 0 {
+        return 0.0;
+    }
+    let annualized_pnl = total_pnl * YEAR_MS / span_ms as f64;
+    if max_drawdown > 0.0 {
+        annualized_pnl / max_drawdown
+    } else if annualized_pnl > 0.0 {
+        f64::INFINITY
+    } else {
+        0.0
+    }
+}
+
+/// Sharpe ratio of per-trade `returns` annualized by the trade frequency: `returns.len()` trades
+/// over `span_ms` scaled to a year. Fills are irregular and intraday, so a fixed 252-period
+/// factor would make runs of different frequency incomparable. Fewer than two returns, an empty
+/// span or constant returns yield 0.0.
+pub fn annualized_sharpe(returns: &[f64], span_ms: i64) -> f64 {
+    if returns.len() < 2 || span_ms <= 0 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    if variance <= 0.0 {
+        return 0.0;
+    }
+    let trades_per_year = returns.len() as f64 * YEAR_MS / span_ms as f64;
+    mean / variance.sqrt() * trades_per_year.sqrt()
+}
+
+/// Sortino ratio of per-trade `returns`: the mean over the downside deviation (root mean
+/// square of the negative returns, zero target), annualized by the trade frequency like
+/// `annualized_sharpe`. Fewer than two returns, an empty span or no losing returns yield 0.0.
+pub fn annualized_sortino(returns: &[f64], span_ms: i64) -> f64 {
+    if returns.len() < 2 || span_ms <= 0 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let downside_variance = returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64;
+    if downside_variance <= 0.0 {
+        return 0.0;
+    }
+    let trades_per_year = returns.len() as f64 * YEAR_MS / span_ms as f64;
+    mean / downside_variance.sqrt() * trades_per_year.sqrt()
+}
+
+/// Fees paid as a share of gross P&L. Near or above 1.0 the fees eat all the edge;
+/// fees against a non-positive gross P&L yield `f64::INFINITY`, no fees yield 0.0.
+pub fn fee_to_pnl_ratio(fees: f64, gross_pnl: f64) -> f64 {
+    if fees <= 0.0 {
+        0.0
+    } else if gross_pnl > 0.0 {
+        fees / gross_pnl
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Runs of consecutive winning and losing closed trades
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Streaks {
+    pub longest_win: usize,
+    pub longest_loss: usize,
+    /// Length of the run the sequence ends in: positive for wins, negative for losses,
+    /// 0 after a break-even trade
+    pub current: i64,
+}
+
+/// Win and loss streaks over closed-trade P&Ls in time order. A zero-P&L trade is
+/// neither a win nor a loss and ends whichever streak was running.
+pub fn streaks(pnls: impl IntoIterator<Item = f64>) -> Streaks {
+    let mut streaks = Streaks::default();
+    for pnl in pnls {
+        streaks.current = if pnl > 0.0 {
+            streaks.current.max(0) + 1
+        } else if pnl < 0.0 {
+            streaks.current.min(0) - 1
+        } else {
+            0
+        };
+        if streaks.current > 0 {
+            streaks.longest_win = streaks.longest_win.max(streaks.current as usize);
+        } else {
+            streaks.longest_loss = streaks.longest_loss.max(streaks.current.unsigned_abs() as usize);
+        }
+    }
+    streaks
+}
+
 #[derive(Debug, Clone)]
 pub struct TradingMetrics {
     pub total_trades: usize,
@@ -8,10 +106,15 @@ pub struct TradingMetrics {
     pub total_pnl: f64,
     pub max_drawdown: f64,
     pub sharpe_ratio: f64,
+    /// Like Sharpe, but only losing returns count towards the deviation
+    pub sortino_ratio: f64,
+    /// Annualized P&L over max drawdown, as `calmar_ratio`
+    pub calmar_ratio: f64,
     pub win_rate: f64,
     pub avg_win: f64,
     pub avg_loss: f64,
     pub profit_factor: f64,
+    pub streaks: Streaks,
 }
 
 impl Default for TradingMetrics {
@@ -23,10 +126,13 @@ impl Default for TradingMetrics {
             total_pnl: 0.0,
             max_drawdown: 0.0,
             sharpe_ratio: 0.0,
+            sortino_ratio: 0.0,
+            calmar_ratio: 0.0,
             win_rate: 0.0,
             avg_win: 0.0,
             avg_loss: 0.0,
             profit_factor: 0.0,
+            streaks: Streaks::default(),
         }
     }
 }
@@ -55,7 +161,8 @@ impl MetricsCalculator {
         self.cumulative_pnl.push(cumulative);
     }
     
-    pub fn calculate_metrics(&self) -> TradingMetrics {
+    /// Metrics of the closed trades over a run lasting `span_ms`, which the ratios annualize by
+    pub fn calculate_metrics(&self, span_ms: i64) -> TradingMetrics {
         if self.closed_trades.is_empty() {
             return TradingMetrics::default();
         }
@@ -103,7 +210,10 @@ impl MetricsCalculator {
         };
         
         let max_drawdown = self.calculate_max_drawdown();
-        let sharpe_ratio = self.calculate_sharpe_ratio();
+        let returns = self.trade_returns();
+        let sharpe_ratio = annualized_sharpe(&returns, span_ms);
+        let sortino_ratio = annualized_sortino(&returns, span_ms);
+        let calmar_ratio = calmar_ratio(total_pnl, span_ms, max_drawdown);
         
         TradingMetrics {
             total_trades,
@@ -112,10 +222,13 @@ impl MetricsCalculator {
             total_pnl,
             max_drawdown,
             sharpe_ratio,
+            sortino_ratio,
+            calmar_ratio,
             win_rate,
             avg_win,
             avg_loss,
             profit_factor,
+            streaks: streaks(self.closed_trades.iter().map(|t| t.pnl)),
         }
     }
     
@@ -140,28 +253,17 @@ impl MetricsCalculator {
         max_drawdown
     }
     
-    fn calculate_sharpe_ratio(&self) -> f64 {
-        if self.closed_trades.len() < 2 {
-            return 0.0;
-        }
-        
-        let returns: Vec<f64> = self.closed_trades.iter()
+    /// Per-trade return on the notional opened
+    fn trade_returns(&self) -> Vec<f64> {
+        self.closed_trades.iter()
             .map(|t| t.pnl / t.quantity / t.open_price)
-            .collect();
-        
-        let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-        
-        let variance = returns.iter()
-            .map(|r| (r - mean_return).powi(2))
-            .sum::<f64>() / (returns.len() - 1) as f64;
-        
-        let std_dev = variance.sqrt();
-        
-        if std_dev > 0.0 {
-            mean_return / std_dev * (252.0_f64).sqrt() // Annualized
-        } else {
-            0.0
-        }
+            .collect()
+    }
+    
+    /// Calmar (and MAR) ratio of the closed trades over a run lasting `span_ms`
+    pub fn calculate_calmar(&self, span_ms: i64) -> f64 {
+        let total_pnl = self.cumulative_pnl.last().copied().unwrap_or(0.0);
+        calmar_ratio(total_pnl, span_ms, self.calculate_max_drawdown())
     }
     
     pub fn get_cumulative_pnl(&self) -> &[f64] {
@@ -177,4 +279,108 @@ impl Default for MetricsCalculator {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400_000;
+
+    fn closed(pnl: f64) -> ClosedTrade {
+        ClosedTrade {
+            open_side: "Buy".to_string(),
+            quantity: 1.0,
+            open_price: 100.0,
+            close_side: "Sell".to_string(),
+            close_price: 100.0 + pnl,
+            pnl,
+        }
+    }
+
+    #[test]
+    fn test_streaks_on_win_loss_sequence() {
+        let mut calculator = MetricsCalculator::new();
+        // W W L L L W W W 0 W L
+        for pnl in [1.0, 2.0, -1.0, -3.0, -2.0, 1.0, 1.0, 4.0, 0.0, 2.0, -1.0] {
+            calculator.add_closed_trade(closed(pnl));
+        }
+
+        let metrics = calculator.calculate_metrics(DAY);
+        assert_eq!(metrics.streaks, Streaks { longest_win: 3, longest_loss: 3, current: -1 });
+
+        // The break-even trade splits W W W | W into runs of 3 and 1
+        assert_eq!(streaks([1.0, 1.0, 0.0, 1.0, 1.0]), Streaks { longest_win: 2, longest_loss: 0, current: 2 });
+        assert_eq!(streaks([-1.0, 0.0]).current, 0);
+        assert_eq!(streaks([]), Streaks::default());
+    }
+
+    #[test]
+    fn test_sharpe_annualizes_by_trade_frequency() {
+        const MINUTE: i64 = 60_000;
+        let edge = [0.002, -0.001, 0.003, 0.0, 0.001, -0.002, 0.004, 0.001];
+        let twice: Vec<f64> = edge.iter().chain(&edge).copied().collect();
+
+        // Same per-trade edge at the same pace: the same annualized Sharpe however long the run
+        let one_per_minute = annualized_sharpe(&edge, edge.len() as i64 * MINUTE);
+        assert!(one_per_minute > 0.0);
+        assert!((annualized_sharpe(&twice, twice.len() as i64 * MINUTE) - one_per_minute).abs() < 1e-9);
+
+        // Trading four times less often halves it
+        let one_per_four_minutes = annualized_sharpe(&edge, edge.len() as i64 * 4 * MINUTE);
+        assert!((one_per_minute / one_per_four_minutes - 2.0).abs() < 1e-9);
+
+        // A year of one trade a day scales the per-trade ratio by sqrt(365)
+        let daily = annualized_sharpe(&edge, edge.len() as i64 * 1_440 * MINUTE);
+        let sample_sd = (edge.iter().map(|r| (r - 0.001).powi(2)).sum::<f64>() / 7.0).sqrt();
+        assert!((daily - 0.001 / sample_sd * 365.0_f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(annualized_sharpe(&[0.01], MINUTE), 0.0);
+        assert_eq!(annualized_sharpe(&[0.01, 0.01], MINUTE), 0.0);
+        assert_eq!(annualized_sharpe(&edge, 0), 0.0);
+    }
+
+    #[test]
+    fn test_sortino_and_calmar_on_volatile_upside() {
+        let mut calculator = MetricsCalculator::new();
+        // Large, uneven wins and small, steady losses
+        for pnl in [5.0, -0.5, 10.0, -0.5, 1.0, -0.5, 8.0, -1.0] {
+            calculator.add_closed_trade(closed(pnl));
+        }
+
+        // One trade a day
+        let metrics = calculator.calculate_metrics(8 * DAY);
+        assert!(metrics.sharpe_ratio > 0.0);
+        assert!(metrics.sortino_ratio > metrics.sharpe_ratio,
+                "sortino {} <= sharpe {}", metrics.sortino_ratio, metrics.sharpe_ratio);
+
+        // Returns are pnl / 100; downside deviation is sqrt((3 * 0.005^2 + 0.01^2) / 8)
+        let downside = ((3.0 * 0.005_f64.powi(2) + 0.01_f64.powi(2)) / 8.0).sqrt();
+        assert!((metrics.sortino_ratio - 0.026875 / downside * 365.0_f64.sqrt()).abs() < 1e-9);
+
+        // 21.5 over 8 days, annualized, against the final 1.0 drawdown
+        assert_eq!(metrics.max_drawdown, 1.0);
+        assert!((metrics.calmar_ratio - 21.5 * 365.0 / 8.0).abs() < 1e-9);
+        assert_eq!(metrics.calmar_ratio, calculator.calculate_calmar(8 * DAY));
+
+        // Trading four times less often halves the Sortino ratio
+        let slower = calculator.calculate_metrics(32 * DAY);
+        assert!((metrics.sortino_ratio / slower.sortino_ratio - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sortino_without_downside_is_zero() {
+        let mut calculator = MetricsCalculator::new();
+        for pnl in [1.0, 3.0, 2.0] {
+            calculator.add_closed_trade(closed(pnl));
+        }
+
+        let metrics = calculator.calculate_metrics(3 * DAY);
+        assert!(metrics.sharpe_ratio > 0.0);
+        assert_eq!(metrics.sortino_ratio, 0.0);
+        // Profitable without a drawdown, like `calmar_ratio`
+        assert!(metrics.calmar_ratio.is_infinite());
+        assert_eq!(calculator.calculate_metrics(0).calmar_ratio, 0.0);
+        assert_eq!(TradingMetrics::default().sortino_ratio, 0.0);
+    }
+}
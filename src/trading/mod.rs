@@ -1,7 +1,11 @@
 pub mod executor;
 pub mod position;
 pub mod metrics;
+pub mod instrument;
+pub mod quotes;
 
-pub use executor::{TradeEmitter, BacktestTradeEmitter, BacktestConfig};
+pub use executor::{TradeEmitter, BacktestTradeEmitter, BacktestConfig, SizingMode, CrossFileState, DEFAULT_MARGIN_RATE};
 pub use position::{Position, PositionTracker};
-pub use metrics::{TradingMetrics, MetricsCalculator};
\ No newline at end of file
+pub use metrics::{TradingMetrics, MetricsCalculator, Streaks};
+pub use instrument::{InstrumentSpec, InstrumentSpecRegistry};
+pub use quotes::{QuoteSimulator, QuoteStats};
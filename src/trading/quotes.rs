@@ -0,0 +1,122 @@
+use crate::core::{OrderBook, Trade};
+use crate::utils::logging::log_fill;
+
+/// Counters for resting maker quotes
+#[derive(Debug, Clone, Default)]
+pub struct QuoteStats {
+    pub placed: usize,
+    pub cancelled: usize,
+    pub filled: usize,
+    /// Time with a quote resting in the book, in ms
+    pub uptime_ms: i64,
+    /// Time covered by the books seen so far, in ms
+    pub elapsed_ms: i64,
+}
+
+impl QuoteStats {
+    /// Share of placed quotes that were filled
+    pub fn fill_ratio(&self) -> f64 {
+        if self.placed == 0 {
+            return 0.0;
+        }
+        self.filled as f64 / self.placed as f64
+    }
+
+    /// Share of elapsed time with a quote resting
+    pub fn uptime_ratio(&self) -> f64 {
+        if self.elapsed_ms == 0 {
+            return 0.0;
+        }
+        self.uptime_ms as f64 / self.elapsed_ms as f64
+    }
+}
+
+/// Simulates resting maker quotes, at most one per side, with free cancel-replace
+///
+/// A resting buy fills at its own price once the best ask trades down to it,
+/// a resting sell once the best bid trades up to it. Quotes are snapped to the instrument's
+/// grid and checked against its minimum notional before they rest, as taker orders are, but
+/// `fill_rate`, `rejection_rate`, slippage and book depth do not apply to them: a touched quote
+/// always fills in full.
+#[derive(Debug, Default)]
+pub struct QuoteSimulator {
+    bid: Option<Trade>,
+    ask: Option<Trade>,
+    last_time: Option<i64>,
+    stats: QuoteStats,
+}
+
+impl QuoteSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advance to `order_book`, returning the resting quotes the book touched as filled trades
+    pub fn on_book(&mut self, order_book: &OrderBook) -> Vec<Trade> {
+        if let Some(last_time) = self.last_time {
+            let elapsed = (order_book.current_time - last_time).max(0);
+            self.stats.elapsed_ms += elapsed;
+            if self.bid.is_some() || self.ask.is_some() {
+                self.stats.uptime_ms += elapsed;
+            }
+        }
+        self.last_time = Some(order_book.current_time);
+
+        let bid_touched = self.bid.as_ref()
+            .is_some_and(|quote| order_book.best_ask().is_some_and(|(ask, _)| ask <= quote.price));
+        let ask_touched = self.ask.as_ref()
+            .is_some_and(|quote| order_book.best_bid().is_some_and(|(bid, _)| bid >= quote.price));
+
+        let mut fills = Vec::new();
+        for (slot, touched) in [(&mut self.bid, bid_touched), (&mut self.ask, ask_touched)] {
+            if let Some(mut fill) = slot.take_if(|_| touched) {
+                fill.time = order_book.current_time;
+                fill.status = "filled".to_string();
+                self.stats.filled += 1;
+                log_fill(&fill);
+                fills.push(fill);
+            }
+        }
+        fills
+    }
+
+    /// Rest `quote` on its side, cancelling that side's quote unless it already sits at the same price and size.
+    /// Returns the cancelled quote, if any.
+    pub fn requote(&mut self, quote: Trade) -> Option<Trade> {
+        if let Some(resting) = self.resting(&quote.side) {
+            if resting.price == quote.price && resting.quantity == quote.quantity {
+                return None;
+            }
+        }
+        let cancelled = self.cancel_side(&quote.side);
+        self.stats.placed += 1;
+        *self.slot(&quote.side) = Some(quote);
+        cancelled
+    }
+
+    /// Pull the resting quote on `side`, if any
+    pub fn cancel_side(&mut self, side: &str) -> Option<Trade> {
+        let mut cancelled = self.slot(side).take()?;
+        cancelled.status = "cancelled".to_string();
+        self.stats.cancelled += 1;
+        Some(cancelled)
+    }
+
+    /// Pull every resting quote
+    pub fn cancel(&mut self) -> Vec<Trade> {
+        ["Buy", "Sell"].into_iter().filter_map(|side| self.cancel_side(side)).collect()
+    }
+
+    /// Quote resting on `side` ("Buy" or "Sell"), if any
+    pub fn resting(&self, side: &str) -> Option<&Trade> {
+        if side == "Buy" { self.bid.as_ref() } else { self.ask.as_ref() }
+    }
+
+    fn slot(&mut self, side: &str) -> &mut Option<Trade> {
+        if side == "Buy" { &mut self.bid } else { &mut self.ask }
+    }
+
+    pub fn stats(&self) -> &QuoteStats {
+        &self.stats
+    }
+}
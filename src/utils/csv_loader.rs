@@ -0,0 +1,501 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use log::info;
+
+use crate::core::{OrderBook, errors::{Result, TradeError}, traits::DataSource};
+use super::loader::extract_symbol_from_filename;
+
+/// Reference to a CSV column, either by position or by header name
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+impl CsvColumn {
+    pub fn name(name: &str) -> Self {
+        CsvColumn::Name(name.to_string())
+    }
+}
+
+/// Columns holding the order book levels
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvBookColumns {
+    /// One column per side with JSON-encoded levels, e.g. `[["100.5","1.2"],["100.4","3.0"]]`
+    Json {
+        bids: CsvColumn,
+        asks: CsvColumn,
+    },
+    /// Top of book only, with separate price and size columns
+    TopOfBook {
+        bid_price: CsvColumn,
+        bid_size: CsvColumn,
+        ask_price: CsvColumn,
+        ask_size: CsvColumn,
+    },
+}
+
+/// Layout of a CSV/TSV order book file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvSchema {
+    /// Field delimiter (',' for CSV, ';' or '\t' for other exports)
+    pub delimiter: char,
+    /// Whether the file may start with a header row. The first line is taken as the header
+    /// only if none of its fields is a number, so headerless files read correctly either way.
+    pub has_header: bool,
+    /// Timestamp column (milliseconds)
+    pub timestamp: CsvColumn,
+    /// Optional symbol column; the symbol is taken from the filename otherwise
+    pub symbol: Option<CsvColumn>,
+    /// Book level columns. Named JSON columns also match their `_json` suffixed form,
+    /// e.g. `bids` finds a `bids_json` header.
+    pub book: CsvBookColumns,
+}
+
+impl Default for CsvSchema {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_header: true,
+            timestamp: CsvColumn::name("timestamp"),
+            symbol: None,
+            book: CsvBookColumns::Json {
+                bids: CsvColumn::name("bids"),
+                asks: CsvColumn::name("asks"),
+            },
+        }
+    }
+}
+
+/// Column indices resolved against the header row
+#[derive(Debug, Clone)]
+struct ResolvedColumns {
+    timestamp: usize,
+    symbol: Option<usize>,
+    book: ResolvedBook,
+}
+
+#[derive(Debug, Clone)]
+enum ResolvedBook {
+    Json { bids: usize, asks: usize },
+    TopOfBook { bid_price: usize, bid_size: usize, ask_price: usize, ask_size: usize },
+}
+
+/// CSV-based data source for order book snapshots
+pub struct CsvDataSource {
+    file_path: PathBuf,
+    symbol: String,
+    schema: CsvSchema,
+    reader: Option<BufReader<File>>,
+    columns: Option<ResolvedColumns>,
+    /// First record, read while checking for a header
+    pending_record: Option<String>,
+    total_messages: Option<usize>,
+}
+
+impl CsvDataSource {
+    pub fn new(file_path: impl AsRef<Path>, schema: CsvSchema) -> Result<Self> {
+        let path = file_path.as_ref().to_path_buf();
+        if !path.exists() {
+            return Err(TradeError::DataLoadingError(
+                format!("File not found: {:?}", path)
+            ));
+        }
+
+        let symbol = path.file_name()
+            .and_then(|n| n.to_str())
+            .map(extract_symbol_from_filename)
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+
+        Ok(Self {
+            file_path: path,
+            symbol,
+            schema,
+            reader: None,
+            columns: None,
+            pending_record: None,
+            total_messages: None,
+        })
+    }
+
+    /// Open the file and resolve column positions, consuming the header if present
+    fn init_reader(&mut self) -> Result<()> {
+        if self.reader.is_some() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.file_path)
+            .map_err(|e| TradeError::DataLoadingError(
+                format!("Failed to open CSV file: {}", e)
+            ))?;
+        let mut reader = BufReader::new(file);
+
+        let mut header = None;
+        if self.schema.has_header {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            let fields = split_record(line.trim_end_matches(['\r', '\n']), self.schema.delimiter);
+            if is_header(&fields) {
+                header = Some(fields);
+            } else {
+                self.pending_record = Some(line);
+            }
+        }
+
+        self.columns = Some(resolve_columns(&self.schema, header.as_deref())?);
+        self.reader = Some(reader);
+        Ok(())
+    }
+
+    /// Parse one CSV record into an OrderBook
+    fn parse_record(&self, fields: &[String], columns: &ResolvedColumns) -> Result<OrderBook> {
+        let field = |index: usize| record_field(fields, index);
+
+        let timestamp = field(columns.timestamp)?.parse::<f64>()
+            .map_err(|_| TradeError::InvalidOrderBook(
+                format!("Invalid timestamp: {}", field(columns.timestamp).unwrap_or(""))
+            ))? as i64;
+
+        let symbol = match columns.symbol {
+            Some(index) => field(index)?.to_string(),
+            None => self.symbol.clone(),
+        };
+
+        let (bids, asks) = match &columns.book {
+            ResolvedBook::Json { bids, asks } => {
+                (parse_json_levels(field(*bids)?)?, parse_json_levels(field(*asks)?)?)
+            }
+            ResolvedBook::TopOfBook { bid_price, bid_size, ask_price, ask_size } => {
+                let bid = (parse_number(field(*bid_price)?)?, parse_number(field(*bid_size)?)?);
+                let ask = (parse_number(field(*ask_price)?)?, parse_number(field(*ask_size)?)?);
+                (vec![bid], vec![ask])
+            }
+        };
+
+        Ok(OrderBook::new(symbol, bids, asks, timestamp))
+    }
+
+    /// Pre-count total records in the file (optional, for progress tracking)
+    pub fn count_messages(&mut self) -> Result<usize> {
+        if let Some(count) = self.total_messages {
+            return Ok(count);
+        }
+
+        let start = Instant::now();
+        let file = File::open(&self.file_path)?;
+        let reader = BufReader::new(file);
+
+        let mut lines = reader.lines().map_while(|l| l.ok()).peekable();
+        let header = self.schema.has_header
+            && lines.peek().is_some_and(|first| is_header(&split_record(first, self.schema.delimiter)));
+        let records = lines.filter(|l| !l.trim().is_empty()).count();
+        let count = if header { records.saturating_sub(1) } else { records };
+        self.total_messages = Some(count);
+
+        info!("Counted {} CSV records in {:.2}s", count, start.elapsed().as_secs_f64());
+        Ok(count)
+    }
+}
+
+impl DataSource for CsvDataSource {
+    fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+        self.init_reader()?;
+
+        let mut line = self.pending_record.take().unwrap_or_default();
+        while line.trim().is_empty() {
+            line.clear();
+            let read = self.reader.as_mut().unwrap().read_line(&mut line)?;
+            if read == 0 {
+                return Ok(None); // EOF
+            }
+        }
+
+        let fields = split_record(line.trim_end_matches(['\r', '\n']), self.schema.delimiter);
+        let columns = self.columns.as_ref().unwrap();
+        self.parse_record(&fields, columns).map(Some)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.reader = None;
+        self.columns = None;
+        self.pending_record = None;
+        Ok(())
+    }
+
+    fn total_count(&self) -> Option<usize> {
+        self.total_messages
+    }
+}
+
+fn record_field(fields: &[String], index: usize) -> Result<&str> {
+    fields.get(index)
+        .map(|f| f.trim())
+        .ok_or_else(|| TradeError::InvalidOrderBook(
+            format!("Missing column {} in CSV record", index)
+        ))
+}
+
+/// Resolve a column reference to an index, looking names up in the header
+fn resolve_column(column: &CsvColumn, header: Option<&[String]>) -> Result<usize> {
+    match column {
+        CsvColumn::Index(index) => Ok(*index),
+        CsvColumn::Name(name) => {
+            let header = header.ok_or_else(|| TradeError::DataLoadingError(
+                format!("Column '{}' is referenced by name but the CSV schema has no header", name)
+            ))?;
+            header.iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| TradeError::DataLoadingError(
+                    format!("Column '{}' not found in CSV header", name)
+                ))
+        }
+    }
+}
+
+/// `resolve_column`, falling back to the `_json` suffixed header for a named column
+fn resolve_json_column(column: &CsvColumn, header: Option<&[String]>) -> Result<usize> {
+    resolve_column(column, header).or_else(|e| match column {
+        CsvColumn::Name(name) => resolve_column(&CsvColumn::Name(format!("{}_json", name)), header).map_err(|_| e),
+        CsvColumn::Index(_) => Err(e),
+    })
+}
+
+/// A header row has no numeric field; every record has at least its timestamp
+fn is_header(fields: &[String]) -> bool {
+    fields.iter().all(|field| field.trim().parse::<f64>().is_err())
+}
+
+fn resolve_columns(schema: &CsvSchema, header: Option<&[String]>) -> Result<ResolvedColumns> {
+    let book = match &schema.book {
+        CsvBookColumns::Json { bids, asks } => ResolvedBook::Json {
+            bids: resolve_json_column(bids, header)?,
+            asks: resolve_json_column(asks, header)?,
+        },
+        CsvBookColumns::TopOfBook { bid_price, bid_size, ask_price, ask_size } => ResolvedBook::TopOfBook {
+            bid_price: resolve_column(bid_price, header)?,
+            bid_size: resolve_column(bid_size, header)?,
+            ask_price: resolve_column(ask_price, header)?,
+            ask_size: resolve_column(ask_size, header)?,
+        },
+    };
+
+    Ok(ResolvedColumns {
+        timestamp: resolve_column(&schema.timestamp, header)?,
+        symbol: schema.symbol.as_ref().map(|c| resolve_column(c, header)).transpose()?,
+        book,
+    })
+}
+
+/// Split a CSV record, honoring double-quoted fields (with `""` as an escaped quote)
+fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    current.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                current.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    fields.push(current);
+
+    fields
+}
+
+fn parse_number(value: &str) -> Result<f64> {
+    value.parse::<f64>()
+        .map_err(|_| TradeError::InvalidOrderBook(format!("Invalid number: {}", value)))
+}
+
+/// Parse JSON-encoded levels; prices and sizes may be strings or numbers
+fn parse_json_levels(value: &str) -> Result<Vec<(f64, f64)>> {
+    let levels: Vec<Vec<serde_json::Value>> = serde_json::from_str(value)?;
+    let to_f64 = |v: &serde_json::Value| -> Result<f64> {
+        match v {
+            serde_json::Value::String(s) => parse_number(s),
+            other => other.as_f64()
+                .ok_or_else(|| TradeError::InvalidOrderBook(format!("Invalid level value: {}", other))),
+        }
+    };
+
+    levels.iter()
+        .filter(|level| level.len() >= 2)
+        .map(|level| Ok((to_f64(&level[0])?, to_f64(&level[1])?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+    use std::io::Write;
+
+    /// Write `contents` to `name` in a directory of its own, removed when the returned guard drops
+    fn write_fixture(name: &str, contents: &str) -> (TestDir, PathBuf) {
+        let dir = TestDir::new(&format!("csv_{}", name.trim_end_matches(".csv")));
+        let path = dir.join(name);
+        let mut file = File::create(&path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        (dir, path)
+    }
+
+    fn read_all(source: &mut CsvDataSource) -> Vec<OrderBook> {
+        let mut books = Vec::new();
+        while let Some(book) = source.next_orderbook().unwrap() {
+            books.push(book);
+        }
+        books
+    }
+
+    #[test]
+    fn test_comma_file_with_json_levels() {
+        let (_dir, path) = write_fixture(
+            "BTCUSDT_comma.csv",
+            "asks,timestamp,bids\n\
+             \"[[\"\"101.0\"\",\"\"2.0\"\"]]\",1000,\"[[\"\"100.0\"\",\"\"1.5\"\"],[\"\"99.5\"\",\"\"3.0\"\"]]\"\n\
+             \"[[101.5,1.0]]\",2000,\"[[100.5,4.0]]\"\n",
+        );
+
+        let mut source = CsvDataSource::new(&path, CsvSchema::default()).unwrap();
+        assert_eq!(source.count_messages().unwrap(), 2);
+
+        let books = read_all(&mut source);
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].symbol, "BTCUSDT");
+        assert_eq!(books[0].current_time, 1000);
+        assert_eq!(books[0].bids, vec![(100.0, 1.5), (99.5, 3.0)]);
+        assert_eq!(books[0].asks, vec![(101.0, 2.0)]);
+        assert_eq!(books[1].mid_price(), 101.0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_semicolon_file_with_top_of_book_columns() {
+        let (_dir, path) = write_fixture(
+            "ETHUSDT_semicolon.csv",
+            "ask_size;ask_price;symbol;ts;bid_size;bid_price\n\
+             2.0;2001.0;ETHUSDT;1000;1.0;2000.0\n\
+             \n\
+             3.0;2002.0;ETHUSDT;2000;4.0;2001.0\n",
+        );
+
+        let schema = CsvSchema {
+            delimiter: ';',
+            timestamp: CsvColumn::name("ts"),
+            symbol: Some(CsvColumn::name("symbol")),
+            book: CsvBookColumns::TopOfBook {
+                bid_price: CsvColumn::name("bid_price"),
+                bid_size: CsvColumn::name("bid_size"),
+                ask_price: CsvColumn::name("ask_price"),
+                ask_size: CsvColumn::name("ask_size"),
+            },
+            ..Default::default()
+        };
+
+        let mut source = CsvDataSource::new(&path, schema).unwrap();
+        let books = read_all(&mut source);
+
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].symbol, "ETHUSDT");
+        assert_eq!(books[0].bids, vec![(2000.0, 1.0)]);
+        assert_eq!(books[0].asks, vec![(2001.0, 2.0)]);
+        assert_eq!(books[1].current_time, 2000);
+        assert_eq!(books[1].bids, vec![(2001.0, 4.0)]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_headerless_file_with_column_indices() {
+        let (_dir, path) = write_fixture(
+            "SOLUSDT_tabs.tsv",
+            "1000\t150.1\t5\t150.0\t7\n",
+        );
+
+        let schema = CsvSchema {
+            delimiter: '\t',
+            has_header: false,
+            timestamp: CsvColumn::Index(0),
+            symbol: None,
+            book: CsvBookColumns::TopOfBook {
+                bid_price: CsvColumn::Index(3),
+                bid_size: CsvColumn::Index(4),
+                ask_price: CsvColumn::Index(1),
+                ask_size: CsvColumn::Index(2),
+            },
+        };
+
+        let mut source = CsvDataSource::new(&path, schema).unwrap();
+        let books = read_all(&mut source);
+
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].bids, vec![(150.0, 7.0)]);
+        assert_eq!(books[0].asks, vec![(150.1, 5.0)]);
+
+        source.reset().unwrap();
+        assert_eq!(read_all(&mut source).len(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_named_column_is_an_error() {
+        let (_dir, path) = write_fixture("XRPUSDT_missing.csv", "time,bids,asks\n1,[],[]\n");
+
+        let mut source = CsvDataSource::new(&path, CsvSchema::default()).unwrap();
+        assert!(source.next_orderbook().is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_header_is_detected_and_json_suffix_columns_resolve() {
+        let csv = "timestamp,bids_json,asks_json\n\
+                   1000,\"[[100.0,1.0],[99.9,2.0]]\",\"[[100.1,3.0]]\"\n\
+                   2000,\"[[100.2,1.5]]\",\"[[100.3,0.5]]\"\n";
+        let (_dir, path) = write_fixture("ADAUSDT_suffix.csv", csv);
+
+        let mut source = CsvDataSource::new(&path, CsvSchema::default()).unwrap();
+        assert_eq!(source.count_messages().unwrap(), 2);
+        let books = read_all(&mut source);
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].symbol, "ADAUSDT");
+        assert_eq!(books[0].bids, vec![(100.0, 1.0), (99.9, 2.0)]);
+        assert_eq!(books[1].asks, vec![(100.3, 0.5)]);
+        std::fs::remove_file(path).unwrap();
+
+        // The same rows without a header: the first line is kept as a record
+        let headerless = csv.split_once('\n').unwrap().1;
+        let (_dir, path) = write_fixture("ADAUSDT_headerless.csv", headerless);
+        let schema = CsvSchema {
+            timestamp: CsvColumn::Index(0),
+            book: CsvBookColumns::Json { bids: CsvColumn::Index(1), asks: CsvColumn::Index(2) },
+            ..Default::default()
+        };
+        let mut source = CsvDataSource::new(&path, schema).unwrap();
+        assert_eq!(source.count_messages().unwrap(), 2);
+        let round_trip = read_all(&mut source);
+        assert_eq!(round_trip.iter().map(|b| b.current_time).collect::<Vec<_>>(), vec![1000, 2000]);
+        assert_eq!(round_trip[0].bids, books[0].bids);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use log::debug;
+
+use crate::core::{OrderBook, errors::Result, traits::DataSource};
+
+/// Data source adapter that drops duplicate and out-of-sequence books
+///
+/// A book is skipped when its `update_id` is not greater than the last one seen
+/// for its symbol. Books without an `update_id` always pass.
+pub struct DedupDataSource {
+    inner: Box<dyn DataSource>,
+    last_update_ids: HashMap<String, i64>,
+    skipped: usize,
+}
+
+impl DedupDataSource {
+    pub fn new(inner: Box<dyn DataSource>) -> Self {
+        Self {
+            inner,
+            last_update_ids: HashMap::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Number of books dropped so far
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl DataSource for DedupDataSource {
+    fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+        while let Some(order_book) = self.inner.next_orderbook()? {
+            let Some(update_id) = order_book.update_id else {
+                return Ok(Some(order_book));
+            };
+            match self.last_update_ids.get(&order_book.symbol) {
+                Some(&last) if update_id <= last => {
+                    self.skipped += 1;
+                    debug!("Dropping {} book at {}: update_id {} does not follow {}",
+                           order_book.symbol, order_book.current_time, update_id, last);
+                }
+                _ => {
+                    self.last_update_ids.insert(order_book.symbol.clone(), update_id);
+                    return Ok(Some(order_book));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.last_update_ids.clear();
+        self.skipped = 0;
+        self.inner.reset()
+    }
+
+    fn total_count(&self) -> Option<usize> {
+        self.inner.total_count()
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use log::info;
+
+use crate::core::errors::{Result, TradeError};
+
+/// Timestamped external features (funding rate, open interest, model signals, ...)
+///
+/// Rows are looked up as-of a book time: the latest row at or before it.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureSource {
+    timestamps: Vec<i64>,
+    rows: Vec<HashMap<String, f64>>,
+}
+
+impl FeatureSource {
+    /// Build from `(timestamp, values)` rows, sorting them by time
+    pub fn from_rows(mut rows: Vec<(i64, HashMap<String, f64>)>) -> Self {
+        rows.sort_by_key(|(timestamp, _)| *timestamp);
+        let (timestamps, rows) = rows.into_iter().unzip();
+        Self { timestamps, rows }
+    }
+
+    /// Load a comma-separated file with a `timestamp` column followed by one
+    /// column per feature; the header row supplies the feature names.
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path)?);
+        let mut lines = reader.lines();
+
+        let header = lines.next()
+            .ok_or_else(|| TradeError::DataLoadingError(format!("Empty feature file: {:?}", path)))??;
+        let columns: Vec<String> = header.split(',').map(|c| c.trim().to_string()).collect();
+        let timestamp_idx = columns.iter().position(|c| c == "timestamp")
+            .ok_or_else(|| TradeError::DataLoadingError(
+                format!("Feature file {:?} has no 'timestamp' column", path)
+            ))?;
+
+        let mut rows = Vec::new();
+        for (line_no, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != columns.len() {
+                return Err(TradeError::DataLoadingError(format!(
+                    "Feature file {:?} line {}: expected {} fields, got {}",
+                    path, line_no + 2, columns.len(), fields.len()
+                )));
+            }
+
+            let parse_err = |field: &str| TradeError::DataLoadingError(format!(
+                "Feature file {:?} line {}: invalid number '{}'", path, line_no + 2, field
+            ));
+            let timestamp = fields[timestamp_idx].parse::<i64>().map_err(|_| parse_err(fields[timestamp_idx]))?;
+
+            let mut values = HashMap::new();
+            for (i, field) in fields.iter().enumerate() {
+                if i != timestamp_idx {
+                    values.insert(columns[i].clone(), field.parse::<f64>().map_err(|_| parse_err(field))?);
+                }
+            }
+            rows.push((timestamp, values));
+        }
+
+        info!("Loaded {} feature rows from {:?}", rows.len(), path);
+        Ok(Self::from_rows(rows))
+    }
+
+    /// Feature values in effect at `time`, or `None` before the first row
+    pub fn values_at(&self, time: i64) -> Option<&HashMap<String, f64>> {
+        let idx = self.timestamps.partition_point(|&t| t <= time);
+        if idx == 0 {
+            None
+        } else {
+            Some(&self.rows[idx - 1])
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+}
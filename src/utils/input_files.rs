@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+
+use crate::core::errors::{Result, TradeError};
+
+/// Extensions of the order book formats the loaders read
+const DATA_EXTENSIONS: &[&str] = &["jsonl", "json", "parquet", "csv"];
+
+/// Whether `input` names a file rather than a filename pattern: it contains a path
+/// separator, or ends in a data file extension and has no regex syntax besides dots
+pub fn looks_like_path(input: &str) -> bool {
+    if input.contains('/') || input.contains(std::path::MAIN_SEPARATOR) {
+        return true;
+    }
+    let has_data_extension = Path::new(input)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| DATA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    has_data_extension && !input.contains(|c| "\\*+?()[]{}|^$".contains(c))
+}
+
+/// Files in `directory` whose names match `pattern`, sorted by name
+pub fn find_matching_files(directory: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let regex = Regex::new(pattern)
+        .map_err(|e| TradeError::DataLoadingError(format!("Invalid file pattern '{}': {}", pattern, e)))?;
+    let mut matching_files = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            continue;
+        }
+        if path.file_name().and_then(|name| name.to_str()).is_some_and(|name| regex.is_match(name)) {
+            matching_files.push(path);
+        }
+    }
+
+    // Sort files for consistent processing order
+    matching_files.sort();
+    Ok(matching_files)
+}
+
+/// Data files for an `--file` argument: the file itself when `input` is an existing file,
+/// otherwise the files in `directory` matching it as a pattern.
+///
+/// Inputs that look like paths are never searched for, so a mistyped file name
+/// reports "File not found" rather than an empty pattern match.
+pub fn resolve_input_files(input: &str, directory: &Path) -> Result<Vec<PathBuf>> {
+    let path = Path::new(input);
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if looks_like_path(input) {
+        return Err(TradeError::DataLoadingError(format!("File not found: {}", input)));
+    }
+
+    if !directory.is_dir() {
+        return Err(TradeError::DataLoadingError(
+            format!("Directory '{}' does not exist or is not a directory", directory.display())
+        ));
+    }
+    let matching_files = find_matching_files(directory, input)?;
+    if matching_files.is_empty() {
+        return Err(TradeError::DataLoadingError(
+            format!("No files in '{}' match pattern '{}'", directory.display(), input)
+        ));
+    }
+    Ok(matching_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+
+    #[test]
+    fn test_missing_file_and_unmatched_pattern_report_different_errors() {
+        let dir = TestDir::new("input_files");
+        fs::write(dir.join("BTCUSDT_20240101.jsonl"), "").unwrap();
+
+        let missing = resolve_input_files("BTCUSDT_20240102.jsonl", &dir).unwrap_err().to_string();
+        assert!(missing.contains("File not found: BTCUSDT_20240102.jsonl"), "{}", missing);
+        let missing_path = resolve_input_files("data/nope.parquet", &dir).unwrap_err().to_string();
+        assert!(missing_path.contains("File not found: data/nope.parquet"), "{}", missing_path);
+
+        let unmatched = resolve_input_files(r"ETHUSDT_.*\.jsonl", &dir).unwrap_err().to_string();
+        assert!(unmatched.contains(r"match pattern 'ETHUSDT_.*\.jsonl'"), "{}", unmatched);
+
+        let matched = resolve_input_files(r"BTCUSDT_.*\.jsonl", &dir).unwrap();
+        assert_eq!(matched, vec![dir.join("BTCUSDT_20240101.jsonl")]);
+    }
+}
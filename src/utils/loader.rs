@@ -1,8 +1,10 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use flate2::read::GzDecoder;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
-use log::{info, debug};
+use log::{info, debug, warn};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use crate::core::{OrderBook, errors::{Result, TradeError}, traits::DataSource};
@@ -18,6 +20,8 @@ pub struct OrderBookMessage {
 pub struct OrderBookData {
     pub b: Vec<Vec<String>>, // bids: [[price, quantity], ...]
     pub a: Vec<Vec<String>>, // asks: [[price, quantity], ...]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub u: Option<i64>,      // update_id
 }
 
 // Alternative format for newer JSONL/Parquet files
@@ -31,11 +35,51 @@ pub struct OrderBookMessageV2 {
     pub fetch_time: i64,
 }
 
-/// File-based data source for order book messages
+/// Compression of a data file, taken from its `.gz` / `.zst` suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// `filename` without the compression suffix
+    pub(crate) fn strip_suffix(filename: &str) -> &str {
+        filename
+            .strip_suffix(".gz")
+            .or_else(|| filename.strip_suffix(".zst"))
+            .unwrap_or(filename)
+    }
+
+    /// Open `path` for buffered reading, decompressing on the fly
+    fn open(self, path: &Path) -> Result<Box<dyn BufRead + Send>> {
+        let file = File::open(path)
+            .map_err(|e| TradeError::DataLoadingError(
+                format!("Failed to open file: {}", e)
+            ))?;
+        Ok(match self {
+            Compression::None => Box::new(BufReader::new(file)),
+            Compression::Gzip => Box::new(BufReader::new(GzDecoder::new(file))),
+            Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::new(file)?)),
+        })
+    }
+}
+
+/// File-based data source for order book messages, optionally gzip or zstd compressed
 pub struct FileDataSource {
     file_path: PathBuf,
     symbol: String,
-    reader: Option<BufReader<File>>,
+    compression: Compression,
+    reader: Option<Box<dyn BufRead + Send>>,
     buffer: Vec<String>,
     current_index: usize,
     batch_size: usize,
@@ -52,15 +96,17 @@ impl FileDataSource {
         }
         
         // Extract symbol from filename
+        let compression = Compression::from_path(&path);
         let symbol = path.file_name()
             .and_then(|n| n.to_str())
-            .and_then(|n| n.split('_').next())
+            .and_then(|n| Compression::strip_suffix(n).split('_').next())
             .unwrap_or("UNKNOWN")
             .to_string();
         
         Ok(Self {
             file_path: path,
             symbol,
+            compression,
             reader: None,
             buffer: Vec::new(),
             current_index: 0,
@@ -77,11 +123,7 @@ impl FileDataSource {
     /// Load a batch of lines from the file
     fn load_batch(&mut self) -> Result<bool> {
         if self.reader.is_none() {
-            let file = File::open(&self.file_path)
-                .map_err(|e| TradeError::DataLoadingError(
-                    format!("Failed to open file: {}", e)
-                ))?;
-            self.reader = Some(BufReader::new(file));
+            self.reader = Some(self.compression.open(&self.file_path)?);
         }
         
         self.buffer.clear();
@@ -143,7 +185,11 @@ impl FileDataSource {
             }
         }
         
-        Ok(OrderBook::new(self.symbol.clone(), bids, asks, message.ts))
+        let order_book = OrderBook::new(self.symbol.clone(), bids, asks, message.ts);
+        Ok(match message.data.u {
+            Some(update_id) => order_book.with_update_id(update_id),
+            None => order_book,
+        })
     }
     
     /// Parse a V2 format message into an OrderBook
@@ -178,7 +224,7 @@ impl FileDataSource {
             }
         }
         
-        Ok(OrderBook::new(message.symbol.clone(), bids, asks, message.timestamp))
+        Ok(OrderBook::new(message.symbol.clone(), bids, asks, message.timestamp).with_update_id(message.update_id))
     }
     
     /// Pre-count total messages in the file (optional, for progress tracking)
@@ -188,8 +234,7 @@ impl FileDataSource {
         }
         
         let start = Instant::now();
-        let file = File::open(&self.file_path)?;
-        let reader = BufReader::new(file);
+        let reader = self.compression.open(&self.file_path)?;
         
         let count = reader.lines().filter(|l| l.is_ok()).count();
         self.total_messages = Some(count);
@@ -242,4 +287,94 @@ impl DataSource for FileDataSource {
 /// Extract symbol from filename (e.g., "ETHUSDT_3600_sec_123.jsonl" -> "ETHUSDT")
 pub fn extract_symbol_from_filename(filename: &str) -> String {
     filename.split('_').next().unwrap_or("UNKNOWN").to_string()
-}
\ No newline at end of file
+}
+
+/// How the traded symbol is derived from a data file name
+#[derive(Debug, Clone, Default)]
+pub enum SymbolExtractor {
+    /// Text before the first `_` (see `extract_symbol_from_filename`)
+    #[default]
+    FirstSegment,
+    /// Concatenated capture groups of the first match, or the whole match without groups;
+    /// falls back to `FirstSegment` when the name doesn't match
+    Regex(Regex),
+    /// Fixed symbol, ignoring the file name
+    Override(String),
+}
+
+impl SymbolExtractor {
+    pub fn extract(&self, filename: &str) -> String {
+        match self {
+            SymbolExtractor::FirstSegment => extract_symbol_from_filename(filename),
+            SymbolExtractor::Regex(regex) => match regex.captures(filename) {
+                Some(captures) if captures.len() > 1 => captures
+                    .iter()
+                    .skip(1)
+                    .flatten()
+                    .map(|group| group.as_str())
+                    .collect(),
+                Some(captures) => captures[0].to_string(),
+                None => {
+                    warn!("Symbol regex {} does not match {}, using the filename prefix", regex, filename);
+                    extract_symbol_from_filename(filename)
+                }
+            },
+            SymbolExtractor::Override(symbol) => symbol.clone(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+
+    #[test]
+    fn test_symbol_extraction_strategies() {
+        let filename = "BTC_USDT_20240101_12:00_3600s_mainnet.jsonl";
+        assert_eq!(SymbolExtractor::FirstSegment.extract(filename), "BTC");
+
+        let split_pair = SymbolExtractor::Regex(Regex::new(r"^([A-Z]+)_([A-Z]+)_\d{8}").unwrap());
+        assert_eq!(split_pair.extract(filename), "BTCUSDT");
+
+        let whole_match = SymbolExtractor::Regex(Regex::new(r"^[A-Z]+USDT").unwrap());
+        assert_eq!(whole_match.extract("ETHUSDT_20240101.jsonl"), "ETHUSDT");
+        assert_eq!(whole_match.extract("btc.jsonl"), "btc.jsonl");
+
+        let fixed = SymbolExtractor::Override("SOLUSDT".to_string());
+        assert_eq!(fixed.extract(filename), "SOLUSDT");
+    }
+
+    #[test]
+    fn test_compressed_files_match_uncompressed() {
+        use std::io::Write;
+
+        let dir = TestDir::new("compressed");
+        let contents = [
+            r#"{"ts":1000,"data":{"b":[["100.0","1.0"]],"a":[["100.1","2.0"]]}}"#,
+            r#"{"ts":2000,"data":{"b":[["100.2","1.5"]],"a":[["100.3","0.5"]]}}"#,
+        ].join("\n");
+
+        let plain = dir.join("BTCUSDT_20240101.jsonl");
+        std::fs::write(&plain, &contents).unwrap();
+        let gzipped = dir.join("BTCUSDT_20240101.jsonl.gz");
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&gzipped).unwrap(), flate2::Compression::default());
+        encoder.write_all(contents.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        let zstded = dir.join("BTCUSDT_20240101.jsonl.zst");
+        std::fs::write(&zstded, zstd::encode_all(contents.as_bytes(), 0).unwrap()).unwrap();
+
+        let read_all = |path: &Path| {
+            let mut source = FileDataSource::new(path).unwrap();
+            assert_eq!(source.count_messages().unwrap(), 2);
+            std::iter::from_fn(|| source.next_orderbook().unwrap())
+                .map(|book| (book.symbol, book.current_time, book.bids, book.asks))
+                .collect::<Vec<_>>()
+        };
+        let expected = read_all(&plain);
+        assert_eq!(expected.len(), 2);
+        assert_eq!(read_all(&gzipped), expected);
+        assert_eq!(read_all(&zstded), expected);
+
+        assert_eq!(Compression::strip_suffix("btc.jsonl.gz"), "btc.jsonl");
+    }
+}
@@ -0,0 +1,116 @@
+use log::Level;
+
+use crate::core::Trade;
+
+/// Format an event as `event=<name> key=value ...`, quoting values that contain spaces
+pub fn format_event(event: &str, fields: &[(&str, String)]) -> String {
+    let mut line = format!("event={}", event);
+    for (key, value) in fields {
+        if value.contains(' ') {
+            line.push_str(&format!(" {}=\"{}\"", key, value));
+        } else {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    line
+}
+
+/// Log a key=value event at `level`
+pub fn log_event(level: Level, event: &str, fields: &[(&str, String)]) {
+    log::log!(level, "{}", format_event(event, fields));
+}
+
+/// Routine fill, at debug
+pub fn log_fill(trade: &Trade) {
+    if !log::log_enabled!(Level::Debug) {
+        return;
+    }
+    log_event(Level::Debug, "fill", &[
+        ("symbol", trade.symbol.clone()),
+        ("side", trade.side.clone()),
+        ("qty", trade.quantity.to_string()),
+        ("price", trade.price.to_string()),
+        ("id", trade.id.clone()),
+    ]);
+}
+
+/// Decision to reduce a position, at info
+pub fn log_close(trade: &Trade, reason: &str, inventory: f64) {
+    log_event(Level::Info, "close", &[
+        ("symbol", trade.symbol.clone()),
+        ("side", trade.side.clone()),
+        ("qty", trade.quantity.to_string()),
+        ("price", format!("{:.4}", trade.price)),
+        ("reason", reason.to_string()),
+        ("inventory", inventory.to_string()),
+    ]);
+}
+
+/// Risk event such as a limit hit or rejected order, at warn
+pub fn log_risk(event: &str, fields: &[(&str, String)]) {
+    log_event(Level::Warn, event, fields);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use log::{LevelFilter, Log, Metadata, Record};
+    use crate::trading::{BacktestConfig, BacktestTradeEmitter, InstrumentSpec, InstrumentSpecRegistry, TradeEmitter};
+
+    static CAPTURED: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+    struct CapturingLogger;
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            CAPTURED.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger;
+
+    fn captured_for(symbol: &str) -> Vec<(Level, String)> {
+        let marker = format!("symbol={}", symbol);
+        CAPTURED.lock().unwrap().iter().filter(|(_, line)| line.contains(&marker)).cloned().collect()
+    }
+
+    #[test]
+    fn test_format_event_quotes_spaced_values() {
+        let line = format_event("close", &[("qty", "0.5".to_string()), ("reason", "TAKE_PROFIT: 21.0 bps".to_string())]);
+        assert_eq!(line, "event=close qty=0.5 reason=\"TAKE_PROFIT: 21.0 bps\"");
+    }
+
+    #[test]
+    fn test_fill_logs_at_debug_and_risk_event_at_warn() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        let config = BacktestConfig {
+            fill_rate: 1.0,
+            rejection_rate: 0.0,
+            instruments: InstrumentSpecRegistry::new()
+                .with_spec("LOGSMALL", InstrumentSpec { min_notional: 1_000.0, ..Default::default() }),
+            ..Default::default()
+        };
+        let mut executor = BacktestTradeEmitter::new(config);
+        executor.execute_trade(Some(Trade::new(1000, "LOGFILL".to_string(), "Buy".to_string(), 100.0, 1.0)));
+        executor.execute_trade(Some(Trade::new(1000, "LOGSMALL".to_string(), "Buy".to_string(), 100.0, 1.0)));
+
+        let fills = captured_for("LOGFILL");
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].0, Level::Debug);
+        assert!(fills[0].1.starts_with("event=fill "));
+
+        let risks = captured_for("LOGSMALL");
+        assert_eq!(risks.len(), 1);
+        assert_eq!(risks[0].0, Level::Warn);
+        assert!(risks[0].1.starts_with("event=min_notional_reject "));
+    }
+}
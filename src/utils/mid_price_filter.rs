@@ -0,0 +1,199 @@
+use std::collections::{HashMap, VecDeque};
+use log::debug;
+
+use crate::core::{OrderBook, errors::Result, traits::DataSource};
+
+/// Smallest deviation scale as a fraction of the median, so a flat window
+/// doesn't reject the first ordinary tick
+const MIN_RELATIVE_SCALE: f64 = 2e-4;
+
+/// Settings for the Hampel filter on book mid prices
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MidPriceFilterConfig {
+    /// Number of recent mids in the rolling median
+    pub window: usize,
+    /// Mids further than this many scaled median absolute deviations from the median are rejected
+    pub max_deviation_mads: f64,
+}
+
+impl Default for MidPriceFilterConfig {
+    fn default() -> Self {
+        Self {
+            window: 21,
+            max_deviation_mads: 5.0,
+        }
+    }
+}
+
+/// Rolling Hampel filter rejecting isolated mid-price spikes
+///
+/// Every mid enters the window, rejected or not, so a genuine level shift is
+/// accepted once it makes up half the window.
+pub struct MidPriceFilter {
+    config: MidPriceFilterConfig,
+    recent: VecDeque<f64>,
+}
+
+impl MidPriceFilter {
+    pub fn new(config: MidPriceFilterConfig) -> Self {
+        Self {
+            config,
+            recent: VecDeque::with_capacity(config.window),
+        }
+    }
+
+    /// Whether `mid` is consistent with the recent mids
+    pub fn accept(&mut self, mid: f64) -> bool {
+        // One-sided or crossed-to-zero books have no usable mid
+        if mid <= 0.0 {
+            return false;
+        }
+
+        let accepted = if self.recent.len() < 3 {
+            true
+        } else {
+            let median = median(self.recent.iter().copied().collect());
+            let mad = median(self.recent.iter().map(|x| (x - median).abs()).collect());
+            let scale = (1.4826 * mad).max(median * MIN_RELATIVE_SCALE);
+            (mid - median).abs() <= self.config.max_deviation_mads * scale
+        };
+
+        self.recent.push_back(mid);
+        if self.recent.len() > self.config.window {
+            self.recent.pop_front();
+        }
+
+        accepted
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 1 {
+        values[mid]
+    } else {
+        (values[mid - 1] + values[mid]) / 2.0
+    }
+}
+
+/// Data source adapter that drops books whose mid fails a `MidPriceFilter`, with one
+/// filter per symbol so interleaved streams are each compared against their own mids
+pub struct FilteredDataSource {
+    inner: Box<dyn DataSource>,
+    config: MidPriceFilterConfig,
+    filters: HashMap<String, MidPriceFilter>,
+    rejected: usize,
+}
+
+impl FilteredDataSource {
+    pub fn new(inner: Box<dyn DataSource>, config: MidPriceFilterConfig) -> Self {
+        Self {
+            inner,
+            config,
+            filters: HashMap::new(),
+            rejected: 0,
+        }
+    }
+
+    /// Number of books dropped so far
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+}
+
+impl DataSource for FilteredDataSource {
+    fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+        while let Some(order_book) = self.inner.next_orderbook()? {
+            let mid = order_book.mid_price();
+            let config = self.config;
+            let filter = self.filters.entry(order_book.symbol.clone())
+                .or_insert_with(|| MidPriceFilter::new(config));
+            if filter.accept(mid) {
+                return Ok(Some(order_book));
+            }
+            self.rejected += 1;
+            debug!("Dropping {} book at {}: mid {} rejected by spike filter",
+                   order_book.symbol, order_book.current_time, mid);
+        }
+        Ok(None)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.filters.clear();
+        self.rejected = 0;
+        self.inner.reset()
+    }
+
+    fn total_count(&self) -> Option<usize> {
+        self.inner.total_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_spike_rejected() {
+        let mut filter = MidPriceFilter::new(MidPriceFilterConfig { window: 9, max_deviation_mads: 5.0 });
+        let mids = [100.00, 100.02, 100.01, 100.03, 100.02, 100.04, 100.03, 101.50, 100.05, 100.04, 100.06];
+
+        let rejected: Vec<f64> = mids.iter().copied().filter(|&mid| !filter.accept(mid)).collect();
+
+        assert_eq!(rejected, vec![101.50]);
+    }
+
+    #[test]
+    fn test_level_shift_eventually_accepted() {
+        let mut filter = MidPriceFilter::new(MidPriceFilterConfig { window: 5, max_deviation_mads: 5.0 });
+        for mid in [100.0, 100.0, 100.05, 100.0, 100.05] {
+            assert!(filter.accept(mid));
+        }
+
+        let accepted: Vec<bool> = (0..5).map(|_| filter.accept(110.0)).collect();
+        assert_eq!(accepted, vec![false, false, false, true, true]);
+    }
+
+    struct VecSource {
+        books: Vec<OrderBook>,
+        index: usize,
+    }
+
+    impl DataSource for VecSource {
+        fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+            self.index += 1;
+            Ok(self.books.get(self.index - 1).cloned())
+        }
+
+        fn reset(&mut self) -> Result<()> {
+            self.index = 0;
+            Ok(())
+        }
+
+        fn total_count(&self) -> Option<usize> {
+            Some(self.books.len())
+        }
+    }
+
+    #[test]
+    fn test_interleaved_symbols_filtered_separately() {
+        let book = |symbol: &str, mid: f64, time: i64| {
+            OrderBook::new(symbol.to_string(), vec![(mid - 0.5, 1.0)], vec![(mid + 0.5, 1.0)], time)
+        };
+        let books = (0..10)
+            .flat_map(|i| [book("BTCUSDT", 30_000.0 + i as f64, i * 2), book("ETHUSDT", 2_000.0 + i as f64, i * 2 + 1)])
+            .chain([book("ETHUSDT", 2_500.0, 20)])
+            .collect();
+        let config = MidPriceFilterConfig { window: 9, max_deviation_mads: 5.0 };
+        let mut source = FilteredDataSource::new(Box::new(VecSource { books, index: 0 }), config);
+
+        let mut passed = 0;
+        while source.next_orderbook().unwrap().is_some() {
+            passed += 1;
+        }
+        // Each symbol's ticks are in line with its own mids; only the ETH spike is dropped
+        assert_eq!(passed, 20);
+        assert_eq!(source.rejected(), 1);
+    }
+}
@@ -1,7 +1,110 @@
 pub mod loader;
 pub mod parquet_loader;
+pub mod csv_loader;
+pub mod feature_source;
+pub mod mid_price_filter;
+pub mod logging;
 pub mod multi_file_source;
+pub mod resampling_source;
+pub mod dedup_source;
+pub mod time_format;
+pub mod input_files;
+pub mod thread_pool;
+pub(crate) mod parquet_recovery;
+#[cfg(test)]
+pub(crate) mod test_dir;
 
-pub use loader::{FileDataSource, OrderBookMessage, extract_symbol_from_filename};
+use std::path::Path;
+
+use crate::core::{errors::{Result, TradeError}, traits::DataSource};
+
+pub use loader::{FileDataSource, OrderBookMessage, extract_symbol_from_filename, SymbolExtractor};
 pub use parquet_loader::ParquetDataSource;
-pub use multi_file_source::MultiFileDataSource;
\ No newline at end of file
+pub use csv_loader::{CsvDataSource, CsvSchema, CsvColumn, CsvBookColumns};
+pub use feature_source::FeatureSource;
+pub use mid_price_filter::{MidPriceFilter, MidPriceFilterConfig, FilteredDataSource};
+pub use multi_file_source::MultiFileDataSource;
+pub use resampling_source::ResamplingDataSource;
+pub use dedup_source::DedupDataSource;
+pub use time_format::{TimeFormat, TimestampFormatter};
+pub use input_files::{find_matching_files, looks_like_path, resolve_input_files};
+pub use thread_pool::init_global_thread_pool;
+
+/// Open `path` as a counted data source, picking the reader by extension:
+/// `.jsonl`/`.json` lines (optionally `.gz` / `.zst` compressed), `.parquet` or `.csv`
+pub fn open_data_source(path: &Path) -> Result<Box<dyn DataSource>> {
+    open_data_source_with(path, false)
+}
+
+/// `open_data_source`, recovering footerless Parquet files when `lenient_parquet` is set
+pub fn open_data_source_with(path: &Path, lenient_parquet: bool) -> Result<Box<dyn DataSource>> {
+    let filename = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    let uncompressed = loader::Compression::strip_suffix(filename);
+    let extension = Path::new(uncompressed)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+    // Only the line-based readers decompress; a compressed Parquet or CSV file is unsupported
+    let compressed = uncompressed.len() < filename.len();
+    let source: Box<dyn DataSource> = match (extension.to_ascii_lowercase().as_str(), compressed) {
+        ("jsonl" | "json", _) => {
+            let mut source = FileDataSource::new(path)?.with_batch_size(10000);
+            source.count_messages()?;
+            Box::new(source)
+        }
+        ("parquet", false) => {
+            let mut source = if lenient_parquet {
+                ParquetDataSource::open_lenient(path)?
+            } else {
+                ParquetDataSource::new(path)?
+            };
+            source.count_messages()?;
+            Box::new(source)
+        }
+        ("csv", false) => {
+            let mut source = CsvDataSource::new(path, CsvSchema::default())?;
+            source.count_messages()?;
+            Box::new(source)
+        }
+        _ => return Err(TradeError::DataLoadingError(format!(
+            "Unsupported data file extension {:?} for {:?} (expected jsonl, json, parquet or csv)",
+            format!("{}{}", extension, &filename[uncompressed.len()..]), path
+        ))),
+    };
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+
+    #[test]
+    fn test_open_data_source_dispatches_on_extension() {
+        let dir = TestDir::new("open_data_source");
+
+        let jsonl = dir.join("BTCUSDT_20240101.jsonl");
+        std::fs::write(&jsonl, r#"{"ts":1000,"data":{"b":[["100.0","1.0"]],"a":[["100.1","1.0"]]}}"#).unwrap();
+        let csv = dir.join("BTCUSDT_20240101.csv");
+        std::fs::write(&csv, "timestamp,bids_json,asks_json\n1000,\"[[100.0,1.0]]\",\"[[100.1,1.0]]\"\n").unwrap();
+
+        for path in [&jsonl, &csv] {
+            let mut source = open_data_source(path).unwrap();
+            assert_eq!(source.total_count(), Some(1), "{:?}", path);
+            assert_eq!(source.next_orderbook().unwrap().unwrap().bids, vec![(100.0, 1.0)]);
+        }
+
+        let unknown = dir.join("BTCUSDT_20240101.txt");
+        std::fs::write(&unknown, "").unwrap();
+        let err = open_data_source(&unknown).err().unwrap();
+        assert!(matches!(err, TradeError::DataLoadingError(_)));
+        assert!(err.to_string().contains("\"txt\""), "{}", err);
+
+        // Only JSON lines are read through the decompressor
+        let compressed_csv = dir.join("BTCUSDT_20240101.csv.gz");
+        std::fs::write(&compressed_csv, "").unwrap();
+        let err = open_data_source(&compressed_csv).err().unwrap();
+        assert!(matches!(err, TradeError::DataLoadingError(_)));
+        assert!(err.to_string().contains("\"csv.gz\""), "{}", err);
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use log::info;
+
+use crate::core::{OrderBook, errors::Result, traits::DataSource};
+use crate::utils::open_data_source_with;
+
+/// Data source that chains several files into one continuous stream
+pub struct MultiFileDataSource {
+    sources: Vec<Box<dyn DataSource>>,
+    current_file: usize,
+}
+
+impl MultiFileDataSource {
+    /// Open each path (JSONL, `.parquet` or `.csv`) in order
+    pub fn new(file_paths: Vec<PathBuf>) -> Result<Self> {
+        Self::open(file_paths, false)
+    }
+
+    /// Open each path in order, recovering footerless Parquet files when `lenient_parquet` is set
+    pub fn open(file_paths: Vec<PathBuf>, lenient_parquet: bool) -> Result<Self> {
+        let sources = file_paths
+            .iter()
+            .map(|path| open_file(path, lenient_parquet))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_sources(sources))
+    }
+
+    /// Chain already-opened sources, one per file
+    pub fn from_sources(sources: Vec<Box<dyn DataSource>>) -> Self {
+        Self { sources, current_file: 0 }
+    }
+
+    /// Wrap each file's source, e.g. in a spike filter or resampler
+    pub fn map_files<F>(mut self, wrap: F) -> Self
+    where
+        F: FnMut(Box<dyn DataSource>) -> Box<dyn DataSource>,
+    {
+        self.sources = self.sources.into_iter().map(wrap).collect();
+        self
+    }
+
+    /// Index of the file the last returned book came from
+    pub fn current_file(&self) -> usize {
+        self.current_file
+    }
+
+    /// Number of chained files
+    pub fn file_count(&self) -> usize {
+        self.sources.len()
+    }
+}
+
+/// Open a single file as a counted data source, picking the reader by extension
+fn open_file(path: &Path, lenient_parquet: bool) -> Result<Box<dyn DataSource>> {
+    let source = open_data_source_with(path, lenient_parquet)?;
+    info!("Opened {:?} ({} messages)", path, source.total_count().unwrap_or(0));
+    Ok(source)
+}
+
+impl DataSource for MultiFileDataSource {
+    fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+        while let Some(source) = self.sources.get_mut(self.current_file) {
+            if let Some(order_book) = source.next_orderbook()? {
+                return Ok(Some(order_book));
+            }
+            if self.current_file + 1 >= self.sources.len() {
+                break;
+            }
+            self.current_file += 1;
+        }
+        Ok(None)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        for source in &mut self.sources {
+            source.reset()?;
+        }
+        self.current_file = 0;
+        Ok(())
+    }
+
+    fn total_count(&self) -> Option<usize> {
+        self.sources.iter().map(|source| source.total_count()).sum()
+    }
+}
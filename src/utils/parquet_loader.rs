@@ -1,12 +1,21 @@
 use std::fs::File;
 use std::path::{Path, PathBuf};
-use log::{info, debug};
-use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
-use arrow::array::{Int64Array, StringArray};
+use std::sync::Arc;
+use log::{info, debug, warn};
+use parquet::arrow::arrow_reader::{ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReaderBuilder};
+use parquet::file::metadata::ParquetMetaData;
+use arrow::array::{
+    Array, ArrayRef, Float32Array, Float64Array, Int32Array, Int64Array, LargeListArray, LargeStringArray,
+    ListArray, StringArray, StructArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+    TimestampNanosecondArray, TimestampSecondArray, UInt64Array,
+};
+use arrow::datatypes::{DataType, TimeUnit};
 use arrow::record_batch::RecordBatch;
 use serde_json;
 
 use crate::core::{OrderBook, errors::{Result, TradeError}, traits::DataSource};
+use crate::reader::storage::ParquetWriter;
+use crate::utils::parquet_recovery::recover_metadata;
 
 /// Parquet-based data source for order book messages
 pub struct ParquetDataSource {
@@ -16,6 +25,8 @@ pub struct ParquetDataSource {
     current_row: usize,
     batch_reader: Option<parquet::arrow::arrow_reader::ParquetRecordBatchReader>,
     total_messages: Option<usize>,
+    /// Metadata rebuilt by `open_lenient` for a file without a footer
+    recovered: Option<Arc<ParquetMetaData>>,
 }
 
 impl ParquetDataSource {
@@ -41,9 +52,29 @@ impl ParquetDataSource {
             current_row: 0,
             batch_reader: None,
             total_messages: None,
+            recovered: None,
         })
     }
     
+    /// Open a file that may lack its footer, e.g. a reader capture cut short by a crash.
+    ///
+    /// Files that open normally are read as usual. Otherwise the complete row groups
+    /// written by the reader's `ParquetWriter` are recovered and the partial tail dropped.
+    pub fn open_lenient(file_path: impl AsRef<Path>) -> Result<Self> {
+        let mut source = Self::new(file_path)?;
+        if let Err(e) = source.init_reader() {
+            warn!("{}; recovering complete row groups from {:?}", e, source.file_path);
+            let metadata = recover_metadata(
+                &source.file_path,
+                &ParquetWriter::create_schema(),
+                &ParquetWriter::writer_properties(),
+            )?;
+            source.recovered = Some(Arc::new(metadata));
+            source.init_reader()?;
+        }
+        Ok(source)
+    }
+    
     /// Initialize the Parquet reader
     fn init_reader(&mut self) -> Result<()> {
         if self.batch_reader.is_some() {
@@ -55,10 +86,14 @@ impl ParquetDataSource {
                 format!("Failed to open parquet file: {}", e)
             ))?;
         
-        let builder = ParquetRecordBatchReaderBuilder::try_new(file)
-            .map_err(|e| TradeError::DataLoadingError(
-                format!("Failed to create parquet reader: {}", e)
-            ))?;
+        let builder = match &self.recovered {
+            Some(metadata) => ArrowReaderMetadata::try_new(metadata.clone(), ArrowReaderOptions::new())
+                .map(|metadata| ParquetRecordBatchReaderBuilder::new_with_metadata(file, metadata)),
+            None => ParquetRecordBatchReaderBuilder::try_new(file),
+        }
+        .map_err(|e| TradeError::DataLoadingError(
+            format!("Failed to create parquet reader: {}", e)
+        ))?;
         
         // Get total row count from metadata
         let metadata = builder.metadata();
@@ -101,78 +136,38 @@ impl ParquetDataSource {
     }
     
     /// Parse a row from the current batch into an OrderBook
+    ///
+    /// Accepts `timestamp` (or `ts`) as integer, float or Arrow timestamp columns, and
+    /// `bids`/`asks` as JSON strings or native lists of `[price, quantity]` pairs or
+    /// `{price, quantity}` structs. A `symbol` column overrides the filename symbol.
     fn parse_row(&self, batch: &RecordBatch, row_idx: usize) -> Result<OrderBook> {
-        // The Parquet file has columns: timestamp (Int64), bids (String/JSON), asks (String/JSON)
-        
-        let ts_column = batch
-            .column_by_name("timestamp")
-            .ok_or_else(|| TradeError::DataLoadingError("Missing 'timestamp' column in parquet".to_string()))?
-            .as_any()
-            .downcast_ref::<Int64Array>()
-            .ok_or_else(|| TradeError::DataLoadingError("'timestamp' column is not Int64".to_string()))?;
-        
-        let bids_column = batch
-            .column_by_name("bids")
-            .ok_or_else(|| TradeError::DataLoadingError("Missing 'bids' column in parquet".to_string()))?
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| TradeError::DataLoadingError("'bids' column is not String".to_string()))?;
-            
-        let asks_column = batch
-            .column_by_name("asks")
-            .ok_or_else(|| TradeError::DataLoadingError("Missing 'asks' column in parquet".to_string()))?
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| TradeError::DataLoadingError("'asks' column is not String".to_string()))?;
-        
-        let ts = ts_column.value(row_idx);
-        let bids_json = bids_column.value(row_idx);
-        let asks_json = asks_column.value(row_idx);
-        
-        // Parse the JSON arrays directly
-        let bid_array: Vec<Vec<String>> = serde_json::from_str(bids_json)
-            .map_err(|e| TradeError::DataLoadingError(
-                format!("Failed to parse bids JSON: {}", e)
-            ))?;
-            
-        let ask_array: Vec<Vec<String>> = serde_json::from_str(asks_json)
-            .map_err(|e| TradeError::DataLoadingError(
-                format!("Failed to parse asks JSON: {}", e)
-            ))?;
+        let column = |name: &str| batch.column_by_name(name);
+        let required = |names: &[&str]| {
+            names.iter().find_map(|name| column(name)).ok_or_else(|| TradeError::DataLoadingError(
+                format!("Missing '{}' column in parquet", names[0])
+            ))
+        };
         
-        // Parse bids
-        let mut bids = Vec::new();
-        for bid in &bid_array {
-            if bid.len() >= 2 {
-                let price = bid[0].parse::<f64>()
-                    .map_err(|_| TradeError::InvalidOrderBook(
-                        format!("Invalid bid price: {}", bid[0])
-                    ))?;
-                let quantity = bid[1].parse::<f64>()
-                    .map_err(|_| TradeError::InvalidOrderBook(
-                        format!("Invalid bid quantity: {}", bid[1])
-                    ))?;
-                bids.push((price, quantity));
-            }
-        }
+        let ts = timestamp_millis(required(&["timestamp", "ts"])?, row_idx)?;
+        let bids = levels_at(required(&["bids"])?, row_idx, "bid")?;
+        let asks = levels_at(required(&["asks"])?, row_idx, "ask")?;
         
-        // Parse asks
-        let mut asks = Vec::new();
-        for ask in &ask_array {
-            if ask.len() >= 2 {
-                let price = ask[0].parse::<f64>()
-                    .map_err(|_| TradeError::InvalidOrderBook(
-                        format!("Invalid ask price: {}", ask[0])
-                    ))?;
-                let quantity = ask[1].parse::<f64>()
-                    .map_err(|_| TradeError::InvalidOrderBook(
-                        format!("Invalid ask quantity: {}", ask[1])
-                    ))?;
-                asks.push((price, quantity));
-            }
-        }
+        let symbol = column("symbol")
+            .and_then(|column| string_at(column, row_idx))
+            .unwrap_or_else(|| self.symbol.clone());
+        
+        // Optional exchange sequence number
+        let update_id = batch
+            .column_by_name("update_id")
+            .and_then(|column| column.as_any().downcast_ref::<Int64Array>())
+            .filter(|column| !column.is_null(row_idx))
+            .map(|column| column.value(row_idx));
         
-        Ok(OrderBook::new(self.symbol.clone(), bids, asks, ts))
+        let order_book = OrderBook::new(symbol, bids, asks, ts);
+        Ok(match update_id {
+            Some(update_id) => order_book.with_update_id(update_id),
+            None => order_book,
+        })
     }
     
     /// Count total messages (rows) in the file
@@ -218,4 +213,226 @@ impl DataSource for ParquetDataSource {
     fn total_count(&self) -> Option<usize> {
         self.total_messages
     }
-}
\ No newline at end of file
+}
+
+/// Millisecond timestamp from an integer (ms), float (ms) or Arrow timestamp column
+fn timestamp_millis(column: &ArrayRef, row: usize) -> Result<i64> {
+    let any = column.as_any();
+    let ts = match column.data_type() {
+        DataType::Int64 => any.downcast_ref::<Int64Array>().map(|a| a.value(row)),
+        DataType::Int32 => any.downcast_ref::<Int32Array>().map(|a| a.value(row) as i64),
+        DataType::UInt64 => any.downcast_ref::<UInt64Array>().map(|a| a.value(row) as i64),
+        DataType::Float64 => any.downcast_ref::<Float64Array>().map(|a| a.value(row) as i64),
+        DataType::Timestamp(TimeUnit::Second, _) => any.downcast_ref::<TimestampSecondArray>().map(|a| a.value(row) * 1000),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => any.downcast_ref::<TimestampMillisecondArray>().map(|a| a.value(row)),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => any.downcast_ref::<TimestampMicrosecondArray>().map(|a| a.value(row) / 1000),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => any.downcast_ref::<TimestampNanosecondArray>().map(|a| a.value(row) / 1_000_000),
+        _ => None,
+    };
+    ts.ok_or_else(|| TradeError::DataLoadingError(
+        format!("Unsupported timestamp column type in parquet: {:?}", column.data_type())
+    ))
+}
+
+fn string_at(column: &ArrayRef, row: usize) -> Option<String> {
+    if column.is_null(row) {
+        return None;
+    }
+    let any = column.as_any();
+    any.downcast_ref::<StringArray>().map(|a| a.value(row).to_string())
+        .or_else(|| any.downcast_ref::<LargeStringArray>().map(|a| a.value(row).to_string()))
+}
+
+/// Number from a float, integer or numeric-string array
+fn number_at(values: &ArrayRef, row: usize, side: &str) -> Result<f64> {
+    let any = values.as_any();
+    let number = any.downcast_ref::<Float64Array>().map(|a| a.value(row))
+        .or_else(|| any.downcast_ref::<Float32Array>().map(|a| a.value(row) as f64))
+        .or_else(|| any.downcast_ref::<Int64Array>().map(|a| a.value(row) as f64))
+        .or_else(|| string_at(values, row).and_then(|s| s.parse::<f64>().ok()));
+    number.ok_or_else(|| TradeError::InvalidOrderBook(
+        format!("Invalid {} level value of type {:?}", side, values.data_type())
+    ))
+}
+
+/// Book levels from a JSON string column or a native list column
+fn levels_at(column: &ArrayRef, row: usize, side: &str) -> Result<Vec<(f64, f64)>> {
+    if let Some(json) = string_at(column, row) {
+        return parse_json_levels(&json, side);
+    }
+    let any = column.as_any();
+    let levels = any.downcast_ref::<ListArray>().map(|a| a.value(row))
+        .or_else(|| any.downcast_ref::<LargeListArray>().map(|a| a.value(row)))
+        .ok_or_else(|| TradeError::DataLoadingError(
+            format!("Unsupported {}s column type in parquet: {:?}", side, column.data_type())
+        ))?;
+    
+    let levels_any = levels.as_any();
+    if let Some(pairs) = levels_any.downcast_ref::<ListArray>() {
+        (0..pairs.len())
+            .map(|i| {
+                let pair = pairs.value(i);
+                if pair.len() < 2 {
+                    return Err(TradeError::InvalidOrderBook(format!("Incomplete {} level", side)));
+                }
+                Ok((number_at(&pair, 0, side)?, number_at(&pair, 1, side)?))
+            })
+            .collect()
+    } else if let Some(structs) = levels_any.downcast_ref::<StructArray>() {
+        let field = |names: &[&str]| names.iter().find_map(|name| structs.column_by_name(name)).cloned()
+            .ok_or_else(|| TradeError::DataLoadingError(
+                format!("{} level structs need '{}' field", side, names[0])
+            ));
+        let prices = field(&["price", "p"])?;
+        let quantities = field(&["quantity", "size", "qty", "q"])?;
+        (0..structs.len())
+            .map(|i| Ok((number_at(&prices, i, side)?, number_at(&quantities, i, side)?)))
+            .collect()
+    } else {
+        Err(TradeError::DataLoadingError(
+            format!("Unsupported {} level type in parquet: {:?}", side, levels.data_type())
+        ))
+    }
+}
+
+/// Levels from a JSON array of `[price, quantity]` pairs given as strings or numbers
+fn parse_json_levels(json: &str, side: &str) -> Result<Vec<(f64, f64)>> {
+    let levels: Vec<Vec<serde_json::Value>> = serde_json::from_str(json)
+        .map_err(|e| TradeError::DataLoadingError(
+            format!("Failed to parse {}s JSON: {}", side, e)
+        ))?;
+    let number = |value: &serde_json::Value| {
+        let parsed = match value {
+            serde_json::Value::String(s) => s.parse::<f64>().ok(),
+            other => other.as_f64(),
+        };
+        parsed.ok_or_else(|| TradeError::InvalidOrderBook(format!("Invalid {} value: {}", side, value)))
+    };
+    
+    levels.iter()
+        .filter(|level| level.len() >= 2)
+        .map(|level| Ok((number(&level[0])?, number(&level[1])?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Float64Builder, ListBuilder, StringBuilder};
+    use arrow::datatypes::{Field, Schema};
+    use parquet::arrow::ArrowWriter;
+    use crate::utils::test_dir::TestDir;
+
+    fn write_parquet(path: &Path, columns: Vec<(&str, ArrayRef)>) {
+        let schema = Schema::new(columns.iter()
+            .map(|(name, array)| Field::new(*name, array.data_type().clone(), true))
+            .collect::<Vec<_>>());
+        let batch = RecordBatch::try_new(Arc::new(schema), columns.into_iter().map(|(_, a)| a).collect()).unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    fn read_all(path: &Path) -> Vec<OrderBook> {
+        let mut source = ParquetDataSource::new(path).unwrap();
+        let mut books = Vec::new();
+        while let Some(book) = source.next_orderbook().unwrap() {
+            books.push(book);
+        }
+        books
+    }
+
+    #[test]
+    fn test_reader_schema_and_pyarrow_schema_both_load() {
+        let dir = TestDir::new("parquet");
+
+        // Schema written by the reader: Int64 timestamps and JSON-string levels
+        let json = |levels: &str| -> ArrayRef { Arc::new(StringArray::from(vec![levels])) };
+        let reader_path = dir.join("BTCUSDT_reader.parquet");
+        write_parquet(&reader_path, vec![
+            ("timestamp", Arc::new(Int64Array::from(vec![1_000])) as ArrayRef),
+            ("bids", json(r#"[["100.0","1.5"],["99.9","2.0"]]"#)),
+            ("asks", json(r#"[["100.1","0.5"]]"#)),
+        ]);
+
+        // pandas/pyarrow style: symbol column, float timestamps, native list levels
+        let list = |levels: &[(f64, f64)]| -> ArrayRef {
+            let mut builder = ListBuilder::new(ListBuilder::new(Float64Builder::new()));
+            for (price, quantity) in levels {
+                builder.values().values().append_value(*price);
+                builder.values().values().append_value(*quantity);
+                builder.values().append(true);
+            }
+            builder.append(true);
+            Arc::new(builder.finish())
+        };
+        let pyarrow_path = dir.join("export.parquet");
+        let mut symbols = StringBuilder::new();
+        symbols.append_value("ETHUSDT");
+        write_parquet(&pyarrow_path, vec![
+            ("symbol", Arc::new(symbols.finish()) as ArrayRef),
+            ("timestamp", Arc::new(Float64Array::from(vec![2_000.0]))),
+            ("bids", list(&[(100.0, 1.5), (99.9, 2.0)])),
+            ("asks", list(&[(100.1, 0.5)])),
+        ]);
+
+        let reader_books = read_all(&reader_path);
+        let pyarrow_books = read_all(&pyarrow_path);
+
+        assert_eq!(reader_books.len(), 1);
+        assert_eq!(reader_books[0].symbol, "BTCUSDT");
+        assert_eq!(reader_books[0].current_time, 1_000);
+        assert_eq!(pyarrow_books.len(), 1);
+        assert_eq!(pyarrow_books[0].symbol, "ETHUSDT");
+        assert_eq!(pyarrow_books[0].current_time, 2_000);
+        for book in [&reader_books[0], &pyarrow_books[0]] {
+            assert_eq!(book.bids, vec![(100.0, 1.5), (99.9, 2.0)]);
+            assert_eq!(book.asks, vec![(100.1, 0.5)]);
+        }
+    }
+
+    #[test]
+    fn test_open_lenient_recovers_complete_row_groups_of_truncated_capture() {
+        use crate::reader::models::OrderbookData;
+        use crate::reader::storage::{StorageWriter, WriterConfig};
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let dir = TestDir::new("parquet_lenient");
+        let base = dir.join("BTCUSDT_capture");
+
+        // Three row groups of two books each
+        let mut writer = ParquetWriter::new();
+        writer.init(WriterConfig { base_filename: base.to_string_lossy().into_owned(), buffer_size: 2, ..Default::default() }).unwrap();
+        for i in 0..6 {
+            writer.write(&OrderbookData {
+                symbol: "BTCUSDT".to_string(),
+                bids: vec![[format!("{}", 100 + i), "1".to_string()]],
+                asks: vec![[format!("{}", 101 + i), "1".to_string()]],
+                timestamp: 1_000 * i,
+                update_id: i,
+                fetch_time: 1_000 * i,
+                msg_type: None,
+            }).unwrap();
+        }
+        writer.close().unwrap();
+
+        // Cut the file partway into the third row group, losing it and the footer
+        let path = dir.join("BTCUSDT_capture.parquet");
+        let third_group_start = SerializedFileReader::new(File::open(&path).unwrap()).unwrap()
+            .metadata().row_group(2).column(0).byte_range().0;
+        File::options().write(true).open(&path).unwrap().set_len(third_group_start + 10).unwrap();
+
+        assert!(ParquetDataSource::new(&path).unwrap().next_orderbook().is_err());
+
+        let mut source = ParquetDataSource::open_lenient(&path).unwrap();
+        assert_eq!(source.count_messages().unwrap(), 4);
+        let mut books = Vec::new();
+        while let Some(book) = source.next_orderbook().unwrap() {
+            books.push(book);
+        }
+
+        assert_eq!(books.iter().map(|book| book.current_time).collect::<Vec<_>>(), vec![0, 1_000, 2_000, 3_000]);
+        assert_eq!(books[3].update_id, Some(3));
+        assert_eq!(books[3].bids, vec![(103.0, 1.0)]);
+    }
+}
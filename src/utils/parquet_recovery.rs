@@ -0,0 +1,286 @@
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use arrow::datatypes::Schema;
+use log::info;
+use parquet::arrow::ArrowWriter;
+use parquet::file::metadata::{ColumnChunkMetaData, FileMetaData, ParquetMetaData, RowGroupMetaData};
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::schema::types::SchemaDescPtr;
+
+use crate::core::errors::{Result, TradeError};
+
+const MAGIC: &[u8] = b"PAR1";
+const DATA_PAGE: i32 = 0;
+const DICTIONARY_PAGE: i32 = 2;
+const DATA_PAGE_V2: i32 = 3;
+
+static TEMPLATE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn recovery_error(e: impl std::fmt::Display) -> TradeError {
+    TradeError::DataLoadingError(format!("Failed to recover parquet file: {}", e))
+}
+
+/// Rebuild metadata for the complete row groups of a Parquet file that lost its footer.
+///
+/// The file must have been written with `schema` and `props` by a writer that starts every
+/// column chunk with a dictionary page (the arrow writer's default), and the schema must be
+/// flat, so every column of a row group holds one value per row. Pages are located by
+/// scanning their headers; a row group is kept only if all its columns are complete.
+pub(crate) fn recover_metadata(path: &Path, schema: &Schema, props: &WriterProperties) -> Result<ParquetMetaData> {
+    let data = fs::read(path)?;
+    if !data.starts_with(MAGIC) {
+        return Err(recovery_error("missing PAR1 header"));
+    }
+
+    let (schema_descr, key_value_metadata) = template_metadata(schema, props)?;
+    let num_columns = schema_descr.num_columns();
+
+    let mut row_groups = Vec::new();
+    for group in scan_chunks(&data).chunks_exact(num_columns) {
+        let num_rows = group[0].num_values;
+        if group.iter().any(|chunk| chunk.num_values != num_rows) {
+            break;
+        }
+
+        let columns = group.iter().zip(schema_descr.columns())
+            .map(|(chunk, descr)| {
+                ColumnChunkMetaData::builder(descr.clone())
+                    .set_compression(props.compression(descr.path()))
+                    .set_num_values(chunk.num_values)
+                    .set_total_compressed_size((chunk.end - chunk.start) as i64)
+                    .set_total_uncompressed_size(chunk.uncompressed_size)
+                    .set_data_page_offset(chunk.data_offset.unwrap_or(chunk.start) as i64)
+                    .set_dictionary_page_offset(chunk.dictionary_offset.map(|offset| offset as i64))
+                    .build()
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(recovery_error)?;
+        let row_group = RowGroupMetaData::builder(schema_descr.clone())
+            .set_num_rows(num_rows)
+            .set_total_byte_size(group.iter().map(|chunk| chunk.uncompressed_size).sum())
+            .set_column_metadata(columns)
+            .build()
+            .map_err(recovery_error)?;
+        row_groups.push(row_group);
+    }
+
+    let num_rows = row_groups.iter().map(|group| group.num_rows()).sum();
+    info!("Recovered {} complete row groups ({} rows) from {:?}", row_groups.len(), num_rows, path);
+    let file_metadata = FileMetaData::new(1, num_rows, None, key_value_metadata, schema_descr, None);
+    Ok(ParquetMetaData::new(file_metadata, row_groups))
+}
+
+/// Parquet schema and key-value metadata (which carries the Arrow schema) the writer
+/// produces for `schema`, read back from an empty file
+fn template_metadata(
+    schema: &Schema,
+    props: &WriterProperties,
+) -> Result<(SchemaDescPtr, Option<Vec<parquet::format::KeyValue>>)> {
+    let path = std::env::temp_dir().join(format!(
+        "happytest_template_{}_{}.parquet",
+        std::process::id(),
+        TEMPLATE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let writer = ArrowWriter::try_new(File::create(&path)?, Arc::new(schema.clone()), Some(props.clone()))
+        .map_err(recovery_error)?;
+    writer.close().map_err(recovery_error)?;
+
+    let reader = SerializedFileReader::new(File::open(&path)?).map_err(recovery_error);
+    fs::remove_file(&path)?;
+    let file_metadata = reader?.metadata().file_metadata().clone();
+    Ok((file_metadata.schema_descr_ptr(), file_metadata.key_value_metadata().cloned()))
+}
+
+/// Byte span and value count of one column chunk found by the page scan
+struct ChunkScan {
+    start: usize,
+    end: usize,
+    dictionary_offset: Option<usize>,
+    data_offset: Option<usize>,
+    num_values: i64,
+    uncompressed_size: i64,
+}
+
+/// Column chunks in file order, ending at the first truncated or unreadable page.
+/// A dictionary page starts a new chunk.
+fn scan_chunks(data: &[u8]) -> Vec<ChunkScan> {
+    let mut chunks = Vec::new();
+    let mut current: Option<ChunkScan> = None;
+    let mut pos = MAGIC.len();
+
+    while let Some(header) = page_header(&data[pos..]) {
+        let page_end = pos + header.header_size + header.compressed_size;
+        if page_end > data.len() || ![DATA_PAGE, DICTIONARY_PAGE, DATA_PAGE_V2].contains(&header.page_type) {
+            break;
+        }
+
+        let is_dictionary = header.page_type == DICTIONARY_PAGE;
+        if is_dictionary || current.is_none() {
+            chunks.extend(current.take());
+        }
+        let chunk = current.get_or_insert(ChunkScan {
+            start: pos,
+            end: pos,
+            dictionary_offset: None,
+            data_offset: None,
+            num_values: 0,
+            uncompressed_size: 0,
+        });
+        if is_dictionary {
+            chunk.dictionary_offset = Some(pos);
+        } else {
+            chunk.data_offset.get_or_insert(pos);
+            chunk.num_values += header.num_values;
+        }
+        chunk.uncompressed_size += (header.header_size + header.uncompressed_size) as i64;
+        chunk.end = page_end;
+        pos = page_end;
+    }
+
+    chunks.extend(current);
+    chunks
+}
+
+#[derive(Debug, Default)]
+struct PageHeader {
+    page_type: i32,
+    uncompressed_size: usize,
+    compressed_size: usize,
+    num_values: i64,
+    header_size: usize,
+}
+
+/// Decode the Thrift `PageHeader` at the start of `data`
+fn page_header(data: &[u8]) -> Option<PageHeader> {
+    let mut input = CompactInput { data, pos: 0 };
+    let mut header = PageHeader::default();
+    let mut last_id = 0;
+    while let Some((id, kind)) = input.field(&mut last_id)? {
+        match (id, kind) {
+            (1, I32) => header.page_type = input.zigzag()? as i32,
+            (2, I32) => header.uncompressed_size = usize::try_from(input.zigzag()?).ok()?,
+            (3, I32) => header.compressed_size = usize::try_from(input.zigzag()?).ok()?,
+            // `num_values` is field 1 of both DataPageHeader and DataPageHeaderV2
+            (5 | 8, STRUCT) => header.num_values = input.first_int_field()?,
+            _ => input.skip(kind)?,
+        }
+    }
+    header.header_size = input.pos;
+    Some(header)
+}
+
+const I32: u8 = 5;
+const STRUCT: u8 = 12;
+
+/// Minimal reader for the Thrift compact protocol used by Parquet page headers
+struct CompactInput<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl CompactInput<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn advance(&mut self, len: usize) -> Option<()> {
+        (self.pos + len <= self.data.len()).then(|| self.pos += len)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn zigzag(&mut self) -> Option<i64> {
+        let value = self.varint()?;
+        Some((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Next struct field as `(id, type)`, or None at the end of the struct
+    fn field(&mut self, last_id: &mut i16) -> Option<Option<(i16, u8)>> {
+        let byte = self.byte()?;
+        if byte == 0 {
+            return Some(None);
+        }
+        let delta = (byte >> 4) as i16;
+        let id = if delta == 0 { self.zigzag()? as i16 } else { *last_id + delta };
+        *last_id = id;
+        Some(Some((id, byte & 0x0f)))
+    }
+
+    /// Value of field 1 of a nested struct, skipping the rest of it
+    fn first_int_field(&mut self) -> Option<i64> {
+        let mut value = None;
+        let mut last_id = 0;
+        while let Some((id, kind)) = self.field(&mut last_id)? {
+            if id == 1 && kind == I32 {
+                value = Some(self.zigzag()?);
+            } else {
+                self.skip(kind)?;
+            }
+        }
+        value
+    }
+
+    fn skip(&mut self, kind: u8) -> Option<()> {
+        match kind {
+            // Booleans are carried in the field header
+            1 | 2 => Some(()),
+            3 => self.advance(1),
+            4..=6 => self.varint().map(|_| ()),
+            7 => self.advance(8),
+            8 => {
+                let len = usize::try_from(self.varint()?).ok()?;
+                self.advance(len)
+            }
+            9 | 10 => {
+                let header = self.byte()?;
+                let size = match header >> 4 {
+                    15 => usize::try_from(self.varint()?).ok()?,
+                    size => size as usize,
+                };
+                (0..size).try_for_each(|_| self.skip_element(header & 0x0f))
+            }
+            11 => {
+                let size = usize::try_from(self.varint()?).ok()?;
+                if size == 0 {
+                    return Some(());
+                }
+                let types = self.byte()?;
+                (0..size).try_for_each(|_| {
+                    self.skip_element(types >> 4)?;
+                    self.skip_element(types & 0x0f)
+                })
+            }
+            STRUCT => {
+                let mut last_id = 0;
+                while let Some((_, kind)) = self.field(&mut last_id)? {
+                    self.skip(kind)?;
+                }
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// Skip a list, set or map element, where booleans take a byte of their own
+    fn skip_element(&mut self, kind: u8) -> Option<()> {
+        match kind {
+            1 | 2 => self.advance(1),
+            kind => self.skip(kind),
+        }
+    }
+}
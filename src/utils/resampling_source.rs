@@ -0,0 +1,144 @@
+use crate::core::{OrderBook, errors::Result, traits::DataSource};
+
+/// Data source adapter emitting the latest book on a fixed time grid
+///
+/// Grid points are multiples of `interval_ms`, starting at the first one at or
+/// after the first update. Each emitted book is the most recent update at or
+/// before its grid point, restamped to the grid time; updates superseded within
+/// an interval are dropped. The stream ends at the first grid point at or after
+/// the last update.
+pub struct ResamplingDataSource {
+    inner: Box<dyn DataSource>,
+    interval_ms: i64,
+    latest: Option<OrderBook>,
+    lookahead: Option<OrderBook>,
+    next_grid: Option<i64>,
+    exhausted: bool,
+}
+
+impl ResamplingDataSource {
+    pub fn new(inner: Box<dyn DataSource>, interval_ms: i64) -> Self {
+        assert!(interval_ms > 0, "resampling interval must be positive");
+        Self {
+            inner,
+            interval_ms,
+            latest: None,
+            lookahead: None,
+            next_grid: None,
+            exhausted: false,
+        }
+    }
+
+    /// Pull the next underlying update, remembering when the stream runs out
+    fn pull(&mut self) -> Result<Option<OrderBook>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        let next = self.inner.next_orderbook()?;
+        self.exhausted = next.is_none();
+        Ok(next)
+    }
+}
+
+impl DataSource for ResamplingDataSource {
+    fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+        let grid = match self.next_grid {
+            Some(grid) => grid,
+            None => {
+                let Some(first) = self.pull()? else {
+                    return Ok(None);
+                };
+                let grid = first.current_time.div_euclid(self.interval_ms) * self.interval_ms;
+                let grid = if grid < first.current_time { grid + self.interval_ms } else { grid };
+                self.lookahead = Some(first);
+                grid
+            }
+        };
+
+        // Fold every update up to the grid point into the latest book
+        loop {
+            if self.lookahead.is_none() {
+                self.lookahead = self.pull()?;
+            }
+            match self.lookahead.take() {
+                Some(book) if book.current_time <= grid => self.latest = Some(book),
+                Some(book) => {
+                    self.lookahead = Some(book);
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        // Past the last update there is nothing new to carry forward
+        let latest_time = self.latest.as_ref().map(|book| book.current_time);
+        if self.exhausted && self.lookahead.is_none() && latest_time.is_some_and(|time| time + self.interval_ms <= grid) {
+            return Ok(None);
+        }
+
+        self.next_grid = Some(grid + self.interval_ms);
+        Ok(self.latest.as_ref().map(|book| OrderBook {
+            current_time: grid,
+            ..book.clone()
+        }))
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.latest = None;
+        self.lookahead = None;
+        self.next_grid = None;
+        self.exhausted = false;
+        self.inner.reset()
+    }
+
+    /// Update count of the underlying stream; the resampled count depends on its time span
+    fn total_count(&self) -> Option<usize> {
+        self.inner.total_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct VecSource {
+        books: Vec<OrderBook>,
+        index: usize,
+    }
+
+    impl DataSource for VecSource {
+        fn next_orderbook(&mut self) -> Result<Option<OrderBook>> {
+            let book = self.books.get(self.index).cloned();
+            self.index += 1;
+            Ok(book)
+        }
+
+        fn reset(&mut self) -> Result<()> {
+            self.index = 0;
+            Ok(())
+        }
+
+        fn total_count(&self) -> Option<usize> {
+            Some(self.books.len())
+        }
+    }
+
+    #[test]
+    fn test_irregular_stream_resampled_to_even_grid() {
+        let book = |bid: f64, time: i64| OrderBook::new("BTCUSDT".to_string(), vec![(bid, 1.0)], vec![(bid + 0.1, 1.0)], time);
+        let updates = vec![book(100.0, 1030), book(100.1, 1050), book(100.2, 1170), book(100.3, 1420)];
+        let mut source = ResamplingDataSource::new(Box::new(VecSource { books: updates, index: 0 }), 100);
+
+        let mut emitted = Vec::new();
+        while let Some(order_book) = source.next_orderbook().unwrap() {
+            emitted.push((order_book.current_time, order_book.best_bid().unwrap().0));
+        }
+
+        // 1050 supersedes 1030 within the first interval; 1170 carries forward until 1420
+        assert_eq!(emitted, vec![(1100, 100.1), (1200, 100.2), (1300, 100.2), (1400, 100.2), (1500, 100.3)]);
+        assert!(emitted.windows(2).all(|w| w[1].0 - w[0].0 == 100));
+
+        source.reset().unwrap();
+        assert_eq!(source.next_orderbook().unwrap().unwrap().current_time, 1100);
+    }
+}
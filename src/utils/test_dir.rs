@@ -0,0 +1,38 @@
+//! Scratch directories for tests
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Fresh directory under the system temp dir, removed with its contents when dropped,
+/// so a failing assertion doesn't leave fixtures behind
+pub(crate) struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Create `happytest_<name>_<pid>`, clearing whatever an aborted run left there
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("happytest_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
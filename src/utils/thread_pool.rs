@@ -0,0 +1,38 @@
+use log::warn;
+
+/// Size rayon's global pool to `workers` threads (0 = rayon's default).
+///
+/// The global pool can only be built once per process, so later calls, or a pool an
+/// embedding program already set up, leave the existing pool in place. Returns whether
+/// this call configured it.
+pub fn init_global_thread_pool(workers: usize) -> bool {
+    if workers == 0 {
+        return false;
+    }
+    match rayon::ThreadPoolBuilder::new().num_threads(workers).build_global() {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                "Keeping the existing rayon pool of {} threads instead of {} workers: {}",
+                rayon::current_num_threads(), workers, e
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_second_parallel_batch_reuses_the_pool() {
+        for _ in 0..2 {
+            init_global_thread_pool(2);
+            let sum: u64 = (1..=100u64).into_par_iter().sum();
+            assert_eq!(sum, 5050);
+        }
+        assert!(!init_global_thread_pool(2));
+    }
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset};
+
+/// How timestamps are rendered on chart axes and in exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    /// Date and time when the timestamps span more than one calendar day, time of day otherwise
+    #[default]
+    Auto,
+    /// Time of day only
+    Time,
+    /// Date and time
+    DateTime,
+    /// Raw epoch milliseconds
+    EpochMillis,
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(TimeFormat::Auto),
+            "time" => Ok(TimeFormat::Time),
+            "datetime" => Ok(TimeFormat::DateTime),
+            "epoch" | "epoch_ms" => Ok(TimeFormat::EpochMillis),
+            other => Err(format!("unknown time format '{}', expected auto, time, datetime or epoch", other)),
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Formatter for timestamps in `start_ms..=end_ms`, shown at `offset` from UTC
+    pub fn formatter(self, offset: FixedOffset, start_ms: i64, end_ms: i64) -> TimestampFormatter {
+        let format = match self {
+            TimeFormat::Auto => {
+                let day = |ms: i64| DateTime::from_timestamp_millis(ms).map(|dt| dt.with_timezone(&offset).date_naive());
+                if day(start_ms) == day(end_ms) { TimeFormat::Time } else { TimeFormat::DateTime }
+            }
+            format => format,
+        };
+        TimestampFormatter { format, offset }
+    }
+}
+
+/// A `TimeFormat` resolved for a particular time span and timezone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimestampFormatter {
+    format: TimeFormat,
+    offset: FixedOffset,
+}
+
+impl TimestampFormatter {
+    /// Raw epoch milliseconds, as exports wrote before formatting was configurable
+    pub fn epoch_millis() -> Self {
+        Self { format: TimeFormat::EpochMillis, offset: FixedOffset::east_opt(0).unwrap() }
+    }
+
+    pub fn is_epoch_millis(&self) -> bool {
+        self.format == TimeFormat::EpochMillis
+    }
+
+    /// Short label for chart axes
+    pub fn format(&self, ms: i64) -> String {
+        self.render(ms, "%H:%M", "%Y-%m-%d %H:%M")
+    }
+
+    /// Full-precision text for exports
+    pub fn format_precise(&self, ms: i64) -> String {
+        self.render(ms, "%H:%M:%S%.3f", "%Y-%m-%d %H:%M:%S%.3f%:z")
+    }
+
+    fn render(&self, ms: i64, time_pattern: &str, datetime_pattern: &str) -> String {
+        let pattern = match self.format {
+            TimeFormat::Time => time_pattern,
+            TimeFormat::DateTime | TimeFormat::Auto => datetime_pattern,
+            TimeFormat::EpochMillis => return ms.to_string(),
+        };
+        DateTime::from_timestamp_millis(ms)
+            .map(|dt| dt.with_timezone(&self.offset).format(pattern).to_string())
+            .unwrap_or_else(|| ms.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_day_span_labels_include_date() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        // 2024-01-01 23:30 UTC to 2024-01-03 01:00 UTC
+        let (start, end) = (1_704_151_800_000, 1_704_243_600_000);
+
+        let multi_day = TimeFormat::Auto.formatter(utc, start, end);
+        assert_eq!(multi_day.format(start), "2024-01-01 23:30");
+        assert_eq!(multi_day.format(end), "2024-01-03 01:00");
+
+        let intraday = TimeFormat::Auto.formatter(utc, start, start + 60_000);
+        assert_eq!(intraday.format(start), "23:30");
+
+        // The same span is intraday two hours east of UTC
+        let plus_two = FixedOffset::east_opt(2 * 3600).unwrap();
+        let shifted = TimeFormat::Auto.formatter(plus_two, start, start + 3_600_000);
+        assert_eq!(shifted.format(start), "01:30");
+
+        assert_eq!(TimestampFormatter::epoch_millis().format_precise(start), start.to_string());
+        assert_eq!("datetime".parse::<TimeFormat>(), Ok(TimeFormat::DateTime));
+    }
+}